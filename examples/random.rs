@@ -1,9 +1,6 @@
-use std::{fs::File, time::Instant};
-
 use anyhow::Result;
 use clap::Parser;
-use ibu::{Header, Record, Writer, HEADER_SIZE, RECORD_SIZE};
-use rand::{rngs::SmallRng, Rng, SeedableRng};
+use ibu::{write_random_parallel_with, Header, HEADER_SIZE, RECORD_SIZE};
 
 #[derive(Parser)]
 struct Args {
@@ -21,6 +18,9 @@ struct Args {
     bc_len: u32,
     #[clap(long, default_value_t = 12)]
     umi_len: u32,
+    /// Number of threads to use (0 = all available cores)
+    #[clap(long, default_value_t = 0)]
+    threads: usize,
     #[clap(long)]
     seed: Option<u64>,
 }
@@ -30,27 +30,19 @@ fn main() -> Result<()> {
     let header = Header::new(args.bc_len, args.umi_len);
     header.validate()?;
 
-    let handle = File::create(&args.path)?;
-    let mut writer = Writer::new(handle, header)?;
-    let mut rng = if let Some(seed) = args.seed {
-        SmallRng::seed_from_u64(seed)
-    } else {
-        SmallRng::from_os_rng()
-    };
-
-    let start = Instant::now();
-    let num_records = (args.records * 1_000_000.0) as usize;
-    for _ in 0..num_records {
-        let barcode = rng.random_range(0..args.barcodes);
-        let index = rng.random_range(0..args.max_index);
-        let umi = rng.random();
-        let record = Record::new(barcode, umi, index);
-        writer.write_record(&record)?;
-    }
-    writer.finish()?;
-    let elapsed = start.elapsed();
-
-    let total_bytes = HEADER_SIZE + (num_records * RECORD_SIZE);
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let num_records = (args.records * 1_000_000.0) as u64;
+    let elapsed = write_random_parallel_with(
+        &args.path,
+        header,
+        num_records,
+        args.threads,
+        seed,
+        Some(args.barcodes),
+        Some(args.max_index),
+    )?;
+
+    let total_bytes = HEADER_SIZE + (num_records as usize * RECORD_SIZE);
 
     eprintln!("Finished generating {} records", num_records);
     eprintln!("Elapsed time: {:?}", elapsed);
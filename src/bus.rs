@@ -0,0 +1,375 @@
+//! Conversion between IBU and BUS files.
+//!
+//! The [BUS format](https://github.com/BUStools/BUS-format) of kallisto and
+//! bustools stores barcodes and UMIs with the same 2-bit encoding as IBU (first
+//! base in the highest bits), so both translate directly. BUS records carry an
+//! equivalence class and a count where IBU records have a single `index`, which
+//! is packed as follows:
+//!
+//! ```text
+//! index = (ec as u32 as u64) << 32 | count as u64
+//! ```
+//!
+//! The equivalence class takes the upper 32 bits (negative classes keep their
+//! two's complement bits) and the count the lower 32 bits. The mapping is
+//! lossless in both directions except for the BUS `flags` field, which IBU has
+//! no room for: it is dropped on import and written as zero on export.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{Header, IbuError, Reader, Record, Writer};
+
+/// BUS magic bytes (`BUS\0`) read as a little-endian integer.
+const BUS_MAGIC: u32 = u32::from_le_bytes(*b"BUS\0");
+
+/// BUS format version read and written.
+const BUS_VERSION: u32 = 1;
+
+/// Size of the fixed part of the BUS header, before the text section.
+const BUS_HEADER_SIZE: usize = 20;
+
+/// Size of a BUS record in bytes.
+const BUS_RECORD_SIZE: usize = std::mem::size_of::<BusRecord>();
+
+/// On-disk BUS record.
+#[derive(Copy, Clone, Pod, Zeroable, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+struct BusRecord {
+    /// 2-bit encoded barcode
+    barcode: u64,
+
+    /// 2-bit encoded UMI
+    umi: u64,
+
+    /// Equivalence class
+    ec: i32,
+
+    /// Number of reads collapsed into the record
+    count: u32,
+
+    /// Application-specific flags
+    flags: u32,
+
+    /// Padding to 32 bytes
+    pad: u32,
+}
+
+impl From<BusRecord> for Record {
+    fn from(bus: BusRecord) -> Self {
+        let index = (u64::from(bus.ec as u32) << 32) | u64::from(bus.count);
+        Record::new(bus.barcode, bus.umi, index)
+    }
+}
+
+impl From<Record> for BusRecord {
+    fn from(record: Record) -> Self {
+        Self {
+            barcode: record.barcode,
+            umi: record.umi,
+            ec: (record.index >> 32) as u32 as i32,
+            count: record.index as u32,
+            ..Default::default()
+        }
+    }
+}
+
+/// Converts a BUS file to IBU records written to `output`.
+///
+/// The IBU header takes the barcode and UMI lengths of the BUS header. The
+/// BUS text section (usually the kallisto command line) is skipped, and the
+/// equivalence class and count of each record are packed into its index as
+/// `(ec as u32 as u64) << 32 | count as u64`, so negative classes keep their
+/// two's complement bits. BUS flags are dropped. The writer is finished before
+/// returning.
+///
+/// # Arguments
+///
+/// * `path` - Path of the BUS file
+/// * `output` - Destination of the IBU data
+///
+/// # Returns
+///
+/// The number of records converted.
+///
+/// # Errors
+///
+/// Returns [`IbuError::InvalidMagicNumber`] or [`IbuError::InvalidVersion`]
+/// if the file is not a version 1 BUS file, the errors of [`Header::validate`]
+/// for barcode or UMI lengths outside `1..=32`, and
+/// [`IbuError::TruncatedRecord`] with the byte offset of a partial trailing
+/// record. Also returns any error from reading the file or writing the output.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::from_bus;
+/// use std::fs::File;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let converted = from_bus("output.bus", File::create("output.ibu")?)?;
+/// println!("Converted {} records", converted);
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_bus<P: AsRef<Path>, W: Write>(path: P, output: W) -> crate::Result<u64> {
+    read_bus(BufReader::new(File::open(path)?), output)
+}
+
+/// Converts the records of an IBU reader to a BUS file at `path`.
+///
+/// The BUS header takes the barcode and UMI lengths of the reader's header and
+/// has an empty text section. The upper 32 bits of each index become the
+/// equivalence class and the lower 32 bits the count, reversing [`from_bus`],
+/// and flags are written as zero.
+///
+/// # Arguments
+///
+/// * `reader` - Source of the IBU records
+/// * `path` - Path of the BUS file to create
+///
+/// # Returns
+///
+/// The number of records converted.
+///
+/// # Errors
+///
+/// Returns the first error from reading the records or writing the file.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{to_bus, Reader};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = Reader::from_path("data.ibu")?;
+/// to_bus(reader, "data.bus")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_bus<R: Read, P: AsRef<Path>>(reader: Reader<R>, path: P) -> crate::Result<u64> {
+    write_bus(reader, BufWriter::new(File::create(path)?))
+}
+
+/// Reads a BUS stream and writes its records as IBU to `output`.
+fn read_bus<R: Read, W: Write>(mut reader: R, output: W) -> crate::Result<u64> {
+    let mut fixed = [0u8; BUS_HEADER_SIZE];
+    reader.read_exact(&mut fixed)?;
+    let field = |i: usize| u32::from_le_bytes(fixed[4 * i..4 * i + 4].try_into().unwrap());
+    let (magic, version, bc_len, umi_len, text_len) =
+        (field(0), field(1), field(2), field(3), field(4));
+    if magic != BUS_MAGIC {
+        return Err(IbuError::InvalidMagicNumber {
+            expected: BUS_MAGIC,
+            actual: magic,
+        });
+    }
+    if version != BUS_VERSION {
+        return Err(IbuError::InvalidVersion {
+            expected: BUS_VERSION,
+            actual: version,
+        });
+    }
+    let header = Header::new(bc_len, umi_len);
+    header.validate()?;
+
+    // Skip the free-form text section
+    let skipped = std::io::copy(
+        &mut reader.by_ref().take(text_len.into()),
+        &mut std::io::sink(),
+    )?;
+    if skipped != u64::from(text_len) {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let mut writer = Writer::new(output, header)?;
+    let mut pos = BUS_HEADER_SIZE + text_len as usize;
+    let mut converted = 0;
+    let mut buffer = [0u8; BUS_RECORD_SIZE];
+    loop {
+        let filled = read_full(&mut reader, &mut buffer)?;
+        if filled == 0 {
+            break;
+        }
+        if filled < BUS_RECORD_SIZE {
+            return Err(IbuError::TruncatedRecord { pos });
+        }
+        let bus: BusRecord = bytemuck::pod_read_unaligned(&buffer);
+        writer.write_record(&bus.into())?;
+        pos += BUS_RECORD_SIZE;
+        converted += 1;
+    }
+    writer.finish()?;
+    Ok(converted)
+}
+
+/// Writes the records of an IBU reader as a BUS stream to `output`.
+fn write_bus<R: Read, W: Write>(reader: Reader<R>, mut output: W) -> crate::Result<u64> {
+    let header = reader.header();
+    for field in [BUS_MAGIC, BUS_VERSION, header.bc_len, header.umi_len, 0] {
+        output.write_all(&field.to_le_bytes())?;
+    }
+    let mut converted = 0;
+    for record in reader {
+        output.write_all(bytemuck::bytes_of(&BusRecord::from(record?)))?;
+        converted += 1;
+    }
+    output.flush()?;
+    Ok(converted)
+}
+
+/// Reads until `buffer` is full or the stream ends, returning the bytes read.
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Builds a BUS stream with a text section.
+    fn bus_bytes(bc_len: u32, umi_len: u32, text: &[u8], records: &[BusRecord]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for field in [BUS_MAGIC, BUS_VERSION, bc_len, umi_len, text.len() as u32] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(text);
+        for record in records {
+            bytes.extend_from_slice(bytemuck::bytes_of(record));
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_bus_roundtrip() {
+        assert_eq!(BUS_RECORD_SIZE, 32);
+        let records: Vec<BusRecord> = (0..100)
+            .map(|i| BusRecord {
+                barcode: i,
+                umi: i * 3,
+                ec: i as i32 - 50,
+                count: i as u32 + 1,
+                ..Default::default()
+            })
+            .collect();
+        let text = b"kallisto bus -i index.idx -o out";
+        let mut ibu = Vec::new();
+        let converted = read_bus(Cursor::new(bus_bytes(16, 12, text, &records)), &mut ibu);
+        assert_eq!(converted.unwrap(), 100);
+
+        let reader = Reader::new(Cursor::new(ibu.clone())).unwrap();
+        assert_eq!(reader.header(), Header::new(16, 12));
+        let converted: Vec<Record> = reader.collect::<crate::Result<_>>().unwrap();
+        assert_eq!(
+            converted[0],
+            Record::new(0, 0, (u64::from(u32::MAX - 49) << 32) | 1)
+        );
+        assert_eq!(converted[99], Record::new(99, 297, (49 << 32) | 100));
+
+        // Exporting reproduces the original records
+        let mut bus = Vec::new();
+        let reader = Reader::new(Cursor::new(ibu)).unwrap();
+        assert_eq!(write_bus(reader, &mut bus).unwrap(), 100);
+        assert_eq!(bus, bus_bytes(16, 12, b"", &records));
+    }
+
+    #[test]
+    fn test_bus_flags_dropped() {
+        let record = BusRecord {
+            barcode: 1,
+            umi: 2,
+            ec: -1,
+            count: 3,
+            flags: 7,
+            pad: 0,
+        };
+        let converted = Record::from(record);
+        assert_eq!(converted.index, 0xffff_ffff_0000_0003);
+        assert_eq!(BusRecord::from(converted), BusRecord { flags: 0, ..record });
+    }
+
+    #[test]
+    fn test_from_bus_errors() {
+        let record = BusRecord::default();
+        let mut bytes = bus_bytes(16, 12, b"text", &[record]);
+        bytes[0] = b'X';
+        assert!(matches!(
+            read_bus(Cursor::new(bytes), Vec::new()),
+            Err(IbuError::InvalidMagicNumber { .. })
+        ));
+
+        let mut bytes = bus_bytes(16, 12, b"text", &[record]);
+        bytes[4] = 2;
+        assert!(matches!(
+            read_bus(Cursor::new(bytes), Vec::new()),
+            Err(IbuError::InvalidVersion {
+                expected: 1,
+                actual: 2
+            })
+        ));
+
+        let bytes = bus_bytes(33, 12, b"", &[]);
+        assert!(matches!(
+            read_bus(Cursor::new(bytes), Vec::new()),
+            Err(IbuError::InvalidBarcodeLength(33))
+        ));
+
+        let mut bytes = bus_bytes(16, 12, b"text", &[record, record]);
+        bytes.pop();
+        assert!(matches!(
+            read_bus(Cursor::new(bytes), Vec::new()),
+            Err(IbuError::TruncatedRecord { pos: 56 })
+        ));
+
+        // Text section longer than the file
+        let mut bytes = bus_bytes(16, 12, b"", &[]);
+        bytes[16] = 10;
+        assert!(matches!(
+            read_bus(Cursor::new(bytes), Vec::new()),
+            Err(IbuError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_bus_files() {
+        let (bus_path, ibu_path) = ("test_bus_files.bus", "test_bus_files.ibu");
+        let records = [Record::new(1, 2, 3), Record::new(4, 5, 6 | (7 << 32))];
+        let mut writer = Writer::new(Vec::new(), Header::new(8, 4)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        let reader = Reader::new(Cursor::new(writer.into_inner())).unwrap();
+
+        assert_eq!(to_bus(reader, bus_path).unwrap(), 2);
+        assert_eq!(
+            std::fs::metadata(bus_path).unwrap().len() as usize,
+            BUS_HEADER_SIZE + 2 * BUS_RECORD_SIZE
+        );
+        assert_eq!(
+            from_bus(bus_path, File::create(ibu_path).unwrap()).unwrap(),
+            2
+        );
+        let read: Vec<Record> = Reader::from_path(ibu_path)
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert_eq!(read, records);
+
+        std::fs::remove_file(bus_path).unwrap();
+        std::fs::remove_file(ibu_path).unwrap();
+    }
+}
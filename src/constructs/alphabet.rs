@@ -0,0 +1,152 @@
+use crate::IbuError;
+
+/// Mapping between nucleotides and 2-bit codes.
+///
+/// Barcodes and UMIs are stored as sequences of 2-bit codes. The alphabet
+/// defines which nucleotide each code represents, so files produced with a
+/// non-standard mapping can still be decoded and converted.
+///
+/// The [`canonical`](Alphabet::canonical) alphabet is the mapping used
+/// throughout the format documentation:
+/// - A = 00
+/// - C = 01
+/// - G = 10
+/// - T = 11
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::Alphabet;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let canonical = Alphabet::canonical();
+/// assert_eq!(canonical.decode(0b10), b'G');
+///
+/// // An alphabet where T = 00, G = 01, C = 10, A = 11
+/// let reversed = Alphabet::new(*b"TGCA")?;
+/// assert_eq!(reversed.encode(b'T'), Some(0b00));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Alphabet {
+    /// Nucleotide represented by each 2-bit code
+    bases: [u8; 4],
+}
+
+impl Alphabet {
+    /// Creates an alphabet from the nucleotides assigned to codes `0..4`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bases` - Nucleotide for each code, in code order
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidAlphabet`] if `bases` is not a permutation
+    /// of `ACGT` (uppercase).
+    pub fn new(bases: [u8; 4]) -> crate::Result<Self> {
+        let mut sorted = bases;
+        sorted.sort_unstable();
+        if &sorted != b"ACGT" {
+            return Err(IbuError::InvalidAlphabet(bases));
+        }
+        Ok(Self { bases })
+    }
+
+    /// Returns the canonical alphabet (A=00, C=01, G=10, T=11).
+    pub fn canonical() -> Self {
+        Self { bases: *b"ACGT" }
+    }
+
+    /// Returns the nucleotides assigned to codes `0..4`.
+    pub fn bases(&self) -> [u8; 4] {
+        self.bases
+    }
+
+    /// Returns the 2-bit code for a nucleotide, or `None` if not in the alphabet.
+    pub fn encode(&self, base: u8) -> Option<u8> {
+        self.bases.iter().position(|&b| b == base).map(|c| c as u8)
+    }
+
+    /// Returns the nucleotide for a 2-bit code.
+    ///
+    /// Only the lowest 2 bits of `code` are used.
+    pub fn decode(&self, code: u8) -> u8 {
+        self.bases[(code & 0b11) as usize]
+    }
+
+    /// Returns the code translation table from this alphabet to `to`.
+    ///
+    /// Entry `c` of the table is the code under `to` of the nucleotide
+    /// represented by `c` under `self`.
+    #[cfg(feature = "std")]
+    pub(crate) fn translation(&self, to: &Alphabet) -> [u8; 4] {
+        // Both alphabets are permutations of ACGT, so every base is present
+        self.bases.map(|base| to.encode(base).unwrap())
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Self::canonical()
+    }
+}
+
+/// Translates every 2-bit code of a `len`-base sequence through `table`.
+#[cfg(feature = "std")]
+pub(crate) fn translate_sequence(seq: u64, len: u32, table: &[u8; 4]) -> u64 {
+    (0..len.min(32)).fold(seq, |acc, i| {
+        let shift = 2 * i;
+        let code = ((seq >> shift) & 0b11) as usize;
+        (acc & !(0b11 << shift)) | ((table[code] as u64) << shift)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alphabet_new() {
+        assert!(Alphabet::new(*b"TGCA").is_ok());
+        assert!(matches!(
+            Alphabet::new(*b"ACGA"),
+            Err(IbuError::InvalidAlphabet(_))
+        ));
+        assert!(Alphabet::new(*b"acgt").is_err());
+        assert_eq!(Alphabet::default(), Alphabet::canonical());
+    }
+
+    #[test]
+    fn test_alphabet_encode_decode() {
+        let alphabet = Alphabet::new(*b"GTAC").unwrap();
+        for code in 0..4 {
+            assert_eq!(alphabet.encode(alphabet.decode(code)), Some(code));
+        }
+        assert_eq!(alphabet.encode(b'N'), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_translate_sequence() {
+        let from = Alphabet::canonical();
+        let to = Alphabet::new(*b"TGCA").unwrap();
+        let table = from.translation(&to);
+
+        // ACGT -> codes under `to` are 11 10 01 00
+        let seq = 0b00_01_10_11;
+        assert_eq!(translate_sequence(seq, 4, &table), 0b11_10_01_00);
+
+        // Bits beyond the sequence length are left untouched
+        assert_eq!(translate_sequence(0xF00, 2, &table), 0xF00 | 0b11_11);
+
+        // Round trip
+        let back = to.translation(&from);
+        let value = 0x1234_5678_9abc_def0;
+        assert_eq!(
+            translate_sequence(translate_sequence(value, 32, &table), 32, &back),
+            value
+        );
+    }
+}
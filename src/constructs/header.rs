@@ -1,10 +1,23 @@
 use bytemuck::{Pod, Zeroable};
 
-use crate::IbuError;
+use crate::{packed::base_mask, IbuError};
 
 pub const MAGIC: u32 = 0x21554249; // "IBU!"
 pub const VERSION: u32 = 2;
-pub const HEADER_SIZE: usize = std::mem::size_of::<Header>();
+pub const HEADER_SIZE: usize = core::mem::size_of::<Header>();
+/// Size of a version 1 header in bytes.
+pub const V1_HEADER_SIZE: usize = 13;
+
+/// Flag bit marking the records as sorted.
+const FLAG_SORTED: u64 = 1;
+/// Flag bit marking the reserved checksum bytes as holding a record checksum.
+const FLAG_CHECKSUM: u64 = 1 << 1;
+/// Flag bit marking the records as UMI-collapsed.
+const FLAG_COLLAPSED: u64 = 1 << 2;
+/// Reserved bytes holding the record checksum.
+const CHECKSUM_BYTES: core::ops::Range<usize> = 0..4;
+/// Reserved bytes holding the record count.
+const COUNT_BYTES: core::ops::Range<usize> = 4..8;
 
 /// Binary format header for IBU files.
 ///
@@ -20,8 +33,24 @@ pub const HEADER_SIZE: usize = std::mem::size_of::<Header>();
 /// | 4      | 4    | version       | Format version (currently 2)                  |
 /// | 8      | 4    | bc_len        | Barcode length in bases (1-32)                |
 /// | 12     | 4    | umi_len       | UMI length in bases (1-32)                    |
-/// | 16     | 8    | flags         | Bit flags (bit 0: sorted, others reserved)    |
-/// | 24     | 8    | reserved      | Reserved bytes for future extensions          |
+/// | 16     | 8    | flags         | Bit flags (see below)                          |
+/// | 24     | 4    | reserved[0..4] | Record checksum (CRC-32) if flag bit 1 is set, zero otherwise |
+/// | 28     | 4    | reserved[4..8] | Record count (0 if unknown)                  |
+///
+/// All multi-byte fields, including those in the reserved bytes, are
+/// little-endian.
+///
+/// # Flag Bits
+///
+/// | Bit  | Meaning                                                        |
+/// |------|----------------------------------------------------------------|
+/// | 0    | Records are sorted ([`sorted`](Self::sorted))                  |
+/// | 1    | Reserved bytes hold a record checksum ([`checksum`](Self::checksum)) |
+/// | 2    | Records are UMI-collapsed ([`collapsed`](Self::collapsed))     |
+/// | 3-63 | Unassigned; new features must claim the next free bit          |
+///
+/// The checksum and the record count have separate storage, so a file can
+/// carry both.
 ///
 /// # Examples
 ///
@@ -42,8 +71,7 @@ pub const HEADER_SIZE: usize = std::mem::size_of::<Header>();
 /// header.validate().unwrap();
 /// ```
 #[derive(Copy, Clone, Pod, Zeroable, Debug, PartialEq, Eq, Hash)]
-#[cfg(feature = "serde")]
-#[derive(serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct Header {
     /// Magic number for file type validation: 0x21554249 ("IBU!")
@@ -54,9 +82,11 @@ pub struct Header {
     pub bc_len: u32,
     /// UMI length in bases (1-32)
     pub umi_len: u32,
-    /// Bit flags: bit 0 = sorted, others reserved for future use
+    /// Bit flags: bit 0 = sorted, bit 1 = checksum, bit 2 = collapsed,
+    /// others reserved for future use
     pub flags: u64,
-    /// Reserved bytes for future extensions
+    /// Reserved bytes: the record checksum in bytes 0-3 (if bit 1 is set) and
+    /// the record count in bytes 4-7 (zero if unknown)
     pub reserved: [u8; 8],
 }
 impl Header {
@@ -92,6 +122,80 @@ impl Header {
         }
     }
 
+    /// Upgrades a version 1 header to the current format.
+    ///
+    /// Version 1 headers are 13 bytes, all little-endian, without a magic
+    /// number:
+    ///
+    /// | Offset | Size | Field   | Maps to                        |
+    /// |--------|------|---------|--------------------------------|
+    /// | 0      | 4    | version | must be 1; replaced by [`VERSION`] |
+    /// | 4      | 4    | bc_len  | `bc_len`                       |
+    /// | 8      | 4    | umi_len | `umi_len`                      |
+    /// | 12     | 1    | sorted  | sorted flag (bit 0) if non-zero |
+    ///
+    /// The magic number is filled in and the reserved bytes are zeroed, so no
+    /// checksum or record count is carried over. Version 1 records share the
+    /// 24-byte layout of the current format and need no conversion.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidVersion`] if the version field is not 1, or
+    /// the errors of [`Header::validate`] for invalid lengths.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, V1_HEADER_SIZE};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut bytes = [0u8; V1_HEADER_SIZE];
+    /// bytes[0..4].copy_from_slice(&1u32.to_le_bytes());
+    /// bytes[4..8].copy_from_slice(&16u32.to_le_bytes());
+    /// bytes[8..12].copy_from_slice(&12u32.to_le_bytes());
+    /// bytes[12] = 1;
+    ///
+    /// let header = Header::upgrade_from_v1(&bytes)?;
+    /// assert_eq!(header.version, ibu::VERSION);
+    /// assert_eq!((header.bc_len, header.umi_len), (16, 12));
+    /// assert!(header.sorted());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn upgrade_from_v1(bytes: &[u8; V1_HEADER_SIZE]) -> crate::Result<Self> {
+        let field = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let version = field(0);
+        if version != 1 {
+            return Err(IbuError::InvalidVersion {
+                expected: 1,
+                actual: version,
+            });
+        }
+        let mut header = Self::new(field(4), field(8));
+        if bytes[12] != 0 {
+            header.set_sorted();
+        }
+        header.validate()?;
+        Ok(header)
+    }
+
+    /// Returns a builder for constructing a validated header.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Header;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let header = Header::builder().bc_len(16).umi_len(12).sorted(true).build()?;
+    /// assert!(header.sorted());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> HeaderBuilder {
+        HeaderBuilder::default()
+    }
+
     /// Marks the file as containing sorted records.
     ///
     /// Sets bit 0 of the flags field to indicate that records in the file
@@ -109,7 +213,25 @@ impl Header {
     /// assert!(header.sorted());
     /// ```
     pub fn set_sorted(&mut self) {
-        self.flags |= 1;
+        self.flags |= FLAG_SORTED;
+    }
+
+    /// Clears the sorted flag.
+    ///
+    /// Used when a transformation may break the sort order of the records.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Header;
+    ///
+    /// let mut header = Header::new(16, 12);
+    /// header.set_sorted();
+    /// header.clear_sorted();
+    /// assert!(!header.sorted());
+    /// ```
+    pub fn clear_sorted(&mut self) {
+        self.flags &= !FLAG_SORTED;
     }
 
     /// Returns whether the file is marked as containing sorted records.
@@ -128,7 +250,213 @@ impl Header {
     /// assert!(header.sorted());
     /// ```
     pub fn sorted(&self) -> bool {
-        self.flags & 1 != 0
+        self.flags & FLAG_SORTED != 0
+    }
+
+    /// Marks the records as UMI-collapsed.
+    ///
+    /// Sets bit 2 of the flags field to indicate that each record stands for a
+    /// whole molecule, so its `index` is a read count rather than a read
+    /// ordinal. This is set by [`Ibu::collapse_umis`](crate::Ibu::collapse_umis).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Header;
+    ///
+    /// let mut header = Header::new(16, 12);
+    /// assert!(!header.collapsed());
+    ///
+    /// header.set_collapsed();
+    /// assert!(header.collapsed());
+    /// header.validate().unwrap();
+    /// ```
+    pub fn set_collapsed(&mut self) {
+        self.flags |= FLAG_COLLAPSED;
+    }
+
+    /// Returns whether the records are marked as UMI-collapsed.
+    ///
+    /// Checks bit 2 of the flags field.
+    pub fn collapsed(&self) -> bool {
+        self.flags & FLAG_COLLAPSED != 0
+    }
+
+    /// Returns the flags describing the records rather than the file bytes.
+    ///
+    /// These carry over to files holding the same records, such as a merge
+    /// of several inputs. The checksum flag does not, since the checksum
+    /// belongs to the original file.
+    #[cfg(feature = "std")]
+    pub(crate) fn record_flags(&self) -> u64 {
+        self.flags & !FLAG_CHECKSUM
+    }
+
+    /// Stores a record checksum in the reserved bytes.
+    ///
+    /// Sets bit 1 of the flags field and writes the checksum into the first
+    /// four reserved bytes in little-endian order. Any stored record count is
+    /// kept. This is normally done by
+    /// [`Writer::finish_with_checksum`](crate::Writer::finish_with_checksum)
+    /// rather than by hand.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Header;
+    ///
+    /// let mut header = Header::new(16, 12);
+    /// assert_eq!(header.checksum(), None);
+    ///
+    /// header.set_checksum(0xDEADBEEF);
+    /// assert_eq!(header.checksum(), Some(0xDEADBEEF));
+    /// ```
+    pub fn set_checksum(&mut self, checksum: u32) {
+        self.flags |= FLAG_CHECKSUM;
+        self.reserved[CHECKSUM_BYTES].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    /// Returns the stored record checksum, if any.
+    ///
+    /// Returns `None` if bit 1 of the flags field is not set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Header;
+    ///
+    /// let header = Header::new(16, 12);
+    /// assert_eq!(header.checksum(), None);
+    /// ```
+    pub fn checksum(&self) -> Option<u32> {
+        if self.flags & FLAG_CHECKSUM != 0 {
+            Some(u32::from_le_bytes(
+                self.reserved[CHECKSUM_BYTES].try_into().unwrap(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Stores the number of records in the reserved bytes.
+    ///
+    /// Writes the count into the last four reserved bytes in little-endian
+    /// order. Any stored checksum is kept. Zero means the count is unknown,
+    /// and so does a count too large for 32 bits, which is stored as zero.
+    /// This is normally done by
+    /// [`Writer::finish_with_count`](crate::Writer::finish_with_count) rather
+    /// than by hand.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Header;
+    ///
+    /// let mut header = Header::new(16, 12);
+    /// header.set_checksum(0xDEADBEEF);
+    ///
+    /// header.set_record_count(1000);
+    /// assert_eq!(header.record_count(), Some(1000));
+    /// assert_eq!(header.checksum(), Some(0xDEADBEEF));
+    ///
+    /// header.set_record_count(u64::MAX);
+    /// assert_eq!(header.record_count(), None);
+    /// ```
+    pub fn set_record_count(&mut self, count: u64) {
+        let count = u32::try_from(count).unwrap_or(0);
+        self.reserved[COUNT_BYTES].copy_from_slice(&count.to_le_bytes());
+    }
+
+    /// Clears the checksum and record count stored in the reserved bytes.
+    ///
+    /// Used when records are added to an existing file, which invalidates both.
+    #[cfg(feature = "std")]
+    pub(crate) fn clear_reserved(&mut self) {
+        self.flags &= !FLAG_CHECKSUM;
+        self.reserved = [0; 8];
+    }
+
+    /// Returns the stored record count, if any.
+    ///
+    /// Returns `None` if the stored count is zero, i.e. the count is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Header;
+    ///
+    /// let header = Header::new(16, 12);
+    /// assert_eq!(header.record_count(), None);
+    /// ```
+    pub fn record_count(&self) -> Option<u64> {
+        let count = u32::from_le_bytes(self.reserved[COUNT_BYTES].try_into().unwrap());
+        (count != 0).then_some(u64::from(count))
+    }
+
+    /// Clears the bits of a packed barcode beyond the header's barcode length.
+    ///
+    /// Keeps the low `2 * bc_len` bits, i.e. the last `bc_len` bases. Useful
+    /// to canonicalize values from producers that leave high bits set, or to
+    /// truncate barcodes from a chemistry with longer barcodes, before
+    /// comparing, hashing, or sorting them: set high bits change the sort
+    /// order and break binary search over sorted data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Header;
+    ///
+    /// let header = Header::new(2, 1);
+    /// assert_eq!(header.mask_barcode(0b11_01_10), 0b01_10);
+    /// assert_eq!(header.mask_umi(0b11_01_10), 0b10);
+    /// ```
+    pub fn mask_barcode(&self, value: u64) -> u64 {
+        value & base_mask(self.bc_len)
+    }
+
+    /// Clears the bits of a packed UMI beyond the header's UMI length.
+    ///
+    /// See [`mask_barcode`](Self::mask_barcode).
+    pub fn mask_umi(&self, value: u64) -> u64 {
+        value & base_mask(self.umi_len)
+    }
+
+    /// Checks that the checksum bytes are unused unless the checksum flag
+    /// claims them.
+    ///
+    /// The first four reserved bytes must be zero unless the checksum flag
+    /// (bit 1) is set, in which case they hold the record checksum. The last
+    /// four always hold the record count and may take any value. This is
+    /// stricter than [`validate`](Self::validate), which ignores the reserved
+    /// bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::UnexpectedReserved`] if the checksum bytes are
+    /// nonzero and unclaimed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, IbuError};
+    ///
+    /// let mut header = Header::new(16, 12);
+    /// assert!(header.validate_reserved().is_ok());
+    ///
+    /// header.reserved[0] = 0xFF;
+    /// assert!(matches!(
+    ///     header.validate_reserved(),
+    ///     Err(IbuError::UnexpectedReserved(_))
+    /// ));
+    ///
+    /// header.set_checksum(0xFF);
+    /// assert!(header.validate_reserved().is_ok());
+    /// ```
+    pub fn validate_reserved(&self) -> crate::Result<()> {
+        if self.flags & FLAG_CHECKSUM == 0 && self.reserved[CHECKSUM_BYTES] != [0; 4] {
+            return Err(IbuError::UnexpectedReserved(self.reserved));
+        }
+        Ok(())
     }
 
     /// Validates the header fields.
@@ -228,6 +556,82 @@ impl Header {
     }
 }
 
+/// Builder for [`Header`].
+///
+/// Created by [`Header::builder`]. The barcode and UMI lengths have no default
+/// and must be set; the version defaults to [`VERSION`] and the records to
+/// unsorted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HeaderBuilder {
+    /// Format version
+    version: u32,
+
+    /// Barcode length in bases
+    bc_len: u32,
+
+    /// UMI length in bases
+    umi_len: u32,
+
+    /// Whether the records are sorted
+    sorted: bool,
+}
+
+impl Default for HeaderBuilder {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            bc_len: 0,
+            umi_len: 0,
+            sorted: false,
+        }
+    }
+}
+
+impl HeaderBuilder {
+    /// Sets the format version.
+    ///
+    /// Only [`VERSION`] passes validation; other versions make
+    /// [`build`](Self::build) fail.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the barcode length in bases.
+    pub fn bc_len(mut self, bc_len: u32) -> Self {
+        self.bc_len = bc_len;
+        self
+    }
+
+    /// Sets the UMI length in bases.
+    pub fn umi_len(mut self, umi_len: u32) -> Self {
+        self.umi_len = umi_len;
+        self
+    }
+
+    /// Sets whether the records are sorted.
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Builds and validates the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Header::validate`], e.g. if the barcode or UMI
+    /// length was not set.
+    pub fn build(self) -> crate::Result<Header> {
+        let mut header = Header::new(self.bc_len, self.umi_len);
+        header.version = self.version;
+        if self.sorted {
+            header.set_sorted();
+        }
+        header.validate()?;
+        Ok(header)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,6 +648,73 @@ mod tests {
         assert_eq!(header.reserved, [0; 8]);
     }
 
+    #[test]
+    fn test_header_builder() {
+        let header = Header::builder()
+            .version(VERSION)
+            .bc_len(16)
+            .umi_len(12)
+            .sorted(false)
+            .build()
+            .unwrap();
+        assert_eq!(header, Header::new(16, 12));
+
+        let sorted = Header::builder().bc_len(8).umi_len(8).sorted(true).build();
+        assert!(sorted.unwrap().sorted());
+
+        assert!(matches!(
+            Header::builder().umi_len(12).build(),
+            Err(IbuError::InvalidBarcodeLength(0))
+        ));
+        assert!(matches!(
+            Header::builder().version(1).bc_len(16).umi_len(12).build(),
+            Err(IbuError::InvalidVersion { actual: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_upgrade_from_v1() {
+        let v1 = |version: u32, bc_len: u32, sorted: u8| {
+            let mut bytes = [0u8; V1_HEADER_SIZE];
+            bytes[0..4].copy_from_slice(&version.to_le_bytes());
+            bytes[4..8].copy_from_slice(&bc_len.to_le_bytes());
+            bytes[8..12].copy_from_slice(&12u32.to_le_bytes());
+            bytes[12] = sorted;
+            bytes
+        };
+
+        let header = Header::upgrade_from_v1(&v1(1, 16, 0)).unwrap();
+        assert_eq!(header, Header::new(16, 12));
+        assert!(Header::upgrade_from_v1(&v1(1, 16, 1)).unwrap().sorted());
+
+        assert!(matches!(
+            Header::upgrade_from_v1(&v1(2, 16, 0)),
+            Err(IbuError::InvalidVersion {
+                expected: 1,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            Header::upgrade_from_v1(&v1(1, 33, 0)),
+            Err(IbuError::InvalidBarcodeLength(33))
+        ));
+    }
+
+    #[test]
+    fn test_collapsed_flag() {
+        let mut header = Header::new(16, 12);
+        header.set_record_count(10);
+        header.set_collapsed();
+        assert!(header.collapsed());
+        assert!(!header.sorted());
+        assert_eq!(header.record_count(), Some(10));
+        header.validate().unwrap();
+
+        // The checksum leaves the collapsed bit alone
+        header.set_checksum(7);
+        assert!(header.collapsed());
+    }
+
     #[test]
     fn test_header_size() {
         assert_eq!(HEADER_SIZE, 32);
@@ -267,6 +738,74 @@ mod tests {
         header.set_sorted();
         assert!(header.sorted());
         assert_eq!(header.flags, 1);
+
+        // Clear sorted
+        header.clear_sorted();
+        assert!(!header.sorted());
+        assert_eq!(header.flags, 0);
+    }
+
+    #[test]
+    fn test_checksum_flag() {
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        assert_eq!(header.checksum(), None);
+
+        header.set_checksum(0x01234567);
+        assert_eq!(header.checksum(), Some(0x01234567));
+        assert_eq!(header.reserved[..4], 0x01234567u32.to_le_bytes());
+
+        // Sorted flag is preserved
+        assert!(header.sorted());
+        assert!(header.validate().is_ok());
+
+        // Survives a byte roundtrip
+        let reconstructed = Header::from_bytes(header.as_bytes());
+        assert_eq!(reconstructed.checksum(), Some(0x01234567));
+    }
+
+    #[test]
+    fn test_mask_barcode_umi() {
+        let header = Header::new(16, 12);
+        assert_eq!(header.mask_barcode(u64::MAX), (1 << 32) - 1);
+        assert_eq!(header.mask_umi(u64::MAX), (1 << 24) - 1);
+        assert_eq!(header.mask_barcode(0xABCD), 0xABCD);
+
+        let header = Header::new(32, 1);
+        assert_eq!(header.mask_barcode(u64::MAX), u64::MAX);
+        assert_eq!(header.mask_umi(0b1_11), 0b11);
+    }
+
+    #[test]
+    fn test_record_count_flag() {
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        header.set_checksum(7);
+
+        // The count and the checksum are stored side by side
+        header.set_record_count(123_456);
+        assert_eq!(header.record_count(), Some(123_456));
+        assert_eq!(header.checksum(), Some(7));
+        assert!(header.sorted());
+        assert!(header.validate_reserved().is_ok());
+
+        header.set_checksum(9);
+        assert_eq!(header.record_count(), Some(123_456));
+        assert_eq!(header.checksum(), Some(9));
+
+        // Zero and counts beyond 32 bits are unknown
+        header.set_record_count(0);
+        assert_eq!(header.record_count(), None);
+        header.set_record_count(1 << 32);
+        assert_eq!(header.record_count(), None);
+        assert_eq!(header.checksum(), Some(9));
+
+        // The count bytes are always claimed
+        let mut header = Header::new(16, 12);
+        header.reserved[7] = 1;
+        assert!(header.validate_reserved().is_ok());
+        header.reserved[0] = 1;
+        assert!(header.validate_reserved().is_err());
     }
 
     #[test]
@@ -383,6 +922,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn test_header_derives() {
         let header1 = Header::new(16, 12);
         let header2 = Header::new(16, 12);
@@ -0,0 +1,677 @@
+#[cfg(feature = "serde")]
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::{load_to_vec, slice, Header, IbuError, Reader, Record, Writer};
+
+/// Owned in-memory IBU collection.
+///
+/// `Ibu` pairs a [`Header`] with all of its records in a `Vec<Record>`, and
+/// offers the same query surface as [`MmapReader`](crate::MmapReader) for data
+/// that already lives in memory.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{Header, Ibu, Record};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let records = vec![
+///     Record::new(2, 0, 0),
+///     Record::new(1, 1, 0),
+///     Record::new(1, 0, 0),
+/// ];
+/// let mut ibu = Ibu::new(Header::new(16, 12), records);
+///
+/// // Sort in place (marks the header as sorted)
+/// ibu.sort();
+/// assert!(ibu.header.sorted());
+/// assert!(ibu.verify_sorted());
+///
+/// // Query by barcode
+/// let hits = ibu.search_barcode(1)?;
+/// assert_eq!(hits.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ibu {
+    /// File header
+    pub header: Header,
+    /// All records in file order
+    pub records: Vec<Record>,
+}
+
+impl Ibu {
+    /// Creates a new collection from a header and its records.
+    ///
+    /// The header is taken as-is; in particular the sorted flag is not checked
+    /// against the records. Use [`Ibu::verify_sorted`] to check it.
+    pub fn new(header: Header, records: Vec<Record>) -> Self {
+        Self { header, records }
+    }
+
+    /// Decodes a collection from the bytes of an IBU file.
+    ///
+    /// The header is validated and every record is read, so the header
+    /// (including its sorted flag) is preserved exactly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header is invalid or the record data is
+    /// truncated.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Ibu, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut ibu = Ibu::new(Header::new(16, 12), vec![Record::new(2, 0, 0), Record::new(1, 0, 0)]);
+    /// ibu.sort();
+    ///
+    /// let bytes = ibu.write_bytes()?;
+    /// assert_eq!(Ibu::from_bytes(&bytes)?, ibu);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        let reader = Reader::new(bytes)?;
+        let header = reader.header();
+        let records = reader.collect::<crate::Result<Vec<_>>>()?;
+        Ok(Self::new(header, records))
+    }
+
+    /// Loads a collection from an IBU file.
+    ///
+    /// Uncompressed files are read in one pass with [`load_to_vec`]. With the
+    /// `niffler` feature enabled, compressed files are also accepted and
+    /// decoded through a streaming [`Reader`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, the header is invalid, or
+    /// the record data is truncated.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::Ibu;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut ibu = Ibu::from_path("data.ibu")?;
+    /// ibu.sort();
+    /// ibu.to_path("sorted.ibu")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        match load_to_vec(path.as_ref()) {
+            Ok((header, records)) => Ok(Self::new(header, records)),
+            // A compressed file fails on its magic number
+            #[cfg(feature = "niffler")]
+            Err(IbuError::InvalidMagicNumber { .. }) => {
+                let reader = Reader::from_path(path)?;
+                let header = reader.header();
+                let records = reader.collect::<crate::Result<Vec<_>>>()?;
+                Ok(Self::new(header, records))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes the collection to an uncompressed IBU file.
+    ///
+    /// The file is created or truncated, and the header is written followed by
+    /// all records. Any record count or checksum in the header is left out,
+    /// since the records may have changed since it was read. For compressed
+    /// output use [`Writer::from_path_auto`] directly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn to_path<P: AsRef<Path>>(&self, path: P) -> crate::Result<()> {
+        let mut writer = Writer::from_path(path, self.written_header())?;
+        writer.write_batch(&self.records)?;
+        writer.finish()
+    }
+
+    /// Encodes the collection as the bytes of an IBU file.
+    ///
+    /// The header is written followed by all records, leaving out any record
+    /// count or checksum as [`to_path`](Self::to_path) does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the records fails.
+    pub fn write_bytes(&self) -> crate::Result<Vec<u8>> {
+        let mut writer = Writer::new(Vec::new(), self.written_header())?;
+        writer.write_batch(&self.records)?;
+        writer.finish()?;
+        Ok(writer.into_inner())
+    }
+
+    /// Returns the header to write, without a stale record count or checksum.
+    fn written_header(&self) -> Header {
+        let mut header = self.header;
+        header.clear_reserved();
+        header
+    }
+
+    /// Writes the collection as JSON.
+    ///
+    /// The output is an object with a `header` (all header fields, including
+    /// the raw `flags` and `reserved` bytes) and a `records` array of
+    /// `{barcode, umi, index}` objects, with packed sequences as integers.
+    ///
+    /// JSON is roughly ten times larger than the binary format and slow to
+    /// parse, so this is intended for small test fixtures and golden-file
+    /// tests, where a readable diff matters more than size. Use
+    /// [`to_path`](Self::to_path) or [`write_bytes`](Self::write_bytes) for
+    /// real data.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::Json`] if serialization or writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Ibu, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let ibu = Ibu::new(Header::new(16, 12), vec![Record::new(1, 2, 3)]);
+    ///
+    /// let mut json = Vec::new();
+    /// ibu.to_json_writer(&mut json)?;
+    /// assert_eq!(Ibu::from_json_reader(json.as_slice())?, ibu);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn to_json_writer<W: Write>(&self, writer: W) -> crate::Result<()> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a collection written by [`to_json_writer`](Self::to_json_writer).
+    ///
+    /// The header is validated as when reading an IBU file; like
+    /// [`from_bytes`](Self::from_bytes), the sorted flag is taken as-is.
+    /// Intended for small test fixtures, see
+    /// [`to_json_writer`](Self::to_json_writer).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::Json`] if the input is not a valid JSON
+    /// collection, or a header validation error (see [`Header::validate`]).
+    #[cfg(feature = "serde")]
+    pub fn from_json_reader<R: Read>(reader: R) -> crate::Result<Self> {
+        let ibu: Self = serde_json::from_reader(reader)?;
+        ibu.header.validate()?;
+        Ok(ibu)
+    }
+
+    /// Returns all records with the given barcode.
+    ///
+    /// Uses binary search over the records, so the header must be marked as
+    /// sorted. Returns an empty slice if the barcode is not present.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::NotSorted`] if the header is not marked as sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Ibu, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut ibu = Ibu::new(
+    ///     Header::new(16, 12),
+    ///     vec![Record::new(1, 0, 0), Record::new(3, 0, 0), Record::new(3, 1, 0)],
+    /// );
+    /// ibu.sort();
+    ///
+    /// assert_eq!(ibu.search_barcode(3)?.len(), 2);
+    /// assert!(ibu.search_barcode(2)?.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn search_barcode(&self, barcode: u64) -> crate::Result<&[Record]> {
+        if !self.header.sorted() {
+            return Err(IbuError::NotSorted);
+        }
+        Ok(slice::search_barcode(&self.records, barcode))
+    }
+
+    /// Iterates over runs of consecutive records sharing a barcode.
+    ///
+    /// Yields `(barcode, records)` pairs in file order. For sorted data each
+    /// barcode is yielded exactly once; for unsorted data a barcode may appear
+    /// in several non-adjacent runs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Ibu, Record};
+    ///
+    /// let ibu = Ibu::new(
+    ///     Header::new(16, 12),
+    ///     vec![Record::new(1, 0, 0), Record::new(1, 1, 0), Record::new(2, 0, 0)],
+    /// );
+    ///
+    /// let groups: Vec<(u64, usize)> = ibu
+    ///     .group_by_barcode()
+    ///     .map(|(barcode, records)| (barcode, records.len()))
+    ///     .collect();
+    /// assert_eq!(groups, vec![(1, 2), (2, 1)]);
+    /// ```
+    pub fn group_by_barcode(&self) -> impl Iterator<Item = (u64, &[Record])> {
+        slice::group_by_barcode(&self.records)
+    }
+
+    /// Sorts the records and marks the header as sorted.
+    ///
+    /// Records are ordered by barcode, then UMI, then index. Any checksum and
+    /// record count in the header are cleared, since the checksum covers the
+    /// previous record order.
+    pub fn sort(&mut self) {
+        self.records.sort_unstable();
+        self.header.set_sorted();
+        self.header.clear_reserved();
+    }
+
+    /// Removes duplicate molecules, keeping one record per `(barcode, umi)`.
+    ///
+    /// Records are compared with [`Record::same_molecule`], ignoring the
+    /// index. The survivor of each molecule is its first record, which in
+    /// sorted data is the record with the smallest index. For exact-record
+    /// deduplication, which also compares indices, use
+    /// `ibu.records.dedup()` instead.
+    ///
+    /// If any record is removed, the header's record count and checksum are
+    /// cleared.
+    ///
+    /// # Returns
+    ///
+    /// The number of records removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::NotSorted`] if the header is not marked as sorted,
+    /// since duplicates are only guaranteed to be adjacent in sorted data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Ibu, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut ibu = Ibu::new(
+    ///     Header::new(16, 12),
+    ///     vec![Record::new(1, 7, 9), Record::new(1, 7, 2), Record::new(2, 7, 0)],
+    /// );
+    /// ibu.sort();
+    ///
+    /// assert_eq!(ibu.dedup_molecules()?, 1);
+    /// assert_eq!(ibu.records, vec![Record::new(1, 7, 2), Record::new(2, 7, 0)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dedup_molecules(&mut self) -> crate::Result<usize> {
+        if !self.header.sorted() {
+            return Err(IbuError::NotSorted);
+        }
+        let before = self.records.len();
+        self.records.dedup_by(|b, a| a.same_molecule(b));
+        if self.records.len() != before {
+            self.header.clear_reserved();
+        }
+        Ok(before - self.records.len())
+    }
+
+    /// Collapses duplicate molecules into one record per `(barcode, umi)`.
+    ///
+    /// Each run of records sharing a barcode and UMI becomes a single record
+    /// whose index is the number of records in the run (the read count of the
+    /// molecule). The original indices are discarded; use
+    /// [`dedup_molecules`](Self::dedup_molecules) to keep them instead.
+    ///
+    /// If the collection is already marked as collapsed, its indices are read
+    /// counts, and each run's counts are summed instead. Collapsing twice
+    /// therefore leaves the counts unchanged, and collapsed collections that
+    /// were concatenated and sorted combine their counts.
+    ///
+    /// The returned collection is sorted and marked as collapsed (see
+    /// [`Header::collapsed`]), and its header keeps the barcode and UMI
+    /// lengths but no other header metadata.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::NotSorted`] if the header is not marked as sorted,
+    /// since duplicates are only guaranteed to be adjacent in sorted data.
+    /// Call [`sort`](Self::sort) first. Returns [`IbuError::CountOverflow`]
+    /// if a summed count of collapsed input exceeds `u64::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Ibu, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut ibu = Ibu::new(
+    ///     Header::new(16, 12),
+    ///     vec![Record::new(1, 7, 9), Record::new(2, 7, 0), Record::new(1, 7, 2)],
+    /// );
+    /// ibu.sort();
+    ///
+    /// let collapsed = ibu.collapse_umis()?;
+    /// assert_eq!(collapsed.records, vec![Record::new(1, 7, 2), Record::new(2, 7, 1)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn collapse_umis(&self) -> crate::Result<Ibu> {
+        if !self.header.sorted() {
+            return Err(IbuError::NotSorted);
+        }
+        let collapsed = self.header.collapsed();
+        let records = self
+            .records
+            .chunk_by(|a, b| a.same_molecule(b))
+            .map(|run| {
+                let count = if collapsed {
+                    run.iter()
+                        .try_fold(0u64, |sum, record| sum.checked_add(record.index))
+                        .ok_or(IbuError::CountOverflow {
+                            barcode: run[0].barcode,
+                            umi: run[0].umi,
+                        })?
+                } else {
+                    run.len() as u64
+                };
+                Ok(Record::new(run[0].barcode, run[0].umi, count))
+            })
+            .collect::<crate::Result<_>>()?;
+        let mut header = Header::new(self.header.bc_len, self.header.umi_len);
+        header.set_sorted();
+        header.set_collapsed();
+        Ok(Ibu::new(header, records))
+    }
+
+    /// Returns whether the records are actually in sorted order.
+    ///
+    /// This scans all records and ignores the header's sorted flag, so it can be
+    /// used to check that the flag is trustworthy. It is the non-mutating
+    /// counterpart of [`sort`](Self::sort).
+    pub fn verify_sorted(&self) -> bool {
+        slice::is_sorted(&self.records)
+    }
+
+    /// Returns whether the records are actually in sorted order.
+    ///
+    /// Same as [`Ibu::verify_sorted`]: scans the records without modifying
+    /// them or consulting the header.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Ibu, Record};
+    ///
+    /// let mut ibu = Ibu::new(
+    ///     Header::new(16, 12),
+    ///     vec![Record::new(2, 0, 0), Record::new(1, 0, 0)],
+    /// );
+    /// assert!(!ibu.is_sorted());
+    ///
+    /// ibu.sort();
+    /// assert!(ibu.is_sorted());
+    /// ```
+    pub fn is_sorted(&self) -> bool {
+        self.verify_sorted()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unsorted() -> Ibu {
+        Ibu::new(
+            Header::new(16, 12),
+            vec![
+                Record::new(5, 0, 0),
+                Record::new(1, 1, 0),
+                Record::new(3, 0, 0),
+                Record::new(1, 0, 0),
+                Record::new(5, 0, 1),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let mut ibu = unsorted();
+        let bytes = ibu.write_bytes().unwrap();
+        assert_eq!(bytes.len(), 32 + 5 * 24);
+        assert_eq!(Ibu::from_bytes(&bytes).unwrap(), ibu);
+
+        ibu.sort();
+        let decoded = Ibu::from_bytes(&ibu.write_bytes().unwrap()).unwrap();
+        assert!(decoded.header.sorted());
+        assert_eq!(decoded, ibu);
+
+        assert!(Ibu::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Ibu::from_bytes(&bytes[..16]).is_err());
+    }
+
+    #[test]
+    fn test_path_roundtrip() {
+        let temp_file = "test_ibu_path_roundtrip.ibu";
+        let mut ibu = unsorted();
+        ibu.sort();
+        ibu.to_path(temp_file).unwrap();
+        assert_eq!(Ibu::from_path(temp_file).unwrap(), ibu);
+        std::fs::remove_file(temp_file).unwrap();
+
+        assert!(Ibu::from_path("does_not_exist.ibu").is_err());
+    }
+
+    #[test]
+    fn test_write_drops_stale_reserved() {
+        let temp_file = "test_ibu_stale_reserved.ibu";
+        let mut writer = Writer::new_with_checksum(
+            std::fs::File::create(temp_file).unwrap(),
+            Header::new(16, 12),
+        )
+        .unwrap();
+        writer.write_batch(&unsorted().records).unwrap();
+        writer.finish_with_count().unwrap();
+        drop(writer);
+
+        // Records added after loading are not covered by the stored metadata
+        let mut ibu = Ibu::from_path(temp_file).unwrap();
+        assert_eq!(ibu.header.record_count(), Some(5));
+        ibu.records.push(Record::new(9, 9, 9));
+        ibu.to_path(temp_file).unwrap();
+        let reader = crate::MmapReader::new_verified(temp_file).unwrap();
+        assert_eq!(reader.len(), 6);
+        assert_eq!(reader.header().record_count(), None);
+        let decoded = Ibu::from_bytes(&ibu.write_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.header.checksum(), None);
+
+        // Sorting invalidates the checksum over the old order
+        ibu.header.set_checksum(0xDEAD_BEEF);
+        ibu.sort();
+        assert_eq!(ibu.header.checksum(), None);
+
+        std::fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "niffler")]
+    fn test_from_path_compressed() {
+        let temp_file = "test_ibu_from_path_compressed.ibu.gz";
+        let ibu = unsorted();
+        let mut writer = Writer::from_path_auto(temp_file, ibu.header).unwrap();
+        writer.write_batch(&ibu.records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        assert_eq!(Ibu::from_path(temp_file).unwrap(), ibu);
+        std::fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_roundtrip() {
+        let mut ibu = unsorted();
+        ibu.sort();
+        ibu.header.set_checksum(0xDEAD_BEEF);
+        let mut json = Vec::new();
+        ibu.to_json_writer(&mut json).unwrap();
+        assert_eq!(Ibu::from_json_reader(json.as_slice()).unwrap(), ibu);
+
+        let text = String::from_utf8(json).unwrap();
+        assert!(text.contains("\"barcode\": 5"));
+
+        let mut invalid = ibu.clone();
+        invalid.header.bc_len = 0;
+        let mut json = Vec::new();
+        invalid.to_json_writer(&mut json).unwrap();
+        assert!(matches!(
+            Ibu::from_json_reader(json.as_slice()),
+            Err(IbuError::InvalidBarcodeLength(0))
+        ));
+        assert!(matches!(
+            Ibu::from_json_reader(&b"{\"header\": 1}"[..]),
+            Err(IbuError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut ibu = unsorted();
+        assert!(!ibu.header.sorted());
+        assert!(!ibu.verify_sorted());
+        assert!(!ibu.is_sorted());
+
+        ibu.sort();
+        assert!(ibu.header.sorted());
+        assert!(ibu.verify_sorted());
+        assert!(ibu.is_sorted());
+        assert_eq!(ibu.records[0], Record::new(1, 0, 0));
+        assert_eq!(ibu.records[4], Record::new(5, 0, 1));
+    }
+
+    #[test]
+    fn test_search_barcode_requires_sorted() {
+        let mut ibu = unsorted();
+        assert!(matches!(ibu.search_barcode(1), Err(IbuError::NotSorted)));
+
+        ibu.sort();
+        assert_eq!(
+            ibu.search_barcode(1).unwrap(),
+            &[Record::new(1, 0, 0), Record::new(1, 1, 0)]
+        );
+        assert!(ibu.search_barcode(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dedup_molecules() {
+        let mut ibu = unsorted();
+        assert!(matches!(ibu.dedup_molecules(), Err(IbuError::NotSorted)));
+
+        ibu.sort();
+        assert_eq!(ibu.dedup_molecules().unwrap(), 1);
+        assert_eq!(
+            ibu.records,
+            vec![
+                Record::new(1, 0, 0),
+                Record::new(1, 1, 0),
+                Record::new(3, 0, 0),
+                Record::new(5, 0, 0),
+            ]
+        );
+        assert_eq!(ibu.dedup_molecules().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_group_by_barcode() {
+        let mut ibu = unsorted();
+        // Unsorted: runs are only merged when adjacent
+        assert_eq!(ibu.group_by_barcode().count(), 5);
+
+        ibu.sort();
+        let groups: Vec<(u64, usize)> = ibu
+            .group_by_barcode()
+            .map(|(bc, run)| (bc, run.len()))
+            .collect();
+        assert_eq!(groups, vec![(1, 2), (3, 1), (5, 2)]);
+    }
+
+    #[test]
+    fn test_collapse_umis() {
+        let mut ibu = unsorted();
+        assert!(matches!(ibu.collapse_umis(), Err(IbuError::NotSorted)));
+
+        // Singletons and runs
+        ibu.records
+            .extend(std::iter::repeat_n(Record::new(3, 0, 9), 1000));
+        ibu.sort();
+        let collapsed = ibu.collapse_umis().unwrap();
+        assert!(collapsed.header.sorted());
+        assert!(collapsed.header.collapsed());
+        assert_eq!(
+            collapsed.records,
+            vec![
+                Record::new(1, 0, 1),
+                Record::new(1, 1, 1),
+                Record::new(3, 0, 1001),
+                Record::new(5, 0, 2),
+            ]
+        );
+
+        // Empty input
+        let mut empty = Ibu::new(Header::new(16, 12), Vec::new());
+        empty.sort();
+        assert!(empty.collapse_umis().unwrap().records.is_empty());
+    }
+
+    #[test]
+    fn test_collapse_umis_twice() {
+        let mut ibu = unsorted();
+        ibu.records
+            .extend(std::iter::repeat_n(Record::new(3, 0, 9), 1000));
+        ibu.sort();
+        let collapsed = ibu.collapse_umis().unwrap();
+
+        // Counts are kept rather than reset to one
+        let again = collapsed.collapse_umis().unwrap();
+        assert_eq!(again.records, collapsed.records);
+        assert!(again.header.collapsed());
+
+        // Collapsed duplicates sum their counts
+        let mut combined = collapsed.clone();
+        combined.records.extend_from_slice(&collapsed.records);
+        combined.sort();
+        let summed = combined.collapse_umis().unwrap();
+        let doubled: Vec<Record> = collapsed
+            .records
+            .iter()
+            .map(|r| Record::new(r.barcode, r.umi, 2 * r.index))
+            .collect();
+        assert_eq!(summed.records, doubled);
+
+        // Summed counts must fit in the index
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        header.set_collapsed();
+        let overflow = Ibu::new(header, vec![Record::new(1, 1, u64::MAX); 2]);
+        assert!(matches!(
+            overflow.collapse_umis(),
+            Err(IbuError::CountOverflow { barcode: 1, umi: 1 })
+        ));
+    }
+}
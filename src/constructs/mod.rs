@@ -1,5 +1,13 @@
+mod alphabet;
 mod header;
+#[cfg(feature = "std")]
+mod ibu;
 mod record;
 
-pub use header::{Header, HEADER_SIZE, MAGIC, VERSION};
-pub use record::{Record, RECORD_SIZE};
+#[cfg(feature = "std")]
+pub(crate) use alphabet::translate_sequence;
+pub use alphabet::Alphabet;
+pub use header::{Header, HeaderBuilder, HEADER_SIZE, MAGIC, V1_HEADER_SIZE, VERSION};
+#[cfg(feature = "std")]
+pub use ibu::Ibu;
+pub use record::{Record, RecordBuilder, RecordDisplay, RECORD_SIZE};
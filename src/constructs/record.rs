@@ -1,6 +1,18 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, str::FromStr};
+
 use bytemuck::{Pod, Zeroable};
 
-pub const RECORD_SIZE: usize = std::mem::size_of::<Record>();
+use crate::{
+    packed::{base_mask, pack, unpack},
+    Alphabet, Header, IbuError,
+};
+
+pub const RECORD_SIZE: usize = core::mem::size_of::<Record>();
 
 /// Binary format record for IBU files.
 ///
@@ -24,7 +36,11 @@ pub const RECORD_SIZE: usize = std::mem::size_of::<Record>();
 /// - G = 10
 /// - T = 11
 ///
-/// This allows up to 32 bases to be stored in a single u64 value.
+/// This allows up to 32 bases to be stored in a single u64 value. The first
+/// base occupies the most significant used bits, so `"ACGT"` is `0b00_01_10_11`.
+/// Use [`encode_barcode`](Record::encode_barcode) and
+/// [`decode_barcode`](Record::decode_barcode) (or the UMI equivalents) to
+/// convert between sequences and encoded values.
 ///
 /// # Ordering
 ///
@@ -56,8 +72,7 @@ pub const RECORD_SIZE: usize = std::mem::size_of::<Record>();
 /// assert_eq!(record, reconstructed);
 /// ```
 #[derive(Copy, Clone, Pod, Zeroable, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-#[cfg(feature = "serde")]
-#[derive(serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct Record {
     pub barcode: u64,
@@ -91,6 +106,21 @@ impl Record {
             index,
         }
     }
+    /// Returns a builder for constructing a record field by field.
+    ///
+    /// Fields that are not set default to zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Record;
+    ///
+    /// let record = Record::builder().barcode(0x1234).index(42).build();
+    /// assert_eq!(record, Record::new(0x1234, 0, 42));
+    /// ```
+    pub fn builder() -> RecordBuilder {
+        RecordBuilder::default()
+    }
     /// Returns the record as a byte slice.
     ///
     /// Uses zero-copy conversion via `bytemuck` to get a view of the record
@@ -130,6 +160,482 @@ impl Record {
     pub fn from_bytes(bytes: &[u8]) -> Self {
         *bytemuck::from_bytes(bytes)
     }
+    /// Returns a wrapper displaying the record with decoded sequences.
+    ///
+    /// Records do not store their sequence lengths, so plain
+    /// [`Display`](fmt::Display) prints the packed values in hex. The wrapper
+    /// takes the lengths from `header` and prints
+    /// `barcode=<bases> umi=<bases> index=<index>`, which
+    /// [`parse_with`](Self::parse_with) reads back.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Record};
+    ///
+    /// let record = Record::new(0b00_01_10_11, 0b11_00, 7);
+    /// assert_eq!(record.to_string(), "barcode=0x1b umi=0xc index=7");
+    ///
+    /// let header = Header::new(4, 2);
+    /// assert_eq!(
+    ///     record.display_with(&header).to_string(),
+    ///     "barcode=ACGT umi=TA index=7"
+    /// );
+    /// ```
+    pub fn display_with(&self, header: &Header) -> RecordDisplay {
+        RecordDisplay {
+            record: *self,
+            bc_len: header.bc_len,
+            umi_len: header.umi_len,
+        }
+    }
+
+    /// Parses a record from the form printed by
+    /// [`display_with`](Self::display_with).
+    ///
+    /// The barcode and UMI must have exactly the lengths declared in `header`.
+    /// Fields are separated by whitespace and must appear in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidRecord`] if a field is missing, out of order,
+    /// or malformed, or a sequence has the wrong length or an invalid base.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let header = Header::new(4, 2);
+    /// let record = Record::parse_with("barcode=ACGT umi=TA index=7", &header)?;
+    /// assert_eq!(record, Record::new(0b00_01_10_11, 0b11_00, 7));
+    ///
+    /// assert!(Record::parse_with("barcode=ACG umi=TA index=7", &header).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_with(s: &str, header: &Header) -> crate::Result<Record> {
+        let [barcode, umi, index] = fields(s)?;
+        let encode = |seq: &str, len: u32| {
+            if seq.len() != len as usize {
+                return Err(invalid_record(
+                    s,
+                    format!("expected {len} bases, found {}", seq.len()),
+                ));
+            }
+            pack(seq.as_bytes(), &Alphabet::canonical())
+                .map_err(|e| invalid_record(s, e.to_string()))
+        };
+        Ok(Record::new(
+            encode(barcode, header.bc_len)?,
+            encode(umi, header.umi_len)?,
+            parse_index(s, index)?,
+        ))
+    }
+
+    /// Returns a copy with the barcode and UMI masked to the header lengths.
+    ///
+    /// See [`Header::mask_barcode`] and [`Header::mask_umi`]. The index is
+    /// unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Record};
+    ///
+    /// let header = Header::new(2, 1);
+    /// let record = Record::new(0b11_01_10, 0b01_11, 7);
+    /// assert_eq!(record.masked(&header), Record::new(0b01_10, 0b11, 7));
+    /// ```
+    pub fn masked(&self, header: &Header) -> Record {
+        Record {
+            barcode: header.mask_barcode(self.barcode),
+            umi: header.mask_umi(self.umi),
+            index: self.index,
+        }
+    }
+
+    /// Returns whether two records come from the same molecule.
+    ///
+    /// Records are the same molecule if their barcode and UMI match; the index
+    /// is ignored. Compare with `==` for exact record equality.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Record;
+    ///
+    /// let a = Record::new(1, 2, 3);
+    /// let b = Record::new(1, 2, 4);
+    /// assert!(a.same_molecule(&b));
+    /// assert_ne!(a, b);
+    /// ```
+    pub fn same_molecule(&self, other: &Record) -> bool {
+        self.barcode == other.barcode && self.umi == other.umi
+    }
+    /// Encodes a barcode sequence with the 2-bit encoding.
+    ///
+    /// The first base is stored in the most significant used bits, so the
+    /// encoded values of equal-length sequences sort lexicographically.
+    ///
+    /// # Arguments
+    ///
+    /// * `seq` - Barcode of at most 32 uppercase `A`, `C`, `G` or `T` bases
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidBase`] if `seq` contains another character,
+    /// or [`IbuError::InvalidBarcodeLength`] if it is longer than 32 bases.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{IbuError, Record};
+    ///
+    /// assert_eq!(Record::encode_barcode("ACGT").unwrap(), 0b00_01_10_11);
+    /// assert!(matches!(
+    ///     Record::encode_barcode("ACNT"),
+    ///     Err(IbuError::InvalidBase { base: 'N' })
+    /// ));
+    /// ```
+    pub fn encode_barcode(seq: &str) -> crate::Result<u64> {
+        if seq.len() > 32 {
+            return Err(IbuError::InvalidBarcodeLength(seq.len() as u32));
+        }
+        pack(seq.as_bytes(), &Alphabet::canonical())
+    }
+    /// Encodes a barcode, tolerating lowercase and `N` bases.
+    ///
+    /// Like [`encode_barcode`](Record::encode_barcode), but bases are
+    /// uppercased first and `N` calls are encoded as `A`. See
+    /// [`encode_barcode_lossy_with`](Record::encode_barcode_lossy_with) to
+    /// choose another substitute base.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the encoded barcode and an ambiguity mask in which bit `i`
+    /// is set if base `i` (counting from the first base) was an `N`. Bits at
+    /// positions beyond the sequence length are always zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidBase`] for characters other than `ACGTN` in
+    /// either case, or [`IbuError::InvalidBarcodeLength`] if the sequence is
+    /// longer than 32 bases.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Record;
+    ///
+    /// let (barcode, mask) = Record::encode_barcode_lossy("acNt").unwrap();
+    /// assert_eq!(barcode, Record::encode_barcode("ACAT").unwrap());
+    /// assert_eq!(mask, 0b0100);
+    ///
+    /// // The strict encoder still rejects N
+    /// assert!(Record::encode_barcode("ACNT").is_err());
+    /// ```
+    pub fn encode_barcode_lossy(seq: &str) -> crate::Result<(u64, u32)> {
+        Self::encode_barcode_lossy_with(seq, b'A')
+    }
+    /// Encodes a barcode, substituting `fill` for `N` bases.
+    ///
+    /// See [`encode_barcode_lossy`](Record::encode_barcode_lossy).
+    ///
+    /// # Errors
+    ///
+    /// As for [`encode_barcode_lossy`](Record::encode_barcode_lossy), and
+    /// [`IbuError::InvalidBase`] if `fill` is not one of `A`, `C`, `G` or `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Record;
+    ///
+    /// let (barcode, mask) = Record::encode_barcode_lossy_with("NNGT", b'T').unwrap();
+    /// assert_eq!(barcode, Record::encode_barcode("TTGT").unwrap());
+    /// assert_eq!(mask, 0b0011);
+    /// ```
+    pub fn encode_barcode_lossy_with(seq: &str, fill: u8) -> crate::Result<(u64, u32)> {
+        if seq.len() > 32 {
+            return Err(IbuError::InvalidBarcodeLength(seq.len() as u32));
+        }
+        let alphabet = Alphabet::canonical();
+        let fill = fill.to_ascii_uppercase();
+        if alphabet.encode(fill).is_none() {
+            return Err(IbuError::InvalidBase { base: fill as char });
+        }
+
+        let mut mask = 0u32;
+        let bases: Vec<u8> = seq
+            .bytes()
+            .enumerate()
+            .map(|(i, base)| match base.to_ascii_uppercase() {
+                b'N' => {
+                    mask |= 1 << i;
+                    fill
+                }
+                base => base,
+            })
+            .collect();
+        let barcode = pack(&bases, &alphabet)?;
+        Ok((barcode, mask))
+    }
+    /// Decodes a 2-bit encoded barcode of `len` bases.
+    ///
+    /// The length is required because leading `A` bases encode as zero bits:
+    /// `"AC"` and `"C"` have the same value. Bits above the `len` bases are
+    /// ignored, and `len` is capped at 32.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Record;
+    ///
+    /// assert_eq!(Record::decode_barcode(0b01, 1), "C");
+    /// assert_eq!(Record::decode_barcode(0b01, 3), "AAC");
+    /// ```
+    pub fn decode_barcode(value: u64, len: u32) -> String {
+        decode(value, len)
+    }
+    /// Encodes a UMI sequence with the 2-bit encoding.
+    ///
+    /// See [`encode_barcode`](Record::encode_barcode).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidBase`] if `seq` contains a character other
+    /// than `A`, `C`, `G` or `T`, or [`IbuError::InvalidUmiLength`] if it is
+    /// longer than 32 bases.
+    pub fn encode_umi(seq: &str) -> crate::Result<u64> {
+        if seq.len() > 32 {
+            return Err(IbuError::InvalidUmiLength(seq.len() as u32));
+        }
+        pack(seq.as_bytes(), &Alphabet::canonical())
+    }
+    /// Decodes a 2-bit encoded UMI of `len` bases.
+    ///
+    /// See [`decode_barcode`](Record::decode_barcode).
+    pub fn decode_umi(value: u64, len: u32) -> String {
+        decode(value, len)
+    }
+    /// Returns the number of bases differing between two 2-bit encoded
+    /// barcodes of `len` bases.
+    ///
+    /// Each base is compared as a whole, so a base whose two bits both differ
+    /// (e.g. `A` = 00 vs `T` = 11) counts once. A plain popcount of the XOR
+    /// would count it twice. Bits above the `len` bases are ignored, and `len`
+    /// is capped at 32.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Record;
+    ///
+    /// let a = Record::encode_barcode("ACGT").unwrap();
+    /// let b = Record::encode_barcode("TCGA").unwrap();
+    /// assert_eq!(Record::barcode_hamming(a, b, 4), 2);
+    /// ```
+    pub fn barcode_hamming(a: u64, b: u64, len: u32) -> u32 {
+        let diff = (a ^ b) & base_mask(len);
+        // Fold each base's high bit onto its low bit
+        ((diff | (diff >> 1)) & 0x5555_5555_5555_5555).count_ones()
+    }
+
+    /// Returns the reverse complement of a 2-bit encoded sequence of `len`
+    /// bases.
+    ///
+    /// Complements every base (`A` <-> `T`, `C` <-> `G`, i.e. flipping both
+    /// bits of its code) and reverses the order of the `len` bases, for
+    /// barcodes read from the opposite strand. Bits above the `len` bases are
+    /// ignored and cleared in the result, and `len` is capped at 32.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Record;
+    ///
+    /// let seq = Record::encode_barcode("AACG").unwrap();
+    /// let rc = Record::reverse_complement(seq, 4);
+    /// assert_eq!(Record::decode_barcode(rc, 4), "CGTT");
+    /// ```
+    pub fn reverse_complement(value: u64, len: u32) -> u64 {
+        let len = len.min(32);
+        if len == 0 {
+            return 0;
+        }
+        let mut x = !value;
+        // Reverse the 32 bases of the word: swap adjacent bases, then
+        // adjacent base pairs, then the bytes
+        x = ((x >> 2) & 0x3333_3333_3333_3333) | ((x & 0x3333_3333_3333_3333) << 2);
+        x = ((x >> 4) & 0x0F0F_0F0F_0F0F_0F0F) | ((x & 0x0F0F_0F0F_0F0F_0F0F) << 4);
+        x.swap_bytes() >> (2 * (32 - len))
+    }
+
+    /// Returns this record with its barcode reverse complemented.
+    ///
+    /// See [`reverse_complement`](Record::reverse_complement). The UMI and
+    /// index are unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::Record;
+    ///
+    /// let record = Record::new(Record::encode_barcode("ACCT").unwrap(), 5, 9);
+    /// let rc = record.with_rc_barcode(4);
+    /// assert_eq!(Record::decode_barcode(rc.barcode, 4), "AGGT");
+    /// assert_eq!((rc.umi, rc.index), (5, 9));
+    /// ```
+    pub fn with_rc_barcode(self, len: u32) -> Record {
+        Record {
+            barcode: Record::reverse_complement(self.barcode, len),
+            ..self
+        }
+    }
+}
+
+/// Prints the packed values as `barcode=0x... umi=0x... index=...`.
+///
+/// Use [`Record::display_with`] to print decoded sequences instead.
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "barcode={:#x} umi={:#x} index={}",
+            self.barcode, self.umi, self.index
+        )
+    }
+}
+
+/// Parses the [`Display`](fmt::Display) form of a record.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::Record;
+///
+/// let record = Record::new(0x1234, 0x5678, 42);
+/// assert_eq!(record.to_string().parse::<Record>().unwrap(), record);
+/// assert!("barcode=0x1 umi=2 index=3".parse::<Record>().is_err());
+/// ```
+impl FromStr for Record {
+    type Err = IbuError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let [barcode, umi, index] = fields(s)?;
+        let parse_hex = |field: &str, value: &str| {
+            value
+                .strip_prefix("0x")
+                .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| invalid_record(s, format!("invalid hex {field} {value:?}")))
+        };
+        Ok(Record::new(
+            parse_hex("barcode", barcode)?,
+            parse_hex("umi", umi)?,
+            parse_index(s, index)?,
+        ))
+    }
+}
+
+/// Record displayed with decoded sequences.
+///
+/// Created by [`Record::display_with`].
+#[derive(Clone, Copy, Debug)]
+pub struct RecordDisplay {
+    /// Record to display
+    record: Record,
+
+    /// Barcode length in bases
+    bc_len: u32,
+
+    /// UMI length in bases
+    umi_len: u32,
+}
+
+impl fmt::Display for RecordDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "barcode={} umi={} index={}",
+            Record::decode_barcode(self.record.barcode, self.bc_len),
+            Record::decode_umi(self.record.umi, self.umi_len),
+            self.record.index
+        )
+    }
+}
+
+/// Splits the text form of a record into its barcode, UMI and index values.
+fn fields(s: &str) -> crate::Result<[&str; 3]> {
+    let mut tokens = s.split_whitespace();
+    let mut values = [""; 3];
+    for (value, key) in values.iter_mut().zip(["barcode", "umi", "index"]) {
+        *value = tokens
+            .next()
+            .and_then(|token| token.strip_prefix(key)?.strip_prefix('='))
+            .ok_or_else(|| invalid_record(s, format!("expected {key}=<value>")))?;
+    }
+    if tokens.next().is_some() {
+        return Err(invalid_record(s, "unexpected trailing text"));
+    }
+    Ok(values)
+}
+
+/// Parses the decimal index of a record.
+fn parse_index(s: &str, index: &str) -> crate::Result<u64> {
+    index
+        .parse()
+        .map_err(|_| invalid_record(s, format!("invalid index {index:?}")))
+}
+
+/// Creates an error for unparseable record text.
+fn invalid_record(text: &str, message: impl Into<String>) -> IbuError {
+    IbuError::InvalidRecord {
+        text: text.into(),
+        message: message.into(),
+    }
+}
+
+/// Decodes a packed sequence with the canonical alphabet.
+fn decode(value: u64, len: u32) -> String {
+    let bases = unpack(value, len, &Alphabet::canonical());
+    // Canonical bases are ASCII
+    String::from_utf8(bases).unwrap()
+}
+
+/// Builder for [`Record`].
+///
+/// Created by [`Record::builder`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordBuilder {
+    /// Record under construction
+    record: Record,
+}
+
+impl RecordBuilder {
+    /// Sets the packed barcode.
+    pub fn barcode(mut self, barcode: u64) -> Self {
+        self.record.barcode = barcode;
+        self
+    }
+
+    /// Sets the packed UMI.
+    pub fn umi(mut self, umi: u64) -> Self {
+        self.record.umi = umi;
+        self
+    }
+
+    /// Sets the index.
+    pub fn index(mut self, index: u64) -> Self {
+        self.record.index = index;
+        self
+    }
+
+    /// Builds the record.
+    pub fn build(self) -> Record {
+        self.record
+    }
 }
 
 #[cfg(test)]
@@ -145,12 +651,35 @@ mod tests {
         assert_eq!(record.index, 42);
     }
 
+    #[test]
+    fn test_record_builder() {
+        let record = Record::builder().barcode(1).umi(2).index(3).build();
+        assert_eq!(record, Record::new(1, 2, 3));
+        assert_eq!(Record::builder().build(), Record::default());
+    }
+
     #[test]
     fn test_record_size() {
         assert_eq!(RECORD_SIZE, 24);
         assert_eq!(std::mem::size_of::<Record>(), RECORD_SIZE);
     }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_roundtrip() {
+        let record = Record::new(1, 2, 3);
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(json, r#"{"barcode":1,"umi":2,"index":3}"#);
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
+
+        let header = crate::Header::new(16, 12);
+        let json = serde_json::to_string(&header).unwrap();
+        assert_eq!(
+            serde_json::from_str::<crate::Header>(&json).unwrap(),
+            header
+        );
+    }
+
     #[test]
     fn test_record_default() {
         let record = Record::default();
@@ -264,6 +793,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn test_record_derives() {
         let record1 = Record::new(1, 2, 3);
         let record2 = Record::new(1, 2, 3);
@@ -309,6 +839,14 @@ mod tests {
         assert_ne!(record2, record3);
     }
 
+    #[test]
+    fn test_same_molecule() {
+        let record = Record::new(1, 2, 3);
+        assert!(record.same_molecule(&Record::new(1, 2, 99)));
+        assert!(!record.same_molecule(&Record::new(1, 3, 3)));
+        assert!(!record.same_molecule(&Record::new(2, 2, 3)));
+    }
+
     #[test]
     fn test_large_values() {
         // Test with realistic genomic data values
@@ -319,4 +857,209 @@ mod tests {
         assert_eq!(record.umi, max_32_bases);
         assert_eq!(record.index, u64::MAX);
     }
+
+    #[test]
+    fn test_sequence_roundtrip() {
+        for seq in ["A", "ACGT", "TTTTGGGGCCCCAAAA", &"GATC".repeat(8)] {
+            let barcode = Record::encode_barcode(seq).unwrap();
+            assert_eq!(Record::decode_barcode(barcode, seq.len() as u32), seq);
+            let umi = Record::encode_umi(seq).unwrap();
+            assert_eq!(Record::decode_umi(umi, seq.len() as u32), seq);
+        }
+        assert_eq!(Record::encode_barcode("").unwrap(), 0);
+        assert_eq!(Record::decode_barcode(u64::MAX, 0), "");
+    }
+
+    #[test]
+    fn test_sequence_encoding_errors() {
+        let long = "A".repeat(33);
+        assert!(matches!(
+            Record::encode_barcode(&long),
+            Err(IbuError::InvalidBarcodeLength(33))
+        ));
+        assert!(matches!(
+            Record::encode_umi(&long),
+            Err(IbuError::InvalidUmiLength(33))
+        ));
+        assert!(matches!(
+            Record::encode_umi("acgt"),
+            Err(IbuError::InvalidBase { base: 'a' })
+        ));
+    }
+
+    #[test]
+    fn test_encode_barcode_lossy() {
+        let (barcode, mask) = Record::encode_barcode_lossy("NacgtN").unwrap();
+        assert_eq!(Record::decode_barcode(barcode, 6), "AACGTA");
+        assert_eq!(mask, 0b100001);
+
+        // Full-length sequence sets the top mask bit
+        let seq = format!("{}N", "C".repeat(31));
+        let (_, mask) = Record::encode_barcode_lossy(&seq).unwrap();
+        assert_eq!(mask, 1 << 31);
+
+        let (barcode, mask) = Record::encode_barcode_lossy_with("nG", b'g').unwrap();
+        assert_eq!(
+            (Record::decode_barcode(barcode, 2).as_str(), mask),
+            ("GG", 1)
+        );
+
+        assert!(matches!(
+            Record::encode_barcode_lossy("ACXT"),
+            Err(IbuError::InvalidBase { base: 'X' })
+        ));
+        assert!(matches!(
+            Record::encode_barcode_lossy_with("ACGT", b'N'),
+            Err(IbuError::InvalidBase { base: 'N' })
+        ));
+        assert!(matches!(
+            Record::encode_barcode_lossy(&"N".repeat(33)),
+            Err(IbuError::InvalidBarcodeLength(33))
+        ));
+    }
+
+    #[test]
+    fn test_record_display_parse() {
+        let record = Record::new(0xDEAD, 0, u64::MAX);
+        let text = record.to_string();
+        assert_eq!(text, format!("barcode=0xdead umi=0x0 index={}", u64::MAX));
+        assert_eq!(text.parse::<Record>().unwrap(), record);
+        assert_eq!(
+            "  barcode=0xFF\tumi=0x1  index=2 "
+                .parse::<Record>()
+                .unwrap(),
+            Record::new(0xFF, 1, 2)
+        );
+
+        let header = crate::Header::new(32, 3);
+        let record = Record::new(u64::MAX, 0b00_01_10, 5);
+        let text = record.display_with(&header).to_string();
+        assert_eq!(text, format!("barcode={} umi=ACG index=5", "T".repeat(32)));
+        assert_eq!(Record::parse_with(&text, &header).unwrap(), record);
+
+        for bad in [
+            "",
+            "barcode=0x1 umi=0x2",
+            "umi=0x2 barcode=0x1 index=3",
+            "barcode=0x1 umi=0x2 index=3 extra",
+            "barcode=1 umi=0x2 index=3",
+            "barcode=0xZZ umi=0x2 index=3",
+            "barcode=0x1 umi=0x2 index=-3",
+            "barcode = 0x1 umi=0x2 index=3",
+        ] {
+            assert!(
+                matches!(bad.parse::<Record>(), Err(IbuError::InvalidRecord { .. })),
+                "{bad:?} should not parse"
+            );
+        }
+        let header = crate::Header::new(2, 1);
+        for bad in [
+            "barcode=ACG umi=T index=0",
+            "barcode=AC umi= index=0",
+            "barcode=AN umi=T index=0",
+            "barcode=ac umi=T index=0",
+        ] {
+            assert!(
+                matches!(
+                    Record::parse_with(bad, &header),
+                    Err(IbuError::InvalidRecord { .. })
+                ),
+                "{bad:?} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn test_masked() {
+        let header = Header::new(4, 2);
+        let record = Record::new(u64::MAX, 0b1_00_11, 9);
+        assert_eq!(record.masked(&header), Record::new(0xFF, 0b11, 9));
+
+        // Unmasked high bits sort far from their masked value
+        let mut records = [Record::new(0x1_00, 0, 0), Record::new(0x01, 0, 0)];
+        records.sort();
+        assert_eq!(records[0].barcode, 0x01);
+        let mut masked = records.map(|r| r.masked(&header));
+        masked.sort();
+        assert_eq!(masked[0].barcode, 0x00);
+
+        let full = Header::new(32, 32);
+        assert_eq!(record.masked(&full), record);
+    }
+
+    #[test]
+    fn test_reverse_complement() {
+        let rc = |seq: &str| {
+            let len = seq.len() as u32;
+            let value = Record::encode_barcode(seq).unwrap();
+            Record::decode_barcode(Record::reverse_complement(value, len), len)
+        };
+        assert_eq!(rc("A"), "T");
+        assert_eq!(rc("G"), "C");
+        assert_eq!(rc("ACG"), "CGT");
+        assert_eq!(rc("AACGTTG"), "CAACGTT");
+        assert_eq!(rc("ACGT"), "ACGT");
+
+        // Full width, with distinct bases at both ends
+        let seq = "GATTACA".repeat(4) + "CCGT";
+        let expected: String = seq
+            .chars()
+            .rev()
+            .map(|base| match base {
+                'A' => 'T',
+                'C' => 'G',
+                'G' => 'C',
+                _ => 'A',
+            })
+            .collect();
+        assert_eq!(rc(&seq), expected);
+        assert_eq!(Record::reverse_complement(0, 32), u64::MAX);
+
+        // Involution, bits above the length cleared, length capped
+        for len in 0..=32 {
+            let value = 0x0123_4567_89AB_CDEF & base_mask(len);
+            let once = Record::reverse_complement(value, len);
+            assert_eq!(once & !base_mask(len), 0);
+            assert_eq!(Record::reverse_complement(once, len), value);
+        }
+        assert_eq!(Record::reverse_complement(0b11_00, 1), 0b11);
+        assert_eq!(Record::reverse_complement(0, 0), 0);
+        assert_eq!(
+            Record::reverse_complement(7, 40),
+            Record::reverse_complement(7, 32)
+        );
+
+        let record = Record::new(Record::encode_barcode("AAC").unwrap(), 3, 4);
+        assert_eq!(
+            record.with_rc_barcode(3),
+            Record::new(Record::encode_barcode("GTT").unwrap(), 3, 4)
+        );
+    }
+
+    #[test]
+    fn test_barcode_hamming() {
+        let encode = |seq| Record::encode_barcode(seq).unwrap();
+        // A <-> T flips both bits of the base but is one substitution
+        assert_eq!((encode("A") ^ encode("T")).count_ones(), 2);
+        assert_eq!(Record::barcode_hamming(encode("A"), encode("T"), 1), 1);
+        assert_eq!(Record::barcode_hamming(encode("C"), encode("G"), 1), 1);
+        assert_eq!(
+            Record::barcode_hamming(encode("AAAA"), encode("TTTT"), 4),
+            4
+        );
+        assert_eq!(
+            Record::barcode_hamming(encode("ACGT"), encode("ACGT"), 4),
+            0
+        );
+        assert_eq!(
+            Record::barcode_hamming(encode("ACGT"), encode("ACTT"), 4),
+            1
+        );
+
+        // Bits above the length are ignored
+        assert_eq!(Record::barcode_hamming(0b11_00, 0b00_00, 1), 0);
+        assert_eq!(Record::barcode_hamming(u64::MAX, 0, 32), 32);
+        assert_eq!(Record::barcode_hamming(u64::MAX, 0, 40), 32);
+        assert_eq!(Record::barcode_hamming(u64::MAX, 0, 0), 0);
+    }
 }
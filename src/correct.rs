@@ -0,0 +1,271 @@
+//! Whitelist barcode correction.
+//!
+//! Single-cell protocols draw barcodes from a known whitelist, so a barcode
+//! missing from it is usually a sequencing error. [`BarcodeCorrector`] keeps
+//! records whose barcode is on the whitelist, corrects barcodes one
+//! substitution away from exactly one whitelisted barcode, and drops the rest.
+
+use std::{
+    collections::HashSet,
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use crate::{hamming_neighbors, ParallelProcessor, Record, Writer};
+
+/// Outcome counts of barcode correction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CorrectionStats {
+    /// Records whose barcode was already on the whitelist
+    pub exact: u64,
+
+    /// Records corrected to the single whitelisted barcode at Hamming distance 1
+    pub corrected: u64,
+
+    /// Records dropped because no (or more than one) whitelisted barcode was
+    /// within Hamming distance 1
+    pub uncorrectable: u64,
+}
+
+impl CorrectionStats {
+    /// Returns the number of records processed.
+    pub fn total(&self) -> u64 {
+        self.exact + self.corrected + self.uncorrectable
+    }
+
+    /// Adds the counts of `other` to these counts.
+    fn merge(&mut self, other: &Self) {
+        self.exact += other.exact;
+        self.corrected += other.corrected;
+        self.uncorrectable += other.uncorrectable;
+    }
+}
+
+/// Output writer and counts merged across threads.
+struct SharedOutput<W: Write> {
+    /// Destination of kept records
+    writer: Writer<W>,
+
+    /// Counts of all completed batches
+    stats: CorrectionStats,
+}
+
+/// Parallel processor correcting barcodes against a whitelist.
+///
+/// For each record:
+/// - A barcode on the whitelist is written unchanged (`exact`)
+/// - Otherwise, all `3 * bc_len` single-base substitutions of the barcode are
+///   looked up. If exactly one is whitelisted, the record is written with that
+///   barcode (`corrected`)
+/// - Otherwise the barcode is unknown or ambiguous and the record is dropped
+///   (`uncorrectable`)
+///
+/// Substitutions replace one 2-bit base code at a time, so barcodes must be
+/// packed with the first base in the highest bits, as produced by the rest of
+/// this crate.
+///
+/// Each thread buffers its kept records and counts, and appends them to the
+/// shared writer when a batch completes. Under parallel processing batches
+/// are written in completion order, so the output is not sorted even if the
+/// input was. Call [`finish`](Self::finish) once processing is done to flush
+/// the writer.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{BarcodeCorrector, MmapReader, ParallelReader, Writer};
+/// use std::collections::HashSet;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = MmapReader::new("data.ibu")?;
+/// let whitelist: HashSet<u64> = [0x1234, 0x5678].into_iter().collect();
+/// let writer = Writer::from_path("corrected.ibu", reader.header())?;
+///
+/// let corrector = BarcodeCorrector::new(whitelist, reader.header().bc_len, writer);
+/// reader.process_parallel(corrector.clone(), 0)?;
+/// corrector.finish()?;
+///
+/// let stats = corrector.stats();
+/// println!("{} exact, {} corrected", stats.exact, stats.corrected);
+/// # Ok(())
+/// # }
+/// ```
+pub struct BarcodeCorrector<W: Write> {
+    /// Known barcodes
+    whitelist: Arc<HashSet<u64>>,
+
+    /// Barcode length in bases
+    bc_len: u32,
+
+    /// Records kept since the last completed batch
+    local: Vec<Record>,
+
+    /// Counts since the last completed batch
+    local_stats: CorrectionStats,
+
+    /// Writer and counts merged from all completed batches
+    shared: Arc<Mutex<SharedOutput<W>>>,
+}
+
+// Manual impl: clones share the writer, so `W` itself need not be `Clone`
+impl<W: Write> Clone for BarcodeCorrector<W> {
+    fn clone(&self) -> Self {
+        Self {
+            whitelist: Arc::clone(&self.whitelist),
+            bc_len: self.bc_len,
+            local: self.local.clone(),
+            local_stats: self.local_stats,
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<W: Write> BarcodeCorrector<W> {
+    /// Creates a corrector writing kept records to `writer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `whitelist` - Packed barcodes considered valid
+    /// * `bc_len` - Barcode length in bases
+    /// * `writer` - Destination of exact and corrected records
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bc_len` is greater than 32.
+    pub fn new(whitelist: HashSet<u64>, bc_len: u32, writer: Writer<W>) -> Self {
+        assert!(bc_len <= 32, "barcode length {bc_len} exceeds 32 bases");
+        Self {
+            whitelist: Arc::new(whitelist),
+            bc_len,
+            local: Vec::new(),
+            local_stats: CorrectionStats::default(),
+            shared: Arc::new(Mutex::new(SharedOutput {
+                writer,
+                stats: CorrectionStats::default(),
+            })),
+        }
+    }
+
+    /// Returns the corrected barcode, or `None` if it cannot be corrected.
+    ///
+    /// A whitelisted barcode is returned unchanged.
+    pub fn correct(&self, barcode: u64) -> Option<u64> {
+        if self.whitelist.contains(&barcode) {
+            return Some(barcode);
+        }
+        let mut matches = hamming_neighbors(barcode, self.bc_len)
+            .filter(|neighbor| self.whitelist.contains(neighbor));
+        match (matches.next(), matches.next()) {
+            (Some(neighbor), None) => Some(neighbor),
+            _ => None,
+        }
+    }
+
+    /// Returns the counts over all completed batches.
+    pub fn stats(&self) -> CorrectionStats {
+        self.shared.lock().unwrap().stats
+    }
+
+    /// Flushes the shared writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if flushing the writer fails.
+    pub fn finish(&self) -> crate::Result<()> {
+        self.shared.lock().unwrap().writer.finish()
+    }
+}
+
+impl<W: Write + Send> ParallelProcessor for BarcodeCorrector<W> {
+    fn process_record(&mut self, mut record: Record) -> crate::Result<()> {
+        match self.correct(record.barcode) {
+            Some(barcode) if barcode == record.barcode => self.local_stats.exact += 1,
+            Some(barcode) => {
+                record.barcode = barcode;
+                self.local_stats.corrected += 1;
+            }
+            None => {
+                self.local_stats.uncorrectable += 1;
+                return Ok(());
+            }
+        }
+        self.local.push(record);
+        Ok(())
+    }
+
+    fn on_batch_complete(&mut self) -> crate::Result<()> {
+        let mut shared = self.shared.lock().unwrap();
+        shared.writer.write_batch(&self.local)?;
+        shared.stats.merge(&std::mem::take(&mut self.local_stats));
+        self.local.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, MmapReader, ParallelReader, Reader};
+    use std::{fs, io::Cursor};
+
+    #[test]
+    fn test_correct() {
+        // "AAAA" and "AACC"
+        let whitelist: HashSet<u64> = [0b00_00_00_00, 0b00_00_01_01].into_iter().collect();
+        let corrector = BarcodeCorrector::new(whitelist, 4, Writer::new_headless(Vec::new()));
+
+        assert_eq!(corrector.correct(0b00_00_00_00), Some(0b00_00_00_00));
+        // "TAAA" differs from "AAAA" in the first base only
+        assert_eq!(corrector.correct(0b11_00_00_00), Some(0b00_00_00_00));
+        // "AACA" is one substitution from both
+        assert_eq!(corrector.correct(0b00_00_01_00), None);
+        // "TTTT" is far from both
+        assert_eq!(corrector.correct(0b11_11_11_11), None);
+    }
+
+    #[test]
+    fn test_barcode_corrector_parallel() {
+        let temp_file = "test_barcode_corrector.ibu";
+        let whitelist: HashSet<u64> = (0..16u64).map(|i| i << 8).collect();
+        // Exact, one substitution in the last base, and far from any barcode
+        let records: Vec<Record> = (0..3000u64)
+            .map(|i| match i % 3 {
+                0 => Record::new((i % 16) << 8, i, i),
+                1 => Record::new(((i % 16) << 8) | 0b10, i, i),
+                _ => Record::new(0xFF, i, i),
+            })
+            .collect();
+        let mut writer =
+            Writer::new(fs::File::create(temp_file).unwrap(), Header::new(8, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        let output = Writer::new(Vec::new(), reader.header()).unwrap();
+        let corrector = BarcodeCorrector::new(whitelist.clone(), 8, output);
+        reader.process_parallel(corrector.clone(), 2).unwrap();
+        corrector.finish().unwrap();
+
+        let stats = corrector.stats();
+        assert_eq!(
+            stats,
+            CorrectionStats {
+                exact: 1000,
+                corrected: 1000,
+                uncorrectable: 1000,
+            }
+        );
+        assert_eq!(stats.total(), 3000);
+
+        let bytes = corrector.shared.lock().unwrap().writer.get_ref().clone();
+        let kept = Reader::new(Cursor::new(bytes))
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(kept.len(), 2000);
+        assert!(kept.iter().all(|r| whitelist.contains(&r.barcode)));
+
+        fs::remove_file(temp_file).unwrap();
+    }
+}
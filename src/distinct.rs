@@ -0,0 +1,245 @@
+//! Distinct barcode counting.
+//!
+//! [`DistinctBarcodeCounter`] counts distinct barcodes exactly by keeping a
+//! set of every barcode seen, while [`ApproxBarcodeCounter`] estimates the
+//! count with a HyperLogLog sketch in fixed memory. Both are parallel
+//! processors: each thread collects into its own state and merges it into the
+//! shared result when a batch completes.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use crate::{entropy::mix, ParallelProcessor, Record};
+
+/// Parallel processor counting distinct barcodes exactly.
+///
+/// Memory grows with the number of distinct barcodes (roughly 16 bytes per
+/// barcode per thread, plus the shared set). For very high cardinality use
+/// [`ApproxBarcodeCounter`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{DistinctBarcodeCounter, MmapReader, ParallelReader};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = MmapReader::new("data.ibu")?;
+/// let counter = DistinctBarcodeCounter::new();
+/// reader.process_parallel(counter.clone(), 0)?;
+/// println!("{} distinct barcodes", counter.count());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DistinctBarcodeCounter {
+    /// Barcodes seen since the last completed batch
+    local: HashSet<u64>,
+
+    /// Barcodes merged from all completed batches
+    shared: Arc<Mutex<HashSet<u64>>>,
+}
+
+impl DistinctBarcodeCounter {
+    /// Creates a counter with no observations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of distinct barcodes over all completed batches.
+    pub fn count(&self) -> usize {
+        self.shared.lock().unwrap().len()
+    }
+}
+
+impl ParallelProcessor for DistinctBarcodeCounter {
+    fn process_record(&mut self, record: Record) -> crate::Result<()> {
+        self.local.insert(record.barcode);
+        Ok(())
+    }
+
+    fn on_batch_complete(&mut self) -> crate::Result<()> {
+        self.shared.lock().unwrap().extend(self.local.drain());
+        Ok(())
+    }
+}
+
+/// HyperLogLog sketch over 64-bit hashes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HyperLogLog {
+    /// Number of hash bits used to select a register
+    precision: u32,
+
+    /// Maximum leading-zero rank observed per register
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new(precision: u32) -> Self {
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    fn insert(&mut self, value: u64) {
+        let hash = mix(value);
+        let register = (hash >> (64 - self.precision)) as usize;
+        // Rank of the first set bit in the remaining bits, capped if all are zero
+        let rank = ((hash << self.precision).leading_zeros() + 1).min(65 - self.precision);
+        let slot = &mut self.registers[register];
+        *slot = (*slot).max(rank as u8);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for (slot, &other) in self.registers.iter_mut().zip(&other.registers) {
+            *slot = (*slot).max(other);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.registers.fill(0);
+    }
+
+    fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-(rank as i32)))
+            .sum();
+        let raw = alpha * m * m / sum;
+
+        // Linear counting is more accurate while many registers are empty
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+}
+
+/// Parallel processor estimating the number of distinct barcodes.
+///
+/// Uses a HyperLogLog sketch with `2^precision` one-byte registers, so memory
+/// is fixed regardless of cardinality. The relative standard error of the
+/// estimate is about `1.04 / sqrt(2^precision)`: roughly 1.6% at precision
+/// 12 (4 KiB) and 0.4% at precision 16 (64 KiB).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{ApproxBarcodeCounter, MmapReader, ParallelReader};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = MmapReader::new("data.ibu")?;
+/// let counter = ApproxBarcodeCounter::new(14);
+/// reader.process_parallel(counter.clone(), 0)?;
+/// println!("~{} distinct barcodes", counter.count());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ApproxBarcodeCounter {
+    /// Sketch of barcodes seen since the last completed batch
+    local: HyperLogLog,
+
+    /// Sketch merged from all completed batches
+    shared: Arc<Mutex<HyperLogLog>>,
+}
+
+impl ApproxBarcodeCounter {
+    /// Creates a counter with `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not between 4 and 18.
+    pub fn new(precision: u32) -> Self {
+        assert!(
+            (4..=18).contains(&precision),
+            "HyperLogLog precision must be between 4 and 18"
+        );
+        let sketch = HyperLogLog::new(precision);
+        Self {
+            local: sketch.clone(),
+            shared: Arc::new(Mutex::new(sketch)),
+        }
+    }
+
+    /// Returns the estimated number of distinct barcodes over all completed
+    /// batches.
+    pub fn count(&self) -> u64 {
+        self.shared.lock().unwrap().estimate().round() as u64
+    }
+}
+
+impl ParallelProcessor for ApproxBarcodeCounter {
+    fn process_record(&mut self, record: Record) -> crate::Result<()> {
+        self.local.insert(record.barcode);
+        Ok(())
+    }
+
+    fn on_batch_complete(&mut self) -> crate::Result<()> {
+        self.shared.lock().unwrap().merge(&self.local);
+        self.local.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, MmapReader, ParallelReader, Writer};
+    use std::fs;
+
+    #[test]
+    fn test_hyperloglog_estimate() {
+        for (precision, distinct) in [(4, 10u64), (12, 1000), (14, 200_000)] {
+            let mut sketch = HyperLogLog::new(precision);
+            for i in 0..distinct {
+                sketch.insert(i);
+                sketch.insert(i);
+            }
+            let error = (sketch.estimate() - distinct as f64).abs() / distinct as f64;
+            let tolerance = 4.0 * 1.04 / ((1u64 << precision) as f64).sqrt();
+            assert!(error < tolerance, "p={precision}: error {error}");
+        }
+        assert_eq!(HyperLogLog::new(10).estimate(), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "between 4 and 18")]
+    fn test_approx_counter_precision() {
+        ApproxBarcodeCounter::new(3);
+    }
+
+    #[test]
+    fn test_barcode_counters() {
+        let temp_file = "test_distinct_barcode_counters.ibu";
+        let records: Vec<Record> = (0..100_000).map(|i| Record::new(i % 3000, i, 0)).collect();
+        let mut writer =
+            Writer::new(fs::File::create(temp_file).unwrap(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        let exact = DistinctBarcodeCounter::new();
+        reader.process_parallel(exact.clone(), 2).unwrap();
+        assert_eq!(exact.count(), 3000);
+
+        let approx = ApproxBarcodeCounter::new(14);
+        reader.process_parallel(approx.clone(), 2).unwrap();
+        assert!(approx.count().abs_diff(3000) < 100);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+}
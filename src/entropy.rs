@@ -0,0 +1,356 @@
+//! Shannon entropy of the barcode distribution.
+//!
+//! Barcode entropy is a library-complexity metric: a library dominated by a
+//! few barcodes has low entropy, while one spread evenly over many barcodes
+//! has high entropy. [`BarcodeEntropy`] accumulates barcode counts as records
+//! stream by and reports the entropy on demand; [`EntropyProcessor`] does the
+//! same under parallel processing.
+//!
+//! Entropy is reported in bits (base-2 logarithm) over the empirical
+//! distribution `p(b) = count(b) / total`. Divide by `log2(distinct)` to
+//! normalize it to `[0, 1]`, or multiply by `ln(2)` to convert it to nats.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{ParallelProcessor, Record};
+
+/// Per-barcode counts backing an entropy estimate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Counts {
+    /// One counter per distinct barcode
+    Exact(HashMap<u64, u64>),
+
+    /// Fixed number of counters, indexed by barcode hash
+    Buckets(Vec<u64>),
+}
+
+/// Streaming estimator of barcode Shannon entropy.
+///
+/// # Exact and Sketched Counting
+///
+/// By default every distinct barcode gets its own counter, so the entropy is
+/// exact but memory grows with the number of distinct barcodes. For very high
+/// cardinality (e.g. unfiltered droplet data),
+/// [`with_buckets`](Self::with_buckets) instead hashes barcodes into a fixed
+/// number of counters. Barcodes sharing a bucket are merged, so the sketched
+/// entropy is a lower bound on the exact value: it is close when the number
+/// of buckets is much larger than the number of distinct barcodes, and can
+/// never exceed `log2(buckets)` bits.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::BarcodeEntropy;
+///
+/// let mut entropy = BarcodeEntropy::new();
+/// for barcode in [1, 2, 3, 4] {
+///     entropy.observe(barcode);
+/// }
+/// // Four equally frequent barcodes carry two bits
+/// assert!((entropy.entropy() - 2.0).abs() < 1e-12);
+///
+/// entropy.observe(1);
+/// assert!(entropy.entropy() < 2.0);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BarcodeEntropy {
+    /// Barcode counts
+    counts: Counts,
+
+    /// Number of barcodes observed
+    total: u64,
+}
+
+impl Default for BarcodeEntropy {
+    fn default() -> Self {
+        Self {
+            counts: Counts::Exact(HashMap::new()),
+            total: 0,
+        }
+    }
+}
+
+impl BarcodeEntropy {
+    /// Creates an exact estimator with no observations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a sketched estimator using a fixed number of counters.
+    ///
+    /// Memory use is `8 * buckets` bytes regardless of cardinality. See
+    /// [Exact and Sketched Counting](Self#exact-and-sketched-counting).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is zero.
+    pub fn with_buckets(buckets: usize) -> Self {
+        assert!(buckets > 0, "entropy sketch needs at least one bucket");
+        Self {
+            counts: Counts::Buckets(vec![0; buckets]),
+            total: 0,
+        }
+    }
+
+    /// Records one occurrence of `barcode`.
+    pub fn observe(&mut self, barcode: u64) {
+        self.observe_n(barcode, 1);
+    }
+
+    /// Records `n` occurrences of `barcode`.
+    pub fn observe_n(&mut self, barcode: u64, n: u64) {
+        match &mut self.counts {
+            Counts::Exact(map) => *map.entry(barcode).or_default() += n,
+            Counts::Buckets(buckets) => {
+                let bucket = (mix(barcode) % buckets.len() as u64) as usize;
+                buckets[bucket] += n;
+            }
+        }
+        self.total += n;
+    }
+
+    /// Adds the observations of `other` to this estimator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the estimators do not use the same counting mode (exact, or
+    /// sketched with the same number of buckets).
+    pub fn merge(&mut self, other: &Self) {
+        match (&mut self.counts, &other.counts) {
+            (Counts::Exact(map), Counts::Exact(other_map)) => {
+                for (&barcode, &count) in other_map {
+                    *map.entry(barcode).or_default() += count;
+                }
+            }
+            (Counts::Buckets(buckets), Counts::Buckets(other_buckets))
+                if buckets.len() == other_buckets.len() =>
+            {
+                for (count, other_count) in buckets.iter_mut().zip(other_buckets) {
+                    *count += other_count;
+                }
+            }
+            _ => panic!("cannot merge entropy estimators with different counting modes"),
+        }
+        self.total += other.total;
+    }
+
+    /// Returns the number of barcodes observed.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the number of distinct barcodes observed.
+    ///
+    /// For sketched estimators this counts occupied buckets, which is a lower
+    /// bound on the number of distinct barcodes.
+    pub fn distinct(&self) -> usize {
+        match &self.counts {
+            Counts::Exact(map) => map.len(),
+            Counts::Buckets(buckets) => buckets.iter().filter(|&&c| c > 0).count(),
+        }
+    }
+
+    /// Returns the Shannon entropy of the observed barcodes, in bits.
+    ///
+    /// Returns 0 if nothing has been observed.
+    pub fn entropy(&self) -> f64 {
+        match &self.counts {
+            Counts::Exact(map) => entropy_of(map.values().copied(), self.total),
+            Counts::Buckets(buckets) => entropy_of(buckets.iter().copied(), self.total),
+        }
+    }
+
+    /// Clears all observations, keeping the counting mode.
+    fn clear(&mut self) {
+        match &mut self.counts {
+            Counts::Exact(map) => map.clear(),
+            Counts::Buckets(buckets) => buckets.fill(0),
+        }
+        self.total = 0;
+    }
+}
+
+/// Parallel processor accumulating barcode entropy across threads.
+///
+/// Each thread counts into its own [`BarcodeEntropy`] and merges it into the
+/// shared estimate when a batch completes, so [`entropy`](Self::entropy)
+/// reflects every completed batch and can be polled while processing runs.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{EntropyProcessor, MmapReader, ParallelReader};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = MmapReader::new("data.ibu")?;
+/// let processor = EntropyProcessor::with_buckets(1 << 24);
+/// reader.process_parallel(processor.clone(), 0)?;
+/// println!("Barcode entropy: {:.3} bits", processor.entropy());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EntropyProcessor {
+    /// Counts since the last completed batch
+    local: BarcodeEntropy,
+
+    /// Counts merged from all completed batches
+    shared: Arc<Mutex<BarcodeEntropy>>,
+}
+
+impl EntropyProcessor {
+    /// Creates a processor with exact counting.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a processor with sketched counting.
+    ///
+    /// See [`BarcodeEntropy::with_buckets`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is zero.
+    pub fn with_buckets(buckets: usize) -> Self {
+        let estimator = BarcodeEntropy::with_buckets(buckets);
+        Self {
+            local: estimator.clone(),
+            shared: Arc::new(Mutex::new(estimator)),
+        }
+    }
+
+    /// Returns the entropy over all completed batches, in bits.
+    pub fn entropy(&self) -> f64 {
+        self.shared.lock().unwrap().entropy()
+    }
+
+    /// Returns a copy of the merged estimator.
+    pub fn estimate(&self) -> BarcodeEntropy {
+        self.shared.lock().unwrap().clone()
+    }
+}
+
+impl ParallelProcessor for EntropyProcessor {
+    fn process_record(&mut self, record: Record) -> crate::Result<()> {
+        self.local.observe(record.barcode);
+        Ok(())
+    }
+
+    fn on_batch_complete(&mut self) -> crate::Result<()> {
+        self.shared.lock().unwrap().merge(&self.local);
+        self.local.clear();
+        Ok(())
+    }
+}
+
+/// Computes `log2(total) - sum(c * log2(c)) / total` over nonzero counts.
+///
+/// This form avoids computing many tiny probabilities and their logarithms.
+fn entropy_of(counts: impl Iterator<Item = u64>, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let total = total as f64;
+    let weighted: f64 = counts
+        .filter(|&c| c > 0)
+        .map(|c| {
+            let c = c as f64;
+            c * c.log2()
+        })
+        .sum();
+    // Clamp rounding error for single-barcode distributions
+    (total.log2() - weighted / total).max(0.0)
+}
+
+/// Scrambles a barcode so that similar barcodes land in different buckets.
+pub(crate) fn mix(value: u64) -> u64 {
+    // SplitMix64 finalizer
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, MmapReader, ParallelReader, Writer};
+    use std::fs;
+
+    #[test]
+    fn test_barcode_entropy_exact() {
+        let mut entropy = BarcodeEntropy::new();
+        assert_eq!(entropy.entropy(), 0.0);
+
+        entropy.observe_n(7, 100);
+        assert_eq!(entropy.entropy(), 0.0);
+
+        // p = (1/2, 1/4, 1/4) -> 1.5 bits
+        let mut entropy = BarcodeEntropy::new();
+        entropy.observe_n(1, 2);
+        entropy.observe(2);
+        entropy.observe(3);
+        assert!((entropy.entropy() - 1.5).abs() < 1e-12);
+        assert_eq!((entropy.total(), entropy.distinct()), (4, 3));
+    }
+
+    #[test]
+    fn test_barcode_entropy_merge() {
+        let mut a = BarcodeEntropy::new();
+        let mut b = BarcodeEntropy::new();
+        let mut whole = BarcodeEntropy::new();
+        for i in 0..1000u64 {
+            let barcode = i % 37;
+            if i % 2 == 0 { &mut a } else { &mut b }.observe(barcode);
+            whole.observe(barcode);
+        }
+        a.merge(&b);
+        assert_eq!(a, whole);
+    }
+
+    #[test]
+    #[should_panic(expected = "different counting modes")]
+    fn test_barcode_entropy_merge_mismatch() {
+        BarcodeEntropy::new().merge(&BarcodeEntropy::with_buckets(8));
+    }
+
+    #[test]
+    fn test_barcode_entropy_buckets() {
+        let mut exact = BarcodeEntropy::new();
+        let mut sketch = BarcodeEntropy::with_buckets(1 << 16);
+        let mut tiny = BarcodeEntropy::with_buckets(4);
+        for i in 0..10_000u64 {
+            let barcode = (i * i) % 1000;
+            exact.observe(barcode);
+            sketch.observe(barcode);
+            tiny.observe(barcode);
+        }
+        // Sketches never exceed the exact value, and are close with many buckets
+        assert!(sketch.entropy() <= exact.entropy() + 1e-9);
+        assert!(exact.entropy() - sketch.entropy() < 0.05);
+        assert!(tiny.entropy() <= 2.0 + 1e-12);
+        assert_eq!(sketch.total(), 10_000);
+    }
+
+    #[test]
+    fn test_entropy_processor() {
+        let temp_file = "test_entropy_processor.ibu";
+        let records: Vec<Record> = (0..51_200).map(|i| Record::new(i % 256, 0, 0)).collect();
+        let mut writer =
+            Writer::new(fs::File::create(temp_file).unwrap(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        let processor = EntropyProcessor::new();
+        reader.process_parallel(processor.clone(), 2).unwrap();
+        assert!((processor.entropy() - 8.0).abs() < 1e-9);
+        assert_eq!(processor.estimate().total(), 51_200);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+}
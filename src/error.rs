@@ -3,9 +3,12 @@
 //! This module defines all error types that can occur during IBU file operations,
 //! including I/O errors, format validation errors, and processing errors.
 
-use std::error::Error as StdError;
+use alloc::{boxed::Box, string::String};
+use core::error::Error as StdError;
 use thiserror::Error;
 
+use crate::Record;
+
 /// A specialized `Result` type for IBU operations.
 ///
 /// This type is used throughout the IBU library for any operation that can fail.
@@ -22,7 +25,7 @@ use thiserror::Error;
 ///     Ok(header)
 /// }
 /// ```
-pub type Result<T> = std::result::Result<T, IbuError>;
+pub type Result<T> = core::result::Result<T, IbuError>;
 
 /// Error types for IBU operations.
 ///
@@ -59,6 +62,7 @@ pub enum IbuError {
     ///
     /// This wraps standard I/O errors that can occur when reading from or
     /// writing to files, network streams, or other I/O sources.
+    #[cfg(feature = "std")]
     #[error("I/O error")]
     Io(#[from] std::io::Error),
 
@@ -66,9 +70,26 @@ pub enum IbuError {
     ///
     /// This occurs when there are problems with compressed file formats
     /// like gzip or zstd when the `niffler` feature is enabled.
+    #[cfg(feature = "niffler")]
     #[error("Niffler error")]
     Niffler(#[from] niffler::Error),
 
+    /// JSON serialization error.
+    ///
+    /// This occurs when reading or writing a manifest fails to (de)serialize,
+    /// e.g. because the file is not a valid manifest.
+    #[cfg(feature = "serde")]
+    #[error("JSON error")]
+    Json(#[from] serde_json::Error),
+
+    /// HDF5 error.
+    ///
+    /// This occurs when a `molecule_info.h5` file cannot be opened or lacks
+    /// an expected dataset when the `hdf5` feature is enabled.
+    #[cfg(feature = "hdf5")]
+    #[error("HDF5 error")]
+    Hdf5(#[from] hdf5::Error),
+
     /// Invalid magic number in file header.
     ///
     /// The file doesn't start with the expected IBU magic number (0x21554249).
@@ -104,6 +125,125 @@ pub enum IbuError {
     #[error("Invalid UMI length: {0} (must be 1-32)")]
     InvalidUmiLength(u32),
 
+    /// Headers of files being combined disagree on barcode or UMI length.
+    ///
+    /// This occurs when records from multiple files are read or written as a
+    /// single stream, and a file's `(bc_len, umi_len)` differs from the first.
+    #[error("Header mismatch, expected (bc_len, umi_len) = {expected:?}, found {actual:?}")]
+    HeaderMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+
+    /// Operation requires sorted records but the header is not marked as sorted.
+    ///
+    /// Searches and other order-dependent operations rely on the sorted flag
+    /// in the header. Sort the records (and set the flag) before retrying.
+    #[error("Records are not marked as sorted")]
+    NotSorted,
+
+    /// Record written out of sorted order.
+    ///
+    /// This occurs when [`Writer::write_batch_checked`](crate::Writer::write_batch_checked)
+    /// receives a record that sorts before the record preceding it.
+    #[error("Records out of order: {curr:?} follows {prev:?}")]
+    OutOfOrder { prev: Record, curr: Record },
+
+    /// Record index has no entry in a remapping table.
+    ///
+    /// This occurs when remapping indices with a policy that requires every
+    /// index to be present in the table.
+    #[error("Index {0} is missing from the remapping table")]
+    MissingIndex(u64),
+
+    /// Record index decreases within a barcode run.
+    ///
+    /// This occurs when verifying that records are index-sorted within each
+    /// barcode, and the record at `pos` has a smaller index than the record
+    /// before it despite sharing its barcode.
+    #[error("Index regression within barcode at record {pos}")]
+    IndexRegression { pos: usize },
+
+    /// Destination buffers do not match the number of records.
+    ///
+    /// This occurs when copying records into caller-provided buffers whose
+    /// combined length differs from the number of records in the source.
+    #[error("Buffer size mismatch, expected {expected} records, found {actual}")]
+    BufferSizeMismatch { expected: usize, actual: usize },
+
+    /// Record index overflowed when an offset was applied.
+    ///
+    /// This occurs when reading with an index offset under a policy that
+    /// rejects overflow, and `index + offset` exceeds `u64::MAX`.
+    #[error("Index {index} overflows when offset by {offset}")]
+    IndexOverflow { index: u64, offset: u64 },
+
+    /// Summed read count of a molecule overflowed.
+    ///
+    /// This occurs when collapsing UMIs adds up the read counts of the
+    /// records sharing a barcode and UMI, and the total exceeds `u64::MAX`.
+    #[error("Read count of molecule (barcode {barcode:#x}, UMI {umi:#x}) exceeds u64::MAX")]
+    CountOverflow { barcode: u64, umi: u64 },
+
+    /// Packed sequence has nonzero bits beyond its declared length.
+    ///
+    /// This occurs during strict decoding if a barcode or UMI value does not
+    /// fit in the number of bases declared in the header, or when writing
+    /// with validation enabled if a UMI does not. This usually means the
+    /// producer did not zero-extend (mask) its values.
+    #[error("Record {pos} has a {field} value ({value:#x}) exceeding {len} bases")]
+    ValueOutOfRange {
+        pos: usize,
+        field: &'static str,
+        value: u64,
+        len: u32,
+    },
+
+    /// Barcode has nonzero bits beyond the header's barcode length.
+    ///
+    /// This occurs when writing with validation enabled if a barcode does
+    /// not fit in `bc_len` bases, i.e. `value >> (2 * bc_len)` is nonzero.
+    #[error("Barcode value ({value:#x}) exceeds {bc_len} bases")]
+    BarcodeOverflow { value: u64, bc_len: u32 },
+
+    /// Sequence contains a character other than a nucleotide.
+    ///
+    /// This occurs when encoding a sequence with a base outside the alphabet,
+    /// such as `N` or a lowercase base.
+    #[error("Invalid base {base:?} in sequence")]
+    InvalidBase { base: char },
+
+    /// Alphabet is not a permutation of `ACGT`.
+    ///
+    /// Each of the four 2-bit codes must map to a distinct uppercase
+    /// nucleotide.
+    #[error("Invalid alphabet {0:?} (must be a permutation of ACGT)")]
+    InvalidAlphabet([u8; 4]),
+
+    /// Header reserved bytes are nonzero but no flag claims them.
+    ///
+    /// This occurs on strict reads when a producer wrote into the reserved
+    /// bytes without setting a flag (such as the checksum flag) that gives
+    /// them a meaning.
+    #[error("Unexpected nonzero reserved bytes {0:?} (not claimed by any flag)")]
+    UnexpectedReserved([u8; 8]),
+
+    /// Operation requires an uncompressed input.
+    ///
+    /// This occurs when an operation that needs to seek within the file, such
+    /// as reading the last record directly, is given a compressed file.
+    /// Decompress the file first, or use the streaming `Reader`.
+    #[error("Compressed input is not supported for this operation (requires seeking)")]
+    CompressedInput,
+
+    /// Stored record count does not match the records in the file.
+    ///
+    /// This occurs when a header carries a nonzero record count but the
+    /// file holds a different number of records, e.g. because it was
+    /// truncated or appended to after the count was written.
+    #[error("Record count mismatch, expected ({expected}), found ({actual})")]
+    RecordCountMismatch { expected: u64, actual: u64 },
+
     /// File data size is not a multiple of the record size.
     ///
     /// This indicates the file is corrupted or was not written properly,
@@ -111,6 +251,30 @@ pub enum IbuError {
     #[error("Invalid map size - not a multiple of record size")]
     InvalidMapSize,
 
+    /// Seek table of a seekable zstd file is missing or malformed.
+    ///
+    /// This occurs when opening a file that was not written by
+    /// `Writer::to_seekable_zstd`, or whose footer was truncated or corrupted.
+    #[error("Invalid seek table: {0}")]
+    InvalidSeekTable(&'static str),
+
+    /// Line of a text table could not be parsed into a record.
+    ///
+    /// This occurs when importing records from text, for example when a line
+    /// has the wrong number of columns, a sequence of the wrong length or with
+    /// an invalid base, or an index that is not an integer. Lines are numbered
+    /// from 1.
+    #[error("Invalid line {line}: {message}")]
+    InvalidLine { line: usize, message: String },
+
+    /// Text could not be parsed as a record.
+    ///
+    /// This occurs when parsing a record from its
+    /// [`Display`](core::fmt::Display) form, e.g. with a missing field, a
+    /// malformed number, or a sequence of the wrong length.
+    #[error("Invalid record {text:?}: {message}")]
+    InvalidRecord { text: String, message: String },
+
     /// Array index is out of bounds.
     ///
     /// This occurs when trying to access records beyond the end of the file
@@ -118,6 +282,14 @@ pub enum IbuError {
     #[error("Invalid index ({idx}) - Must be less than {max}")]
     InvalidIndex { idx: usize, max: usize },
 
+    /// Record checksum stored in the header does not match the file contents.
+    ///
+    /// This occurs when checksum verification is requested on a file whose
+    /// header carries a checksum, and the record bytes hash to a different value.
+    /// It indicates the records were modified or corrupted after writing.
+    #[error("Checksum mismatch, expected ({expected:#x}), found ({actual:#x})")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+
     /// Error occurred during parallel processing.
     ///
     /// This wraps errors that occur in user-defined parallel processors,
@@ -127,6 +299,71 @@ pub enum IbuError {
     Process(Box<dyn StdError + Send + Sync>),
 }
 
+impl IbuError {
+    /// Returns a reference to the wrapped processor error if it is of type `E`.
+    ///
+    /// Returns `None` for variants other than [`IbuError::Process`], or if the
+    /// wrapped error has a different type.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{IbuError, IntoIbuError};
+    /// use std::fmt;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum DomainError {
+    ///     UnknownBarcode(u64),
+    /// }
+    ///
+    /// impl fmt::Display for DomainError {
+    ///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    ///         write!(f, "{:?}", self)
+    ///     }
+    /// }
+    ///
+    /// impl std::error::Error for DomainError {}
+    ///
+    /// let err = DomainError::UnknownBarcode(42).into_ibu_error();
+    /// match err.downcast_process_ref::<DomainError>() {
+    ///     Some(DomainError::UnknownBarcode(barcode)) => assert_eq!(*barcode, 42),
+    ///     None => unreachable!(),
+    /// }
+    /// assert!(err.downcast_process_ref::<std::fmt::Error>().is_none());
+    /// ```
+    pub fn downcast_process_ref<E: StdError + 'static>(&self) -> Option<&E> {
+        match self {
+            IbuError::Process(inner) => inner.downcast_ref::<E>(),
+            _ => None,
+        }
+    }
+
+    /// Extracts the wrapped processor error if it is of type `E`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `self` unchanged for variants other than [`IbuError::Process`],
+    /// or if the wrapped error has a different type.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{IbuError, IntoIbuError};
+    ///
+    /// let err = std::fmt::Error.into_ibu_error();
+    /// let err = err.downcast_process::<std::io::Error>().unwrap_err();
+    /// assert_eq!(err.downcast_process::<std::fmt::Error>().unwrap(), std::fmt::Error);
+    /// ```
+    pub fn downcast_process<E: StdError + 'static>(self) -> core::result::Result<E, IbuError> {
+        match self {
+            IbuError::Process(inner) => {
+                inner.downcast::<E>().map(|e| *e).map_err(IbuError::Process)
+            }
+            other => Err(other),
+        }
+    }
+}
+
 /// Trait for converting errors into `IbuError::Process` variants.
 ///
 /// This trait provides a convenient way to convert custom error types
@@ -169,7 +406,7 @@ pub trait IntoIbuError {
 /// can be automatically converted to `IbuError::Process`.
 impl<E> IntoIbuError for E
 where
-    E: std::error::Error + Send + Sync + 'static,
+    E: StdError + Send + Sync + 'static,
 {
     fn into_ibu_error(self) -> IbuError {
         IbuError::Process(self.into())
@@ -229,17 +466,155 @@ mod tests {
         assert!(display.contains("0"));
         assert!(display.contains("1-32"));
 
+        // Test HeaderMismatch
+        let err = IbuError::HeaderMismatch {
+            expected: (16, 12),
+            actual: (16, 10),
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("(16, 12)"));
+        assert!(display.contains("(16, 10)"));
+
+        // Test NotSorted
+        let err = IbuError::NotSorted;
+        let display = format!("{}", err);
+        assert!(display.contains("sorted"));
+
+        // Test OutOfOrder
+        let err = IbuError::OutOfOrder {
+            prev: Record::new(2, 0, 0),
+            curr: Record::new(1, 0, 0),
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("out of order"));
+        assert!(display.contains("barcode: 2"));
+
+        // Test MissingIndex
+        let err = IbuError::MissingIndex(42);
+        let display = format!("{}", err);
+        assert!(display.contains("42"));
+
+        // Test IndexRegression
+        let err = IbuError::IndexRegression { pos: 17 };
+        let display = format!("{}", err);
+        assert!(display.contains("17"));
+
+        // Test BufferSizeMismatch
+        let err = IbuError::BufferSizeMismatch {
+            expected: 10,
+            actual: 9,
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("expected 10 records, found 9"));
+
+        // Test IndexOverflow
+        let err = IbuError::IndexOverflow {
+            index: u64::MAX,
+            offset: 1,
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("overflows"));
+
+        // Test CountOverflow
+        let err = IbuError::CountOverflow {
+            barcode: 0xAB,
+            umi: 0xCD,
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("barcode 0xab, UMI 0xcd"));
+        assert!(display.contains("exceeds u64::MAX"));
+
+        // Test ValueOutOfRange
+        let err = IbuError::ValueOutOfRange {
+            pos: 3,
+            field: "barcode",
+            value: 0x100,
+            len: 4,
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("barcode"));
+        assert!(display.contains("4 bases"));
+
+        // Test BarcodeOverflow
+        let err = IbuError::BarcodeOverflow {
+            value: 0x100,
+            bc_len: 4,
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("0x100"));
+        assert!(display.contains("4 bases"));
+
+        // Test InvalidBase
+        let err = IbuError::InvalidBase { base: 'N' };
+        let display = format!("{}", err);
+        assert!(display.contains("'N'"));
+
+        // Test InvalidAlphabet
+        let err = IbuError::InvalidAlphabet(*b"AACG");
+        let display = format!("{}", err);
+        assert!(display.contains("permutation of ACGT"));
+
+        // Test UnexpectedReserved
+        let err = IbuError::UnexpectedReserved([0, 0, 0, 1, 0, 0, 0, 0]);
+        let display = format!("{}", err);
+        assert!(display.contains("reserved"));
+
+        // Test CompressedInput
+        let err = IbuError::CompressedInput;
+        let display = format!("{}", err);
+        assert!(display.contains("Compressed"));
+
+        // Test RecordCountMismatch
+        let err = IbuError::RecordCountMismatch {
+            expected: 10,
+            actual: 9,
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("expected (10)"));
+
         // Test InvalidMapSize
         let err = IbuError::InvalidMapSize;
         let display = format!("{}", err);
         assert!(display.contains("not a multiple"));
 
+        // Test InvalidSeekTable
+        let err = IbuError::InvalidSeekTable("bad magic number");
+        assert_eq!(format!("{}", err), "Invalid seek table: bad magic number");
+
+        // Test InvalidLine
+        let err = IbuError::InvalidLine {
+            line: 3,
+            message: "expected 3 columns".to_string(),
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("line 3"));
+        assert!(display.contains("expected 3 columns"));
+
+        // Test InvalidRecord
+        let err = IbuError::InvalidRecord {
+            text: "barcode=0x1".to_string(),
+            message: "missing umi".to_string(),
+        };
+        assert_eq!(
+            format!("{}", err),
+            "Invalid record \"barcode=0x1\": missing umi"
+        );
+
         // Test InvalidIndex
         let err = IbuError::InvalidIndex { idx: 100, max: 50 };
         let display = format!("{}", err);
         assert!(display.contains("100"));
         assert!(display.contains("50"));
 
+        // Test ChecksumMismatch
+        let err = IbuError::ChecksumMismatch {
+            expected: 0xABC,
+            actual: 0xDEF,
+        };
+        let display = format!("{}", err);
+        assert!(display.contains("0xabc"));
+        assert!(display.contains("0xdef"));
+
         // Test Process error
         let custom_err = CustomError("test error".to_string());
         let err = IbuError::Process(custom_err.into());
@@ -258,6 +633,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_io_error_conversion() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "File not found");
         let ibu_err: IbuError = io_err.into();
@@ -312,6 +688,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_error_source_chain() {
         let io_err = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Access denied");
         let ibu_err = IbuError::Io(io_err);
@@ -367,4 +744,30 @@ mod tests {
         assert!(display.contains("Thread 3 error"));
         assert!(display.contains("Processing failed"));
     }
+
+    #[test]
+    fn test_downcast_process() {
+        let err = CustomError("bad".to_string()).into_ibu_error();
+        assert_eq!(err.downcast_process_ref::<CustomError>().unwrap().0, "bad");
+        assert!(err.downcast_process_ref::<std::io::Error>().is_none());
+
+        // A failed downcast returns the original error
+        let err = err.downcast_process::<std::io::Error>().unwrap_err();
+        assert_eq!(err.downcast_process::<CustomError>().unwrap().0, "bad");
+
+        // Other variants never downcast
+        let err = IbuError::NotSorted;
+        assert!(err.downcast_process_ref::<CustomError>().is_none());
+        assert!(matches!(
+            err.downcast_process::<CustomError>(),
+            Err(IbuError::NotSorted)
+        ));
+        #[cfg(feature = "std")]
+        {
+            let io = std::io::Error::other("io");
+            assert!(IbuError::from(io)
+                .downcast_process_ref::<std::io::Error>()
+                .is_none());
+        }
+    }
 }
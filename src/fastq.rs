@@ -0,0 +1,300 @@
+//! Conversion of FASTQ reads to IBU records.
+//!
+//! Barcodes and UMIs usually sit at fixed positions of a read (e.g. the first
+//! 16 bases of read 1 for the barcode and the next 12 for the UMI). This module
+//! slices those regions out of every FASTQ read, packs them with the canonical
+//! alphabet, and writes one record per read.
+
+use std::{
+    io::{BufRead, Write},
+    ops::Range,
+};
+
+use crate::{packed::pack, Alphabet, Header, IbuError, Record, Writer};
+
+/// Counts of reads seen while ingesting a FASTQ stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FastqStats {
+    /// Reads parsed from the stream
+    pub reads: u64,
+
+    /// Records written to the output
+    pub written: u64,
+
+    /// Reads skipped because their barcode or UMI contained `N`
+    pub skipped_n: u64,
+}
+
+/// Configuration for converting FASTQ reads to records.
+///
+/// The barcode and UMI are the bases of each read's sequence in `bc_range`
+/// and `umi_range`. Every read is assigned its 0-based position in the stream
+/// as its index, so indices increase monotonically and skipped reads leave
+/// gaps; the index of a record always identifies its source read.
+///
+/// Bases are packed with the canonical `ACGT` alphabet, case-insensitively. By
+/// default reads with an `N` in the barcode or UMI are skipped and counted in
+/// [`FastqStats::skipped_n`]. With [`skip_n(false)`](Self::skip_n) they are an
+/// error instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{FastqIngest, Header, Writer};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let fastq = "@r0\nACGTTTGG\n+\nIIIIIIII\n@r1\nANGTTTGG\n+\nIIIIIIII\n";
+/// let mut writer = Writer::new(Vec::new(), Header::new(4, 3))?;
+///
+/// let stats = FastqIngest::new(0..4, 4..7).ingest(fastq.as_bytes(), &mut writer)?;
+/// assert_eq!((stats.written, stats.skipped_n), (1, 1));
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FastqIngest {
+    /// Positions of the barcode within the read sequence
+    bc_range: Range<usize>,
+
+    /// Positions of the UMI within the read sequence
+    umi_range: Range<usize>,
+
+    /// Skip reads with `N` in the barcode or UMI instead of failing
+    skip_n: bool,
+}
+
+impl FastqIngest {
+    /// Creates a configuration extracting the barcode and UMI at fixed ranges.
+    ///
+    /// # Arguments
+    ///
+    /// * `bc_range` - Positions of the barcode within each read sequence
+    /// * `umi_range` - Positions of the UMI within each read sequence
+    pub fn new(bc_range: Range<usize>, umi_range: Range<usize>) -> Self {
+        Self {
+            bc_range,
+            umi_range,
+            skip_n: true,
+        }
+    }
+
+    /// Sets whether reads with `N` in the barcode or UMI are skipped (default)
+    /// or rejected with an error.
+    pub fn skip_n(mut self, skip_n: bool) -> Self {
+        self.skip_n = skip_n;
+        self
+    }
+
+    /// Converts every read of a FASTQ stream and writes the records to `output`.
+    ///
+    /// Records are written in read order. `output` is not finished, so more
+    /// records can follow; call [`Writer::finish`] when done.
+    ///
+    /// # Returns
+    ///
+    /// Counts of parsed, written, and skipped reads.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidBarcodeLength`] or
+    /// [`IbuError::InvalidUmiLength`] if a range is empty or longer than 32
+    /// bases. Returns [`IbuError::InvalidLine`] with the 1-based line number
+    /// for a malformed or truncated FASTQ record, a sequence too short for the
+    /// ranges, a base outside `ACGTN`, or an `N` when skipping is disabled.
+    /// Also returns any error from reading the stream or writing the records.
+    pub fn ingest<R: BufRead, W: Write>(
+        &self,
+        reader: R,
+        output: &mut Writer<W>,
+    ) -> crate::Result<FastqStats> {
+        Header::new(self.bc_range.len() as u32, self.umi_range.len() as u32).validate()?;
+        let alphabet = Alphabet::canonical();
+        let mut stats = FastqStats::default();
+        let mut lines = reader.lines();
+        let mut line_number = 0;
+        let mut next_line = || {
+            line_number += 1;
+            lines.next().transpose().map(|line| (line_number, line))
+        };
+
+        loop {
+            let (id_line, id) = next_line()?;
+            let Some(id) = id else {
+                break;
+            };
+            // Tolerate trailing blank lines
+            if id.trim().is_empty() {
+                continue;
+            }
+            if !id.starts_with('@') {
+                return Err(invalid_line(
+                    id_line,
+                    "expected a read header starting with '@'",
+                ));
+            }
+            let mut field = |expected: &str| -> crate::Result<(usize, String)> {
+                match next_line()? {
+                    (n, Some(line)) => Ok((n, line)),
+                    (n, None) => Err(invalid_line(
+                        n,
+                        format!("truncated read, expected {expected}"),
+                    )),
+                }
+            };
+            let (seq_line, seq) = field("a sequence")?;
+            let (sep_line, sep) = field("a '+' separator")?;
+            let (qual_line, qual) = field("a quality line")?;
+            if !sep.starts_with('+') {
+                return Err(invalid_line(sep_line, "expected a '+' separator"));
+            }
+            let seq = seq.trim_end_matches('\r').as_bytes();
+            if qual.trim_end_matches('\r').len() != seq.len() {
+                return Err(invalid_line(
+                    qual_line,
+                    "quality length does not match the sequence",
+                ));
+            }
+
+            let index = stats.reads;
+            stats.reads += 1;
+            let (Some(barcode), Some(umi)) = (
+                seq.get(self.bc_range.clone()),
+                seq.get(self.umi_range.clone()),
+            ) else {
+                return Err(invalid_line(
+                    seq_line,
+                    format!("sequence of {} bases is too short", seq.len()),
+                ));
+            };
+            if self.skip_n && (has_n(barcode) || has_n(umi)) {
+                stats.skipped_n += 1;
+                continue;
+            }
+            let encode = |region: &[u8]| {
+                pack(&region.to_ascii_uppercase(), &alphabet)
+                    .map_err(|e| invalid_line(seq_line, e.to_string()))
+            };
+            output.write_record(&Record::new(encode(barcode)?, encode(umi)?, index))?;
+            stats.written += 1;
+        }
+        Ok(stats)
+    }
+}
+
+/// Converts the reads of a FASTQ stream to records, skipping reads with `N`.
+///
+/// Shorthand for [`FastqIngest::new(bc_range, umi_range).ingest(reader, output)`](FastqIngest::ingest);
+/// see [`FastqIngest`] for details and for rejecting `N` instead.
+///
+/// # Arguments
+///
+/// * `reader` - Source of the FASTQ text
+/// * `bc_range` - Positions of the barcode within each read sequence
+/// * `umi_range` - Positions of the UMI within each read sequence
+/// * `output` - Writer receiving the records
+///
+/// # Errors
+///
+/// Returns the errors of [`FastqIngest::ingest`].
+pub fn ingest_fastq<R: BufRead, W: Write>(
+    reader: R,
+    bc_range: Range<usize>,
+    umi_range: Range<usize>,
+    output: &mut Writer<W>,
+) -> crate::Result<FastqStats> {
+    FastqIngest::new(bc_range, umi_range).ingest(reader, output)
+}
+
+/// Returns whether a region contains an ambiguous base.
+fn has_n(region: &[u8]) -> bool {
+    region.iter().any(|base| base.eq_ignore_ascii_case(&b'N'))
+}
+
+/// Creates an error for a malformed line.
+fn invalid_line(line: usize, message: impl Into<String>) -> IbuError {
+    IbuError::InvalidLine {
+        line,
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reader;
+    use std::io::Cursor;
+
+    fn records(writer: Writer<Vec<u8>>) -> Vec<Record> {
+        Reader::new(Cursor::new(writer.into_inner()))
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_ingest_fastq() {
+        let fastq = "@r0\nACGTTAxx\n+\nIIIIIIII\n\
+                     @r1\nANGTTAxx\n+r1\nIIIIIIII\n\
+                     @r2\r\ntttaCCxx\r\n+\r\nIIIIIIII\r\n\n";
+        let mut writer = Writer::new(Vec::new(), Header::new(4, 2)).unwrap();
+        let stats = ingest_fastq(fastq.as_bytes(), 0..4, 4..6, &mut writer).unwrap();
+        assert_eq!(
+            stats,
+            FastqStats {
+                reads: 3,
+                written: 2,
+                skipped_n: 1,
+            }
+        );
+        writer.finish().unwrap();
+        // The skipped read keeps its index
+        assert_eq!(
+            records(writer),
+            [
+                Record::new(0b00_01_10_11, 0b11_00, 0),
+                Record::new(0b11_11_11_00, 0b01_01, 2),
+            ]
+        );
+
+        // The UMI may precede the barcode
+        let mut writer = Writer::new(Vec::new(), Header::new(2, 2)).unwrap();
+        FastqIngest::new(2..4, 0..2)
+            .ingest("@r\nACGT\n+\nIIII\n".as_bytes(), &mut writer)
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(records(writer), [Record::new(0b10_11, 0b00_01, 0)]);
+    }
+
+    #[test]
+    fn test_ingest_fastq_errors() {
+        let line_of = |ingest: FastqIngest, fastq: &str| {
+            let mut writer = Writer::new_headless(Vec::new());
+            match ingest.ingest(fastq.as_bytes(), &mut writer) {
+                Err(IbuError::InvalidLine { line, .. }) => line,
+                other => panic!("unexpected result {other:?}"),
+            }
+        };
+        let ingest = FastqIngest::new(0..2, 2..4);
+
+        // N rejected when skipping is disabled
+        let fastq = "@r0\nACGT\n+\nIIII\n@r1\nACNT\n+\nIIII\n";
+        assert_eq!(line_of(ingest.clone().skip_n(false), fastq), 6);
+        assert_eq!(line_of(ingest.clone(), "@r\nACXT\n+\nIIII\n"), 2);
+        assert_eq!(line_of(ingest.clone(), "@r\nACG\n+\nIII\n"), 2);
+        assert_eq!(line_of(ingest.clone(), "r\nACGT\n+\nIIII\n"), 1);
+        assert_eq!(line_of(ingest.clone(), "@r\nACGT\n-\nIIII\n"), 3);
+        assert_eq!(line_of(ingest.clone(), "@r\nACGT\n+\nIII\n"), 4);
+        assert_eq!(line_of(ingest.clone(), "@r\nACGT\n"), 3);
+
+        let mut writer = Writer::new_headless(Vec::new());
+        assert!(matches!(
+            ingest_fastq("".as_bytes(), 0..0, 0..4, &mut writer),
+            Err(IbuError::InvalidBarcodeLength(0))
+        ));
+        assert!(matches!(
+            ingest_fastq("".as_bytes(), 0..4, 4..40, &mut writer),
+            Err(IbuError::InvalidUmiLength(36))
+        ));
+    }
+}
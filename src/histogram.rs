@@ -0,0 +1,210 @@
+//! Histogram of record index values.
+//!
+//! [`IndexHistogram`] bins the `index` field of every record into equal-width
+//! buckets over a fixed range under parallel processing, counting values
+//! outside the range separately.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::{ParallelProcessor, Record};
+
+/// Bucket counts merged across threads.
+#[derive(Debug)]
+struct SharedCounts {
+    /// Count per bucket
+    buckets: Vec<AtomicU64>,
+
+    /// Count of indices below the range
+    underflow: AtomicU64,
+
+    /// Count of indices above the range
+    overflow: AtomicU64,
+}
+
+/// Parallel processor binning record indices into a histogram.
+///
+/// The inclusive range `[min, max]` is split into `num_buckets` buckets of
+/// equal width (some one value wider than others if the range does not
+/// divide evenly). Indices below `min` or above `max` are counted in
+/// [`underflow`](Self::underflow) and [`overflow`](Self::overflow) rather than
+/// dropped.
+///
+/// Each thread counts into its own buckets and adds them to shared atomic
+/// counters when a batch completes, so the accessors reflect every completed
+/// batch.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{IndexHistogram, MmapReader, ParallelReader};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = MmapReader::new("data.ibu")?;
+/// let histogram = IndexHistogram::new(0, 99, 10);
+/// reader.process_parallel(histogram.clone(), 0)?;
+/// for (i, count) in histogram.buckets().iter().enumerate() {
+///     println!("[{}, {}]: {}", i * 10, i * 10 + 9, count);
+/// }
+/// println!("{} above range", histogram.overflow());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct IndexHistogram {
+    /// Smallest index in range
+    min: u64,
+
+    /// Number of index values in range (`max - min + 1`)
+    width: u128,
+
+    /// Counts since the last completed batch
+    local: Vec<u64>,
+
+    /// Underflow count since the last completed batch
+    local_underflow: u64,
+
+    /// Overflow count since the last completed batch
+    local_overflow: u64,
+
+    /// Counts merged from all completed batches
+    shared: Arc<SharedCounts>,
+}
+
+impl IndexHistogram {
+    /// Creates a histogram over the inclusive range `[min, max]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_buckets` is zero or `min > max`.
+    pub fn new(min: u64, max: u64, num_buckets: usize) -> Self {
+        assert!(num_buckets > 0, "histogram needs at least one bucket");
+        assert!(min <= max, "histogram range must have min <= max");
+        Self {
+            min,
+            width: (max - min) as u128 + 1,
+            local: vec![0; num_buckets],
+            local_underflow: 0,
+            local_overflow: 0,
+            shared: Arc::new(SharedCounts {
+                buckets: (0..num_buckets).map(|_| AtomicU64::new(0)).collect(),
+                underflow: AtomicU64::new(0),
+                overflow: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns the count of each bucket over all completed batches.
+    pub fn buckets(&self) -> Vec<u64> {
+        self.shared
+            .buckets
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Returns the number of indices below the range.
+    pub fn underflow(&self) -> u64 {
+        self.shared.underflow.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of indices above the range.
+    pub fn overflow(&self) -> u64 {
+        self.shared.overflow.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of records counted, including those out of range.
+    pub fn total(&self) -> u64 {
+        self.buckets().iter().sum::<u64>() + self.underflow() + self.overflow()
+    }
+}
+
+impl ParallelProcessor for IndexHistogram {
+    fn process_record(&mut self, record: Record) -> crate::Result<()> {
+        if record.index < self.min {
+            self.local_underflow += 1;
+            return Ok(());
+        }
+        let offset = (record.index - self.min) as u128;
+        if offset >= self.width {
+            self.local_overflow += 1;
+            return Ok(());
+        }
+        // Wide arithmetic so the full u64 range cannot overflow
+        let bucket = offset * self.local.len() as u128 / self.width;
+        self.local[bucket as usize] += 1;
+        Ok(())
+    }
+
+    fn on_batch_complete(&mut self) -> crate::Result<()> {
+        for (shared, local) in self.shared.buckets.iter().zip(&mut self.local) {
+            if *local > 0 {
+                shared.fetch_add(std::mem::take(local), Ordering::Relaxed);
+            }
+        }
+        self.shared
+            .underflow
+            .fetch_add(std::mem::take(&mut self.local_underflow), Ordering::Relaxed);
+        self.shared
+            .overflow
+            .fetch_add(std::mem::take(&mut self.local_overflow), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, MmapReader, ParallelReader, Writer};
+    use std::fs;
+
+    #[test]
+    fn test_index_histogram_binning() {
+        let mut histogram = IndexHistogram::new(10, 19, 3);
+        for index in [0, 9, 10, 13, 14, 16, 17, 19, 20, u64::MAX] {
+            histogram.process_record(Record::new(0, 0, index)).unwrap();
+        }
+        assert_eq!(histogram.total(), 0);
+        histogram.on_batch_complete().unwrap();
+
+        // Width 10 over 3 buckets: [10, 13], [14, 16], [17, 19]
+        assert_eq!(histogram.buckets(), vec![2, 2, 2]);
+        assert_eq!((histogram.underflow(), histogram.overflow()), (2, 2));
+        assert_eq!(histogram.total(), 10);
+
+        let mut full = IndexHistogram::new(0, u64::MAX, 2);
+        for index in [0, u64::MAX / 2, u64::MAX / 2 + 1, u64::MAX] {
+            full.process_record(Record::new(0, 0, index)).unwrap();
+        }
+        full.on_batch_complete().unwrap();
+        assert_eq!(full.buckets(), vec![2, 2]);
+    }
+
+    #[test]
+    fn test_index_histogram_parallel() {
+        let temp_file = "test_index_histogram.ibu";
+        let records: Vec<Record> = (0..10_000).map(|i| Record::new(0, 0, i % 120)).collect();
+        let mut writer =
+            Writer::new(fs::File::create(temp_file).unwrap(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        let histogram = IndexHistogram::new(0, 99, 10);
+        reader.process_parallel(histogram.clone(), 2).unwrap();
+        assert_eq!(histogram.total(), 10_000);
+        assert_eq!(histogram.overflow(), 83 * 20);
+        assert!(histogram.buckets().iter().all(|&count| count > 0));
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "min <= max")]
+    fn test_index_histogram_invalid_range() {
+        IndexHistogram::new(5, 4, 1);
+    }
+}
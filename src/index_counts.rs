@@ -0,0 +1,280 @@
+//! Read counts per index value.
+//!
+//! When the `index` field encodes an equivalence class (or any other group
+//! identifier), the number of records per index is the per-class read count.
+//! Two ways of computing it are provided:
+//!
+//! * [`IndexCounter`] is a parallel processor accumulating a hash map of
+//!   index to count. It accepts records in any order, but its memory grows
+//!   with the number of distinct indices.
+//! * [`count_by_index_sorted`] streams `(index, count)` pairs in a single pass
+//!   with constant memory, but requires the records to be ordered by index.
+//!
+//! Note that [`Header::sorted`](crate::Header::sorted) marks records sorted
+//! by `(barcode, umi, index)`, which does *not* order them by index: a sorted
+//! file still needs [`IndexCounter`] unless it was written in index order
+//! (for example after a sort on the index alone). The streaming path checks
+//! the order as it goes and fails on the first record whose index decreases,
+//! so it is safe to try it and fall back to [`IndexCounter`] on error.
+
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::{Arc, Mutex},
+};
+
+use crate::{IbuError, ParallelProcessor, Reader, Record};
+
+/// Parallel processor counting records per index.
+///
+/// Each thread counts into its own map and adds it to a shared map when a
+/// batch completes. Clones share the merged counts, so keep one to read them
+/// back after processing.
+///
+/// Memory grows with the number of distinct indices. For very large
+/// cardinalities on index-ordered input, prefer [`count_by_index_sorted`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{IndexCounter, MmapReader, ParallelReader};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = MmapReader::new("data.ibu")?;
+/// let counter = IndexCounter::new();
+/// reader.process_parallel(counter.clone(), 0)?;
+/// for (index, count) in counter.into_counts() {
+///     println!("{index}\t{count}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IndexCounter {
+    /// Counts since the last completed batch
+    local: HashMap<u64, u64>,
+
+    /// Counts merged from all completed batches
+    shared: Arc<Mutex<HashMap<u64, u64>>>,
+}
+
+impl IndexCounter {
+    /// Creates a counter with no observations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of distinct indices over all completed batches.
+    pub fn len(&self) -> usize {
+        self.shared.lock().unwrap().len()
+    }
+
+    /// Returns whether no records were counted in completed batches.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the count per index over all completed batches.
+    ///
+    /// The merged map is moved out if this is the last clone of the counter,
+    /// and copied otherwise.
+    pub fn into_counts(self) -> HashMap<u64, u64> {
+        match Arc::try_unwrap(self.shared) {
+            Ok(shared) => shared.into_inner().unwrap(),
+            Err(shared) => shared.lock().unwrap().clone(),
+        }
+    }
+}
+
+impl ParallelProcessor for IndexCounter {
+    fn process_record(&mut self, record: Record) -> crate::Result<()> {
+        *self.local.entry(record.index).or_default() += 1;
+        Ok(())
+    }
+
+    fn on_batch_complete(&mut self) -> crate::Result<()> {
+        let mut shared = self.shared.lock().unwrap();
+        for (index, count) in self.local.drain() {
+            *shared.entry(index).or_default() += count;
+        }
+        Ok(())
+    }
+}
+
+/// Counts records per index in one streaming pass over index-ordered input.
+///
+/// Yields `(index, count)` pairs in ascending index order, one per distinct
+/// index, holding only the current run in memory. This is the path to use
+/// for very large numbers of distinct indices when the records are ordered
+/// by index. A file marked [`sorted`](crate::Header::sorted) is ordered by
+/// barcode first and generally is *not*; count it with [`IndexCounter`]
+/// instead.
+///
+/// # Arguments
+///
+/// * `reader` - Reader over records in non-decreasing index order
+///
+/// # Errors
+///
+/// The iterator yields [`IbuError::OutOfOrder`] for the first record whose
+/// index is smaller than its predecessor's, and read errors as they occur.
+/// It ends after yielding an error.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{count_by_index_sorted, Header, Reader, Record, Writer};
+/// use std::io::Cursor;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+/// writer.write_batch(&[
+///     Record::new(9, 1, 0),
+///     Record::new(4, 2, 0),
+///     Record::new(7, 3, 5),
+/// ])?;
+/// writer.finish()?;
+///
+/// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+/// let counts = count_by_index_sorted(reader).collect::<ibu::Result<Vec<_>>>()?;
+/// assert_eq!(counts, vec![(0, 2), (5, 1)]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn count_by_index_sorted<R: Read>(reader: Reader<R>) -> SortedIndexCounts<R> {
+    SortedIndexCounts {
+        reader,
+        run: None,
+        done: false,
+    }
+}
+
+/// Iterator over per-index counts of index-ordered records.
+///
+/// Created by [`count_by_index_sorted`].
+pub struct SortedIndexCounts<R: Read> {
+    /// Underlying record stream
+    reader: Reader<R>,
+
+    /// Last record of the current run and the run's length
+    run: Option<(Record, u64)>,
+
+    /// Whether the stream has ended or failed
+    done: bool,
+}
+
+impl<R: Read> Iterator for SortedIndexCounts<R> {
+    type Item = crate::Result<(u64, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let record = match self.reader.next() {
+                Some(Ok(record)) => record,
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.done = true;
+                    return self.run.take().map(|(last, count)| Ok((last.index, count)));
+                }
+            };
+            match &mut self.run {
+                Some((last, count)) if last.index == record.index => {
+                    *last = record;
+                    *count += 1;
+                }
+                Some((last, _)) if record.index < last.index => {
+                    self.done = true;
+                    return Some(Err(IbuError::OutOfOrder {
+                        prev: *last,
+                        curr: record,
+                    }));
+                }
+                Some(_) => {
+                    let (last, count) = self.run.replace((record, 1)).unwrap();
+                    return Some(Ok((last.index, count)));
+                }
+                None => self.run = Some((record, 1)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, MmapReader, ParallelReader, Writer};
+    use std::{fs, io::Cursor};
+
+    fn reader(records: &[Record]) -> Reader<Cursor<Vec<u8>>> {
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+        Reader::new(Cursor::new(writer.into_inner())).unwrap()
+    }
+
+    #[test]
+    fn test_index_counter() {
+        let temp_file = "test_index_counter.ibu";
+        let records: Vec<Record> = (0..50_000).map(|i| Record::new(i, i, i % 37)).collect();
+        let mut writer =
+            Writer::new(fs::File::create(temp_file).unwrap(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let counter = IndexCounter::new();
+        assert!(counter.is_empty());
+        let reader = MmapReader::new(temp_file).unwrap();
+        reader.process_parallel(counter.clone(), 4).unwrap();
+        assert_eq!(counter.len(), 37);
+
+        let mut expected: HashMap<u64, u64> = HashMap::new();
+        for record in &records {
+            *expected.entry(record.index).or_default() += 1;
+        }
+        assert_eq!(counter.into_counts(), expected);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_count_by_index_sorted() {
+        let records = [
+            Record::new(5, 0, 1),
+            Record::new(2, 0, 1),
+            Record::new(9, 3, 1),
+            Record::new(1, 1, 4),
+            Record::new(0, 0, 8),
+            Record::new(0, 1, 8),
+        ];
+        let counts: Vec<_> = count_by_index_sorted(reader(&records))
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert_eq!(counts, vec![(1, 3), (4, 1), (8, 2)]);
+
+        assert_eq!(count_by_index_sorted(reader(&[])).count(), 0);
+    }
+
+    #[test]
+    fn test_count_by_index_sorted_out_of_order() {
+        let records = [
+            Record::new(0, 0, 2),
+            Record::new(0, 0, 3),
+            Record::new(0, 0, 1),
+            Record::new(0, 0, 9),
+        ];
+        let mut counts = count_by_index_sorted(reader(&records));
+        assert_eq!(counts.next().unwrap().unwrap(), (2, 1));
+        assert!(matches!(
+            counts.next(),
+            Some(Err(IbuError::OutOfOrder { prev, curr }))
+                if prev.index == 3 && curr.index == 1
+        ));
+        assert!(counts.next().is_none());
+    }
+}
@@ -0,0 +1,988 @@
+//! Iterator adaptors over streaming readers.
+//!
+//! This module provides specialized views over a [`Reader`]'s record stream
+//! that are built in a single streaming pass without holding all records in
+//! memory.
+
+use std::{collections::HashMap, io::Read, vec};
+
+use crate::{
+    packed::{base_mask, unpack},
+    Alphabet, IbuError, Reader, Record,
+};
+
+impl<R: Read> Reader<R> {
+    /// Converts the reader into an iterator over barcode boundaries.
+    ///
+    /// Yields `(record_index, barcode)` for the first record of every barcode
+    /// run, where `record_index` is the 0-based position of that record in the
+    /// file. This is the streaming primitive for building a barcode offset index
+    /// in a single pass.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::NotSorted`] if the header is not marked as sorted,
+    /// since barcode runs are only meaningful in sorted input.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut header = Header::new(16, 12);
+    /// header.set_sorted();
+    ///
+    /// let records = vec![
+    ///     Record::new(1, 0, 0),
+    ///     Record::new(1, 1, 0),
+    ///     Record::new(4, 0, 0),
+    /// ];
+    /// let mut writer = Writer::new(Vec::new(), header)?;
+    /// writer.write_batch(&records)?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// let boundaries = reader
+    ///     .barcode_boundaries()?
+    ///     .collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(boundaries, vec![(0, 1), (2, 4)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn barcode_boundaries(self) -> crate::Result<BarcodeBoundaries<R>> {
+        if !self.header().sorted() {
+            return Err(IbuError::NotSorted);
+        }
+        Ok(BarcodeBoundaries {
+            reader: self,
+            pos: 0,
+            last: None,
+        })
+    }
+
+    /// Converts the reader into an iterator pairing records with barcode depth.
+    ///
+    /// Yields `(record, depth)` for every record, where `depth` is the number
+    /// of records sharing the record's barcode. Since the depth is only known
+    /// once a barcode's run ends, one run is buffered at a time; memory use is
+    /// bounded by the deepest barcode rather than the file size.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::NotSorted`] if the header is not marked as sorted,
+    /// since barcode runs only hold all of a barcode's records in sorted input.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut header = Header::new(16, 12);
+    /// header.set_sorted();
+    ///
+    /// let records = vec![
+    ///     Record::new(1, 0, 0),
+    ///     Record::new(1, 1, 0),
+    ///     Record::new(4, 0, 0),
+    /// ];
+    /// let mut writer = Writer::new(Vec::new(), header)?;
+    /// writer.write_batch(&records)?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// let depths = reader
+    ///     .with_barcode_depth()?
+    ///     .map(|r| r.map(|(_, depth)| depth))
+    ///     .collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(depths, vec![2, 2, 1]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_barcode_depth(self) -> crate::Result<BarcodeDepth<R>> {
+        if !self.header().sorted() {
+            return Err(IbuError::NotSorted);
+        }
+        Ok(BarcodeDepth {
+            reader: self,
+            run: Vec::new().into_iter(),
+            depth: 0,
+            lookahead: None,
+        })
+    }
+
+    /// Converts the reader into an iterator flagging the start of barcode runs.
+    ///
+    /// Yields `(record, first)` for every record, where `first` is `true` if
+    /// the record's barcode differs from the previous record's (always `true`
+    /// for the first record). On sorted input this marks the first occurrence
+    /// of each barcode, i.e. "new cell" events.
+    ///
+    /// Unlike [`barcode_boundaries`](Reader::barcode_boundaries), unsorted
+    /// input is accepted; the flag then marks the start of each run of
+    /// consecutive equal barcodes, and a barcode may be flagged more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let records = vec![
+    ///     Record::new(1, 0, 0),
+    ///     Record::new(1, 1, 0),
+    ///     Record::new(4, 0, 0),
+    /// ];
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_batch(&records)?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// let flags = reader
+    ///     .with_first_seen_flag()
+    ///     .map(|r| r.map(|(_, first)| first))
+    ///     .collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(flags, vec![true, false, true]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_first_seen_flag(self) -> FirstSeen<R> {
+        FirstSeen {
+            reader: self,
+            last: None,
+        }
+    }
+
+    /// Converts the reader into an iterator over at most `n` records.
+    ///
+    /// Unlike [`Iterator::take`], which counts every item including errors,
+    /// only successfully read records count towards `n`. Errors encountered
+    /// before the `n`-th record are still yielded, so "the first 1000 valid
+    /// records" of a damaged stream can be gathered by skipping errors, while
+    /// `collect::<Result<_>>()` still fails on the first error.
+    ///
+    /// Reading stops once `n` records have been yielded. If fewer than a full
+    /// batch of records is requested before any are buffered, batches are
+    /// shrunk to `n` records, so little more than `n` records is read (and
+    /// decompressed) from the source. This makes previews of large
+    /// compressed files cheap; see also [`Writer::write_head`](crate::Writer::write_head).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let records: Vec<Record> = (0..10).map(|i| Record::new(i, 0, 0)).collect();
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_batch(&records)?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// let preview = reader.take_records(3).collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(preview, &records[..3]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn take_records(mut self, n: usize) -> TakeRecords<R> {
+        self.limit_batch(n);
+        TakeRecords {
+            reader: self,
+            remaining: n,
+        }
+    }
+
+    /// Converts the reader into an iterator over records matching a predicate.
+    ///
+    /// Only records for which `pred` returns `true` are yielded. Read errors
+    /// are never passed to the predicate and are always yielded, so
+    /// `collect::<Result<_>>()` still fails on a truncated or damaged stream
+    /// instead of silently dropping the error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::{collections::HashSet, io::Cursor};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let records: Vec<Record> = (0..10).map(|i| Record::new(i % 5, i, 0)).collect();
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_batch(&records)?;
+    /// writer.finish()?;
+    ///
+    /// let whitelist: HashSet<u64> = [1, 3].into_iter().collect();
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// let kept = reader
+    ///     .filter_records(|r| whitelist.contains(&r.barcode))
+    ///     .collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(kept.len(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn filter_records<F>(self, pred: F) -> FilterRecords<R, F>
+    where
+        F: FnMut(&Record) -> bool,
+    {
+        FilterRecords { reader: self, pred }
+    }
+
+    /// Groups all records by barcode in a single streaming pass.
+    ///
+    /// Records are read in file order and appended to the vector of their
+    /// barcode, so each vector preserves the relative order of its records.
+    /// The input does not need to be sorted.
+    ///
+    /// # Memory
+    ///
+    /// Every record is held in memory, plus per-barcode map and vector
+    /// overhead (roughly 50-80 bytes per distinct barcode). For
+    /// high-cardinality barcode spaces, such as unfiltered droplet data with
+    /// millions of barcodes holding only a few records each, the overhead can
+    /// exceed the size of the records themselves. Use
+    /// [`count_by_barcode`](Reader::count_by_barcode) when only per-barcode
+    /// counts are needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered while reading records.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let records = vec![
+    ///     Record::new(4, 0, 0),
+    ///     Record::new(1, 0, 1),
+    ///     Record::new(4, 1, 2),
+    /// ];
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_batch(&records)?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// let groups = reader.collect_by_barcode()?;
+    /// assert_eq!(groups[&4], vec![records[0], records[2]]);
+    /// assert_eq!(groups[&1], vec![records[1]]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn collect_by_barcode(self) -> crate::Result<HashMap<u64, Vec<Record>>> {
+        let mut groups: HashMap<u64, Vec<Record>> = HashMap::new();
+        for record in self {
+            let record = record?;
+            groups.entry(record.barcode).or_default().push(record);
+        }
+        Ok(groups)
+    }
+
+    /// Counts records per barcode in a single streaming pass.
+    ///
+    /// This is the memory-efficient counterpart of
+    /// [`collect_by_barcode`](Reader::collect_by_barcode): only one `u64`
+    /// count is stored per distinct barcode, so memory scales with the number
+    /// of barcodes rather than the number of records. The input does not need
+    /// to be sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered while reading records.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let records = vec![Record::new(4, 0, 0), Record::new(1, 0, 1), Record::new(4, 1, 2)];
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_batch(&records)?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// let counts = reader.count_by_barcode()?;
+    /// assert_eq!(counts[&4], 2);
+    /// assert_eq!(counts[&1], 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn count_by_barcode(self) -> crate::Result<HashMap<u64, u64>> {
+        let mut counts: HashMap<u64, u64> = HashMap::new();
+        for record in self {
+            *counts.entry(record?.barcode).or_default() += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Converts the reader into an iterator over decoded records, rejecting
+    /// values with bits beyond the declared sequence lengths.
+    ///
+    /// Barcodes and UMIs are decoded to nucleotides with the canonical `ACGT`
+    /// alphabet, using the lengths declared in the header. Before decoding,
+    /// each value is checked for nonzero bits above its `2 * len` low bits;
+    /// such bits would otherwise be silently dropped, hiding producers that
+    /// did not zero-extend their records.
+    ///
+    /// # Errors
+    ///
+    /// Yields [`IbuError::ValueOutOfRange`] for a record whose barcode or UMI
+    /// has bits beyond the declared length, and read errors as usual.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, IbuError, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let records = vec![
+    ///     Record::new(0b00_01_10_11, 0b11_00, 7),
+    ///     Record::new(0b1_00_00_00_00, 0, 8),
+    /// ];
+    /// let mut writer = Writer::new(Vec::new(), Header::new(4, 2))?;
+    /// writer.write_batch(&records)?;
+    /// writer.finish()?;
+    ///
+    /// let mut decoded = Reader::new(Cursor::new(writer.into_inner()))?.decoded_strict();
+    /// let first = decoded.next().unwrap()?;
+    /// assert_eq!(first.barcode, b"ACGT");
+    /// assert_eq!(first.umi, b"TA");
+    /// assert_eq!(first.index, 7);
+    ///
+    /// // The second barcode does not fit in 4 bases
+    /// assert!(matches!(
+    ///     decoded.next(),
+    ///     Some(Err(IbuError::ValueOutOfRange { pos: 1, .. }))
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decoded_strict(self) -> DecodedStrict<R> {
+        let header = self.header();
+        DecodedStrict {
+            reader: self,
+            pos: 0,
+            bc_len: header.bc_len,
+            umi_len: header.umi_len,
+            alphabet: Alphabet::canonical(),
+        }
+    }
+
+    /// Decodes every record to `(barcode, umi, index)` with string sequences.
+    ///
+    /// Barcodes and UMIs are decoded with [`Record::decode_barcode`] and
+    /// [`Record::decode_umi`], using the lengths declared in the header. Bits
+    /// above those lengths are ignored; use
+    /// [`decoded_strict`](Self::decoded_strict) to reject them instead.
+    ///
+    /// This is a convenience for scripts and small tools. Every record
+    /// allocates two strings, so decoding is several times slower than
+    /// iterating over raw [`Record`]s; bulk processing should keep the packed
+    /// values and decode only what it reports.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(4, 2))?;
+    /// writer.write_record(&Record::new(0b00_01_10_11, 0b11_00, 7))?;
+    /// writer.finish()?;
+    ///
+    /// for result in Reader::new(Cursor::new(writer.into_inner()))?.decoded() {
+    ///     let (barcode, umi, index) = result?;
+    ///     assert_eq!((barcode.as_str(), umi.as_str(), index), ("ACGT", "TA", 7));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decoded(self) -> Decoded<R> {
+        let header = self.header();
+        Decoded {
+            reader: self,
+            bc_len: header.bc_len,
+            umi_len: header.umi_len,
+        }
+    }
+}
+
+/// Record with its barcode and UMI decoded to nucleotides.
+///
+/// Yielded by [`Reader::decoded_strict`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DecodedRecord {
+    /// Barcode nucleotides
+    pub barcode: Vec<u8>,
+
+    /// UMI nucleotides
+    pub umi: Vec<u8>,
+
+    /// Index value, unchanged
+    pub index: u64,
+}
+
+/// Iterator decoding records to string tuples.
+///
+/// Created by [`Reader::decoded`].
+pub struct Decoded<R: Read> {
+    /// Underlying record stream
+    reader: Reader<R>,
+
+    /// Declared barcode length in bases
+    bc_len: u32,
+
+    /// Declared UMI length in bases
+    umi_len: u32,
+}
+
+impl<R: Read> Iterator for Decoded<R> {
+    type Item = crate::Result<(String, String, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.reader.next()?.map(|record| {
+            (
+                Record::decode_barcode(record.barcode, self.bc_len),
+                Record::decode_umi(record.umi, self.umi_len),
+                record.index,
+            )
+        }))
+    }
+}
+
+/// Iterator decoding records while rejecting out-of-range values.
+///
+/// Created by [`Reader::decoded_strict`].
+pub struct DecodedStrict<R: Read> {
+    /// Underlying record stream
+    reader: Reader<R>,
+
+    /// Index of the next record to be read
+    pos: usize,
+
+    /// Declared barcode length in bases
+    bc_len: u32,
+
+    /// Declared UMI length in bases
+    umi_len: u32,
+
+    /// Alphabet used for decoding
+    alphabet: Alphabet,
+}
+
+impl<R: Read> DecodedStrict<R> {
+    /// Checks that `value` fits in `len` bases.
+    fn check(&self, field: &'static str, value: u64, len: u32) -> crate::Result<()> {
+        if value & !base_mask(len) != 0 {
+            return Err(IbuError::ValueOutOfRange {
+                pos: self.pos,
+                field,
+                value,
+                len,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for DecodedStrict<R> {
+    type Item = crate::Result<DecodedRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.reader.next()? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        let checked = self
+            .check("barcode", record.barcode, self.bc_len)
+            .and_then(|()| self.check("UMI", record.umi, self.umi_len));
+        self.pos += 1;
+        if let Err(e) = checked {
+            return Some(Err(e));
+        }
+        Some(Ok(DecodedRecord {
+            barcode: unpack(record.barcode, self.bc_len, &self.alphabet),
+            umi: unpack(record.umi, self.umi_len, &self.alphabet),
+            index: record.index,
+        }))
+    }
+}
+
+/// Iterator over the start of each barcode run in a sorted stream.
+///
+/// Created by [`Reader::barcode_boundaries`].
+pub struct BarcodeBoundaries<R: Read> {
+    /// Underlying record stream
+    reader: Reader<R>,
+
+    /// Index of the next record to be read
+    pos: u64,
+
+    /// Barcode of the most recently read record
+    last: Option<u64>,
+}
+
+/// Iterator pairing each record with the depth of its barcode.
+///
+/// Created by [`Reader::with_barcode_depth`].
+pub struct BarcodeDepth<R: Read> {
+    /// Underlying record stream
+    reader: Reader<R>,
+
+    /// Buffered records of the current barcode run
+    run: vec::IntoIter<Record>,
+
+    /// Number of records in the current run
+    depth: u64,
+
+    /// First record of the next run, read while finding the end of the current one
+    lookahead: Option<Record>,
+}
+
+impl<R: Read> BarcodeDepth<R> {
+    /// Buffers the next barcode run, returning `false` at end of stream.
+    fn fill_run(&mut self) -> crate::Result<bool> {
+        let first = match self.lookahead.take() {
+            Some(record) => record,
+            None => match self.reader.next() {
+                Some(result) => result?,
+                None => return Ok(false),
+            },
+        };
+        let mut run = vec![first];
+        for result in self.reader.by_ref() {
+            let record = result?;
+            if record.barcode != first.barcode {
+                self.lookahead = Some(record);
+                break;
+            }
+            run.push(record);
+        }
+        self.depth = run.len() as u64;
+        self.run = run.into_iter();
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for BarcodeDepth<R> {
+    type Item = crate::Result<(Record, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.run.len() == 0 {
+            match self.fill_run() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.run.next().map(|record| Ok((record, self.depth)))
+    }
+}
+
+/// Iterator over a bounded number of successfully read records.
+///
+/// Created by [`Reader::take_records`].
+pub struct TakeRecords<R: Read> {
+    /// Underlying record stream
+    reader: Reader<R>,
+
+    /// Number of records still to be yielded
+    remaining: usize,
+}
+
+impl<R: Read> Iterator for TakeRecords<R> {
+    type Item = crate::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let result = self.reader.next()?;
+        if result.is_ok() {
+            self.remaining -= 1;
+        }
+        Some(result)
+    }
+}
+
+/// Iterator over records matching a predicate.
+///
+/// Created by [`Reader::filter_records`].
+pub struct FilterRecords<R: Read, F> {
+    /// Underlying record stream
+    reader: Reader<R>,
+
+    /// Predicate selecting the records to yield
+    pred: F,
+}
+
+impl<R: Read, F: FnMut(&Record) -> bool> Iterator for FilterRecords<R, F> {
+    type Item = crate::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for result in self.reader.by_ref() {
+            match result {
+                Ok(record) if !(self.pred)(&record) => continue,
+                other => return Some(other),
+            }
+        }
+        None
+    }
+}
+
+/// Iterator flagging the first record of each barcode run.
+///
+/// Created by [`Reader::with_first_seen_flag`].
+pub struct FirstSeen<R: Read> {
+    /// Underlying record stream
+    reader: Reader<R>,
+
+    /// Barcode of the most recently read record
+    last: Option<u64>,
+}
+
+impl<R: Read> Iterator for FirstSeen<R> {
+    type Item = crate::Result<(Record, bool)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.reader.next()? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        let first = self.last != Some(record.barcode);
+        self.last = Some(record.barcode);
+        Some(Ok((record, first)))
+    }
+}
+
+impl<R: Read> Iterator for BarcodeBoundaries<R> {
+    type Item = crate::Result<(u64, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for result in self.reader.by_ref() {
+            let record = match result {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            };
+            let pos = self.pos;
+            self.pos += 1;
+            if self.last != Some(record.barcode) {
+                self.last = Some(record.barcode);
+                return Some(Ok((pos, record.barcode)));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, Writer, HEADER_SIZE, RECORD_SIZE};
+    use std::io::Cursor;
+
+    fn create_reader(header: Header, records: &[Record]) -> Reader<Cursor<Vec<u8>>> {
+        let mut writer = Writer::new(Vec::new(), header).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+        Reader::new(Cursor::new(writer.into_inner())).unwrap()
+    }
+
+    fn sorted_header() -> Header {
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        header
+    }
+
+    #[test]
+    fn test_barcode_boundaries() {
+        let records: Vec<Record> = (0..100_000).map(|i| Record::new(i / 7, i, 0)).collect();
+        let reader = create_reader(sorted_header(), &records);
+
+        let boundaries: Vec<(u64, u64)> = reader
+            .barcode_boundaries()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+
+        let expected: Vec<(u64, u64)> = (0..100_000u64).step_by(7).map(|i| (i, i / 7)).collect();
+        assert_eq!(boundaries, expected);
+    }
+
+    #[test]
+    fn test_barcode_boundaries_empty() {
+        let reader = create_reader(sorted_header(), &[]);
+        assert_eq!(reader.barcode_boundaries().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_barcode_boundaries_requires_sorted() {
+        let reader = create_reader(Header::new(16, 12), &[Record::new(1, 2, 3)]);
+        assert!(matches!(
+            reader.barcode_boundaries(),
+            Err(IbuError::NotSorted)
+        ));
+    }
+
+    #[test]
+    fn test_with_barcode_depth() {
+        // Barcode `b` has `b + 1` records
+        let records: Vec<Record> = (0..50u64)
+            .flat_map(|bc| (0..=bc).map(move |umi| Record::new(bc, umi, 0)))
+            .collect();
+        let annotated: Vec<(Record, u64)> = create_reader(sorted_header(), &records)
+            .with_barcode_depth()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(annotated.len(), records.len());
+        for ((record, depth), expected) in annotated.iter().zip(&records) {
+            assert_eq!(record, expected);
+            assert_eq!(*depth, record.barcode + 1);
+        }
+
+        let reader = create_reader(sorted_header(), &[]);
+        assert_eq!(reader.with_barcode_depth().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_with_barcode_depth_requires_sorted() {
+        let reader = create_reader(Header::new(16, 12), &[Record::new(1, 2, 3)]);
+        assert!(matches!(
+            reader.with_barcode_depth(),
+            Err(IbuError::NotSorted)
+        ));
+    }
+
+    #[test]
+    fn test_with_first_seen_flag() {
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i / 3, i, 0)).collect();
+        let flagged: Vec<(Record, bool)> = create_reader(sorted_header(), &records)
+            .with_first_seen_flag()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(flagged.len(), 1000);
+        for (i, (record, first)) in flagged.iter().enumerate() {
+            assert_eq!(*record, records[i]);
+            assert_eq!(*first, i % 3 == 0);
+        }
+
+        // Unsorted input flags every run start
+        let records = [1, 1, 2, 1].map(|bc| Record::new(bc, 0, 0));
+        let flags: Vec<bool> = create_reader(Header::new(16, 12), &records)
+            .with_first_seen_flag()
+            .map(|r| r.unwrap().1)
+            .collect();
+        assert_eq!(flags, vec![true, false, true, true]);
+    }
+
+    #[test]
+    fn test_take_records() {
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, 0, 0)).collect();
+
+        let taken: Vec<Record> = create_reader(Header::new(16, 12), &records)
+            .take_records(40)
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(taken, &records[..40]);
+
+        // Fewer records than requested
+        let reader = create_reader(Header::new(16, 12), &records);
+        assert_eq!(reader.take_records(1000).count(), 100);
+        let reader = create_reader(Header::new(16, 12), &records);
+        assert_eq!(reader.take_records(0).count(), 0);
+
+        // Only the requested records are read from the source
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        let bytes = writer.into_inner();
+        let mut source = bytes.as_slice();
+        let taken = Reader::new(&mut source)
+            .unwrap()
+            .take_records(3)
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(taken, &records[..3]);
+        assert_eq!(source.len(), bytes.len() - HEADER_SIZE - 3 * RECORD_SIZE);
+
+        // Records already buffered are kept
+        let mut reader = create_reader(Header::new(16, 12), &records);
+        reader.next().unwrap().unwrap();
+        let rest: Vec<Record> = reader
+            .take_records(5)
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rest, &records[1..6]);
+    }
+
+    #[test]
+    fn test_take_records_error_not_counted() {
+        // Overflowing indices yield recoverable errors between valid records
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer
+            .write_batch(&[
+                Record::new(1, 0, 0),
+                Record::new(2, 0, u64::MAX),
+                Record::new(3, 0, 1),
+                Record::new(4, 0, u64::MAX),
+                Record::new(5, 0, 2),
+                Record::new(6, 0, 3),
+            ])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let reader = Reader::new(Cursor::new(writer.into_inner()))
+            .unwrap()
+            .with_index_offset(1, crate::OverflowPolicy::Error);
+        let results: Vec<_> = reader.take_records(3).collect();
+        assert_eq!(results.len(), 5);
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 3);
+        assert!(matches!(results[0], Ok(r) if r == Record::new(1, 0, 1)));
+        assert!(matches!(results[1], Err(IbuError::IndexOverflow { .. })));
+        assert!(matches!(results[2], Ok(r) if r == Record::new(3, 0, 2)));
+        assert!(matches!(results[3], Err(IbuError::IndexOverflow { .. })));
+        assert!(matches!(results[4], Ok(r) if r == Record::new(5, 0, 3)));
+    }
+
+    #[test]
+    fn test_filter_records() {
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i % 7, i, 0)).collect();
+        let kept: Vec<Record> = create_reader(Header::new(16, 12), &records)
+            .filter_records(|r| r.barcode == 3)
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(kept.len(), 143);
+        assert!(kept.iter().all(|r| r.barcode == 3));
+
+        // Errors are yielded even though no record matches
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        let mut bytes = writer.into_inner();
+        bytes.truncate(bytes.len() - 4);
+        let reader = Reader::new(Cursor::new(bytes)).unwrap();
+        let results: Vec<_> = reader.filter_records(|_| false).collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(IbuError::TruncatedRecord { .. })));
+    }
+
+    #[test]
+    fn test_collect_by_barcode() {
+        // Unsorted input with interleaved barcodes
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i % 13, 0, i)).collect();
+
+        let groups = create_reader(Header::new(16, 12), &records)
+            .collect_by_barcode()
+            .unwrap();
+        assert_eq!(groups.len(), 13);
+        for (barcode, group) in &groups {
+            assert!(group.iter().all(|r| r.barcode == *barcode));
+            assert!(group.windows(2).all(|w| w[0].index < w[1].index));
+        }
+        assert_eq!(groups.values().map(Vec::len).sum::<usize>(), 1000);
+
+        let counts = create_reader(Header::new(16, 12), &records)
+            .count_by_barcode()
+            .unwrap();
+        assert_eq!(counts.len(), 13);
+        for (barcode, group) in &groups {
+            assert_eq!(counts[barcode], group.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_collect_by_barcode_error() {
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_record(&Record::new(1, 2, 3)).unwrap();
+        writer.finish().unwrap();
+        let mut bytes = writer.into_inner();
+        bytes.truncate(bytes.len() - 4);
+
+        let reader = Reader::new(Cursor::new(bytes.clone())).unwrap();
+        assert!(reader.collect_by_barcode().is_err());
+        let reader = Reader::new(Cursor::new(bytes)).unwrap();
+        assert!(reader.count_by_barcode().is_err());
+    }
+
+    #[test]
+    fn test_decoded() {
+        let records = vec![
+            Record::new(0b11_10_01_00, 0b01, 1),
+            // Bits above the declared lengths are ignored
+            Record::new(0b1_00_00_00_00, 0b1_11, 2),
+        ];
+        let reader = create_reader(Header::new(4, 1), &records);
+        let decoded: Vec<_> = reader.decoded().collect::<crate::Result<_>>().unwrap();
+        assert_eq!(
+            decoded,
+            vec![
+                ("TGCA".to_string(), "C".to_string(), 1),
+                ("AAAA".to_string(), "T".to_string(), 2),
+            ]
+        );
+
+        let reader = create_reader(Header::new(32, 32), &[Record::new(u64::MAX, 0, 0)]);
+        let (barcode, umi, _) = reader.decoded().next().unwrap().unwrap();
+        assert_eq!(barcode, "T".repeat(32));
+        assert_eq!(umi, "A".repeat(32));
+    }
+
+    #[test]
+    fn test_decoded_strict() {
+        let records = vec![
+            Record::new(0b11_10_01_00, 0b01, 1),
+            Record::new(0b11_10_01_00, 0b1_01, 2),
+            Record::new(u64::MAX, 0, 3),
+            Record::new(0, 0, 4),
+        ];
+        let reader = create_reader(Header::new(4, 1), &records);
+        let results: Vec<_> = reader.decoded_strict().collect();
+        assert_eq!(results.len(), 4);
+
+        let first = results[0].as_ref().unwrap();
+        assert_eq!(
+            (first.barcode.as_slice(), first.umi.as_slice()),
+            (&b"TGCA"[..], &b"C"[..])
+        );
+        assert!(matches!(
+            results[1],
+            Err(IbuError::ValueOutOfRange {
+                pos: 1,
+                field: "UMI",
+                len: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            results[2],
+            Err(IbuError::ValueOutOfRange {
+                pos: 2,
+                field: "barcode",
+                value: u64::MAX,
+                len: 4,
+            })
+        ));
+        assert_eq!(results[3].as_ref().unwrap().barcode, b"AAAA");
+
+        // Full-width sequences use every bit
+        let reader = create_reader(Header::new(32, 32), &[Record::new(u64::MAX, 0, 0)]);
+        let decoded: Vec<_> = reader
+            .decoded_strict()
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert_eq!(decoded[0].barcode, vec![b'T'; 32]);
+    }
+}
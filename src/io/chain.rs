@@ -0,0 +1,198 @@
+//! Chained reader over multiple IBU files.
+//!
+//! This module provides a streaming reader that treats a sequence of IBU files
+//! (e.g. shards of a larger dataset) as a single logical stream of records.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use super::reader::BoxedReader;
+use crate::{Header, IbuError, Reader, Record};
+
+/// Streaming reader over a sequence of IBU files.
+///
+/// Created by [`chain_readers`]. Records are yielded from each file in turn,
+/// with each file's header consumed transparently. Every file after the first
+/// must agree with the first file's barcode and UMI lengths; the first
+/// disagreement is reported as [`IbuError::HeaderMismatch`] and ends iteration.
+///
+/// Files are opened lazily as the previous one is exhausted, so only one file
+/// handle and read buffer is held at a time.
+pub struct ChainedReader {
+    /// Header of the first file in the chain
+    header: Header,
+
+    /// Reader for the file currently being streamed
+    current: Option<Reader<BoxedReader>>,
+
+    /// Paths of the files not yet opened
+    remaining: VecDeque<PathBuf>,
+}
+
+impl ChainedReader {
+    /// Returns the header of the first file in the chain.
+    ///
+    /// All files in the chain share its barcode and UMI lengths. Other header
+    /// fields (such as the sorted flag) only describe the first file and do not
+    /// necessarily hold for the chained stream as a whole.
+    pub fn header(&self) -> Header {
+        self.header
+    }
+
+    /// Opens the next file in the chain and checks its header for compatibility.
+    fn open_next(&mut self) -> crate::Result<bool> {
+        let Some(path) = self.remaining.pop_front() else {
+            return Ok(false);
+        };
+        let reader = Reader::from_path(path)?;
+        let header = reader.header();
+        if header.bc_len != self.header.bc_len || header.umi_len != self.header.umi_len {
+            return Err(IbuError::HeaderMismatch {
+                expected: (self.header.bc_len, self.header.umi_len),
+                actual: (header.bc_len, header.umi_len),
+            });
+        }
+        self.current = Some(reader);
+        Ok(true)
+    }
+}
+
+impl Iterator for ChainedReader {
+    type Item = crate::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(reader) = self.current.as_mut() {
+                match reader.next() {
+                    Some(result) => return Some(result),
+                    None => self.current = None,
+                }
+            }
+            match self.open_next() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => {
+                    // Stop the chain on the first failure
+                    self.remaining.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Streams records from multiple IBU files as one logical reader.
+///
+/// Files are read in the order given, each file's header is skipped
+/// transparently, and all headers must agree on barcode and UMI lengths.
+/// Compressed inputs are handled as in [`Reader::from_path`].
+///
+/// # Arguments
+///
+/// * `paths` - Paths of the files to read, in order
+///
+/// # Errors
+///
+/// Returns an error if no paths are given or the first file cannot be opened.
+/// Errors opening subsequent files, including header mismatches, are yielded
+/// by the iterator when the chain reaches that file.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::chain_readers;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = chain_readers(vec!["shard_0.ibu", "shard_1.ibu", "shard_2.ibu"])?;
+/// println!("Barcode length: {}", reader.header().bc_len);
+///
+/// let mut count = 0;
+/// for record in reader {
+///     let _record = record?;
+///     count += 1;
+/// }
+/// println!("Read {} records across all shards", count);
+/// # Ok(())
+/// # }
+/// ```
+pub fn chain_readers<P: AsRef<Path>>(paths: Vec<P>) -> crate::Result<ChainedReader> {
+    let mut remaining: VecDeque<PathBuf> = paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+    let Some(first) = remaining.pop_front() else {
+        return Err(IbuError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "no input paths provided",
+        )));
+    };
+    let reader = Reader::from_path(first)?;
+    Ok(ChainedReader {
+        header: reader.header(),
+        current: Some(reader),
+        remaining,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Writer;
+    use std::fs;
+
+    fn create_test_file(path: &str, header: Header, records: &[Record]) {
+        let file = fs::File::create(path).unwrap();
+        let mut writer = Writer::new(file, header).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_chain_readers_sequence() {
+        let paths = ["test_chain_a.ibu", "test_chain_b.ibu", "test_chain_c.ibu"];
+        let header = Header::new(16, 12);
+        let a: Vec<Record> = (0..10).map(|i| Record::new(i, 0, 0)).collect();
+        let b: Vec<Record> = vec![];
+        let c: Vec<Record> = (10..15).map(|i| Record::new(i, 0, 0)).collect();
+        create_test_file(paths[0], header, &a);
+        create_test_file(paths[1], header, &b);
+        create_test_file(paths[2], header, &c);
+
+        let reader = chain_readers(paths.to_vec()).unwrap();
+        assert_eq!(reader.header().bc_len, 16);
+        let records: Vec<Record> = reader.collect::<crate::Result<Vec<_>>>().unwrap();
+        let expected: Vec<Record> = a.into_iter().chain(c).collect();
+        assert_eq!(records, expected);
+
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_chain_readers_header_mismatch() {
+        let paths = ["test_chain_mismatch_a.ibu", "test_chain_mismatch_b.ibu"];
+        create_test_file(paths[0], Header::new(16, 12), &[Record::new(1, 2, 3)]);
+        create_test_file(paths[1], Header::new(16, 10), &[Record::new(4, 5, 6)]);
+
+        let mut reader = chain_readers(paths.to_vec()).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), Record::new(1, 2, 3));
+        assert!(matches!(
+            reader.next(),
+            Some(Err(IbuError::HeaderMismatch {
+                expected: (16, 12),
+                actual: (16, 10)
+            }))
+        ));
+        assert!(reader.next().is_none());
+
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_chain_readers_empty() {
+        let paths: Vec<&str> = vec![];
+        assert!(matches!(chain_readers(paths), Err(IbuError::Io(_))));
+    }
+}
@@ -0,0 +1,349 @@
+//! Parallel processing over streaming readers.
+//!
+//! This module provides [`ChannelParallelReader`], which implements
+//! [`ParallelReader`] for any [`Reader`] so that the same processors used with
+//! [`MmapReader`](crate::MmapReader) also work on stdin pipes and compressed
+//! files. A single thread reads record batches into a bounded channel, and
+//! worker threads pull batches from it.
+
+use std::{
+    io::Read,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use crossbeam_channel::Receiver;
+
+use crate::{
+    parallel::{catch_panic, resolve_num_threads, ParallelProcessor, ParallelReader},
+    Header, IbuError, Reader, Record, BATCH_SIZE,
+};
+
+/// Default number of batches buffered between the reader and the workers.
+const DEFAULT_CAPACITY: usize = 4;
+
+/// Parallel reader over a streaming [`Reader`].
+///
+/// Records are read sequentially on a dedicated thread and sent in batches
+/// through a bounded `crossbeam` channel to the worker threads, each of which
+/// runs its own clone of the processor. When the workers fall behind, the
+/// channel fills and the reader blocks, so at most `capacity` batches (plus
+/// one per thread in flight) are held in memory.
+///
+/// Unlike [`MmapReader`](crate::MmapReader), batches are handed to whichever
+/// worker is free, so there is no ordering between threads. Each spawned
+/// worker calls [`set_tid`](ParallelProcessor::set_tid) first,
+/// [`on_batch_complete`](ParallelProcessor::on_batch_complete) after every
+/// batch (at least once, even without records), and
+/// [`on_thread_complete`](ParallelProcessor::on_thread_complete) last.
+///
+/// The stream can only be processed once; later calls return an error.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{ChannelParallelReader, ParallelProcessor, ParallelReader, Reader, Record};
+/// use std::sync::{
+///     atomic::{AtomicU64, Ordering},
+///     Arc,
+/// };
+///
+/// #[derive(Clone, Default)]
+/// struct Counter(Arc<AtomicU64>);
+///
+/// impl ParallelProcessor for Counter {
+///     fn process_record(&mut self, _record: Record) -> ibu::Result<()> {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///         Ok(())
+///     }
+/// }
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = ChannelParallelReader::new(Reader::from_stdin()?);
+/// let counter = Counter::default();
+/// reader.process_parallel(counter.clone(), 4)?;
+/// println!("{} records", counter.0.load(Ordering::Relaxed));
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChannelParallelReader<R: Read + Send> {
+    /// Header of the underlying stream
+    header: Header,
+
+    /// Stream to read (None once processed)
+    reader: Mutex<Option<Reader<R>>>,
+
+    /// Number of records per batch
+    batch_size: usize,
+
+    /// Number of batches buffered in the channel
+    capacity: usize,
+}
+
+impl<R: Read + Send> ChannelParallelReader<R> {
+    /// Creates a parallel reader over a streaming reader.
+    ///
+    /// Uses batches of [`BATCH_SIZE`] records and buffers up to 4 batches.
+    pub fn new(reader: Reader<R>) -> Self {
+        Self {
+            header: reader.header(),
+            reader: Mutex::new(Some(reader)),
+            batch_size: BATCH_SIZE,
+            capacity: DEFAULT_CAPACITY,
+        }
+    }
+
+    /// Sets the number of records per batch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is zero.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batch size must be positive");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Sets the number of batches buffered between the reader and the workers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "channel capacity must be positive");
+        self.capacity = capacity;
+        self
+    }
+
+    /// Returns the header of the underlying stream.
+    pub fn header(&self) -> Header {
+        self.header
+    }
+}
+
+impl<R: Read + Send> ParallelReader for ChannelParallelReader<R> {
+    /// Processes all records of the stream in parallel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream was already processed, if reading fails
+    /// (e.g. a truncated stream), if a thread cannot be spawned, or the first
+    /// error returned by the processor (a panic is returned as
+    /// [`IbuError::Process`] with the panic message). Once any thread fails,
+    /// the others stop at their next batch.
+    fn process_parallel<P: ParallelProcessor + Clone + 'static>(
+        &self,
+        processor: P,
+        num_threads: usize,
+    ) -> crate::Result<()> {
+        let Some(reader) = self.reader.lock().unwrap().take() else {
+            return Err(IbuError::Process("stream was already processed".into()));
+        };
+        let num_threads = resolve_num_threads(num_threads);
+        let batch_size = self.batch_size;
+        let (sender, receiver) = crossbeam_channel::bounded::<Vec<Record>>(self.capacity);
+        let failed = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            let failed = &failed;
+            let producer = scope.spawn(move || -> crate::Result<()> {
+                let mut records = reader;
+                loop {
+                    let batch = records
+                        .by_ref()
+                        .take(batch_size)
+                        .collect::<crate::Result<Vec<Record>>>();
+                    let batch = match batch {
+                        Ok(batch) if batch.is_empty() => return Ok(()),
+                        Ok(batch) => batch,
+                        Err(e) => {
+                            failed.store(true, Ordering::Relaxed);
+                            return Err(e);
+                        }
+                    };
+                    // Stop early once a worker failed or all workers exited
+                    if failed.load(Ordering::Relaxed) || sender.send(batch).is_err() {
+                        return Ok(());
+                    }
+                }
+            });
+
+            let mut handles = Vec::with_capacity(num_threads);
+            for tid in 0..num_threads {
+                let mut processor = processor.clone();
+                let worker_receiver = receiver.clone();
+                let work = move || {
+                    let result =
+                        catch_panic(|| run_worker(&mut processor, tid, &worker_receiver, failed));
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    result
+                };
+                match thread::Builder::new().spawn_scoped(scope, work) {
+                    Ok(handle) => handles.push(handle),
+                    Err(e) => {
+                        failed.store(true, Ordering::Relaxed);
+                        drop(receiver);
+                        return Err(e.into());
+                    }
+                }
+            }
+            // Only workers hold the receiver, so the producer stops if they all exit
+            drop(receiver);
+
+            // Join every thread before reporting the first error
+            let mut results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            results.push(producer.join().unwrap());
+            results.into_iter().collect::<crate::Result<()>>()
+        })
+    }
+}
+
+/// Processes batches from the channel until it is drained or processing fails.
+fn run_worker<P: ParallelProcessor>(
+    processor: &mut P,
+    tid: usize,
+    receiver: &Receiver<Vec<Record>>,
+    failed: &AtomicBool,
+) -> crate::Result<()> {
+    processor.set_tid(tid);
+    let mut any_batch = false;
+    while !failed.load(Ordering::Relaxed) {
+        let Ok(batch) = receiver.recv() else {
+            break;
+        };
+        any_batch = true;
+        for record in batch {
+            processor.process_record(record)?;
+        }
+        processor.on_batch_complete()?;
+    }
+    if failed.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    if !any_batch {
+        processor.on_batch_complete()?;
+    }
+    processor.on_thread_complete()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Writer;
+    use std::{
+        io::Cursor,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+    };
+
+    #[derive(Clone, Default)]
+    struct SumProcessor {
+        local: u64,
+        sum: Arc<AtomicU64>,
+        batches: Arc<AtomicU64>,
+        threads: Arc<AtomicU64>,
+        fail_on: Option<u64>,
+    }
+
+    impl ParallelProcessor for SumProcessor {
+        fn process_record(&mut self, record: Record) -> crate::Result<()> {
+            if Some(record.index) == self.fail_on {
+                return Err(IbuError::Process("Test error".into()));
+            }
+            self.local += record.index;
+            Ok(())
+        }
+
+        fn on_batch_complete(&mut self) -> crate::Result<()> {
+            self.sum
+                .fetch_add(std::mem::take(&mut self.local), Ordering::Relaxed);
+            self.batches.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn on_thread_complete(&mut self) -> crate::Result<()> {
+            self.threads.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn stream(num_records: u64) -> Reader<Cursor<Vec<u8>>> {
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        for i in 0..num_records {
+            writer.write_record(&Record::new(i, i, i)).unwrap();
+        }
+        writer.finish().unwrap();
+        Reader::new(Cursor::new(writer.into_inner())).unwrap()
+    }
+
+    #[test]
+    fn test_channel_parallel_reader() {
+        let reader = ChannelParallelReader::new(stream(10_007))
+            .batch_size(100)
+            .capacity(2);
+        assert_eq!(reader.header(), Header::new(16, 12));
+
+        let processor = SumProcessor::default();
+        reader.process_parallel(processor.clone(), 4).unwrap();
+        assert_eq!(processor.sum.load(Ordering::Relaxed), 10_006 * 10_007 / 2);
+        assert!(processor.batches.load(Ordering::Relaxed) >= 101);
+        let threads = processor.threads.load(Ordering::Relaxed);
+        assert_eq!(threads, resolve_num_threads(4) as u64);
+
+        // The stream is consumed
+        assert!(reader.process_parallel(processor, 4).is_err());
+    }
+
+    #[test]
+    fn test_channel_parallel_reader_empty() {
+        let reader = ChannelParallelReader::new(stream(0));
+        let processor = SumProcessor::default();
+        reader.process_parallel(processor.clone(), 2).unwrap();
+        assert_eq!(processor.sum.load(Ordering::Relaxed), 0);
+        let threads = processor.threads.load(Ordering::Relaxed);
+        assert_eq!(processor.batches.load(Ordering::Relaxed), threads);
+    }
+
+    #[test]
+    fn test_channel_parallel_reader_errors() {
+        let processor = SumProcessor {
+            fail_on: Some(5000),
+            ..Default::default()
+        };
+        let reader = ChannelParallelReader::new(stream(10_000)).batch_size(100);
+        assert!(matches!(
+            reader.process_parallel(processor, 2),
+            Err(IbuError::Process(_))
+        ));
+
+        // A panicking worker is reported with its message
+        #[derive(Clone)]
+        struct PanicProcessor;
+        impl ParallelProcessor for PanicProcessor {
+            fn process_record(&mut self, _record: Record) -> crate::Result<()> {
+                panic!("worker exploded");
+            }
+        }
+        let reader = ChannelParallelReader::new(stream(1000)).batch_size(100);
+        match reader.process_parallel(PanicProcessor, 2) {
+            Err(IbuError::Process(e)) => assert!(e.to_string().contains("worker exploded")),
+            other => panic!("unexpected result {other:?}"),
+        }
+
+        // Truncated stream
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_record(&Record::new(1, 2, 3)).unwrap();
+        writer.finish().unwrap();
+        let mut bytes = writer.into_inner();
+        bytes.pop();
+        let reader = ChannelParallelReader::new(Reader::new(Cursor::new(bytes)).unwrap());
+        assert!(reader.process_parallel(SumProcessor::default(), 2).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Compressed output streams with explicit finalization.
+//!
+//! Compression encoders buffer data internally and write a trailer once the
+//! stream ends. Dropping an encoder finishes it too, but any error is lost at
+//! that point, so [`CompressedWriter`] keeps the concrete encoder and finishes
+//! it explicitly from [`Writer::finish`](crate::Writer::finish).
+
+use std::io::{self, Write};
+
+use super::writer::FinishStream;
+
+/// Concrete encoder for each supported format.
+enum Encoder<W: Write> {
+    /// Uncompressed output
+    Plain(W),
+
+    /// Gzip encoder
+    Gzip(flate2::write::GzEncoder<W>),
+
+    /// Bzip2 encoder
+    Bzip(bzip2::write::BzEncoder<W>),
+
+    /// Xz encoder
+    Lzma(liblzma::write::XzEncoder<W>),
+
+    /// Zstd encoder
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+/// Encoder that reports errors when its stream is finished.
+///
+/// Once [`finish_stream`](FinishStream::finish_stream) has succeeded, the
+/// compressed stream is complete and further writes fail instead of
+/// appending data after the trailer.
+pub(crate) struct CompressedWriter<W: Write> {
+    /// Encoder over the output
+    encoder: Encoder<W>,
+
+    /// Whether the stream has been finished
+    finished: bool,
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Wraps `inner` in an encoder for `format` at `level`.
+    ///
+    /// Levels beyond a format's maximum are clamped to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the zstd encoder cannot be created.
+    pub(crate) fn new(
+        inner: W,
+        format: niffler::Format,
+        level: niffler::Level,
+    ) -> io::Result<Self> {
+        let encoder = match format {
+            niffler::Format::No => Encoder::Plain(inner),
+            niffler::Format::Gzip => {
+                Encoder::Gzip(flate2::write::GzEncoder::new(inner, level.into()))
+            }
+            niffler::Format::Bzip => {
+                Encoder::Bzip(bzip2::write::BzEncoder::new(inner, level.into()))
+            }
+            niffler::Format::Lzma => Encoder::Lzma(liblzma::write::XzEncoder::new(
+                inner,
+                u32::from(level).min(9),
+            )),
+            niffler::Format::Zstd => {
+                Encoder::Zstd(zstd::stream::write::Encoder::new(inner, level.into())?)
+            }
+        };
+        Ok(Self {
+            encoder,
+            finished: false,
+        })
+    }
+
+    /// Returns the encoder, failing if the stream was already finished.
+    fn encoder(&mut self) -> io::Result<&mut dyn Write> {
+        if self.finished {
+            return Err(io::Error::other(
+                "write after the compressed stream was finished",
+            ));
+        }
+        Ok(match &mut self.encoder {
+            Encoder::Plain(inner) => inner,
+            Encoder::Gzip(encoder) => encoder,
+            Encoder::Bzip(encoder) => encoder,
+            Encoder::Lzma(encoder) => encoder,
+            Encoder::Zstd(encoder) => encoder,
+        })
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.encoder()?.flush()
+    }
+}
+
+impl<W: Write + Send> FinishStream for CompressedWriter<W> {
+    fn finish_stream(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        match &mut self.encoder {
+            Encoder::Plain(inner) => inner.flush()?,
+            Encoder::Gzip(encoder) => {
+                encoder.try_finish()?;
+                encoder.get_mut().flush()?;
+            }
+            Encoder::Bzip(encoder) => {
+                encoder.try_finish()?;
+                encoder.get_mut().flush()?;
+            }
+            Encoder::Lzma(encoder) => {
+                encoder.try_finish()?;
+                encoder.get_mut().flush()?;
+            }
+            Encoder::Zstd(encoder) => {
+                encoder.do_finish()?;
+                encoder.get_mut().flush()?;
+            }
+        }
+        self.finished = true;
+        Ok(())
+    }
+}
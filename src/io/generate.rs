@@ -0,0 +1,266 @@
+//! Random record generation for benchmarking.
+//!
+//! This module provides a canonical parallel generator that writes random
+//! records to a file as fast as possible, for standardized throughput
+//! measurements across examples and benchmarks.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Seek, SeekFrom},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    packed::base_mask, parallel::catch_panic, Header, Record, Writer, HEADER_SIZE, RECORD_SIZE,
+};
+
+/// Small deterministic PRNG (SplitMix64) used for record generation.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Writes random records to a file in parallel and returns the elapsed time.
+///
+/// The file is pre-sized to hold the header and all records, then each thread
+/// generates a contiguous range of records and writes it at its own offset
+/// through a headless [`Writer`]. Barcodes and UMIs are masked to the lengths
+/// given in the header; indices are uniformly random.
+///
+/// Each thread's generator is seeded deterministically from `seed` and its
+/// thread id, so the output is reproducible for a fixed `seed` and
+/// `num_threads`.
+///
+/// # Arguments
+///
+/// * `path` - Output file path (created or truncated)
+/// * `header` - Header to write
+/// * `num_records` - Total number of records to generate
+/// * `num_threads` - Number of threads to use (0 = use all available cores)
+/// * `seed` - Base seed for the per-thread generators
+///
+/// # Errors
+///
+/// Returns an error if the header is invalid, or if creating, resizing or
+/// writing the file fails. A panic in a worker thread is returned as
+/// [`IbuError::Process`](crate::IbuError::Process) with the panic message.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{write_random_parallel, Header};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let num_records = 100_000_000;
+/// let elapsed = write_random_parallel("random.ibu", Header::new(16, 12), num_records, 0, 42)?;
+///
+/// let gb = (num_records as f64 * 24.0) / 1e9;
+/// println!("Wrote {:.2} GB in {:?} ({:.2} GB/s)", gb, elapsed, gb / elapsed.as_secs_f64());
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_random_parallel<P: AsRef<Path>>(
+    path: P,
+    header: Header,
+    num_records: u64,
+    num_threads: usize,
+    seed: u64,
+) -> crate::Result<Duration> {
+    write_random_parallel_with(path, header, num_records, num_threads, seed, None, None)
+}
+
+/// Writes random records to a file in parallel with bounded barcodes and indices.
+///
+/// Behaves like [`write_random_parallel`], except that barcodes are drawn
+/// from `0..barcodes` and indices from `0..max_index` when given, to model a
+/// fixed number of cells or a bounded index space. Barcodes are still masked
+/// to the header's barcode length.
+///
+/// # Arguments
+///
+/// * `barcodes` - Number of distinct barcode values (None = any value)
+/// * `max_index` - Exclusive upper bound on indices (None = any value)
+///
+/// See [`write_random_parallel`] for the other arguments.
+///
+/// # Errors
+///
+/// Returns an [`InvalidInput`](std::io::ErrorKind::InvalidInput) I/O error
+/// if `barcodes` or `max_index` is zero, or the errors of
+/// [`write_random_parallel`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{write_random_parallel_with, Header};
+///
+/// # fn main() -> ibu::Result<()> {
+/// // 1,000 cells with indices below 10,000
+/// write_random_parallel_with(
+///     "random.ibu",
+///     Header::new(16, 12),
+///     1_000_000,
+///     0,
+///     42,
+///     Some(1_000),
+///     Some(10_000),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_random_parallel_with<P: AsRef<Path>>(
+    path: P,
+    header: Header,
+    num_records: u64,
+    num_threads: usize,
+    seed: u64,
+    barcodes: Option<u64>,
+    max_index: Option<u64>,
+) -> crate::Result<Duration> {
+    header.validate()?;
+    if barcodes == Some(0) || max_index == Some(0) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "barcode and index bounds must be positive",
+        )
+        .into());
+    }
+    let num_threads = if num_threads == 0 {
+        num_cpus::get()
+    } else {
+        num_threads
+    };
+    let path = path.as_ref();
+
+    let start = Instant::now();
+
+    // Write the header and reserve space for all records
+    {
+        let file = File::create(path)?;
+        let mut writer = Writer::new(&file, header)?;
+        writer.finish()?;
+        file.set_len(HEADER_SIZE as u64 + num_records * RECORD_SIZE as u64)?;
+    }
+
+    let bc_mask = base_mask(header.bc_len);
+    let umi_mask = base_mask(header.umi_len);
+    let records_per_thread = num_records / num_threads as u64;
+    let remainder = num_records % num_threads as u64;
+
+    let mut handles = Vec::with_capacity(num_threads);
+    for tid in 0..num_threads {
+        let start = tid as u64 * records_per_thread;
+        let count = if tid == num_threads - 1 {
+            records_per_thread + remainder
+        } else {
+            records_per_thread
+        };
+        let path = path.to_path_buf();
+        let thread_handle = thread::spawn(move || {
+            catch_panic(|| {
+                let mut file = OpenOptions::new().write(true).open(path)?;
+                file.seek(SeekFrom::Start(
+                    HEADER_SIZE as u64 + start * RECORD_SIZE as u64,
+                ))?;
+
+                let mut rng = SplitMix64(seed ^ (tid as u64).wrapping_mul(0x9e3779b97f4a7c15));
+                let mut writer = Writer::new_headless(file);
+                for _ in 0..count {
+                    let barcode = bounded(rng.next_u64(), barcodes) & bc_mask;
+                    let umi = rng.next_u64() & umi_mask;
+                    let index = bounded(rng.next_u64(), max_index);
+                    writer.write_record(&Record::new(barcode, umi, index))?;
+                }
+                writer.finish()
+            })
+        });
+        handles.push(thread_handle);
+    }
+
+    // Workers catch their own panics, so joining cannot fail
+    for handle in handles {
+        handle.join().unwrap()?;
+    }
+
+    Ok(start.elapsed())
+}
+
+/// Reduces a random value into `0..bound`, or keeps it without a bound.
+fn bounded(value: u64, bound: Option<u64>) -> u64 {
+    bound.map_or(value, |bound| value % bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MmapReader;
+    use std::fs;
+
+    #[test]
+    fn test_write_random_parallel() {
+        let temp_file = "test_write_random_parallel.ibu";
+        let header = Header::new(10, 8);
+
+        write_random_parallel(temp_file, header, 10_001, 4, 7).unwrap();
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert_eq!(reader.len(), 10_001);
+        assert_eq!(reader.header(), header);
+
+        let records = reader.slice(0, reader.len()).unwrap();
+        assert!(records.iter().all(|r| r.barcode < (1 << 20)));
+        assert!(records.iter().all(|r| r.umi < (1 << 16)));
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_write_random_parallel_deterministic() {
+        let paths = ["test_write_random_det_a.ibu", "test_write_random_det_b.ibu"];
+        for path in paths {
+            write_random_parallel(path, Header::new(16, 12), 1000, 3, 42).unwrap();
+        }
+        assert_eq!(fs::read(paths[0]).unwrap(), fs::read(paths[1]).unwrap());
+
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_write_random_parallel_with_bounds() {
+        let temp_file = "test_write_random_bounds.ibu";
+        let header = Header::new(16, 12);
+        write_random_parallel_with(temp_file, header, 5000, 3, 1, Some(10), Some(100)).unwrap();
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        let records = reader.slice(0, reader.len()).unwrap();
+        assert!(records.iter().all(|r| r.barcode < 10 && r.index < 100));
+        assert!(records.iter().all(|r| r.umi < (1 << 24)));
+        fs::remove_file(temp_file).unwrap();
+
+        for (barcodes, max_index) in [(Some(0), None), (None, Some(0))] {
+            assert!(matches!(
+                write_random_parallel_with(temp_file, header, 1, 1, 1, barcodes, max_index),
+                Err(crate::IbuError::Io(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+            ));
+        }
+    }
+
+    #[test]
+    fn test_write_random_parallel_empty() {
+        let temp_file = "test_write_random_empty.ibu";
+        write_random_parallel(temp_file, Header::new(16, 12), 0, 2, 0).unwrap();
+        assert_eq!(MmapReader::new(temp_file).unwrap().len(), 0);
+        fs::remove_file(temp_file).unwrap();
+    }
+}
@@ -4,11 +4,28 @@
 //! for parallel processing. Memory mapping allows the operating system to handle
 //! file I/O efficiently while providing zero-copy access to records.
 
-use std::{fs::File, path::Path, sync::Arc, thread};
+use std::{
+    fs::File,
+    iter::Copied,
+    ops::Range,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
 
 use memmap2::Mmap;
 
-use crate::{parallel::ParallelReader, Header, IbuError, Record, HEADER_SIZE, RECORD_SIZE};
+use crate::{
+    checksum::Checksum,
+    parallel::{
+        catch_panic, resolve_num_threads, thread_ranges, BatchTurns, ParallelOptions,
+        ParallelProcessor, ParallelReader,
+    },
+    slice, Header, IbuError, Record, HEADER_SIZE, RECORD_SIZE,
+};
 
 /// Memory-mapped reader for IBU files.
 ///
@@ -123,6 +140,11 @@ impl MmapReader {
     /// - Memory mapping fails
     /// - The header is invalid
     /// - The file size is inconsistent with the record format
+    /// - The header stores a record count that differs from the number of
+    ///   records in the file ([`IbuError::RecordCountMismatch`])
+    /// - The file is compressed ([`IbuError::CompressedInput`]); use
+    ///   [`MmapReader::open_any`] or the streaming [`Reader`](crate::Reader)
+    ///   instead
     ///
     /// # Safety
     ///
@@ -142,22 +164,121 @@ impl MmapReader {
     /// ```
     pub fn new<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
         let file = File::open(path)?;
-        let map = unsafe { Arc::new(Mmap::map(&file)?) };
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_map(map)
+    }
 
-        // parse header
-        let header = {
-            let header = Header::from_bytes(&map[0..HEADER_SIZE]);
-            header.validate()?;
-            header
-        };
+    /// Creates a memory-mapped reader over a file that may be compressed.
+    ///
+    /// Uncompressed files are mapped as with [`MmapReader::new`]. Compressed
+    /// files (any format detected by niffler, e.g. gzip or zstd) are streamed
+    /// through a decompressor into a temporary file in
+    /// [`std::env::temp_dir`], which is then mapped. Memory use therefore
+    /// stays bounded while decompressing, but the temporary directory needs
+    /// room for the whole decompressed file.
+    ///
+    /// The temporary file is deleted as soon as it is mapped; the mapping
+    /// keeps its data alive until the reader (and all its clones) are
+    /// dropped. On platforms that cannot delete a mapped file (Windows), the
+    /// deletion fails silently and the file is left behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temporary file cannot be created or written,
+    /// or decompression fails, in addition to the errors of
+    /// [`MmapReader::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::MmapReader;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::open_any("data.ibu.zst")?;
+    /// let records = reader.slice(0, reader.len())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "niffler")]
+    pub fn open_any<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
 
-        let record_buffer = &map[HEADER_SIZE..];
-        if record_buffer.len() % RECORD_SIZE != 0 {
-            return Err(IbuError::InvalidMapSize);
+        let (mut reader, format) = niffler::send::from_path(path.as_ref())?;
+        if format == niffler::send::compression::Format::No {
+            return Self::new(path);
         }
-        let len = record_buffer.len() / RECORD_SIZE;
+        let temp = std::env::temp_dir().join(format!(
+            "ibu-{}-{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        let result = Self::map_decompressed(&mut reader, &file);
+        std::fs::remove_file(&temp).ok();
+        result
+    }
 
-        Ok(Self { map, header, len })
+    /// Decompresses `reader` into `file` and maps the result.
+    #[cfg(feature = "niffler")]
+    fn map_decompressed(reader: &mut impl std::io::Read, file: &File) -> crate::Result<Self> {
+        use std::io::Write;
+
+        let mut out = std::io::BufWriter::new(file);
+        let size = std::io::copy(reader, &mut out)?;
+        out.flush()?;
+        drop(out);
+        if size < HEADER_SIZE as u64 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        let map = unsafe { Mmap::map(file)? };
+        Self::from_map(map)
+    }
+
+    /// Parses and validates the header and record region of a mapping.
+    fn from_map(map: Mmap) -> crate::Result<Self> {
+        let (header, len) = parse_mapped(&map)?;
+        Ok(Self {
+            map: Arc::new(map),
+            header,
+            len,
+        })
+    }
+
+    /// Creates a new memory-mapped reader and verifies the record checksum.
+    ///
+    /// Behaves like [`MmapReader::new`], but if the header carries a record
+    /// checksum, all record bytes are hashed and compared against it before
+    /// returning. Files without a checksum are opened without verification.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::ChecksumMismatch`] if the stored checksum does not
+    /// match the record data, in addition to the errors of [`MmapReader::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::MmapReader;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new_verified("data.ibu")?;
+    /// println!("Verified {} records", reader.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_verified<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let reader = Self::new(path)?;
+        if let Some(expected) = reader.header.checksum() {
+            let actual = Checksum::of(&reader.map[HEADER_SIZE..]);
+            if expected != actual {
+                return Err(IbuError::ChecksumMismatch { expected, actual });
+            }
+        }
+        Ok(reader)
     }
     /// Returns the number of records in the file.
     ///
@@ -201,6 +322,49 @@ impl MmapReader {
     pub fn header(&self) -> Header {
         self.header
     }
+
+    /// Returns the record range of shard `shard_id` out of `total_shards`.
+    ///
+    /// The records are split into contiguous ranges whose sizes differ by at
+    /// most one record: the remainder of the division is spread over the
+    /// first shards, one record each. The ranges of all shards cover the file
+    /// exactly once, so workers can each pass their range to
+    /// [`Reader::new_shard`](crate::Reader::new_shard) (or [`slice`](Self::slice))
+    /// without a central coordinator.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidIndex`] if `shard_id >= total_shards`
+    /// (including any shard of zero total shards).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{MmapReader, Reader};
+    /// use std::fs::File;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let (total_shards, shard_id) = (8, 3);
+    /// let range = MmapReader::new("data.ibu")?.shard(total_shards, shard_id)?;
+    /// let reader = Reader::new_shard(File::open("data.ibu")?, range.start, range.end)?;
+    /// for record in reader {
+    ///     let record = record?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shard(&self, total_shards: usize, shard_id: usize) -> crate::Result<Range<usize>> {
+        if shard_id >= total_shards {
+            return Err(IbuError::InvalidIndex {
+                idx: shard_id,
+                max: total_shards,
+            });
+        }
+        let (size, remainder) = (self.len / total_shards, self.len % total_shards);
+        let start = shard_id * size + shard_id.min(remainder);
+        let end = start + size + usize::from(shard_id < remainder);
+        Ok(start..end)
+    }
     /// Returns a slice of records from the specified range.
     ///
     /// Provides zero-copy access to a contiguous range of records. The slice
@@ -268,20 +432,357 @@ impl MmapReader {
         let records = bytemuck::cast_slice(&self.map[start..end]);
         Ok(records)
     }
+
+    /// Returns all records in the file as a single slice.
+    ///
+    /// Unlike [`slice`](Self::slice), this never fails and returns an empty
+    /// slice for files without records.
+    pub(crate) fn records(&self) -> &[Record] {
+        bytemuck::cast_slice(&self.map[HEADER_SIZE..])
+    }
+
+    /// Returns an iterator over all records in file order.
+    ///
+    /// Records are copied out of the mapped data by value. The iterator has an
+    /// exact length and can also be walked from the end.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::MmapReader;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("data.ibu")?;
+    /// for record in reader.iter() {
+    ///     println!("{:?}", record);
+    /// }
+    /// let last = reader.iter().next_back();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter(&self) -> Copied<std::slice::Iter<'_, Record>> {
+        self.records().iter().copied()
+    }
+
+    /// Probes whether the records are sorted by checking a sample of pairs.
+    ///
+    /// Compares `samples` evenly spaced pairs of adjacent records, touching
+    /// only the pages that hold them. A `false` result is definitive: some
+    /// pair is out of order. A `true` result is probabilistic: the file is
+    /// likely sorted, but unsampled pairs may still be out of order. If
+    /// `samples` covers every adjacent pair, the check is exact.
+    ///
+    /// This is a cheap pre-flight before relying on sorted order, e.g. for
+    /// binary search. Use [`verify_sorted`](Self::verify_sorted) for an exact
+    /// guarantee.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Number of adjacent pairs to compare
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::MmapReader;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("data.ibu")?;
+    /// if reader.header().sorted() && !reader.spot_check_sorted(1024) {
+    ///     eprintln!("File claims to be sorted but is not");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spot_check_sorted(&self, samples: usize) -> bool {
+        let records = self.records();
+        let pairs = records.len().saturating_sub(1);
+        if samples >= pairs {
+            return self.verify_sorted();
+        }
+        (0..samples).all(|k| {
+            // Wide arithmetic so `k * pairs` cannot overflow
+            let i = (k as u128 * pairs as u128 / samples as u128) as usize;
+            records[i] <= records[i + 1]
+        })
+    }
+
+    /// Returns whether all records are in sorted order.
+    ///
+    /// This scans every record and ignores the header's sorted flag, so it can
+    /// be used to check that the flag is trustworthy. See
+    /// [`spot_check_sorted`](Self::spot_check_sorted) for a cheaper probe.
+    pub fn verify_sorted(&self) -> bool {
+        slice::is_sorted(self.records())
+    }
+
+    /// Returns the position of a record with the given barcode.
+    ///
+    /// Binary searches the memory-mapped records, touching only O(log n)
+    /// pages. If several records share the barcode, the first of them is
+    /// returned; see [`range_for_barcode`](Self::range_for_barcode) for all
+    /// of them.
+    ///
+    /// Returns `None` if the barcode is not present, or if the header is not
+    /// marked as sorted. The sorted flag is trusted as is; use
+    /// [`verify_sorted`](Self::verify_sorted) to check it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::MmapReader;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("sorted.ibu")?;
+    /// if let Some(pos) = reader.binary_search_barcode(42) {
+    ///     println!("First record with barcode 42 is at {}", pos);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn binary_search_barcode(&self, barcode: u64) -> Option<usize> {
+        self.range_for_barcode(barcode).map(|range| range.start)
+    }
+
+    /// Returns the half-open index range of all records with the given barcode.
+    ///
+    /// Finds both the lower and upper bound by binary search, so runs of
+    /// duplicate barcodes are returned whole. The range can be passed to
+    /// [`slice`](Self::slice) for zero-copy access to the records.
+    ///
+    /// Returns `None` if the barcode is not present, or if the header is not
+    /// marked as sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::MmapReader;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("sorted.ibu")?;
+    /// if let Some(range) = reader.range_for_barcode(42) {
+    ///     let records = reader.slice(range.start, range.end)?;
+    ///     println!("Barcode 42 has {} records", records.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn range_for_barcode(&self, barcode: u64) -> Option<Range<usize>> {
+        if !self.header.sorted() {
+            return None;
+        }
+        let range = slice::barcode_range(self.records(), barcode);
+        (!range.is_empty()).then_some(range)
+    }
+
+    /// Copies all records into caller-provided buffers in parallel.
+    ///
+    /// The file is partitioned into consecutive ranges matching the buffer
+    /// lengths: the first `buffers[0].len()` records are copied into
+    /// `buffers[0]`, the next `buffers[1].len()` into `buffers[1]`, and so on.
+    /// Buffers are distributed across threads as contiguous groups, so each
+    /// buffer is filled by exactly one thread.
+    ///
+    /// This gives explicit control over destination memory, such as pinned or
+    /// specially aligned host buffers for device uploads.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffers` - Destination buffers whose lengths sum to [`len`](Self::len)
+    /// * `num_threads` - Number of threads to use (0 = use all available cores)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::BufferSizeMismatch`] if the buffer lengths do not
+    /// sum to the number of records in the file. No data is copied in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{MmapReader, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("data.ibu")?;
+    /// let half = reader.len() / 2;
+    /// let mut first = vec![Record::default(); half];
+    /// let mut second = vec![Record::default(); reader.len() - half];
+    ///
+    /// reader.copy_chunks_into(&mut [&mut first, &mut second], 2)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn copy_chunks_into(
+        &self,
+        buffers: &mut [&mut [Record]],
+        num_threads: usize,
+    ) -> crate::Result<()> {
+        let total: usize = buffers.iter().map(|b| b.len()).sum();
+        if total != self.len {
+            return Err(IbuError::BufferSizeMismatch {
+                expected: self.len,
+                actual: total,
+            });
+        }
+
+        // Pair every buffer with its source range
+        let mut source = self.records();
+        let mut jobs = Vec::with_capacity(buffers.len());
+        for buffer in buffers.iter_mut() {
+            let (head, tail) = source.split_at(buffer.len());
+            jobs.push((&mut **buffer, head));
+            source = tail;
+        }
+
+        let num_threads = resolve_num_threads(num_threads);
+        let ranges = thread_ranges(jobs.len(), num_threads);
+        let mut jobs = jobs.into_iter();
+        thread::scope(|scope| {
+            for range in ranges {
+                let group: Vec<_> = jobs.by_ref().take(range.len()).collect();
+                scope.spawn(move || {
+                    for (dst, src) in group {
+                        dst.copy_from_slice(src);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    /// Processes records in parallel, one barcode at a time.
+    ///
+    /// Calls `f(barcode, records)` once per distinct barcode with the
+    /// contiguous slice of that barcode's records, so every cell is seen
+    /// whole by a single call. The file is divided into roughly equal record
+    /// ranges, one per thread, with each split moved forward to the next
+    /// barcode boundary (found by binary search), so no barcode is ever
+    /// split across threads. Within a thread, barcodes are visited in
+    /// ascending order.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_threads` - Number of threads to use (0 = use all available cores)
+    /// * `f` - Callback receiving each barcode and its records
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::NotSorted`] if the header is not marked as sorted,
+    /// or the first error returned by `f`. A panic in `f` is returned as
+    /// [`IbuError::Process`] with the panic message. A thread stops at its
+    /// first error; other threads run to completion.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::MmapReader;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("sorted.ibu")?;
+    /// let large_cells = AtomicU64::new(0);
+    /// reader.process_by_barcode(0, |_barcode, records| {
+    ///     if records.len() >= 1000 {
+    ///         large_cells.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    ///     Ok(())
+    /// })?;
+    /// println!("{} cells with at least 1000 records", large_cells.into_inner());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn process_by_barcode<F>(&self, num_threads: usize, f: F) -> crate::Result<()>
+    where
+        F: Fn(u64, &[Record]) -> crate::Result<()> + Sync,
+    {
+        if !self.header.sorted() {
+            return Err(IbuError::NotSorted);
+        }
+        let records = self.records();
+        let num_threads = resolve_num_threads(num_threads).min(records.len().max(1));
+
+        // Move each split point forward past the barcode run it falls in
+        let mut bounds = vec![0];
+        for range in thread_ranges(records.len(), num_threads) {
+            let end = match range.end {
+                0 => 0,
+                end if end == records.len() => end,
+                end => {
+                    let barcode = records[end - 1].barcode;
+                    records.partition_point(|r| r.barcode <= barcode)
+                }
+            };
+            bounds.push(end.max(*bounds.last().unwrap()));
+        }
+
+        let f = &f;
+        thread::scope(|scope| {
+            let handles: Vec<_> = bounds
+                .windows(2)
+                .map(|w| &records[w[0]..w[1]])
+                .filter(|chunk| !chunk.is_empty())
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        catch_panic(|| {
+                            slice::group_by_barcode(chunk).try_for_each(|(bc, run)| f(bc, run))
+                        })
+                    })
+                })
+                .collect();
+
+            // Join every thread before reporting the first error
+            let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            results.into_iter().collect::<crate::Result<()>>()
+        })
+    }
 }
 
-/// Default batch size for parallel processing.
+/// Parses and validates the header of mapped IBU data and counts its records.
 ///
-/// This constant defines how many records are processed in each batch during
-/// parallel processing. The value of 1M records (~24MB) provides a good balance
-/// between:
-/// - Minimizing synchronization overhead (larger batches)
-/// - Maintaining responsive progress updates (smaller batches)
-/// - Fitting comfortably in CPU caches
+/// # Errors
 ///
-/// Each thread processes records in chunks of this size, calling
-/// `on_batch_complete()` after each chunk.
-pub const BATCH_SIZE: usize = 1024 * 1024;
+/// Returns the header and size errors of [`MmapReader::new`].
+pub(crate) fn parse_mapped(map: &[u8]) -> crate::Result<(Header, usize)> {
+    if map.len() < HEADER_SIZE {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+
+    // parse header
+    let header = {
+        let header = Header::from_bytes(&map[0..HEADER_SIZE]);
+        #[cfg(feature = "niffler")]
+        if header.magic != crate::MAGIC {
+            let (_, format) = niffler::send::sniff(Box::new(map))?;
+            if format != niffler::send::compression::Format::No {
+                return Err(IbuError::CompressedInput);
+            }
+        }
+        header.validate()?;
+        header
+    };
+
+    let record_buffer = &map[HEADER_SIZE..];
+    if !record_buffer.len().is_multiple_of(RECORD_SIZE) {
+        return Err(IbuError::InvalidMapSize);
+    }
+    let len = record_buffer.len() / RECORD_SIZE;
+    if let Some(expected) = header.record_count() {
+        if expected != len as u64 {
+            return Err(IbuError::RecordCountMismatch {
+                expected,
+                actual: len as u64,
+            });
+        }
+    }
+    Ok((header, len))
+}
+
+impl<'a> IntoIterator for &'a MmapReader {
+    type Item = Record;
+    type IntoIter = Copied<std::slice::Iter<'a, Record>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
 
 impl ParallelReader for MmapReader {
     fn process_parallel<P: crate::parallel::ParallelProcessor + Clone + 'static>(
@@ -289,45 +790,323 @@ impl ParallelReader for MmapReader {
         processor: P,
         num_threads: usize,
     ) -> crate::Result<()> {
-        let num_threads = if num_threads == 0 {
-            num_cpus::get()
+        self.process(processor, &ParallelOptions::new().threads(num_threads))
+    }
+}
+
+impl MmapReader {
+    /// Processes all records in parallel with look-behind context.
+    ///
+    /// Behaves like [`process_parallel`](ParallelReader::process_parallel),
+    /// but each thread first receives up to `overlap` records preceding its
+    /// range through
+    /// [`process_context_record`](ParallelProcessor::process_context_record).
+    /// This is shorthand for [`process`](Self::process) with
+    /// [`ParallelOptions::overlap`].
+    ///
+    /// # Arguments
+    ///
+    /// * `processor` - The processor to use for handling records
+    /// * `num_threads` - Number of threads to use (0 = use all available cores)
+    /// * `overlap` - Maximum number of context records per thread
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a worker thread cannot be spawned, or the first
+    /// error returned by the processor.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{MmapReader, ParallelProcessor, Record};
+    /// use std::sync::{
+    ///     atomic::{AtomicU64, Ordering},
+    ///     Arc,
+    /// };
+    ///
+    /// /// Counts records whose barcode differs from the previous record's
+    /// #[derive(Clone, Default)]
+    /// struct TransitionCounter {
+    ///     prev: Option<u64>,
+    ///     transitions: Arc<AtomicU64>,
+    /// }
+    ///
+    /// impl ParallelProcessor for TransitionCounter {
+    ///     fn process_record(&mut self, record: Record) -> ibu::Result<()> {
+    ///         if self.prev.is_some_and(|prev| prev != record.barcode) {
+    ///             self.transitions.fetch_add(1, Ordering::Relaxed);
+    ///         }
+    ///         self.prev = Some(record.barcode);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn process_context_record(&mut self, record: Record) -> ibu::Result<()> {
+    ///         self.prev = Some(record.barcode);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("sorted.ibu")?;
+    /// let counter = TransitionCounter::default();
+    /// reader.process_parallel_with_overlap(counter.clone(), 8, 1)?;
+    /// println!("{} barcode transitions", counter.transitions.load(Ordering::Relaxed));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn process_parallel_with_overlap<P: ParallelProcessor>(
+        &self,
+        processor: P,
+        num_threads: usize,
+        overlap: usize,
+    ) -> crate::Result<()> {
+        let options = ParallelOptions::new().threads(num_threads).overlap(overlap);
+        self.process(processor, &options)
+    }
+
+    /// Processes all records in parallel, with threads claiming chunks of
+    /// `chunk_records` records from a shared counter.
+    ///
+    /// Instead of one contiguous range per thread, the file is divided into
+    /// chunks that threads claim one at a time as they finish the previous
+    /// one. This balances the load when the cost per record varies, e.g. for
+    /// whitelist lookups that are much slower on misses. Each chunk is one
+    /// batch: [`on_batch_complete`](ParallelProcessor::on_batch_complete) runs
+    /// after every chunk. See [Work Stealing](ParallelOptions#work-stealing).
+    ///
+    /// # Arguments
+    ///
+    /// * `processor` - The processor to use for handling records
+    /// * `num_threads` - Number of threads to use (0 = use all available cores)
+    /// * `chunk_records` - Number of records per chunk (0 is treated as 1)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a worker thread cannot be spawned, or the first
+    /// error returned by the processor.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{MmapReader, ParallelProcessor, Record};
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct NoOpProcessor;
+    ///
+    /// impl ParallelProcessor for NoOpProcessor {
+    ///     fn process_record(&mut self, _record: Record) -> ibu::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("data.ibu")?;
+    /// reader.process_parallel_chunked(NoOpProcessor, 8, 64 * 1024)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn process_parallel_chunked<P: ParallelProcessor>(
+        &self,
+        processor: P,
+        num_threads: usize,
+        chunk_records: usize,
+    ) -> crate::Result<()> {
+        let options = ParallelOptions::new()
+            .threads(num_threads)
+            .batch_size(chunk_records)
+            .work_stealing(true);
+        self.process(processor, &options)
+    }
+
+    /// Processes all records in parallel with the given options.
+    ///
+    /// This is the configurable entry point behind
+    /// [`process_parallel`](ParallelReader::process_parallel): thread count,
+    /// batch size, ordering, thread names and progress reporting are all set
+    /// through [`ParallelOptions`]. Each thread gets its own clone of the
+    /// processor, whose [`set_tid`](ParallelProcessor::set_tid) is called
+    /// with the thread's 0-based id before any record is processed, and whose
+    /// [`on_thread_complete`](ParallelProcessor::on_thread_complete) is called
+    /// once the thread has processed its last batch.
+    ///
+    /// Threads are only spawned for non-empty ranges, so fewer than the
+    /// requested number of threads run on small files. Every spawned thread
+    /// calls [`on_batch_complete`](ParallelProcessor::on_batch_complete) at
+    /// least once, including the single thread run for an empty file.
+    ///
+    /// # Arguments
+    ///
+    /// * `processor` - The processor to use for handling records
+    /// * `options` - Parallel processing options
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a worker thread cannot be spawned, or the first
+    /// error returned by the processor. A panic in the processor is returned
+    /// as [`IbuError::Process`] with the panic message.
+    ///
+    /// Once any thread fails, the others stop at their next batch boundary
+    /// without calling [`on_thread_complete`](ParallelProcessor::on_thread_complete).
+    /// Cancellation is cooperative at batch granularity: a batch in progress
+    /// is always processed to its end, so lower the batch size for faster
+    /// cancellation. If several threads fail, the error of the lowest thread
+    /// id is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{MmapReader, ParallelOptions, ParallelProcessor, Record};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Collector {
+    ///     local: Vec<u64>,
+    ///     global: Arc<Mutex<Vec<u64>>>,
+    /// }
+    ///
+    /// impl ParallelProcessor for Collector {
+    ///     fn process_record(&mut self, record: Record) -> ibu::Result<()> {
+    ///         self.local.push(record.barcode);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn on_batch_complete(&mut self) -> ibu::Result<()> {
+    ///         self.global.lock().unwrap().append(&mut self.local);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = MmapReader::new("data.ibu")?;
+    /// let collector = Collector::default();
+    ///
+    /// // Barcodes are collected in file order
+    /// reader.process(collector.clone(), &ParallelOptions::new().ordered(true))?;
+    /// assert_eq!(collector.global.lock().unwrap().len(), reader.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn process<P: ParallelProcessor>(
+        &self,
+        processor: P,
+        options: &ParallelOptions,
+    ) -> crate::Result<()> {
+        let records = self.records();
+        let batch_size = options.batch_size;
+        let num_batches = records.len().div_ceil(batch_size);
+        // Never spawn threads without work: each needs a record (or, in
+        // ordered and work-stealing modes, a batch). Empty files still run a single thread.
+        let max_threads = if options.ordered || options.work_stealing {
+            num_batches
         } else {
-            num_threads.min(num_cpus::get())
+            records.len()
         };
-        let records_per_thread = self.len / num_threads;
-        let remainder = self.len % num_threads; // for last thread
-
-        let mut handles = Vec::with_capacity(num_threads);
-        for i in 0..num_threads {
-            let start = i * records_per_thread;
-            let end = if i == num_threads - 1 {
-                start + records_per_thread + remainder
-            } else {
-                start + records_per_thread
-            };
-            let thread_reader = self.clone();
-            let mut thread_processor = processor.clone();
-            let thread_handle = thread::spawn(move || -> crate::Result<()> {
-                let mut batch_start = start;
-                while batch_start < end {
-                    let batch_end = (batch_start + BATCH_SIZE).min(end);
-                    let slice = thread_reader.slice(batch_start, batch_end)?;
-                    for record in slice {
-                        thread_processor.process_record(*record)?;
+        let num_threads = resolve_num_threads(options.threads).min(max_threads.max(1));
+        let progress = AtomicU64::new(0);
+        let turns = BatchTurns::default();
+        let failed = AtomicBool::new(false);
+        let next_batch = AtomicUsize::new(0);
+        let batch_range = move |b: usize| b * batch_size..((b + 1) * batch_size).min(records.len());
+
+        thread::scope(|scope| {
+            let mut handles = Vec::with_capacity(num_threads);
+            for (tid, Range { start, end }) in thread_ranges(records.len(), num_threads).enumerate()
+            {
+                // Batches as (batch id, record range)
+                let batches: Box<dyn Iterator<Item = (usize, Range<usize>)> + Send> =
+                    if options.work_stealing {
+                        let next_batch = &next_batch;
+                        Box::new(std::iter::from_fn(move || {
+                            let b = next_batch.fetch_add(1, Ordering::Relaxed);
+                            (b < num_batches).then(|| (b, batch_range(b)))
+                        }))
+                    } else if options.ordered {
+                        Box::new(
+                            (tid..num_batches)
+                                .step_by(num_threads)
+                                .map(move |b| (b, batch_range(b))),
+                        )
+                    } else {
+                        Box::new(
+                            (start..end)
+                                .step_by(batch_size)
+                                .map(move |s| (0, s..(s + batch_size).min(end))),
+                        )
+                    };
+
+                let mut processor = processor.clone();
+                let (progress, turns, failed) = (&progress, &turns, &failed);
+                let work = move || -> crate::Result<()> {
+                    processor.set_tid(tid);
+                    let mut prev_end = None;
+                    let mut any_batch = false;
+                    for (batch, range) in batches {
+                        // Stop early once another thread failed
+                        if failed.load(Ordering::Relaxed) {
+                            return Ok(());
+                        }
+                        any_batch = true;
+                        let len = range.len() as u64;
+                        if options.overlap > 0 && prev_end != Some(range.start) {
+                            let context = range.start.saturating_sub(options.overlap);
+                            for record in &records[context..range.start] {
+                                processor.process_context_record(*record)?;
+                            }
+                        }
+                        prev_end = Some(range.end);
+                        for record in &records[range] {
+                            processor.process_record(*record)?;
+                        }
+                        if options.ordered {
+                            if !turns.wait_for(batch) {
+                                return Ok(());
+                            }
+                            let result = processor.on_batch_complete();
+                            if result.is_ok() {
+                                turns.advance();
+                            }
+                            result?;
+                        } else {
+                            processor.on_batch_complete()?;
+                        }
+                        let done = progress.fetch_add(len, Ordering::Relaxed) + len;
+                        if let Some(callback) = &options.progress_callback {
+                            callback(done);
+                        }
                     }
-                    thread_processor.on_batch_complete()?;
-                    batch_start += BATCH_SIZE;
-                }
-                Ok(())
-            });
-            handles.push(thread_handle);
-        }
+                    if !any_batch {
+                        // Only an empty file leaves a thread without batches
+                        processor.on_batch_complete()?;
+                    }
+                    processor.on_thread_complete()
+                };
+                let work = move || {
+                    let result = catch_panic(work);
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                        turns.abort();
+                    }
+                    result
+                };
 
-        for handle in handles {
-            handle.join().unwrap()?;
-        }
+                let mut builder = thread::Builder::new();
+                if let Some(prefix) = &options.thread_name_prefix {
+                    builder = builder.name(format!("{prefix}-{tid}"));
+                }
+                match builder.spawn_scoped(scope, work) {
+                    Ok(handle) => handles.push(handle),
+                    Err(e) => {
+                        failed.store(true, Ordering::Relaxed);
+                        turns.abort();
+                        return Err(e.into());
+                    }
+                }
+            }
 
-        Ok(())
+            // Join every thread before reporting the first error
+            let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            results.into_iter().collect::<crate::Result<()>>()
+        })
     }
 }
 
@@ -336,8 +1115,7 @@ mod tests {
     use super::*;
     use crate::{Header, Record, Writer};
     use std::fs;
-    use std::sync::atomic::{AtomicU64, Ordering};
-    use std::sync::Arc;
+    use std::sync::Mutex;
 
     fn create_test_file(path: &str, records: &[Record]) {
         let header = Header::new(16, 12);
@@ -393,6 +1171,31 @@ mod tests {
         fs::remove_file(temp_file).unwrap();
     }
 
+    #[test]
+    fn test_mmap_reader_iter() {
+        let temp_file = "test_mmap_iter.ibu";
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, i * 2, i * 3)).collect();
+        create_test_file(temp_file, &records);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert_eq!(reader.iter().collect::<Vec<_>>(), records);
+        assert_eq!(reader.iter().size_hint(), (100, Some(100)));
+
+        let mut iter = reader.iter();
+        assert_eq!(iter.next_back(), Some(records[99]));
+        assert_eq!(iter.next(), Some(records[0]));
+        assert_eq!(iter.len(), 98);
+
+        let mut count = 0;
+        for record in &reader {
+            assert_eq!(record, records[count]);
+            count += 1;
+        }
+        assert_eq!(count, 100);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
     #[test]
     fn test_mmap_reader_slice() {
         let temp_file = "test_mmap_slice.ibu";
@@ -565,10 +1368,692 @@ mod tests {
     }
 
     #[test]
-    fn test_batch_size_constant() {
-        assert_eq!(BATCH_SIZE, 1024 * 1024);
-        assert!(BATCH_SIZE > 0);
-        // Should be reasonable size for memory usage
-        assert!(BATCH_SIZE * RECORD_SIZE < 100 * 1024 * 1024); // < 100MB
+    fn test_mmap_reader_verified() {
+        let temp_file = "test_mmap_verified.ibu";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, i * 2, i * 3)).collect();
+
+        {
+            let file = fs::File::create(temp_file).unwrap();
+            let mut writer = Writer::new_with_checksum(file, Header::new(16, 12)).unwrap();
+            writer.write_batch(&records).unwrap();
+            writer.finish_with_checksum().unwrap();
+        }
+
+        let reader = MmapReader::new_verified(temp_file).unwrap();
+        assert_eq!(reader.len(), 1000);
+
+        // Corrupt a record byte
+        let mut bytes = fs::read(temp_file).unwrap();
+        bytes[HEADER_SIZE] ^= 0xFF;
+        fs::write(temp_file, &bytes).unwrap();
+
+        assert!(MmapReader::new(temp_file).is_ok());
+        assert!(matches!(
+            MmapReader::new_verified(temp_file),
+            Err(IbuError::ChecksumMismatch { .. })
+        ));
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_copy_chunks_into() {
+        let temp_file = "test_mmap_copy_chunks.ibu";
+        let records: Vec<Record> = (0..10_000).map(|i| Record::new(i, i * 2, i * 3)).collect();
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+
+        // Uneven buffer sizes, including an empty buffer
+        let sizes = [1, 4999, 0, 3000, 2000];
+        let mut storage: Vec<Vec<Record>> =
+            sizes.iter().map(|&n| vec![Record::default(); n]).collect();
+        for num_threads in [1, 2, 8] {
+            let mut buffers: Vec<&mut [Record]> =
+                storage.iter_mut().map(|b| b.as_mut_slice()).collect();
+            reader.copy_chunks_into(&mut buffers, num_threads).unwrap();
+            assert_eq!(storage.concat(), records);
+        }
+
+        // Sizes must sum to the record count
+        let mut short = vec![Record::default(); 9_999];
+        assert!(matches!(
+            reader.copy_chunks_into(&mut [&mut short], 2),
+            Err(IbuError::BufferSizeMismatch {
+                expected: 10_000,
+                actual: 9_999
+            })
+        ));
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[derive(Clone, Default)]
+    struct OrderProcessor {
+        local: Vec<u64>,
+        global: Arc<Mutex<Vec<u64>>>,
+        thread_names: Arc<Mutex<Vec<String>>>,
+        tids: Arc<Mutex<Vec<usize>>>,
+        completed_tids: Arc<Mutex<Vec<usize>>>,
+        batch_tids: Arc<Mutex<Vec<usize>>>,
+        tid: usize,
+        fail_on: Option<u64>,
+    }
+
+    impl ParallelProcessor for OrderProcessor {
+        fn process_record(&mut self, record: Record) -> crate::Result<()> {
+            if Some(record.index) == self.fail_on {
+                return Err(IbuError::Process("Test error".into()));
+            }
+            self.local.push(record.index);
+            Ok(())
+        }
+
+        fn on_batch_complete(&mut self) -> crate::Result<()> {
+            self.global.lock().unwrap().append(&mut self.local);
+            let name = thread::current().name().unwrap_or_default().to_string();
+            self.thread_names.lock().unwrap().push(name);
+            self.batch_tids.lock().unwrap().push(self.tid);
+            Ok(())
+        }
+
+        fn set_tid(&mut self, tid: usize) {
+            self.tid = tid;
+            self.tids.lock().unwrap().push(tid);
+        }
+
+        fn on_thread_complete(&mut self) -> crate::Result<()> {
+            self.completed_tids.lock().unwrap().push(self.tid);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mmap_reader_process_ordered() {
+        let temp_file = "test_mmap_process_ordered.ibu";
+        let records: Vec<Record> = (0..10_007).map(|i| Record::new(i, 0, i)).collect();
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+
+        let processor = OrderProcessor::default();
+        let progress = Arc::new(AtomicU64::new(0));
+        let max_progress = progress.clone();
+        let options = ParallelOptions::new()
+            .threads(4)
+            .batch_size(100)
+            .ordered(true)
+            .thread_name_prefix("ibu-test")
+            .progress_callback(move |done| {
+                max_progress.fetch_max(done, Ordering::Relaxed);
+            });
+        reader.process(processor.clone(), &options).unwrap();
+
+        let expected: Vec<u64> = (0..10_007).collect();
+        assert_eq!(*processor.global.lock().unwrap(), expected);
+        assert_eq!(progress.load(Ordering::Relaxed), 10_007);
+
+        // One completion per batch, all on named worker threads
+        let names = processor.thread_names.lock().unwrap();
+        assert_eq!(names.len(), 101);
+        assert!(names.iter().all(|n| n.starts_with("ibu-test-")));
+
+        let mut tids = processor.tids.lock().unwrap().clone();
+        tids.sort();
+        assert_eq!(tids, (0..resolve_num_threads(4)).collect::<Vec<_>>());
+
+        // Each thread finishes exactly once
+        let mut completed = processor.completed_tids.lock().unwrap().clone();
+        completed.sort();
+        assert_eq!(completed, tids);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_more_threads_than_records() {
+        let temp_file = "test_mmap_more_threads.ibu";
+        create_test_file(temp_file, &[Record::new(1, 2, 3)]);
+        let reader = MmapReader::new(temp_file).unwrap();
+
+        for ordered in [false, true] {
+            let processor = OrderProcessor::default();
+            let options = ParallelOptions::new().threads(16).ordered(ordered);
+            reader.process(processor.clone(), &options).unwrap();
+
+            assert_eq!(*processor.tids.lock().unwrap(), vec![0]);
+            assert_eq!(*processor.global.lock().unwrap(), vec![3]);
+        }
+
+        // Three records: at most one thread per record (or per batch when
+        // ordered), and every thread completes at least one batch
+        let records: Vec<Record> = (0..3).map(|i| Record::new(i, 0, i)).collect();
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+        for (ordered, batch_size, max_threads) in [
+            (false, crate::BATCH_SIZE, 3),
+            (true, crate::BATCH_SIZE, 1),
+            (true, 1, 3),
+        ] {
+            let processor = OrderProcessor::default();
+            let options = ParallelOptions::new()
+                .threads(16)
+                .ordered(ordered)
+                .batch_size(batch_size);
+            reader.process(processor.clone(), &options).unwrap();
+
+            let mut tids = processor.tids.lock().unwrap().clone();
+            tids.sort();
+            assert!(!tids.is_empty() && tids.len() <= max_threads);
+            let mut batch_tids = processor.batch_tids.lock().unwrap().clone();
+            batch_tids.sort();
+            batch_tids.dedup();
+            assert_eq!(batch_tids, tids);
+            let mut values = processor.global.lock().unwrap().clone();
+            values.sort();
+            assert_eq!(values, vec![0, 1, 2]);
+        }
+
+        // Empty files still run a single thread, which completes one batch
+        create_test_file(temp_file, &[]);
+        let reader = MmapReader::new(temp_file).unwrap();
+        for ordered in [false, true] {
+            let processor = OrderProcessor::default();
+            let options = ParallelOptions::new().threads(16).ordered(ordered);
+            reader.process(processor.clone(), &options).unwrap();
+            assert_eq!(*processor.tids.lock().unwrap(), vec![0]);
+            assert_eq!(*processor.batch_tids.lock().unwrap(), vec![0]);
+            assert_eq!(*processor.completed_tids.lock().unwrap(), vec![0]);
+        }
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_process_ordered_error() {
+        let temp_file = "test_mmap_process_ordered_error.ibu";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, 0, i)).collect();
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+
+        // An early failure must not leave other threads waiting for its turn
+        let processor = OrderProcessor {
+            fail_on: Some(5),
+            ..Default::default()
+        };
+        let options = ParallelOptions::new()
+            .threads(4)
+            .batch_size(10)
+            .ordered(true);
+        assert!(matches!(
+            reader.process(processor, &options),
+            Err(IbuError::Process(_))
+        ));
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    /// Indices of each completed batch, paired with the completing thread id
+    type ThreadBatches = Arc<Mutex<Vec<(usize, Vec<u64>)>>>;
+
+    #[derive(Clone, Default)]
+    struct ChunkProcessor {
+        tid: usize,
+        local: Vec<u64>,
+        batches: ThreadBatches,
+    }
+
+    impl ParallelProcessor for ChunkProcessor {
+        fn process_record(&mut self, record: Record) -> crate::Result<()> {
+            self.local.push(record.index);
+            Ok(())
+        }
+
+        fn on_batch_complete(&mut self) -> crate::Result<()> {
+            let batch = std::mem::take(&mut self.local);
+            self.batches.lock().unwrap().push((self.tid, batch));
+            Ok(())
+        }
+
+        fn set_tid(&mut self, tid: usize) {
+            self.tid = tid;
+        }
+    }
+
+    #[test]
+    fn test_mmap_reader_process_parallel_chunked() {
+        let temp_file = "test_mmap_process_chunked.ibu";
+        let records: Vec<Record> = (0..1005).map(|i| Record::new(i, 0, i)).collect();
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+
+        let processor = ChunkProcessor::default();
+        reader
+            .process_parallel_chunked(processor.clone(), 4, 10)
+            .unwrap();
+        let mut batches = processor.batches.lock().unwrap().clone();
+        assert_eq!(batches.len(), 101);
+        // Every chunk is one aligned batch, and all records are seen once
+        batches.sort_by_key(|(_, batch)| batch[0]);
+        for (i, (_, batch)) in batches.iter().enumerate() {
+            let end = (10 * (i as u64 + 1)).min(1005);
+            assert_eq!(*batch, (10 * i as u64..end).collect::<Vec<_>>());
+        }
+
+        // Ordered work stealing completes batches in file order
+        let processor = ChunkProcessor::default();
+        let options = ParallelOptions::new()
+            .threads(4)
+            .batch_size(10)
+            .work_stealing(true)
+            .ordered(true);
+        reader.process(processor.clone(), &options).unwrap();
+        let indices: Vec<u64> = processor
+            .batches
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|(_, batch)| batch.clone())
+            .collect();
+        assert_eq!(indices, (0..1005).collect::<Vec<_>>());
+
+        // Empty files still complete a single batch
+        let empty_file = "test_mmap_process_chunked_empty.ibu";
+        create_test_file(empty_file, &[]);
+        let processor = ChunkProcessor::default();
+        MmapReader::new(empty_file)
+            .unwrap()
+            .process_parallel_chunked(processor.clone(), 4, 0)
+            .unwrap();
+        assert_eq!(*processor.batches.lock().unwrap(), [(0, vec![])]);
+
+        fs::remove_file(temp_file).unwrap();
+        fs::remove_file(empty_file).unwrap();
+    }
+
+    #[derive(Clone, Default)]
+    struct FailFastProcessor {
+        processed: Arc<AtomicU64>,
+        completed_threads: Arc<AtomicU64>,
+    }
+
+    impl ParallelProcessor for FailFastProcessor {
+        fn process_record(&mut self, record: Record) -> crate::Result<()> {
+            if record.index == 0 {
+                return Err(IbuError::Process("first record".into()));
+            }
+            self.processed.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn on_batch_complete(&mut self) -> crate::Result<()> {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            Ok(())
+        }
+
+        fn on_thread_complete(&mut self) -> crate::Result<()> {
+            self.completed_threads.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mmap_reader_process_cancels_on_error() {
+        let temp_file = "test_mmap_process_cancel.ibu";
+        let records: Vec<Record> = (0..20_000).map(|i| Record::new(i, 0, i)).collect();
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+
+        let processor = FailFastProcessor::default();
+        let options = ParallelOptions::new().threads(4).batch_size(10);
+        match reader.process(processor.clone(), &options) {
+            Err(IbuError::Process(e)) => assert_eq!(e.to_string(), "first record"),
+            other => panic!("unexpected result {other:?}"),
+        }
+        // Without cancellation the other threads would process 15,000 records
+        // at 1ms per batch of 10
+        assert!(processor.processed.load(Ordering::Relaxed) < 5_000);
+        assert_eq!(processor.completed_threads.load(Ordering::Relaxed), 0);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[derive(Clone, Default)]
+    struct PanicProcessor;
+
+    impl ParallelProcessor for PanicProcessor {
+        fn process_record(&mut self, record: Record) -> crate::Result<()> {
+            if record.index == 5 {
+                panic!("bad record {}", record.index);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mmap_reader_process_panic() {
+        let temp_file = "test_mmap_process_panic.ibu";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, 0, i)).collect();
+        create_test_file(temp_file, &records);
+        let mut reader = MmapReader::new(temp_file).unwrap();
+
+        // Panics surface as errors carrying the message, in both modes
+        for ordered in [false, true] {
+            let options = ParallelOptions::new()
+                .threads(4)
+                .batch_size(10)
+                .ordered(ordered);
+            match reader.process(PanicProcessor, &options) {
+                Err(IbuError::Process(e)) => assert!(e.to_string().contains("bad record 5")),
+                other => panic!("unexpected result {other:?}"),
+            }
+        }
+
+        reader.header.set_sorted();
+        let result = reader.process_by_barcode(2, |_, _| panic!("static message"));
+        match result {
+            Err(IbuError::Process(e)) => assert!(e.to_string().contains("static message")),
+            other => panic!("unexpected result {other:?}"),
+        }
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    /// Context run indices paired with the index of the record that follows
+    type ContextRuns = Arc<Mutex<Vec<(Vec<u64>, u64)>>>;
+
+    #[derive(Clone, Default)]
+    struct ContextProcessor {
+        context: Vec<u64>,
+        runs: ContextRuns,
+        processed: Arc<AtomicU64>,
+    }
+
+    impl ParallelProcessor for ContextProcessor {
+        fn process_record(&mut self, record: Record) -> crate::Result<()> {
+            if !self.context.is_empty() {
+                let context = std::mem::take(&mut self.context);
+                self.runs.lock().unwrap().push((context, record.index));
+            }
+            self.processed.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn process_context_record(&mut self, record: Record) -> crate::Result<()> {
+            self.context.push(record.index);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mmap_reader_process_with_overlap() {
+        let temp_file = "test_mmap_process_overlap.ibu";
+        let records: Vec<Record> = (0..10_000).map(|i| Record::new(i, 0, i)).collect();
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+
+        for ordered in [false, true] {
+            let processor = ContextProcessor::default();
+            let options = ParallelOptions::new()
+                .threads(4)
+                .batch_size(1000)
+                .ordered(ordered)
+                .overlap(3);
+            reader.process(processor.clone(), &options).unwrap();
+
+            // Context records are not counted as processed
+            assert_eq!(processor.processed.load(Ordering::Relaxed), 10_000);
+
+            // Every run is the 3 records immediately before its range
+            let runs = processor.runs.lock().unwrap();
+            for (context, first) in runs.iter() {
+                assert_eq!(*context, vec![first - 3, first - 2, first - 1]);
+            }
+        }
+
+        // Context is clamped at the start of the file
+        let processor = ContextProcessor::default();
+        reader
+            .process_parallel_with_overlap(processor.clone(), 2, 10_000)
+            .unwrap();
+        assert_eq!(processor.processed.load(Ordering::Relaxed), 10_000);
+        let runs = processor.runs.lock().unwrap();
+        if let Some((context, first)) = runs.first() {
+            assert_eq!(context.len() as u64, *first);
+        }
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_spot_check_sorted() {
+        let temp_file = "test_mmap_spot_check_sorted.ibu";
+        let mut records: Vec<Record> = (0..1000).map(|i| Record::new(i, 0, 0)).collect();
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert!(reader.spot_check_sorted(10));
+        assert!(reader.verify_sorted());
+        drop(reader);
+
+        // A single inversion is missed by a sparse probe but caught by a full one
+        records.swap(500, 501);
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert!(reader.spot_check_sorted(0));
+        assert!(reader.spot_check_sorted(9));
+        assert!(!reader.spot_check_sorted(999));
+        assert!(!reader.spot_check_sorted(usize::MAX));
+        assert!(!reader.verify_sorted());
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_process_by_barcode() {
+        let temp_file = "test_mmap_process_by_barcode.ibu";
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+
+        // Uneven runs, including one spanning most of the file
+        let mut records = Vec::new();
+        for (barcode, count) in [(1u64, 3u64), (2, 5000), (5, 1), (9, 700), (10, 2)] {
+            records.extend((0..count).map(|i| Record::new(barcode, 0, i)));
+        }
+        let file = fs::File::create(temp_file).unwrap();
+        let mut writer = Writer::new(file, header).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        for num_threads in [0, 1, 4] {
+            let seen = Mutex::new(Vec::new());
+            reader
+                .process_by_barcode(num_threads, |barcode, run| {
+                    assert!(run.iter().all(|r| r.barcode == barcode));
+                    seen.lock().unwrap().push((barcode, run.len()));
+                    Ok(())
+                })
+                .unwrap();
+            let mut seen = seen.into_inner().unwrap();
+            seen.sort();
+            assert_eq!(seen, vec![(1, 3), (2, 5000), (5, 1), (9, 700), (10, 2)]);
+        }
+
+        // Errors from the callback are propagated
+        let result = reader.process_by_barcode(2, |barcode, _| {
+            if barcode == 9 {
+                Err(IbuError::Process("Test error".into()))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(matches!(result, Err(IbuError::Process(_))));
+        fs::remove_file(temp_file).unwrap();
+
+        // Unsorted files are rejected
+        create_test_file(temp_file, &records);
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert!(matches!(
+            reader.process_by_barcode(1, |_, _| Ok(())),
+            Err(IbuError::NotSorted)
+        ));
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_record_count() {
+        let temp_file = "test_mmap_record_count.ibu";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, i, i)).collect();
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(temp_file)
+            .unwrap();
+        let mut writer = Writer::new(file, Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_count().unwrap();
+        drop(writer);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert_eq!(reader.header().record_count(), Some(1000));
+        assert_eq!(reader.len(), 1000);
+        drop(reader);
+
+        // Truncating the file breaks the stored count
+        let bytes = fs::read(temp_file).unwrap();
+        fs::write(temp_file, &bytes[..bytes.len() - RECORD_SIZE]).unwrap();
+        assert!(matches!(
+            MmapReader::new(temp_file),
+            Err(IbuError::RecordCountMismatch {
+                expected: 1000,
+                actual: 999
+            })
+        ));
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_range_for_barcode() {
+        let temp_file = "test_mmap_range_for_barcode.ibu";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i / 10 * 2, i, 0)).collect();
+        create_test_file(temp_file, &records);
+
+        // Unsorted header: no search
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert_eq!(reader.range_for_barcode(4), None);
+        assert_eq!(reader.binary_search_barcode(4), None);
+        drop(reader);
+
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        let mut writer = Writer::new(fs::File::create(temp_file).unwrap(), header).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert_eq!(reader.range_for_barcode(0), Some(0..10));
+        assert_eq!(reader.range_for_barcode(4), Some(20..30));
+        assert_eq!(reader.range_for_barcode(198), Some(990..1000));
+        assert_eq!(reader.binary_search_barcode(4), Some(20));
+        assert_eq!(reader.range_for_barcode(5), None);
+        assert_eq!(reader.range_for_barcode(1000), None);
+        assert_eq!(reader.binary_search_barcode(5), None);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_shard() {
+        let temp_file = "test_mmap_shard.ibu";
+        let records: Vec<Record> = (0..1003).map(|i| Record::new(i, i, i)).collect();
+        create_test_file(temp_file, &records);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert_eq!(reader.shard(1, 0).unwrap(), 0..1003);
+        // The remainder of 3 records goes to the first three shards
+        assert_eq!(reader.shard(4, 0).unwrap(), 0..251);
+        assert_eq!(reader.shard(4, 2).unwrap(), 502..753);
+        assert_eq!(reader.shard(4, 3).unwrap(), 753..1003);
+        // More shards than records leaves the last ones empty
+        assert_eq!(reader.shard(2000, 1002).unwrap(), 1002..1003);
+        assert_eq!(reader.shard(2000, 1999).unwrap(), 1003..1003);
+
+        let mut read = Vec::new();
+        for shard_id in 0..4 {
+            let range = reader.shard(4, shard_id).unwrap();
+            let shard = crate::Reader::new_shard(
+                fs::File::open(temp_file).unwrap(),
+                range.start,
+                range.end,
+            )
+            .unwrap();
+            read.extend(shard.collect::<crate::Result<Vec<_>>>().unwrap());
+        }
+        assert_eq!(read, records);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_shard_out_of_range() {
+        let temp_file = "test_mmap_shard_out_of_range.ibu";
+        create_test_file(temp_file, &[Record::new(1, 2, 3)]);
+        let reader = MmapReader::new(temp_file).unwrap();
+        fs::remove_file(temp_file).unwrap();
+        assert!(matches!(
+            reader.shard(2, 2),
+            Err(IbuError::InvalidIndex { idx: 2, max: 2 })
+        ));
+        assert!(matches!(
+            reader.shard(0, 0),
+            Err(IbuError::InvalidIndex { idx: 0, max: 0 })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "niffler")]
+    fn test_mmap_reader_compressed() {
+        let path = "test_mmap_compressed.ibu.gz";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, i, i)).collect();
+        let mut writer = Writer::from_path_auto(path, Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        assert!(matches!(
+            MmapReader::new(path),
+            Err(IbuError::CompressedInput)
+        ));
+        let reader = MmapReader::open_any(path).unwrap();
+        assert_eq!(reader.slice(0, reader.len()).unwrap(), records.as_slice());
+        fs::remove_file(path).unwrap();
+
+        // The decompressed copy is unlinked once mapped
+        #[cfg(unix)]
+        {
+            let prefix = format!("ibu-{}-", std::process::id());
+            let mut temp = fs::read_dir(std::env::temp_dir()).unwrap();
+            assert!(!temp.any(|entry| {
+                entry
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(&prefix)
+            }));
+        }
+        assert_eq!(reader.iter().count(), 1000);
+
+        // Uncompressed files are mapped directly
+        let path = "test_mmap_compressed_raw.ibu";
+        create_test_file(path, &records);
+        assert_eq!(MmapReader::open_any(path).unwrap().len(), 1000);
+
+        // Files shorter than a header are rejected without panicking
+        fs::write(path, b"IBU").unwrap();
+        assert!(MmapReader::new(path).is_err());
+        fs::remove_file(path).unwrap();
     }
 }
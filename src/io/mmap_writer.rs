@@ -0,0 +1,245 @@
+//! Writable memory map for in-place edits of IBU files.
+//!
+//! This module provides [`MmapWriter`], the mutable counterpart of
+//! [`MmapReader`](crate::MmapReader). Post-processing passes that only change
+//! record values (renumbering indices, correcting barcodes) or header flags
+//! can edit the mapped file directly instead of rewriting it.
+
+use std::{fs::OpenOptions, path::Path};
+
+use memmap2::MmapMut;
+
+use crate::{
+    checksum::Checksum, io::mmap::parse_mapped, Header, IbuError, Record, HEADER_SIZE, RECORD_SIZE,
+};
+
+/// Memory-mapped writer for editing IBU files in place.
+///
+/// Opens an existing file read-write and exposes its records as mutable
+/// slices. The number of records is fixed: records can be changed but not
+/// added or removed. Changes reach the file when the operating system writes
+/// back the mapped pages; call [`flush`](Self::flush) to force them to disk.
+///
+/// If the header carries a record checksum, it is recomputed by
+/// [`flush`](Self::flush) so that it matches the edited records. Edits may
+/// break the sort order, so clear the sorted flag with
+/// [`clear_sorted`](Self::clear_sorted) unless the order is preserved.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::MmapWriter;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let mut writer = MmapWriter::new("data.ibu")?;
+///
+/// // Renumber all indices in file order
+/// let len = writer.len();
+/// for (i, record) in writer.slice_mut(0, len)?.iter_mut().enumerate() {
+///     record.index = i as u64;
+/// }
+/// writer.flush()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MmapWriter {
+    /// Writable memory-mapped file data
+    map: MmapMut,
+    /// Parsed file header, kept in sync with the mapped header bytes
+    header: Header,
+    /// Number of records in the file
+    len: usize,
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl MmapWriter {
+    /// Opens an existing IBU file for in-place editing.
+    ///
+    /// The header is parsed and validated as in
+    /// [`MmapReader::new`](crate::MmapReader::new).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the IBU file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened for reading and writing
+    /// or mapped, in addition to the header and size errors of
+    /// [`MmapReader::new`](crate::MmapReader::new).
+    ///
+    /// # Safety
+    ///
+    /// Uses `unsafe` internally for memory mapping, but provides a safe
+    /// interface. The file must not be modified by other processes or
+    /// truncated while it is mapped.
+    pub fn new<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        let (header, len) = parse_mapped(&map)?;
+        Ok(Self { map, header, len })
+    }
+
+    /// Returns the number of records in the file.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns a copy of the file header.
+    pub fn header(&self) -> Header {
+        self.header
+    }
+
+    /// Returns a mutable slice of records from the specified range.
+    ///
+    /// Writes to the slice change the mapped file directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Starting record index (inclusive)
+    /// * `end` - Ending record index (exclusive)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidIndex`] if:
+    /// - `start >= len()` or `end > len()` (out of bounds)
+    /// - `end <= start` (invalid range)
+    pub fn slice_mut(&mut self, start: usize, end: usize) -> crate::Result<&mut [Record]> {
+        if start >= self.len || end > self.len || end <= start {
+            return Err(IbuError::InvalidIndex {
+                idx: end,
+                max: self.len,
+            });
+        }
+        let start = HEADER_SIZE + (start * RECORD_SIZE);
+        let end = HEADER_SIZE + (end * RECORD_SIZE);
+        Ok(bytemuck::cast_slice_mut(&mut self.map[start..end]))
+    }
+
+    /// Marks the records as sorted in the mapped header.
+    ///
+    /// The order is not checked; use
+    /// [`MmapReader::verify_sorted`](crate::MmapReader::verify_sorted) first if
+    /// it is not known.
+    pub fn set_sorted(&mut self) {
+        self.header.set_sorted();
+        self.write_header();
+    }
+
+    /// Clears the sorted flag in the mapped header.
+    pub fn clear_sorted(&mut self) {
+        self.header.clear_sorted();
+        self.write_header();
+    }
+
+    /// Flushes all changes to the file.
+    ///
+    /// Recomputes the record checksum if the header carries one, then
+    /// synchronously writes the mapped pages back to disk (`msync`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the pages back fails.
+    pub fn flush(&mut self) -> crate::Result<()> {
+        if self.header.checksum().is_some() {
+            let checksum = Checksum::of(&self.map[HEADER_SIZE..]);
+            self.header.set_checksum(checksum);
+            self.write_header();
+        }
+        self.map.flush()?;
+        Ok(())
+    }
+
+    /// Copies the in-memory header over the mapped header bytes.
+    fn write_header(&mut self) {
+        self.map[..HEADER_SIZE].copy_from_slice(self.header.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MmapReader, Reader, Writer};
+    use std::fs;
+
+    fn create_test_file(path: &str, header: Header, records: &[Record]) {
+        let mut writer = Writer::new(fs::File::create(path).unwrap(), header).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_mmap_writer_edits() {
+        let temp_file = "test_mmap_writer_edits.ibu";
+        let records: Vec<Record> = (0..100).map(|i| Record::new(100 - i, i, 0)).collect();
+        create_test_file(temp_file, Header::new(16, 12), &records);
+
+        let mut writer = MmapWriter::new(temp_file).unwrap();
+        assert_eq!(writer.len(), 100);
+        assert!(!writer.header().sorted());
+        for (i, record) in writer.slice_mut(10, 20).unwrap().iter_mut().enumerate() {
+            record.index = i as u64 + 1;
+        }
+        writer.slice_mut(0, 100).unwrap().sort_unstable();
+        writer.set_sorted();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert!(reader.header().sorted());
+        assert!(reader.verify_sorted());
+        let indices: u64 = reader.iter().map(|r| r.index).sum();
+        assert_eq!(indices, (1..=10).sum::<u64>());
+
+        let mut writer = MmapWriter::new(temp_file).unwrap();
+        writer.clear_sorted();
+        drop(writer);
+        assert!(!MmapReader::new(temp_file).unwrap().header().sorted());
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_writer_checksum() {
+        let temp_file = "test_mmap_writer_checksum.ibu";
+        let records: Vec<Record> = (0..10).map(|i| Record::new(i, i, i)).collect();
+        let mut writer =
+            Writer::new(fs::File::create(temp_file).unwrap(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_checksum().unwrap();
+        drop(writer);
+
+        let mut writer = MmapWriter::new(temp_file).unwrap();
+        writer.slice_mut(3, 4).unwrap()[0].umi = 42;
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert!(MmapReader::new_verified(temp_file).is_ok());
+        let mut reader = Reader::from_path(temp_file).unwrap();
+        assert_eq!(reader.nth(3).unwrap().unwrap().umi, 42);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_writer_errors() {
+        let temp_file = "test_mmap_writer_errors.ibu";
+        create_test_file(temp_file, Header::new(16, 12), &[Record::default(); 5]);
+
+        let mut writer = MmapWriter::new(temp_file).unwrap();
+        assert!(writer.slice_mut(0, 6).is_err());
+        assert!(writer.slice_mut(5, 5).is_err());
+        assert!(writer.slice_mut(3, 2).is_err());
+        assert_eq!(writer.slice_mut(4, 5).unwrap().len(), 1);
+        drop(writer);
+
+        fs::write(temp_file, [0u8; 40]).unwrap();
+        assert!(matches!(
+            MmapWriter::new(temp_file),
+            Err(IbuError::InvalidMagicNumber { .. })
+        ));
+        assert!(MmapWriter::new("missing_mmap_writer.ibu").is_err());
+
+        fs::remove_file(temp_file).unwrap();
+    }
+}
@@ -1,7 +1,46 @@
+//! Reading and writing IBU files.
+//!
+//! [`Reader`] and [`Writer`] are the canonical streaming reader and writer.
+//! [`MmapReader`] and [`SeekReader`] provide random access (as does
+//! `SeekableZstdReader` for compressed files, with the `zstd` feature),
+//! [`MmapWriter`] edits files in place, and the remaining submodules build
+//! adaptors, parallel readers, and sorting on top of them.
+
+mod adaptors;
+mod chain;
+mod channel;
+#[cfg(feature = "niffler")]
+mod compress;
+#[cfg(target_os = "linux")]
+mod direct;
+mod generate;
 mod mmap;
+mod mmap_writer;
+mod null;
+mod parallel_writer;
 mod reader;
+mod ring;
+mod seek;
+#[cfg(feature = "zstd")]
+mod seekable_zstd;
+mod sort;
 mod writer;
 
+pub use adaptors::{
+    BarcodeBoundaries, BarcodeDepth, Decoded, DecodedRecord, DecodedStrict, FilterRecords,
+    FirstSeen, TakeRecords,
+};
+pub use chain::{chain_readers, ChainedReader};
+pub use channel::ChannelParallelReader;
+pub use generate::{write_random_parallel, write_random_parallel_with};
 pub use mmap::MmapReader;
-pub use reader::{load_to_vec, Reader};
+pub use mmap_writer::MmapWriter;
+pub use null::NullSink;
+pub use parallel_writer::ParallelWriter;
+pub use reader::{load_bounds, load_to_soa, load_to_vec, OverflowPolicy, ReadOptions, Reader};
+pub use ring::{ring_buffer, RingSink, RingSource};
+pub use seek::SeekReader;
+#[cfg(feature = "zstd")]
+pub use seekable_zstd::SeekableZstdReader;
+pub use sort::{external_sort, merge_and_collapse, merge_sorted, sort_file};
 pub use writer::Writer;
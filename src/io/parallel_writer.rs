@@ -0,0 +1,205 @@
+//! Parallel record production with deterministic output order.
+//!
+//! This module provides [`ParallelWriter`], which runs a producer on several
+//! threads, each writing to its own in-memory [`Writer`], and then
+//! [`ingests`](Writer::ingest) the per-thread buffers into a single output in
+//! thread order.
+
+use std::{io::Write, thread};
+
+use crate::{parallel::catch_panic, Writer};
+
+/// Coordinator for writing records from several threads in a fixed order.
+///
+/// [`spawn`](Self::spawn) calls the producer once per thread with the thread
+/// id and a headless `Writer<Vec<u8>>`. Once all threads are done, the buffers
+/// are ingested into the main writer in thread id order: all records of
+/// thread 0, then all records of thread 1, and so on. The output therefore
+/// depends only on what each thread produced, not on which thread finished
+/// first.
+///
+/// Each thread's records are held in memory until ingestion, so for large
+/// outputs call [`spawn`](Self::spawn) repeatedly with bounded work.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{Header, ParallelWriter, Record, Writer};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+/// let mut parallel = ParallelWriter::new(writer);
+///
+/// // Each thread writes a contiguous block of indices
+/// parallel.spawn(4, |tid, _num_threads, writer| {
+///     for i in 0..100 {
+///         writer.write_record(&Record::new(0, 0, tid as u64 * 100 + i))?;
+///     }
+///     Ok(())
+/// })?;
+///
+/// let mut writer = parallel.into_inner();
+/// writer.finish()?;
+/// assert_eq!(writer.records_written(), 400);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ParallelWriter<W: Write> {
+    /// Destination of the ingested records
+    writer: Writer<W>,
+}
+
+impl<W: Write> ParallelWriter<W> {
+    /// Creates a coordinator ingesting into `writer`.
+    pub fn new(writer: Writer<W>) -> Self {
+        Self { writer }
+    }
+
+    /// Runs `produce` on `num_threads` threads and ingests their records in
+    /// thread id order.
+    ///
+    /// `produce` is called as `produce(tid, num_threads, writer)` for every
+    /// `tid` in `0..num_threads`, where `num_threads` is the resolved thread
+    /// count, so each thread can select its share of the work. Ingestion is
+    /// all-or-nothing: if any thread fails, no records of this call are
+    /// written. Can be called repeatedly to append more records.
+    ///
+    /// # Arguments
+    ///
+    /// * `num_threads` - Number of threads to use (0 = use all available cores)
+    /// * `produce` - Producer writing one thread's records
+    ///
+    /// # Returns
+    ///
+    /// The number of records ingested.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the lowest failing thread id, or an error if a
+    /// thread cannot be spawned or the main writer fails. A panic in `produce`
+    /// is returned as [`IbuError::Process`](crate::IbuError::Process) with the
+    /// panic message.
+    pub fn spawn<F>(&mut self, num_threads: usize, produce: F) -> crate::Result<u64>
+    where
+        F: Fn(usize, usize, &mut Writer<Vec<u8>>) -> crate::Result<()> + Sync,
+    {
+        let num_threads = if num_threads == 0 {
+            num_cpus::get()
+        } else {
+            num_threads
+        };
+        let produce = &produce;
+        let buffers = thread::scope(|scope| {
+            let mut handles = Vec::with_capacity(num_threads);
+            for tid in 0..num_threads {
+                let work = move || {
+                    catch_panic(|| {
+                        let mut buffer = Writer::new_headless(Vec::new());
+                        produce(tid, num_threads, &mut buffer)?;
+                        Ok(buffer)
+                    })
+                };
+                handles.push(thread::Builder::new().spawn_scoped(scope, work)?);
+            }
+            // Join in thread id order, so the first error is the lowest id
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<crate::Result<Vec<_>>>()
+        })?;
+
+        let mut ingested = 0;
+        for mut buffer in buffers {
+            ingested += buffer.records_written();
+            self.writer.ingest(&mut buffer)?;
+        }
+        Ok(ingested)
+    }
+
+    /// Returns a reference to the main writer.
+    pub fn get_ref(&self) -> &Writer<W> {
+        &self.writer
+    }
+
+    /// Returns the main writer, e.g. to [`finish`](Writer::finish) it.
+    pub fn into_inner(self) -> Writer<W> {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, IbuError, Reader, Record};
+    use std::{io::Cursor, time::Duration};
+
+    fn records(writer: Writer<Vec<u8>>) -> Vec<Record> {
+        Reader::new(Cursor::new(writer.into_inner()))
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parallel_writer_order() {
+        let writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        let mut parallel = ParallelWriter::new(writer);
+        let ingested = parallel
+            .spawn(4, |tid, num_threads, writer| {
+                assert_eq!(num_threads, 4);
+                // Earlier threads finish last
+                std::thread::sleep(Duration::from_millis(10 * (4 - tid as u64)));
+                for i in 0..1000 {
+                    writer.write_record(&Record::new(0, 0, tid as u64 * 1000 + i))?;
+                }
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(ingested, 4000);
+        assert_eq!(parallel.get_ref().records_written(), 4000);
+
+        // A second call appends
+        parallel
+            .spawn(2, |tid, _, writer| {
+                writer.write_record(&Record::new(0, 0, 4000 + tid as u64))
+            })
+            .unwrap();
+
+        let mut writer = parallel.into_inner();
+        writer.finish().unwrap();
+        let indices: Vec<u64> = records(writer).iter().map(|r| r.index).collect();
+        assert_eq!(indices, (0..4002).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parallel_writer_errors() {
+        let writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        let mut parallel = ParallelWriter::new(writer);
+        let result = parallel.spawn(3, |tid, _, writer| {
+            writer.write_record(&Record::new(0, 0, tid as u64))?;
+            if tid > 0 {
+                return Err(IbuError::Process(format!("thread {tid}").into()));
+            }
+            Ok(())
+        });
+        match result {
+            Err(IbuError::Process(e)) => assert_eq!(e.to_string(), "thread 1"),
+            other => panic!("unexpected result {other:?}"),
+        }
+
+        let result = parallel.spawn(2, |tid, _, _| {
+            assert_ne!(tid, 1, "thread {tid} panicked");
+            Ok(())
+        });
+        match result {
+            Err(IbuError::Process(e)) => assert!(e.to_string().contains("thread 1 panicked")),
+            other => panic!("unexpected result {other:?}"),
+        }
+
+        // Nothing from the failed calls was ingested
+        let mut writer = parallel.into_inner();
+        assert_eq!(writer.records_written(), 0);
+        writer.finish().unwrap();
+        assert!(records(writer).is_empty());
+    }
+}
@@ -5,14 +5,31 @@
 
 use std::{
     fs::File,
-    io::{BufReader, Read},
+    io::{BufReader, Read, Seek, SeekFrom, Take},
+    ops::Range,
     path::Path,
+    sync::{Arc, Mutex},
 };
 
-use crate::{Header, IbuError, Record, HEADER_SIZE, RECORD_SIZE};
+use crate::{
+    checksum::Checksum, Header, IbuError, Record, HEADER_SIZE, RECORD_SIZE, V1_HEADER_SIZE,
+};
 
 const DEFAULT_BUFFER_SIZE: usize = 48 * 1024 * RECORD_SIZE;
-type BoxedReader = Box<dyn Read + Send>;
+pub(crate) type BoxedReader = Box<dyn Read + Send>;
+type ProgressCallback = Box<dyn FnMut(usize) + Send>;
+/// Header with barcode, UMI and index columns, as loaded by [`load_to_soa`].
+type SoaColumns = (Header, Vec<u64>, Vec<u64>, Vec<u64>);
+
+/// Policy for index values that overflow when an offset is applied.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum OverflowPolicy {
+    /// Yield [`IbuError::IndexOverflow`] for the overflowing record.
+    #[default]
+    Error,
+    /// Clamp the index to `u64::MAX`.
+    Saturate,
+}
 
 /// Streaming reader for IBU files.
 ///
@@ -109,6 +126,21 @@ pub struct Reader<R: Read> {
 
     /// Flag indicating end of file has been reached
     eof: bool,
+
+    /// Running checksum compared against the header at end of file (None unless verifying)
+    checksum: Option<Checksum>,
+
+    /// Offset added to every record index, with its overflow policy (None = no offset)
+    index_offset: Option<(u64, OverflowPolicy)>,
+
+    /// Number of records yielded successfully by the iterator
+    records_consumed: u64,
+
+    /// Called with the cumulative bytes read after each batch (shared by clones)
+    progress: Option<Arc<Mutex<ProgressCallback>>>,
+
+    /// Record range of the file covered by this reader (None = whole file)
+    shard: Option<Range<u64>>,
 }
 impl<R: Read> Reader<R> {
     /// Creates a new reader from the given data source.
@@ -150,29 +182,227 @@ impl<R: Read> Reader<R> {
     /// # }
     /// ```
     pub fn new(mut inner: R) -> crate::Result<Self> {
-        // load header
-        let header = {
-            let mut header_bytes = [0u8; HEADER_SIZE];
-            inner.read_exact(&mut header_bytes)?;
-
-            let header: Header = bytemuck::pod_read_unaligned(&header_bytes);
-            header.validate()?;
-            header
-        };
+        let header = read_header(&mut inner)?;
+        Ok(Self::with_header(inner, header, HEADER_SIZE))
+    }
+
+    /// Creates a new reader with an internal buffer of `buffer_records` records.
+    ///
+    /// Behaves like [`Reader::new`], which reads batches of 48K records
+    /// (~1.1MB). A smaller buffer saves memory for many small files, while a
+    /// larger one reduces read calls on fast storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidInput`](std::io::ErrorKind::InvalidInput) I/O
+    /// error if `buffer_records` is zero (or the buffer size overflows
+    /// `usize`), and otherwise the errors of [`Reader::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_iter((0..100).map(|i| Record::new(i, 0, 0)))?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::with_capacity(Cursor::new(writer.into_inner()), 16)?;
+    /// assert_eq!(reader.count(), 100);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_capacity(inner: R, buffer_records: usize) -> crate::Result<Self> {
+        let size = buffer_size(buffer_records)?;
+        let mut reader = Self::new(inner)?;
+        reader.buffer = Vec::with_capacity(size);
+        Ok(reader)
+    }
+
+    /// Caps the size of the next batches at `records` records.
+    ///
+    /// Only shrinks the buffer, and only when no records are buffered, so no
+    /// data is lost. Used when the caller needs few records, so that no more
+    /// than necessary is read (and decompressed) from the source.
+    pub(crate) fn limit_batch(&mut self, records: usize) {
+        let size = records.max(1) * RECORD_SIZE;
+        if self.pos >= self.cap && size < self.buffer.capacity() {
+            self.buffer = Vec::with_capacity(size);
+        }
+    }
+
+    /// Creates a new reader that also accepts version 1 files.
+    ///
+    /// Current-format streams are read exactly as with [`Reader::new`]. A
+    /// stream starting with a version 1 header (see
+    /// [`Header::upgrade_from_v1`]) has its header upgraded to the current
+    /// format, and its records are then read unchanged, so
+    /// [`header`](Self::header) always reports the current version.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header cannot be read, or is neither a valid
+    /// current header nor a valid version 1 header.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Reader, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// // Version 1 header: version, bc_len, umi_len, sorted
+    /// let mut bytes = Vec::new();
+    /// for field in [1u32, 16, 12] {
+    ///     bytes.extend(field.to_le_bytes());
+    /// }
+    /// bytes.push(0);
+    /// bytes.extend(Record::new(1, 2, 3).as_bytes());
+    ///
+    /// let reader = Reader::new_with_upgrade(bytes.as_slice())?;
+    /// assert_eq!(reader.header().version, ibu::VERSION);
+    /// let records = reader.collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(records, vec![Record::new(1, 2, 3)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_upgrade(mut inner: R) -> crate::Result<Self> {
+        // A current header starts with the magic number, a v1 header with its version
+        let mut prefix = [0u8; 4];
+        inner.read_exact(&mut prefix)?;
+        if u32::from_le_bytes(prefix) == 1 {
+            let mut header_bytes = [0u8; V1_HEADER_SIZE];
+            header_bytes[..4].copy_from_slice(&prefix);
+            inner.read_exact(&mut header_bytes[4..])?;
+            let header = Header::upgrade_from_v1(&header_bytes)?;
+            return Ok(Self::with_header(inner, header, V1_HEADER_SIZE));
+        }
 
-        // init buffer
-        let buffer = Vec::with_capacity(DEFAULT_BUFFER_SIZE);
+        let mut header_bytes = [0u8; HEADER_SIZE];
+        header_bytes[..4].copy_from_slice(&prefix);
+        inner.read_exact(&mut header_bytes[4..])?;
+        let header: Header = bytemuck::pod_read_unaligned(&header_bytes);
+        header.validate()?;
+        Ok(Self::with_header(inner, header, HEADER_SIZE))
+    }
 
-        // init struct
-        Ok(Self {
+    /// Creates a reader positioned after a header of `header_len` bytes.
+    fn with_header(inner: R, header: Header, header_len: usize) -> Self {
+        Self {
             inner,
-            buffer,
+            buffer: Vec::with_capacity(DEFAULT_BUFFER_SIZE),
             header,
             pos: 0,
             cap: 0,
-            bytes_read: HEADER_SIZE,
+            bytes_read: header_len,
             eof: false,
-        })
+            checksum: None,
+            index_offset: None,
+            records_consumed: 0,
+            progress: None,
+            shard: None,
+        }
+    }
+
+    /// Enables verification of the header's record checksum.
+    ///
+    /// Since the reader is streaming, records are hashed as they are read and
+    /// the result is compared to the header checksum once the end of the stream
+    /// is reached. On mismatch the iterator yields a final
+    /// [`IbuError::ChecksumMismatch`] instead of ending.
+    ///
+    /// Files whose header carries no checksum are read without verification,
+    /// and so are shards (see [`Reader::new_shard`]), which cover only part of
+    /// the checksummed records.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let header = Header::new(16, 12);
+    /// let mut writer = Writer::new_with_checksum(Cursor::new(Vec::new()), header)?;
+    /// writer.write_record(&Record::new(1, 2, 3))?;
+    /// writer.finish_with_checksum()?;
+    ///
+    /// let cursor = Cursor::new(writer.into_inner().into_inner());
+    /// let reader = Reader::new(cursor)?.with_checksum_verification();
+    /// let records = reader.collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(records.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_checksum_verification(mut self) -> Self {
+        if self.header.checksum().is_some() && self.shard.is_none() {
+            self.checksum = Some(Checksum::default());
+        }
+        self
+    }
+
+    /// Reads the remaining records and verifies the header's record checksum.
+    ///
+    /// Consumes the reader, so the whole stream should be unread for the
+    /// checksum to match. Use
+    /// [`with_checksum_verification`](Self::with_checksum_verification) to
+    /// verify while also processing the records.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the checksum was verified, or `false` if the header carries
+    /// no checksum (the records are still read to the end).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::ChecksumMismatch`] if the records do not match the
+    /// checksum, or the first error encountered while reading.
+    pub fn verify_checksum(self) -> crate::Result<bool> {
+        let verified = self.header.checksum().is_some();
+        for record in self.with_checksum_verification() {
+            record?;
+        }
+        Ok(verified)
+    }
+
+    /// Adds a constant offset to the index of every record read.
+    ///
+    /// This maps per-file local index numbering into a global space while
+    /// streaming, e.g. when chaining shards whose indices each start at zero,
+    /// without a separate rewrite pass. The offset is applied to records
+    /// yielded by the iterator; the header is unchanged.
+    ///
+    /// # Overflow
+    ///
+    /// If `index + offset` exceeds `u64::MAX`:
+    /// - [`OverflowPolicy::Error`] yields [`IbuError::IndexOverflow`] in place
+    ///   of the record; iteration may continue with the following records.
+    /// - [`OverflowPolicy::Saturate`] yields the record with index `u64::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, OverflowPolicy, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_batch(&[Record::new(1, 2, 0), Record::new(1, 2, u64::MAX)])?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?
+    ///     .with_index_offset(1000, OverflowPolicy::Saturate);
+    /// let indices = reader
+    ///     .map(|r| r.map(|r| r.index))
+    ///     .collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(indices, vec![1000, u64::MAX]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_index_offset(mut self, offset: u64, on_overflow: OverflowPolicy) -> Self {
+        self.index_offset = Some((offset, on_overflow));
+        self
     }
 
     /// Reads the next batch of records into the internal buffer.
@@ -238,9 +468,201 @@ impl<R: Read> Reader<R> {
         self.pos = 0;
         self.cap = read / RECORD_SIZE;
         self.bytes_read += read;
+        if read > 0 {
+            if let Some(progress) = &self.progress {
+                (progress.lock().unwrap())(self.bytes_read);
+            }
+        }
+        if let Some(checksum) = self.checksum.as_mut() {
+            checksum.update(&self.buffer[..read]);
+            if read == 0 {
+                // Only verify once, even if the caller keeps polling
+                let actual = checksum.value();
+                self.checksum = None;
+                if let Some(expected) = self.header.checksum() {
+                    if expected != actual {
+                        return Err(IbuError::ChecksumMismatch { expected, actual });
+                    }
+                }
+            }
+        }
         Ok(read > 0)
     }
 
+    /// Advances past the next `n` records without decoding them.
+    ///
+    /// Whole batches are read from the underlying source as usual, but
+    /// skipped records are never copied out of the buffer, which makes this
+    /// much cheaper than calling [`next`](Iterator::next) in a loop. Useful
+    /// for pagination or sharded reads over streams that cannot seek, such as
+    /// compressed files. For seekable uncompressed files,
+    /// [`SeekReader`](crate::SeekReader) jumps directly instead.
+    ///
+    /// Skipped records count towards
+    /// [`records_consumed`](Self::records_consumed) and are included in
+    /// checksum verification, but the index offset is not applied to them, so
+    /// they never fail with [`IbuError::IndexOverflow`].
+    ///
+    /// # Returns
+    ///
+    /// The number of records skipped, which is less than `n` only if the end
+    /// of the stream was reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`read_batch`](Self::read_batch), e.g. for a
+    /// truncated stream or a checksum mismatch at the end of the stream.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// for i in 0..10 {
+    ///     writer.write_record(&Record::new(0, 0, i))?;
+    /// }
+    /// writer.finish()?;
+    ///
+    /// let mut reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// assert_eq!(reader.skip_records(8)?, 8);
+    /// assert_eq!(reader.next().unwrap()?.index, 8);
+    /// assert_eq!(reader.skip_records(5)?, 1);
+    /// assert!(reader.next().is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn skip_records(&mut self, n: usize) -> crate::Result<usize> {
+        let mut skipped = 0;
+        while skipped < n && !self.eof {
+            if self.pos >= self.cap {
+                match self.read_batch() {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        self.eof = true;
+                        break;
+                    }
+                    Err(e) => {
+                        self.records_consumed += skipped as u64;
+                        return Err(e);
+                    }
+                }
+            }
+            let step = (self.cap - self.pos).min(n - skipped);
+            self.pos += step;
+            skipped += step;
+        }
+        self.records_consumed += skipped as u64;
+        Ok(skipped)
+    }
+
+    /// Returns the total number of records in the stream, if known.
+    ///
+    /// This is the record count stored in the header (see
+    /// [`Header::record_count`]), e.g. by
+    /// [`Writer::finish_with_count`](crate::Writer::finish_with_count). Returns
+    /// `None` if the header does not store a count.
+    pub fn total_records(&self) -> Option<u64> {
+        self.header.record_count()
+    }
+
+    /// Returns the number of records the iterator has yielded successfully.
+    ///
+    /// Errors are not counted.
+    pub fn records_consumed(&self) -> u64 {
+        self.records_consumed
+    }
+
+    /// Returns the number of records left to read, if the total is known.
+    ///
+    /// Computed as [`total_records`](Self::total_records) minus
+    /// [`records_consumed`](Self::records_consumed), counting only the records
+    /// of the shard for a reader created with [`Reader::new_shard`]. This is
+    /// only as accurate as the stored count: a truncated stream yields fewer
+    /// records. The iterator's [`size_hint`](Iterator::size_hint) uses it as
+    /// an upper bound only.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Cursor::new(Vec::new()), Header::new(16, 12))?;
+    /// writer.write_batch(&[Record::new(1, 2, 3), Record::new(4, 5, 6)])?;
+    /// writer.finish_with_count()?;
+    ///
+    /// let mut reader = Reader::new(Cursor::new(writer.into_inner().into_inner()))?;
+    /// assert_eq!(reader.total_records(), Some(2));
+    /// reader.next().transpose()?;
+    /// assert_eq!(reader.records_remaining(), Some(1));
+    /// assert_eq!(reader.size_hint(), (0, Some(1)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn records_remaining(&self) -> Option<u64> {
+        let total = self.total_records()?;
+        let available = match &self.shard {
+            Some(shard) => total
+                .saturating_sub(shard.start)
+                .min(shard.end - shard.start),
+            None => total,
+        };
+        Some(available.saturating_sub(self.records_consumed))
+    }
+
+    /// Returns the number of bytes read from the underlying source so far.
+    ///
+    /// Includes the header. Records are read in large batches, so this runs
+    /// ahead of the records yielded by the iterator and reaches the full
+    /// stream length once the last batch has been read.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// Sets a callback invoked with the cumulative bytes read after each batch.
+    ///
+    /// The callback runs once per non-empty batch read from the underlying
+    /// source (not once per record), receiving the same value as
+    /// [`bytes_read`](Self::bytes_read). This is enough to drive a progress
+    /// bar without knowing the stream length in advance. Clones of the reader
+    /// share the callback. Setting a new callback replaces the previous one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::{
+    ///     io::Cursor,
+    ///     sync::{
+    ///         atomic::{AtomicUsize, Ordering},
+    ///         Arc,
+    ///     },
+    /// };
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_record(&Record::new(1, 2, 3))?;
+    /// writer.finish()?;
+    ///
+    /// let progress = Arc::new(AtomicUsize::new(0));
+    /// let seen = Arc::clone(&progress);
+    /// let mut reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// reader.set_progress_callback(Box::new(move |bytes| seen.store(bytes, Ordering::Relaxed)));
+    ///
+    /// let records = reader.collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(records.len(), 1);
+    /// assert_eq!(progress.load(Ordering::Relaxed), 32 + 24);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_progress_callback(&mut self, callback: Box<dyn FnMut(usize) + Send>) {
+        self.progress = Some(Arc::new(Mutex::new(callback)));
+    }
+
     /// Returns a copy of the file header.
     ///
     /// The header contains metadata about the file format, including barcode
@@ -298,13 +720,144 @@ impl<R: Read> Iterator for Reader<R> {
         } else {
             let lpos = RECORD_SIZE * self.pos;
             let rpos = lpos + RECORD_SIZE;
-            let record: &[Record] = bytemuck::cast_slice(&self.buffer[lpos..rpos]);
+            let mut record: Record = bytemuck::pod_read_unaligned(&self.buffer[lpos..rpos]);
             self.pos += 1;
-            Some(Ok(record[0]))
+            if let Some((offset, on_overflow)) = self.index_offset {
+                record.index = match (record.index.checked_add(offset), on_overflow) {
+                    (Some(index), _) => index,
+                    (None, OverflowPolicy::Saturate) => u64::MAX,
+                    (None, OverflowPolicy::Error) => {
+                        return Some(Err(IbuError::IndexOverflow {
+                            index: record.index,
+                            offset,
+                        }))
+                    }
+                };
+            }
+            self.records_consumed += 1;
+            Some(Ok(record))
+        }
+    }
+
+    /// Bounds the number of items left by the header's record count.
+    ///
+    /// The count is metadata from the file rather than a guarantee, and a
+    /// truncated stream ends early, so the lower bound is always zero. The
+    /// upper bound allows for the trailing checksum mismatch error of a
+    /// verifying reader.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = self.records_remaining().and_then(|remaining| {
+            let remaining = usize::try_from(remaining).ok()?;
+            remaining.checked_add(usize::from(self.checksum.is_some()))
+        });
+        (0, upper)
+    }
+}
+
+impl<R: Read + Seek> Reader<Take<R>> {
+    /// Creates a reader over the records `start_record..end_record` of a stream.
+    ///
+    /// Reads and validates the header at the current stream position, seeks
+    /// directly to record `start_record` and yields at most
+    /// `end_record - start_record` records, so independent workers can each
+    /// read their own part of one file without coordination. See
+    /// [`MmapReader::shard`](crate::MmapReader::shard) for computing the
+    /// ranges of evenly sized shards.
+    ///
+    /// The shard ends early if the stream does, and a range starting past the
+    /// end of the stream yields no records. The header is that of the whole
+    /// file, so [`total_records`](Self::total_records) still reports the file
+    /// total, while [`records_remaining`](Self::records_remaining) counts only
+    /// the shard. Checksum verification is not available on shards.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - Seekable, uncompressed data source positioned at the header
+    /// * `start_record` - Position of the first record of the shard
+    /// * `end_record` - Position one past the last record of the shard
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidIndex`] if `start_record > end_record` or the
+    /// range lies beyond any addressable byte offset, or an error if the
+    /// header cannot be read, is invalid, or seeking fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_iter((0..10).map(|i| Record::new(0, 0, i)))?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new_shard(Cursor::new(writer.into_inner()), 3, 6)?;
+    /// let indices = reader
+    ///     .map(|r| r.map(|r| r.index))
+    ///     .collect::<ibu::Result<Vec<_>>>()?;
+    /// assert_eq!(indices, vec![3, 4, 5]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_shard(mut inner: R, start_record: usize, end_record: usize) -> crate::Result<Self> {
+        if start_record > end_record {
+            return Err(IbuError::InvalidIndex {
+                idx: start_record,
+                max: end_record,
+            });
+        }
+        // Byte offsets up to the end of the shard must not overflow
+        let max = (usize::MAX - HEADER_SIZE) / RECORD_SIZE + 1;
+        if end_record >= max {
+            return Err(IbuError::InvalidIndex {
+                idx: end_record,
+                max,
+            });
         }
+        let base = inner.stream_position()?;
+        let header = read_header(&mut inner)?;
+        let offset = HEADER_SIZE + start_record * RECORD_SIZE;
+        inner.seek(SeekFrom::Start(base + offset as u64))?;
+
+        let len = end_record - start_record;
+        let mut reader = Self::with_header(inner.take((len * RECORD_SIZE) as u64), header, offset);
+        reader.shard = Some(start_record as u64..end_record as u64);
+        // Small shards need no more than their own records in memory
+        reader.limit_batch(len);
+        Ok(reader)
     }
 }
 
+/// Returns the size in bytes of a buffer of `records` records.
+///
+/// Fails with an [`InvalidInput`](std::io::ErrorKind::InvalidInput) I/O error
+/// for an empty buffer, which could never make progress, or a size that
+/// overflows `usize`.
+pub(crate) fn buffer_size(records: usize) -> crate::Result<usize> {
+    records
+        .checked_mul(RECORD_SIZE)
+        .filter(|&size| size > 0)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "buffer must hold at least one record",
+            )
+            .into()
+        })
+}
+
+/// Reads and validates a current-format header.
+fn read_header<R: Read>(inner: &mut R) -> crate::Result<Header> {
+    let mut header_bytes = [0u8; HEADER_SIZE];
+    inner.read_exact(&mut header_bytes)?;
+
+    let header: Header = bytemuck::pod_read_unaligned(&header_bytes);
+    header.validate()?;
+    Ok(header)
+}
+
 impl Reader<BoxedReader> {
     /// Creates a reader from a file path.
     ///
@@ -344,15 +897,19 @@ impl Reader<BoxedReader> {
     /// ```
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, IbuError> {
         let rdr = File::open(path).map(BufReader::new)?;
+        Self::from_boxed(Box::new(rdr))
+    }
 
+    /// Creates a reader from a boxed source, detecting compression if enabled.
+    fn from_boxed(rdr: BoxedReader) -> Result<Self, IbuError> {
         #[cfg(feature = "niffler")]
         {
-            let (pt, _format) = niffler::send::get_reader(Box::new(rdr))?;
+            let (pt, _format) = niffler::send::get_reader(rdr)?;
             Self::new(pt)
         }
         #[cfg(not(feature = "niffler"))]
         {
-            Self::new(Box::new(rdr))
+            Self::new(rdr)
         }
     }
 
@@ -387,17 +944,7 @@ impl Reader<BoxedReader> {
     /// # }
     /// ```
     pub fn from_stdin() -> Result<Self, IbuError> {
-        let rdr = Box::new(std::io::stdin());
-
-        #[cfg(feature = "niffler")]
-        {
-            let (pt, _format) = niffler::send::get_reader(rdr)?;
-            Self::new(pt)
-        }
-        #[cfg(not(feature = "niffler"))]
-        {
-            Self::new(rdr)
-        }
+        Self::from_boxed(Box::new(std::io::stdin()))
     }
 
     /// Creates a reader from an optional file path.
@@ -434,6 +981,99 @@ impl Reader<BoxedReader> {
     }
 }
 
+/// Options for opening IBU files for reading.
+///
+/// A builder over the ways a file can be opened, for cases where
+/// [`Reader::from_path`] is not flexible enough.
+///
+/// # Direct I/O
+///
+/// With [`direct_io`](Self::direct_io) enabled, files are opened with
+/// `O_DIRECT` on Linux, so reads bypass the page cache. This is useful for
+/// benchmarking raw disk throughput, and for large sequential scans that would
+/// otherwise evict more useful data from the cache.
+///
+/// `O_DIRECT` requires buffer addresses, file offsets, and read lengths to be
+/// aligned to the device's logical block size. The reader handles this
+/// internally by staging reads through a 4 KiB-aligned buffer, so there are no
+/// constraints on the caller. If the filesystem does not support `O_DIRECT`
+/// (e.g. tmpfs), or on platforms other than Linux, the file is opened with
+/// regular buffered I/O instead.
+///
+/// # Strict Reserved Bytes
+///
+/// With [`strict_reserved`](Self::strict_reserved) enabled, files whose header
+/// reserved bytes are nonzero without a flag claiming them are rejected with
+/// [`IbuError::UnexpectedReserved`](crate::IbuError::UnexpectedReserved). See
+/// [`Header::validate_reserved`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::ReadOptions;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = ReadOptions::new().direct_io(true).open("large.ibu")?;
+/// let count = reader.count();
+/// println!("Scanned {} records", count);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Open files with `O_DIRECT` where supported
+    direct_io: bool,
+
+    /// Reject headers with unclaimed nonzero reserved bytes
+    strict_reserved: bool,
+}
+
+impl ReadOptions {
+    /// Creates options with default settings (buffered I/O).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to bypass the page cache with direct I/O.
+    pub fn direct_io(mut self, enabled: bool) -> Self {
+        self.direct_io = enabled;
+        self
+    }
+
+    /// Sets whether to reject unclaimed nonzero reserved header bytes.
+    pub fn strict_reserved(mut self, enabled: bool) -> Self {
+        self.strict_reserved = enabled;
+        self
+    }
+
+    /// Opens a reader over the file at `path` with these options.
+    ///
+    /// Compressed files are handled as in [`Reader::from_path`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, the header is invalid,
+    /// or decompression fails. With strict reserved bytes enabled, returns
+    /// [`IbuError::UnexpectedReserved`](crate::IbuError::UnexpectedReserved)
+    /// if the header's reserved bytes are unclaimed but nonzero.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> crate::Result<Reader<BoxedReader>> {
+        let reader = self.open_unchecked(path)?;
+        if self.strict_reserved {
+            reader.header().validate_reserved()?;
+        }
+        Ok(reader)
+    }
+
+    fn open_unchecked<P: AsRef<Path>>(&self, path: P) -> crate::Result<Reader<BoxedReader>> {
+        #[cfg(target_os = "linux")]
+        if self.direct_io {
+            let rdr = super::direct::DirectReader::open(path)?;
+            return Reader::from_boxed(Box::new(rdr));
+        }
+        Reader::from_path(path)
+    }
+}
+
 /// Loads an entire IBU file into memory at once.
 ///
 /// This function provides the fastest way to load IBU files when you need all
@@ -534,6 +1174,158 @@ pub fn load_to_vec<P: AsRef<Path>>(path: P) -> crate::Result<(Header, Vec<Record
     Ok((header, records))
 }
 
+/// Loads an entire IBU file into three column vectors.
+///
+/// This is the structure-of-arrays counterpart of [`load_to_vec`]: record `i`
+/// of the file is `(barcodes[i], umis[i], indices[i])`. Analyses that only
+/// touch one field (e.g. counting distinct barcodes) scan a single contiguous
+/// column, which is more cache-friendly and easier to vectorize than striding
+/// over whole records. Columns can be written back with
+/// [`Writer::write_soa`](crate::Writer::write_soa).
+///
+/// Records are read in fixed-size chunks and scattered into the columns, so
+/// peak memory is the size of the columns plus one chunk, not twice the file
+/// size.
+///
+/// # Arguments
+///
+/// * `path` - Path to the IBU file
+///
+/// # Returns
+///
+/// Returns `(header, barcodes, umis, indices)`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The file cannot be opened or read
+/// - The header is invalid
+/// - The file size is not consistent with the record format
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::load_to_soa;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let (header, barcodes, _umis, _indices) = load_to_soa("data.ibu")?;
+/// let max_barcode = barcodes.iter().max();
+/// println!("{} records, max barcode {:?}", barcodes.len(), max_barcode);
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_to_soa<P: AsRef<Path>>(path: P) -> crate::Result<SoaColumns> {
+    /// Records read per chunk (~1.5MB)
+    const CHUNK_RECORDS: usize = 64 * 1024;
+
+    let mut file = File::open(path)?;
+
+    // Read and validate header
+    let mut header_bytes = [0u8; HEADER_SIZE];
+    file.read_exact(&mut header_bytes)?;
+    let header = crate::Header::from_bytes(&header_bytes);
+    header.validate()?;
+
+    let data_size = file.metadata()?.len() as usize - HEADER_SIZE;
+    if !data_size.is_multiple_of(RECORD_SIZE) {
+        return Err(IbuError::InvalidMapSize);
+    }
+    let num_records = data_size / RECORD_SIZE;
+
+    let mut barcodes = Vec::with_capacity(num_records);
+    let mut umis = Vec::with_capacity(num_records);
+    let mut indices = Vec::with_capacity(num_records);
+    let mut chunk = vec![Record::default(); num_records.min(CHUNK_RECORDS)];
+    let mut remaining = num_records;
+    while remaining > 0 {
+        let chunk = &mut chunk[..remaining.min(CHUNK_RECORDS)];
+        file.read_exact(bytemuck::cast_slice_mut(chunk))?;
+        for record in chunk.iter() {
+            barcodes.push(record.barcode);
+            umis.push(record.umi);
+            indices.push(record.index);
+        }
+        remaining -= chunk.len();
+    }
+
+    Ok((header, barcodes, umis, indices))
+}
+
+/// Loads the header and the first and last records of a file.
+///
+/// This is the cheapest way to inspect the range of a file: only the header
+/// and two records are read, by seeking directly to the end of the file. For
+/// sorted files the first and last records bound the barcode range.
+///
+/// # Arguments
+///
+/// * `path` - Path to an uncompressed IBU file
+///
+/// # Returns
+///
+/// Returns `(header, first, last)`, where `first` and `last` are `None` for
+/// files without records. For single-record files both are the same record.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The file cannot be opened or read
+/// - The file is compressed (`IbuError::CompressedInput`), since compressed
+///   streams cannot be seeked
+/// - The header is invalid
+/// - The file size is not consistent with the record format
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::load_bounds;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let (header, first, last) = load_bounds("sorted.ibu")?;
+/// if let (Some(first), Some(last)) = (first, last) {
+///     println!("Barcodes range from {:#x} to {:#x}", first.barcode, last.barcode);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_bounds<P: AsRef<Path>>(
+    path: P,
+) -> crate::Result<(Header, Option<Record>, Option<Record>)> {
+    let mut file = File::open(path)?;
+
+    // Read and validate header
+    let mut header_bytes = [0u8; HEADER_SIZE];
+    file.read_exact(&mut header_bytes)?;
+    let header: Header = bytemuck::pod_read_unaligned(&header_bytes);
+    #[cfg(feature = "niffler")]
+    if header.magic != crate::MAGIC {
+        let (_, format) = niffler::send::sniff(Box::new(&header_bytes[..]))?;
+        if format != niffler::send::compression::Format::No {
+            return Err(IbuError::CompressedInput);
+        }
+    }
+    header.validate()?;
+
+    // Get file size and calculate number of records
+    let data_size = file.metadata()?.len() as usize - HEADER_SIZE;
+    if !data_size.is_multiple_of(RECORD_SIZE) {
+        return Err(IbuError::InvalidMapSize);
+    }
+    if data_size == 0 {
+        return Ok((header, None, None));
+    }
+
+    let mut record_bytes = [0u8; RECORD_SIZE];
+    file.read_exact(&mut record_bytes)?;
+    let first: Record = bytemuck::pod_read_unaligned(&record_bytes);
+
+    file.seek(SeekFrom::End(-(RECORD_SIZE as i64)))?;
+    file.read_exact(&mut record_bytes)?;
+    let last: Record = bytemuck::pod_read_unaligned(&record_bytes);
+
+    Ok((header, Some(first), Some(last)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -574,6 +1366,44 @@ mod tests {
         assert!(matches!(result, Err(IbuError::InvalidMagicNumber { .. })));
     }
 
+    #[test]
+    fn test_reader_new_with_upgrade() {
+        let records = vec![Record::new(1, 2, 3), Record::new(4, 5, 6)];
+
+        // Version 1 stream, sorted
+        let mut v1 = Vec::new();
+        for field in [1u32, 16, 12] {
+            v1.extend(field.to_le_bytes());
+        }
+        v1.push(1);
+        v1.extend(bytemuck::cast_slice::<Record, u8>(&records));
+        let reader = Reader::new_with_upgrade(Cursor::new(v1.clone())).unwrap();
+        let mut expected = Header::new(16, 12);
+        expected.set_sorted();
+        assert_eq!(reader.header(), expected);
+        let read: Vec<Record> = reader.collect::<crate::Result<_>>().unwrap();
+        assert_eq!(read, records);
+
+        // Truncation positions account for the shorter header
+        v1.pop();
+        let results: Vec<_> = Reader::new_with_upgrade(Cursor::new(v1)).unwrap().collect();
+        assert!(matches!(
+            results[0],
+            Err(IbuError::TruncatedRecord { pos: 37 })
+        ));
+
+        // Current streams are unaffected, and v1 is still rejected by `new`
+        let current = create_test_data(&records);
+        let reader = Reader::new_with_upgrade(Cursor::new(current)).unwrap();
+        assert_eq!(reader.header(), Header::new(16, 12));
+        assert_eq!(reader.count(), 2);
+        assert!(Reader::new(Cursor::new(vec![1, 0, 0, 0, 16])).is_err());
+        assert!(matches!(
+            Reader::new_with_upgrade(Cursor::new(vec![0u8; 32])),
+            Err(IbuError::InvalidMagicNumber { .. })
+        ));
+    }
+
     #[test]
     fn test_reader_iterator() {
         let records = vec![
@@ -652,6 +1482,158 @@ mod tests {
         assert!(!has_data);
     }
 
+    fn create_checksummed_data(records: &[Record]) -> Vec<u8> {
+        let header = Header::new(16, 12);
+        let mut writer = Writer::new_with_checksum(Cursor::new(Vec::new()), header).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish_with_checksum().unwrap();
+        writer.into_inner().into_inner()
+    }
+
+    #[test]
+    fn test_reader_checksum_verification() {
+        let records: Vec<Record> = (0..100_000).map(|i| Record::new(i, i * 2, i * 3)).collect();
+        let buffer = create_checksummed_data(&records);
+
+        let reader = Reader::new(Cursor::new(buffer))
+            .unwrap()
+            .with_checksum_verification();
+        let read_records: Vec<_> = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(records, read_records);
+    }
+
+    #[test]
+    fn test_skip_records() {
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, 0, i)).collect();
+        let data = create_test_data(&records);
+
+        // Skips across several small batches
+        let mut reader = Reader::with_capacity(Cursor::new(data.clone()), 64).unwrap();
+        assert_eq!(reader.skip_records(0).unwrap(), 0);
+        assert_eq!(reader.skip_records(10).unwrap(), 10);
+        assert_eq!(reader.next().unwrap().unwrap(), records[10]);
+        assert_eq!(reader.skip_records(500).unwrap(), 500);
+        assert_eq!(reader.records_consumed(), 511);
+        assert_eq!(reader.next().unwrap().unwrap(), records[511]);
+        assert_eq!(reader.skip_records(1000).unwrap(), 488);
+        assert!(reader.next().is_none());
+        assert_eq!(reader.skip_records(1).unwrap(), 0);
+        assert_eq!(reader.records_consumed(), 1000);
+
+        // Skipped records are still checksummed
+        let mut buffer = create_checksummed_data(&records);
+        buffer[HEADER_SIZE] ^= 0xFF;
+        let mut reader = Reader::new(Cursor::new(buffer))
+            .unwrap()
+            .with_checksum_verification();
+        assert!(matches!(
+            reader.skip_records(2000),
+            Err(IbuError::ChecksumMismatch { .. })
+        ));
+
+        // Truncated streams fail
+        let mut reader = Reader::new(Cursor::new(&data[..data.len() - 1])).unwrap();
+        assert!(matches!(
+            reader.skip_records(2000),
+            Err(IbuError::TruncatedRecord { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reader_checksum_mismatch() {
+        let records = vec![Record::new(1, 2, 3), Record::new(4, 5, 6)];
+        let mut buffer = create_checksummed_data(&records);
+
+        // Corrupt the last record's index
+        let last = buffer.len() - 1;
+        buffer[last] ^= 0xFF;
+
+        // Unverified reads are unaffected
+        let reader = Reader::new(Cursor::new(buffer.clone())).unwrap();
+        assert!(reader.collect::<Result<Vec<_>, _>>().is_ok());
+
+        let mut reader = Reader::new(Cursor::new(buffer))
+            .unwrap()
+            .with_checksum_verification();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(IbuError::ChecksumMismatch { .. }))
+        ));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_reader_verify_checksum() {
+        let records = vec![Record::new(1, 2, 3), Record::new(4, 5, 6)];
+        let mut buffer = create_checksummed_data(&records);
+        assert!(Reader::new(Cursor::new(buffer.clone()))
+            .unwrap()
+            .verify_checksum()
+            .unwrap());
+
+        // No checksum stored
+        let plain = create_test_data(&records);
+        assert!(!Reader::new(Cursor::new(plain))
+            .unwrap()
+            .verify_checksum()
+            .unwrap());
+
+        let last = buffer.len() - 1;
+        buffer[last] ^= 0xFF;
+        assert!(matches!(
+            Reader::new(Cursor::new(buffer)).unwrap().verify_checksum(),
+            Err(IbuError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_options_direct_io() {
+        use std::fs;
+
+        let temp_path = "test_read_options_direct.ibu";
+        let records: Vec<Record> = (0..100_000).map(|i| Record::new(i, i * 2, i * 3)).collect();
+        fs::write(temp_path, create_test_data(&records)).unwrap();
+
+        for direct in [false, true] {
+            let reader = ReadOptions::new()
+                .direct_io(direct)
+                .open(temp_path)
+                .unwrap();
+            assert_eq!(reader.header().bc_len, 16);
+            let read_records: Vec<_> = reader.collect::<Result<Vec<_>, _>>().unwrap();
+            assert_eq!(records, read_records);
+        }
+
+        fs::remove_file(temp_path).unwrap();
+    }
+
+    #[test]
+    fn test_read_options_strict_reserved() {
+        use std::fs;
+
+        let temp_path = "test_read_options_strict_reserved.ibu";
+        let records = vec![Record::new(1, 2, 3)];
+
+        // Reserved bytes claimed by the checksum flag are accepted
+        fs::write(temp_path, create_checksummed_data(&records)).unwrap();
+        let strict = ReadOptions::new().strict_reserved(true);
+        assert!(strict.open(temp_path).is_ok());
+
+        // Unclaimed reserved bytes are only rejected in strict mode
+        let mut buffer = create_test_data(&records);
+        buffer[HEADER_SIZE - 8] = 0x42;
+        fs::write(temp_path, buffer).unwrap();
+        assert!(ReadOptions::new().open(temp_path).is_ok());
+        assert!(matches!(
+            strict.open(temp_path),
+            Err(IbuError::UnexpectedReserved(reserved)) if reserved[0] == 0x42
+        ));
+
+        fs::remove_file(temp_path).unwrap();
+    }
+
     #[test]
     fn test_reader_clone() {
         let records = vec![Record::new(1, 2, 3)];
@@ -696,6 +1678,43 @@ mod tests {
         fs::remove_file(temp_path).unwrap();
     }
 
+    #[test]
+    fn test_load_to_soa() {
+        use std::fs;
+
+        // Spans several chunks, the last one partial
+        let records: Vec<Record> = (0..150_000)
+            .map(|i| Record::new(i % 13, i * 2, i))
+            .collect();
+        let temp_path = "test_load_to_soa.ibu";
+        fs::write(temp_path, create_test_data(&records)).unwrap();
+
+        let (header, barcodes, umis, indices) = load_to_soa(temp_path).unwrap();
+        assert_eq!((header.bc_len, header.umi_len), (16, 12));
+        assert_eq!(barcodes.len(), records.len());
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(
+                (barcodes[i], umis[i], indices[i]),
+                (record.barcode, record.umi, record.index)
+            );
+        }
+
+        // Empty and truncated files
+        fs::write(temp_path, create_test_data(&[])).unwrap();
+        let (_, barcodes, umis, indices) = load_to_soa(temp_path).unwrap();
+        assert!(barcodes.is_empty() && umis.is_empty() && indices.is_empty());
+
+        let mut buffer = create_test_data(&records[..2]);
+        buffer.pop();
+        fs::write(temp_path, buffer).unwrap();
+        assert!(matches!(
+            load_to_soa(temp_path),
+            Err(IbuError::InvalidMapSize)
+        ));
+
+        fs::remove_file(temp_path).unwrap();
+    }
+
     #[test]
     fn test_load_to_vec_empty_file() {
         use std::fs;
@@ -740,6 +1759,92 @@ mod tests {
         fs::remove_file(temp_path).unwrap();
     }
 
+    #[test]
+    fn test_reader_index_offset() {
+        let records = vec![
+            Record::new(1, 2, 0),
+            Record::new(1, 2, u64::MAX - 5),
+            Record::new(3, 4, 7),
+        ];
+        let data = create_test_data(&records);
+
+        let reader = Reader::new(Cursor::new(data.clone()))
+            .unwrap()
+            .with_index_offset(10, OverflowPolicy::Saturate);
+        let indices: Vec<u64> = reader.map(|r| r.unwrap().index).collect();
+        assert_eq!(indices, vec![10, u64::MAX, 17]);
+
+        let mut reader = Reader::new(Cursor::new(data))
+            .unwrap()
+            .with_index_offset(10, OverflowPolicy::Error);
+        assert_eq!(reader.next().unwrap().unwrap().index, 10);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(IbuError::IndexOverflow {
+                index,
+                offset: 10
+            })) if index == u64::MAX - 5
+        ));
+        assert_eq!(reader.next().unwrap().unwrap().index, 17);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_load_bounds() {
+        use std::fs;
+
+        let temp_path = "test_load_bounds.ibu";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, i * 2, i * 3)).collect();
+        fs::write(temp_path, create_test_data(&records)).unwrap();
+
+        let (header, first, last) = load_bounds(temp_path).unwrap();
+        assert_eq!(header.bc_len, 16);
+        assert_eq!(first, Some(Record::new(0, 0, 0)));
+        assert_eq!(last, Some(Record::new(999, 1998, 2997)));
+
+        // Single record
+        fs::write(temp_path, create_test_data(&records[5..6])).unwrap();
+        let (_, first, last) = load_bounds(temp_path).unwrap();
+        assert_eq!(first, Some(records[5]));
+        assert_eq!(last, Some(records[5]));
+
+        // Empty file
+        fs::write(temp_path, create_test_data(&[])).unwrap();
+        let (_, first, last) = load_bounds(temp_path).unwrap();
+        assert_eq!(first, None);
+        assert_eq!(last, None);
+
+        fs::remove_file(temp_path).unwrap();
+    }
+
+    #[cfg(feature = "niffler")]
+    #[test]
+    fn test_load_bounds_compressed() {
+        use std::fs;
+
+        let temp_path = "test_load_bounds_compressed.ibu.gz";
+        {
+            let file = fs::File::create(temp_path).unwrap();
+            let mut compressed = niffler::send::get_writer(
+                Box::new(file),
+                niffler::send::compression::Format::Gzip,
+                niffler::Level::One,
+            )
+            .unwrap();
+            std::io::Write::write_all(&mut compressed, &create_test_data(&[Record::new(1, 2, 3)]))
+                .unwrap();
+        }
+
+        // Streaming reads work, but bounds require seeking
+        assert!(Reader::from_path(temp_path).is_ok());
+        assert!(matches!(
+            load_bounds(temp_path),
+            Err(IbuError::CompressedInput)
+        ));
+
+        fs::remove_file(temp_path).unwrap();
+    }
+
     #[test]
     fn test_reader_bytes_read_tracking() {
         let records = vec![Record::new(1, 2, 3), Record::new(4, 5, 6)];
@@ -764,4 +1869,160 @@ mod tests {
         // Note: reader is moved by collect(), so we can't access it anymore
         // But we know it should have read the entire buffer
     }
+
+    #[test]
+    fn test_reader_records_remaining() {
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, 0, 0)).collect();
+
+        // Unknown count
+        let mut reader = Reader::new(Cursor::new(create_test_data(&records))).unwrap();
+        assert_eq!(reader.total_records(), None);
+        assert_eq!(reader.size_hint(), (0, None));
+        reader.next().unwrap().unwrap();
+        assert_eq!(reader.records_consumed(), 1);
+        assert_eq!(reader.records_remaining(), None);
+
+        // Stored count
+        let mut writer = Writer::new(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_count().unwrap();
+        let buffer = writer.into_inner().into_inner();
+        let mut reader = Reader::new(Cursor::new(buffer)).unwrap();
+        assert_eq!(reader.total_records(), Some(1000));
+        assert_eq!(reader.size_hint(), (0, Some(1000)));
+        for _ in 0..400 {
+            reader.next().unwrap().unwrap();
+        }
+        assert_eq!(reader.records_consumed(), 400);
+        assert_eq!(reader.records_remaining(), Some(600));
+        assert_eq!(reader.by_ref().count(), 600);
+        assert_eq!(reader.records_remaining(), Some(0));
+
+        // A truncated stream yields fewer records than its stored count
+        let mut writer = Writer::new(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_count().unwrap();
+        let mut buffer = writer.into_inner().into_inner();
+        buffer.truncate(HEADER_SIZE + 10 * RECORD_SIZE);
+        let reader = Reader::new(Cursor::new(buffer.clone())).unwrap();
+        assert_eq!(reader.size_hint(), (0, Some(1000)));
+        assert_eq!(reader.collect::<Vec<_>>().len(), 10);
+
+        // The checksum mismatch comes after the last record
+        let mut writer =
+            Writer::new_with_checksum(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_count().unwrap();
+        let mut buffer = writer.into_inner().into_inner();
+        buffer[HEADER_SIZE] ^= 1;
+        let reader = Reader::new(Cursor::new(buffer))
+            .unwrap()
+            .with_checksum_verification();
+        assert_eq!(reader.size_hint(), (0, Some(1001)));
+        let items: Vec<_> = reader.collect();
+        assert_eq!(items.len(), 1001);
+        assert!(matches!(
+            items.last(),
+            Some(Err(IbuError::ChecksumMismatch { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_reader_new_shard() {
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, 0, i)).collect();
+        let mut writer =
+            Writer::new_with_checksum(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_count().unwrap();
+        let buffer = writer.into_inner().into_inner();
+
+        let mut reader = Reader::new_shard(Cursor::new(buffer.clone()), 250, 500).unwrap();
+        assert_eq!(reader.total_records(), Some(1000));
+        assert_eq!(reader.size_hint(), (0, Some(250)));
+        reader.next().unwrap().unwrap();
+        assert_eq!(reader.records_remaining(), Some(249));
+
+        // Shards tile the file; checksums are not verified on partial reads
+        let mut read = Vec::new();
+        for (start, end) in [(0, 300), (300, 300), (300, 999), (999, 2000), (5000, 6000)] {
+            let shard = Reader::new_shard(Cursor::new(buffer.clone()), start, end)
+                .unwrap()
+                .with_checksum_verification();
+            read.extend(shard.collect::<crate::Result<Vec<_>>>().unwrap());
+        }
+        assert_eq!(read, records);
+
+        assert!(matches!(
+            Reader::new_shard(Cursor::new(buffer.clone()), 10, 5),
+            Err(IbuError::InvalidIndex { idx: 10, max: 5 })
+        ));
+        assert!(matches!(
+            Reader::new_shard(Cursor::new(buffer), 0, usize::MAX),
+            Err(IbuError::InvalidIndex {
+                idx: usize::MAX,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_reader_with_capacity() {
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, 0, 0)).collect();
+        let buffer = create_test_data(&records);
+
+        for capacity in [1, 7, 1000, 5000] {
+            let reader = Reader::with_capacity(Cursor::new(buffer.clone()), capacity).unwrap();
+            let read: Vec<Record> = reader.collect::<crate::Result<_>>().unwrap();
+            assert_eq!(read, records);
+        }
+
+        // Truncation is still reported at the right position
+        let mut truncated = buffer;
+        truncated.pop();
+        let results: Vec<_> = Reader::with_capacity(Cursor::new(truncated), 7)
+            .unwrap()
+            .collect();
+        assert!(matches!(
+            results.last(),
+            Some(Err(IbuError::TruncatedRecord { pos })) if *pos == HEADER_SIZE + 999 * RECORD_SIZE
+        ));
+
+        // A buffer without room for a record is rejected
+        for capacity in [0, usize::MAX] {
+            assert!(matches!(
+                Reader::with_capacity(Cursor::new(Vec::new()), capacity),
+                Err(IbuError::Io(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+            ));
+        }
+    }
+
+    #[test]
+    fn test_reader_progress_callback() {
+        let records: Vec<Record> = (0..100_000).map(|i| Record::new(i, 0, 0)).collect();
+        let buffer = create_test_data(&records);
+        let total = buffer.len();
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&calls);
+        let mut reader = Reader::new(Cursor::new(buffer)).unwrap();
+        reader.set_progress_callback(Box::new(move |bytes| seen.lock().unwrap().push(bytes)));
+        assert_eq!(reader.bytes_read(), HEADER_SIZE);
+
+        let mut count = 0;
+        for record in reader.by_ref() {
+            record.unwrap();
+            count += 1;
+        }
+        assert_eq!(count, records.len());
+        assert_eq!(reader.bytes_read(), total);
+
+        // Called once per non-empty batch, with increasing cumulative totals
+        let calls = calls.lock().unwrap();
+        assert_eq!(
+            calls.len(),
+            records.len().div_ceil(DEFAULT_BUFFER_SIZE / RECORD_SIZE)
+        );
+        assert!(calls.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(calls.last(), Some(&total));
+    }
 }
@@ -0,0 +1,223 @@
+//! Bounded in-memory ring buffer for streaming between threads.
+//!
+//! This module provides a byte pipe with a fixed capacity. The [`RingSink`]
+//! end implements [`Write`] and blocks when the buffer is full, so a
+//! [`Writer`](crate::Writer) on top of it naturally applies backpressure. The
+//! [`RingSource`] end implements [`Read`] and can be wrapped in a
+//! [`Reader`](crate::Reader) on a consumer thread.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    sync::{Arc, Condvar, Mutex},
+};
+
+/// Buffer state shared by both ends of the ring.
+struct RingState {
+    /// Buffered bytes not yet consumed
+    buffer: VecDeque<u8>,
+
+    /// Maximum number of buffered bytes
+    capacity: usize,
+
+    /// Set once the sink is dropped (no more data will arrive)
+    sink_closed: bool,
+
+    /// Set once the source is dropped (no more data will be consumed)
+    source_closed: bool,
+}
+
+/// Synchronization wrapper around the shared ring state.
+struct Ring {
+    state: Mutex<RingState>,
+    /// Signalled whenever data is produced, consumed, or an end is closed
+    changed: Condvar,
+}
+
+/// Producer end of an in-memory ring buffer.
+///
+/// Created by [`ring_buffer`]. Writes block while the buffer is full and fail
+/// with [`io::ErrorKind::BrokenPipe`] once the [`RingSource`] has been dropped.
+/// Dropping the sink signals end of stream to the source.
+pub struct RingSink {
+    ring: Arc<Ring>,
+}
+
+/// Consumer end of an in-memory ring buffer.
+///
+/// Created by [`ring_buffer`]. Reads block while the buffer is empty and
+/// return `Ok(0)` (end of stream) once the [`RingSink`] has been dropped and
+/// all buffered bytes have been consumed.
+pub struct RingSource {
+    ring: Arc<Ring>,
+}
+
+/// Creates a bounded in-memory ring buffer.
+///
+/// Returns the paired producer and consumer ends. At most `capacity` bytes are
+/// buffered at any time, so memory use stays bounded regardless of how far the
+/// producer runs ahead of the consumer.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{ring_buffer, Header, IbuError, Reader, Record, Writer};
+/// use std::thread;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let (sink, source) = ring_buffer(64 * 1024);
+///
+/// let producer = thread::spawn(move || -> ibu::Result<()> {
+///     let mut writer = Writer::new(sink, Header::new(16, 12))?;
+///     for i in 0..10_000 {
+///         writer.write_record(&Record::new(i, i, i))?;
+///     }
+///     writer.finish()
+/// });
+///
+/// let reader = Reader::new(source)?;
+/// let count = reader.map(|r| r.map(|_| 1)).sum::<ibu::Result<u64>>()?;
+/// assert_eq!(count, 10_000);
+///
+/// // Surface a producer panic as an error rather than re-raising it
+/// producer
+///     .join()
+///     .map_err(|_| IbuError::Process("producer thread panicked".into()))??;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ring_buffer(capacity: usize) -> (RingSink, RingSource) {
+    assert!(capacity > 0, "ring buffer capacity must be non-zero");
+    let ring = Arc::new(Ring {
+        state: Mutex::new(RingState {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            sink_closed: false,
+            source_closed: false,
+        }),
+        changed: Condvar::new(),
+    });
+    (RingSink { ring: ring.clone() }, RingSource { ring })
+}
+
+impl Write for RingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.ring.state.lock().unwrap();
+        while state.buffer.len() == state.capacity && !state.source_closed {
+            state = self.ring.changed.wait(state).unwrap();
+        }
+        if state.source_closed {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "ring buffer source was dropped",
+            ));
+        }
+        let n = buf.len().min(state.capacity - state.buffer.len());
+        state.buffer.extend(&buf[..n]);
+        self.ring.changed.notify_all();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RingSink {
+    fn drop(&mut self) {
+        let mut state = self.ring.state.lock().unwrap();
+        state.sink_closed = true;
+        self.ring.changed.notify_all();
+    }
+}
+
+impl Read for RingSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.ring.state.lock().unwrap();
+        while state.buffer.is_empty() && !state.sink_closed {
+            state = self.ring.changed.wait(state).unwrap();
+        }
+        let n = buf.len().min(state.buffer.len());
+        for (dst, src) in buf.iter_mut().zip(state.buffer.drain(..n)) {
+            *dst = src;
+        }
+        self.ring.changed.notify_all();
+        Ok(n)
+    }
+}
+
+impl Drop for RingSource {
+    fn drop(&mut self) {
+        let mut state = self.ring.state.lock().unwrap();
+        state.source_closed = true;
+        self.ring.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parallel::catch_panic, Header, Reader, Record, Writer};
+    use std::thread;
+
+    #[test]
+    fn test_ring_buffer_roundtrip() {
+        let (sink, source) = ring_buffer(1000);
+        let records: Vec<Record> = (0..100_000).map(|i| Record::new(i, i * 2, i * 3)).collect();
+
+        let expected = records.clone();
+        let producer = thread::spawn(move || {
+            catch_panic(|| {
+                let mut writer = Writer::new(sink, Header::new(16, 12))?;
+                writer.write_batch(&records)?;
+                writer.finish()
+            })
+        });
+
+        let reader = Reader::new(source).unwrap();
+        let read_records: Vec<Record> = reader.collect::<crate::Result<Vec<_>>>().unwrap();
+        assert_eq!(read_records, expected);
+
+        // Panics are caught in the producer, so joining returns them as errors
+        producer.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_ring_buffer_bounded() {
+        let (mut sink, mut source) = ring_buffer(4);
+
+        // Only the available capacity is accepted
+        assert_eq!(sink.write(&[1, 2, 3, 4, 5, 6]).unwrap(), 4);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(source.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], &[1, 2, 3, 4]);
+
+        // End of stream once the sink is dropped and drained
+        sink.write_all(&[7]).unwrap();
+        drop(sink);
+        assert_eq!(source.read(&mut buf).unwrap(), 1);
+        assert_eq!(source.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_ring_buffer_source_dropped() {
+        let (mut sink, source) = ring_buffer(4);
+        sink.write_all(&[1, 2, 3, 4]).unwrap();
+        drop(source);
+
+        // A full buffer must not block forever once the consumer is gone
+        let err = sink.write(&[5]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
@@ -0,0 +1,251 @@
+//! Random-access reader over seekable IBU streams.
+//!
+//! This module provides [`SeekReader`], which fetches individual records or
+//! ranges of records by position using `seek` and `read` calls. It offers the
+//! random access of [`MmapReader`](crate::MmapReader) for sources that cannot
+//! or should not be memory-mapped, such as files on networked filesystems
+//! where mmap is unreliable.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{Header, IbuError, Record, HEADER_SIZE, RECORD_SIZE};
+
+/// Random-access reader over a seekable IBU stream.
+///
+/// The header is read and the number of records is computed from the stream
+/// length once at construction. Each access then seeks directly to the
+/// requested records, so only the bytes of those records are read.
+///
+/// The header is expected at the stream position the reader is created at,
+/// which is usually the start of the stream.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::SeekReader;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let mut reader = SeekReader::from_path("data.ibu")?;
+/// let last = reader.get(reader.len() - 1)?;
+/// let first_ten = reader.get_range(0, 10)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SeekReader<R: Read + Seek> {
+    /// Underlying data source
+    inner: R,
+
+    /// Parsed file header
+    header: Header,
+
+    /// Stream position of the header
+    start: u64,
+
+    /// Number of records in the stream
+    len: usize,
+}
+
+impl<R: Read + Seek> SeekReader<R> {
+    /// Creates a random-access reader from a seekable data source.
+    ///
+    /// Reads and validates the header at the current stream position, then
+    /// seeks to the end of the stream to count the records.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The header cannot be read or is invalid
+    /// - The data after the header is not a whole number of records
+    ///   ([`IbuError::InvalidMapSize`])
+    /// - The header stores a record count that differs from the number of
+    ///   records in the stream ([`IbuError::RecordCountMismatch`])
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Record, SeekReader, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_batch(&[Record::new(1, 2, 3), Record::new(4, 5, 6)])?;
+    /// writer.finish()?;
+    ///
+    /// let mut reader = SeekReader::new(Cursor::new(writer.into_inner()))?;
+    /// assert_eq!(reader.len(), 2);
+    /// assert_eq!(reader.get(1)?, Record::new(4, 5, 6));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(mut inner: R) -> crate::Result<Self> {
+        let start = inner.stream_position()?;
+        let mut header_bytes = [0u8; HEADER_SIZE];
+        inner.read_exact(&mut header_bytes)?;
+        let header: Header = bytemuck::pod_read_unaligned(&header_bytes);
+        header.validate()?;
+
+        let end = inner.seek(SeekFrom::End(0))?;
+        let data_len = end.saturating_sub(start + HEADER_SIZE as u64);
+        if data_len % RECORD_SIZE as u64 != 0 {
+            return Err(IbuError::InvalidMapSize);
+        }
+        let len = data_len / RECORD_SIZE as u64;
+        if let Some(expected) = header.record_count() {
+            if expected != len {
+                return Err(IbuError::RecordCountMismatch {
+                    expected,
+                    actual: len,
+                });
+            }
+        }
+
+        Ok(Self {
+            inner,
+            header,
+            start,
+            len: len as usize,
+        })
+    }
+
+    /// Returns the file header.
+    pub fn header(&self) -> Header {
+        self.header
+    }
+
+    /// Returns the number of records in the stream.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the stream holds no records.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reads the record at position `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidIndex`] if `index >= len()`, or an I/O error
+    /// if seeking or reading fails.
+    pub fn get(&mut self, index: usize) -> crate::Result<Record> {
+        if index >= self.len {
+            return Err(IbuError::InvalidIndex {
+                idx: index,
+                max: self.len,
+            });
+        }
+        self.seek_to(index)?;
+        let mut bytes = [0u8; RECORD_SIZE];
+        self.inner.read_exact(&mut bytes)?;
+        Ok(bytemuck::pod_read_unaligned(&bytes))
+    }
+
+    /// Reads the records at positions `start..end`.
+    ///
+    /// The records are fetched with a single seek and read. An empty range
+    /// returns an empty vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidIndex`] if `end > len()` or `start > end`,
+    /// or an I/O error if seeking or reading fails.
+    pub fn get_range(&mut self, start: usize, end: usize) -> crate::Result<Vec<Record>> {
+        if end > self.len || start > end {
+            return Err(IbuError::InvalidIndex {
+                idx: end,
+                max: self.len,
+            });
+        }
+        let mut records = vec![Record::default(); end - start];
+        self.seek_to(start)?;
+        self.inner
+            .read_exact(bytemuck::cast_slice_mut(records.as_mut_slice()))?;
+        Ok(records)
+    }
+
+    /// Consumes the reader and returns the underlying data source.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Positions the stream at the start of record `index`.
+    fn seek_to(&mut self, index: usize) -> crate::Result<()> {
+        let offset = self.start + (HEADER_SIZE + index * RECORD_SIZE) as u64;
+        self.inner.seek(SeekFrom::Start(offset))?;
+        Ok(())
+    }
+}
+
+impl SeekReader<File> {
+    /// Opens a file for random access.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, in addition to the
+    /// errors of [`SeekReader::new`].
+    pub fn from_path<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        Self::new(File::open(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Writer;
+    use std::io::Cursor;
+
+    fn encoded(records: &[Record]) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_seek_reader() {
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, i * 2, i * 3)).collect();
+        let mut reader = SeekReader::new(Cursor::new(encoded(&records))).unwrap();
+        assert_eq!(reader.len(), 100);
+        assert_eq!(reader.header(), Header::new(16, 12));
+
+        assert_eq!(reader.get(0).unwrap(), records[0]);
+        assert_eq!(reader.get(99).unwrap(), records[99]);
+        assert_eq!(reader.get(42).unwrap(), records[42]);
+        assert_eq!(reader.get_range(10, 20).unwrap(), &records[10..20]);
+        assert_eq!(reader.get_range(100, 100).unwrap(), vec![]);
+
+        assert!(matches!(
+            reader.get(100),
+            Err(IbuError::InvalidIndex { idx: 100, max: 100 })
+        ));
+        assert!(reader.get_range(90, 101).is_err());
+        assert!(reader.get_range(20, 10).is_err());
+    }
+
+    #[test]
+    fn test_seek_reader_offset_and_truncation() {
+        let records: Vec<Record> = (0..10).map(|i| Record::new(i, 0, 0)).collect();
+
+        // Header at a non-zero stream position
+        let mut bytes = vec![0xFF; 7];
+        bytes.extend(encoded(&records));
+        let mut cursor = Cursor::new(bytes);
+        cursor.set_position(7);
+        let mut reader = SeekReader::new(cursor).unwrap();
+        assert_eq!(reader.len(), 10);
+        assert_eq!(reader.get(3).unwrap(), records[3]);
+
+        // Partial trailing record
+        let mut bytes = encoded(&records);
+        bytes.pop();
+        assert!(matches!(
+            SeekReader::new(Cursor::new(bytes)),
+            Err(IbuError::InvalidMapSize)
+        ));
+    }
+}
@@ -0,0 +1,625 @@
+//! Seekable zstd-compressed IBU files.
+//!
+//! A seekable file stores the header and then the records in independent
+//! zstd frames of a fixed number of records each, followed by a seek table
+//! of frame offsets in a zstd skippable frame. [`SeekableZstdReader`] uses
+//! the table to decompress only the frame holding a requested record, giving
+//! random access to compressed data. Files are written with
+//! [`Writer::to_seekable_zstd`](crate::Writer::to_seekable_zstd).
+//!
+//! Since concatenated zstd frames form a valid zstd stream and skippable
+//! frames are ignored by decoders, a seekable file is also an ordinary
+//! `.ibu.zst` file that any streaming [`Reader`](crate::Reader) can read.
+//!
+//! # Layout
+//!
+//! | Part        | Content                                                       |
+//! |-------------|---------------------------------------------------------------|
+//! | Frame 0     | Header (32 bytes)                                             |
+//! | Frames 1..  | `frame_records` records each (fewer in the last frame)        |
+//! | Seek table  | Skippable frame: frame offsets (`u64`), then the trailer      |
+//! | Trailer     | Frame count, records per frame, record count (`u64`), magic   |
+//!
+//! All integers are little-endian, and offsets are from the start of the
+//! file.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use super::writer::FinishStream;
+use crate::{Header, IbuError, Record, HEADER_SIZE, RECORD_SIZE};
+
+/// Magic number of the skippable frame holding the seek table.
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A5E;
+
+/// Magic number ending the seek table ("SEEK").
+const SEEK_TABLE_MAGIC: u32 = 0x4B45_4553;
+
+/// Size of the trailer ending the seek table.
+const TRAILER_SIZE: usize = 3 * 8 + 4;
+
+/// Size of a skippable frame header (magic and payload size).
+const SKIPPABLE_HEADER_SIZE: usize = 8;
+
+/// Sink compressing IBU bytes into independent zstd frames.
+///
+/// The first [`HEADER_SIZE`] bytes form their own frame, and the records
+/// that follow are grouped into frames of `frame_records` records. Partial
+/// frames are kept until [`finish_stream`](FinishStream::finish_stream),
+/// which writes the last frame and the seek table, so flushing does not
+/// shorten frames. Writes after that fail, since the seek table would no
+/// longer describe the file.
+pub(crate) struct SeekableZstdSink<W: Write> {
+    /// Compressed output
+    inner: W,
+
+    /// Bytes not yet compressed
+    pending: Vec<u8>,
+
+    /// Whether the header frame has been written
+    header_done: bool,
+
+    /// Number of records per frame
+    frame_records: usize,
+
+    /// Offset of each record frame
+    offsets: Vec<u64>,
+
+    /// Number of bytes written to the output
+    written: u64,
+
+    /// Number of records compressed
+    records: u64,
+
+    /// Whether the seek table has been written
+    finished: bool,
+}
+
+impl<W: Write> SeekableZstdSink<W> {
+    /// Creates a sink writing frames of `frame_records` records.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidInput`](io::ErrorKind::InvalidInput) error if
+    /// `frame_records` is zero.
+    pub(crate) fn new(inner: W, frame_records: usize) -> io::Result<Self> {
+        if frame_records == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frames must hold at least one record",
+            ));
+        }
+        Ok(Self {
+            inner,
+            pending: Vec::new(),
+            header_done: false,
+            frame_records,
+            offsets: Vec::new(),
+            written: 0,
+            records: 0,
+            finished: false,
+        })
+    }
+
+    /// Compresses `pending[start..end]` into a single frame.
+    fn write_frame(&mut self, start: usize, end: usize) -> io::Result<()> {
+        let frame =
+            zstd::bulk::compress(&self.pending[start..end], zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        self.inner.write_all(&frame)?;
+        self.written += frame.len() as u64;
+        Ok(())
+    }
+
+    /// Compresses a record frame and records its offset.
+    fn write_record_frame(&mut self, start: usize, end: usize) -> io::Result<()> {
+        self.offsets.push(self.written);
+        self.records += ((end - start) / RECORD_SIZE) as u64;
+        self.write_frame(start, end)
+    }
+
+    /// Compresses every complete frame of the pending bytes.
+    fn write_complete_frames(&mut self) -> io::Result<()> {
+        let mut start = 0;
+        if !self.header_done {
+            if self.pending.len() < HEADER_SIZE {
+                return Ok(());
+            }
+            self.write_frame(0, HEADER_SIZE)?;
+            self.header_done = true;
+            start = HEADER_SIZE;
+        }
+        let frame_bytes = self.frame_records * RECORD_SIZE;
+        while self.pending.len() - start >= frame_bytes {
+            self.write_record_frame(start, start + frame_bytes)?;
+            start += frame_bytes;
+        }
+        self.pending.drain(..start);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for SeekableZstdSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(io::Error::other("write after the seek table was written"));
+        }
+        self.pending.extend_from_slice(buf);
+        self.write_complete_frames()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Send> FinishStream for SeekableZstdSink<W> {
+    /// Writes the last partial frame and the seek table.
+    fn finish_stream(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.write_complete_frames()?;
+        if !self.header_done {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "seekable zstd output ended before the header",
+            ));
+        }
+        if !self.pending.is_empty() {
+            self.write_record_frame(0, self.pending.len())?;
+            self.pending.clear();
+        }
+
+        let payload = self.offsets.len() * 8 + TRAILER_SIZE;
+        let mut table = Vec::with_capacity(SKIPPABLE_HEADER_SIZE + payload);
+        table.extend(SKIPPABLE_MAGIC.to_le_bytes());
+        table.extend((payload as u32).to_le_bytes());
+        for offset in &self.offsets {
+            table.extend(offset.to_le_bytes());
+        }
+        table.extend((self.offsets.len() as u64).to_le_bytes());
+        table.extend((self.frame_records as u64).to_le_bytes());
+        table.extend(self.records.to_le_bytes());
+        table.extend(SEEK_TABLE_MAGIC.to_le_bytes());
+        self.inner.write_all(&table)?;
+        self.inner.flush()?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+/// Random-access reader over a seekable zstd-compressed IBU stream.
+///
+/// The seek table and header are read once at construction. Each access
+/// then decompresses only the frame holding the requested records, and the
+/// last decompressed frame is kept so that nearby accesses do not decompress
+/// it again.
+///
+/// The stream is expected to start with the header frame at the position the
+/// reader is created at, which is usually the start of the stream.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::SeekableZstdReader;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let mut reader = SeekableZstdReader::from_path("archive.ibu.zst")?;
+/// let last = reader.get(reader.len() - 1)?;
+/// let first_ten = reader.get_range(0, 10)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SeekableZstdReader<R: Read + Seek> {
+    /// Underlying data source
+    inner: R,
+
+    /// Parsed file header
+    header: Header,
+
+    /// Stream position of each record frame
+    offsets: Vec<u64>,
+
+    /// Stream position of the seek table (the end of the last frame)
+    table_start: u64,
+
+    /// Number of records per frame
+    frame_records: usize,
+
+    /// Number of records in the stream
+    len: usize,
+
+    /// Last decompressed frame and its records
+    cache: Option<(usize, Vec<Record>)>,
+}
+
+impl<R: Read + Seek> SeekableZstdReader<R> {
+    /// Creates a random-access reader from a seekable zstd data source.
+    ///
+    /// Reads the seek table from the end of the stream, then decompresses and
+    /// validates the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The seek table is missing or malformed ([`IbuError::InvalidSeekTable`])
+    /// - The header cannot be decompressed or is invalid
+    /// - Seeking or reading fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::SeekableZstdReader;
+    /// use std::fs::File;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut reader = SeekableZstdReader::new(File::open("archive.ibu.zst")?)?;
+    /// println!("{} records in {} frames", reader.len(), reader.num_frames());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(mut inner: R) -> crate::Result<Self> {
+        let base = inner.stream_position()?;
+        let end = inner.seek(SeekFrom::End(0))?;
+        let min_size = (SKIPPABLE_HEADER_SIZE + TRAILER_SIZE) as u64;
+        if end < base + min_size {
+            return Err(IbuError::InvalidSeekTable("stream too short"));
+        }
+
+        let mut trailer = [0u8; TRAILER_SIZE];
+        inner.seek(SeekFrom::Start(end - TRAILER_SIZE as u64))?;
+        inner.read_exact(&mut trailer)?;
+        let field = |i: usize| u64::from_le_bytes(trailer[i * 8..i * 8 + 8].try_into().unwrap());
+        let (num_frames, frame_records, num_records) = (field(0), field(1), field(2));
+        if u32::from_le_bytes(trailer[24..].try_into().unwrap()) != SEEK_TABLE_MAGIC {
+            return Err(IbuError::InvalidSeekTable("bad magic number"));
+        }
+        if frame_records == 0 || num_records.div_ceil(frame_records) != num_frames {
+            return Err(IbuError::InvalidSeekTable(
+                "frame count does not match record count",
+            ));
+        }
+
+        let payload = num_frames
+            .checked_mul(8)
+            .and_then(|size| size.checked_add(TRAILER_SIZE as u64))
+            .filter(|&size| size + SKIPPABLE_HEADER_SIZE as u64 <= end - base)
+            .ok_or(IbuError::InvalidSeekTable("truncated seek table"))?;
+        let table_start = end - payload - SKIPPABLE_HEADER_SIZE as u64;
+        inner.seek(SeekFrom::Start(table_start))?;
+        let mut table = vec![0u8; SKIPPABLE_HEADER_SIZE + payload as usize - TRAILER_SIZE];
+        inner.read_exact(&mut table)?;
+        if u32::from_le_bytes(table[..4].try_into().unwrap()) != SKIPPABLE_MAGIC
+            || u64::from(u32::from_le_bytes(table[4..8].try_into().unwrap())) != payload
+        {
+            return Err(IbuError::InvalidSeekTable("missing skippable frame"));
+        }
+        let offsets: Vec<u64> = table[SKIPPABLE_HEADER_SIZE..]
+            .chunks_exact(8)
+            .map(|bytes| base + u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        if offsets
+            .iter()
+            .chain([&table_start])
+            .try_fold(base, |prev, &offset| (offset > prev).then_some(offset))
+            .is_none()
+        {
+            return Err(IbuError::InvalidSeekTable("frame offsets out of order"));
+        }
+
+        let header_end = offsets.first().copied().unwrap_or(table_start);
+        let bytes = read_frame(&mut inner, base, header_end, HEADER_SIZE)?;
+        if bytes.len() != HEADER_SIZE {
+            return Err(IbuError::InvalidSeekTable(
+                "header frame has the wrong size",
+            ));
+        }
+        let header: Header = bytemuck::pod_read_unaligned(&bytes);
+        header.validate()?;
+
+        Ok(Self {
+            inner,
+            header,
+            offsets,
+            table_start,
+            frame_records: frame_records as usize,
+            len: num_records as usize,
+            cache: None,
+        })
+    }
+
+    /// Returns the file header.
+    pub fn header(&self) -> Header {
+        self.header
+    }
+
+    /// Returns the number of records in the stream.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the stream holds no records.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of record frames in the stream.
+    pub fn num_frames(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns the number of records per frame (the last frame may hold fewer).
+    pub fn frame_records(&self) -> usize {
+        self.frame_records
+    }
+
+    /// Reads the record at position `index`.
+    ///
+    /// Decompresses the frame holding the record unless it was the last
+    /// frame accessed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidIndex`] if `index >= len()`, or an error if
+    /// the frame cannot be read or decompressed.
+    pub fn get(&mut self, index: usize) -> crate::Result<Record> {
+        if index >= self.len {
+            return Err(IbuError::InvalidIndex {
+                idx: index,
+                max: self.len,
+            });
+        }
+        let frame_records = self.frame_records;
+        let records = self.frame(index / frame_records)?;
+        Ok(records[index % frame_records])
+    }
+
+    /// Reads the records at positions `start..end`.
+    ///
+    /// Only the frames overlapping the range are decompressed. An empty range
+    /// returns an empty vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::InvalidIndex`] if `end > len()` or `start > end`,
+    /// or an error if a frame cannot be read or decompressed.
+    pub fn get_range(&mut self, start: usize, end: usize) -> crate::Result<Vec<Record>> {
+        if end > self.len || start > end {
+            return Err(IbuError::InvalidIndex {
+                idx: end,
+                max: self.len,
+            });
+        }
+        let mut records = Vec::with_capacity(end - start);
+        let mut pos = start;
+        while pos < end {
+            let frame = pos / self.frame_records;
+            let frame_start = frame * self.frame_records;
+            let frame_records = self.frame(frame)?;
+            let upto = (end - frame_start).min(frame_records.len());
+            records.extend_from_slice(&frame_records[pos - frame_start..upto]);
+            pos = frame_start + upto;
+        }
+        Ok(records)
+    }
+
+    /// Consumes the reader and returns the underlying data source.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the records of frame `frame`, decompressing it if needed.
+    fn frame(&mut self, frame: usize) -> crate::Result<&[Record]> {
+        if self
+            .cache
+            .as_ref()
+            .is_none_or(|(cached, _)| *cached != frame)
+        {
+            let start = self.offsets[frame];
+            let end = self
+                .offsets
+                .get(frame + 1)
+                .copied()
+                .unwrap_or(self.table_start);
+            let expected = self
+                .frame_records
+                .min(self.len - frame * self.frame_records);
+            let bytes = read_frame(&mut self.inner, start, end, expected * RECORD_SIZE)?;
+            if bytes.len() != expected * RECORD_SIZE {
+                return Err(IbuError::InvalidSeekTable(
+                    "frame size does not match the seek table",
+                ));
+            }
+            self.cache = Some((frame, bytemuck::pod_collect_to_vec(&bytes)));
+        }
+        Ok(&self.cache.as_ref().unwrap().1)
+    }
+}
+
+impl SeekableZstdReader<File> {
+    /// Opens a seekable zstd file for random access.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, in addition to the
+    /// errors of [`SeekableZstdReader::new`].
+    pub fn from_path<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        Self::new(File::open(path)?)
+    }
+}
+
+/// Reads and decompresses the frame at stream positions `start..end`.
+///
+/// Decompression fails if the frame holds more than `capacity` bytes.
+fn read_frame<R: Read + Seek>(
+    inner: &mut R,
+    start: u64,
+    end: u64,
+    capacity: usize,
+) -> crate::Result<Vec<u8>> {
+    let mut compressed = vec![0u8; (end - start) as usize];
+    inner.seek(SeekFrom::Start(start))?;
+    inner.read_exact(&mut compressed)?;
+    Ok(zstd::bulk::decompress(&compressed, capacity)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Writer;
+    use std::{fs, io::Cursor};
+
+    fn write_seekable(path: &str, records: &[Record], frame_records: usize) {
+        let mut writer =
+            Writer::to_seekable_zstd(path, Header::new(16, 12), frame_records).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_seekable_zstd_roundtrip() {
+        let path = "test_seekable_zstd_roundtrip.ibu.zst";
+        let records: Vec<Record> = (0..10_007).map(|i| Record::new(i, i % 13, i * 3)).collect();
+        write_seekable(path, &records, 1000);
+
+        let mut reader = SeekableZstdReader::from_path(path).unwrap();
+        assert_eq!(reader.header(), Header::new(16, 12));
+        assert_eq!(reader.len(), 10_007);
+        assert_eq!(reader.num_frames(), 11);
+        assert_eq!(reader.frame_records(), 1000);
+        for index in [0, 999, 1000, 5555, 10_006, 3] {
+            assert_eq!(reader.get(index).unwrap(), records[index]);
+        }
+        assert_eq!(reader.get_range(0, 10_007).unwrap(), records);
+        assert_eq!(reader.get_range(990, 2010).unwrap(), &records[990..2010]);
+        assert!(reader.get_range(5, 5).unwrap().is_empty());
+        assert!(matches!(
+            reader.get(10_007),
+            Err(IbuError::InvalidIndex {
+                idx: 10_007,
+                max: 10_007
+            })
+        ));
+        assert!(reader.get_range(10, 5).is_err());
+
+        // Frames are independent of the compressed size
+        let compressed = fs::metadata(path).unwrap().len();
+        assert!(compressed < (records.len() * RECORD_SIZE) as u64);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_seekable_zstd_empty() {
+        let path = "test_seekable_zstd_empty.ibu.zst";
+        write_seekable(path, &[], 16);
+        let mut reader = SeekableZstdReader::from_path(path).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(reader.num_frames(), 0);
+        assert!(reader.get(0).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "niffler")]
+    #[test]
+    fn test_seekable_zstd_streaming() {
+        let path = "test_seekable_zstd_streaming.ibu.zst";
+        let records: Vec<Record> = (0..2500).map(|i| Record::new(i, 0, i)).collect();
+        write_seekable(path, &records, 1000);
+
+        let reader = crate::Reader::from_path(path).unwrap();
+        let read: Vec<Record> = reader.collect::<crate::Result<_>>().unwrap();
+        assert_eq!(read, records);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    /// Sink accepting a fixed number of bytes and failing afterwards.
+    struct LimitedSink(usize);
+
+    impl Write for LimitedSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.len() > self.0 {
+                return Err(io::Error::other("sink is full"));
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_seekable_zstd_zero_frame_records() {
+        let path = "test_seekable_zstd_zero_frame.ibu.zst";
+        assert!(matches!(
+            Writer::to_seekable_zstd(path, Header::new(16, 12), 0),
+            Err(IbuError::Io(e)) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+        assert!(!std::path::Path::new(path).exists());
+        assert!(SeekableZstdSink::new(Vec::new(), 0).is_err());
+    }
+
+    #[test]
+    fn test_seekable_zstd_finish_errors() {
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, i, i)).collect();
+        let write = |sink: LimitedSink| {
+            let sink = SeekableZstdSink::new(sink, 1000).unwrap();
+            let mut writer = Writer::with_finish_stream(sink, Header::new(16, 12))?;
+            writer.write_batch(&records)?;
+            writer.finish()
+        };
+
+        // Measure the complete output
+        let mut sink = SeekableZstdSink::new(Vec::new(), 1000).unwrap();
+        sink.write_all(Header::new(16, 12).as_bytes()).unwrap();
+        sink.write_all(bytemuck::cast_slice(&records)).unwrap();
+        sink.finish_stream().unwrap();
+        let size = sink.inner.len();
+        assert!(write(LimitedSink(size)).is_ok());
+
+        // Failing to write the last frame or the seek table is reported
+        assert!(write(LimitedSink(64)).is_err());
+        assert!(write(LimitedSink(size - 1)).is_err());
+
+        // Writes after the seek table fail
+        assert!(sink.write_all(Record::new(0, 0, 0).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_seekable_zstd_invalid() {
+        // Plain IBU data has no seek table
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_record(&Record::new(1, 2, 3)).unwrap();
+        writer.finish().unwrap();
+        assert!(matches!(
+            SeekableZstdReader::new(Cursor::new(writer.into_inner())),
+            Err(IbuError::InvalidSeekTable(_))
+        ));
+        assert!(matches!(
+            SeekableZstdReader::new(Cursor::new(vec![0u8; 8])),
+            Err(IbuError::InvalidSeekTable("stream too short"))
+        ));
+
+        // A corrupted frame count is caught by the consistency checks
+        let mut sink = SeekableZstdSink::new(Vec::new(), 2).unwrap();
+        sink.write_all(Header::new(16, 12).as_bytes()).unwrap();
+        for i in 0..5 {
+            sink.write_all(Record::new(i, 0, 0).as_bytes()).unwrap();
+        }
+        sink.finish_stream().unwrap();
+        let mut bytes = std::mem::take(&mut sink.inner);
+        let len = bytes.len();
+        bytes[len - TRAILER_SIZE] = 9;
+        assert!(matches!(
+            SeekableZstdReader::new(Cursor::new(bytes)),
+            Err(IbuError::InvalidSeekTable(_))
+        ));
+    }
+}
@@ -0,0 +1,775 @@
+//! Parallel sorting of IBU files.
+//!
+//! This module sorts a whole file by record order (barcode, then UMI, then
+//! index). [`sort_file`] sorts in memory using the memory-mapped reader for
+//! input, while [`external_sort`] bounds memory use by spilling sorted runs to
+//! temporary files and merging them. [`merge_sorted`] combines files that are
+//! already sorted, and [`merge_and_collapse`] also collapses their molecules.
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::{self, File, OpenOptions},
+    io::{BufReader, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use super::reader::BoxedReader;
+use crate::{
+    parallel::resolve_num_threads, Header, IbuError, MmapReader, Reader, Record, Writer,
+    RECORD_SIZE,
+};
+
+/// Sorts the records of an IBU file into a new file.
+///
+/// The input is memory-mapped and copied into a single owned buffer, which is
+/// split into one contiguous chunk per thread. Each thread sorts its chunk in
+/// place, and the sorted chunks are then k-way merged straight into a
+/// buffered [`Writer`], so peak memory is roughly one copy of the records.
+///
+/// The output header keeps the input's barcode and UMI lengths and is marked
+/// as sorted. Other header flags (such as a stored checksum or record count)
+/// are not carried over.
+///
+/// The input is unmapped before the output is created, so `input` and
+/// `output` may be the same path to sort a file in place.
+///
+/// # Arguments
+///
+/// * `input` - Path of the file to sort
+/// * `output` - Path of the sorted file (created or truncated)
+/// * `num_threads` - Number of threads to use (0 = use all available cores)
+///
+/// # Errors
+///
+/// Returns an error if the input cannot be opened or is not a valid IBU file,
+/// or if the output cannot be created or written.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{sort_file, MmapReader};
+///
+/// # fn main() -> ibu::Result<()> {
+/// sort_file("data.ibu", "sorted.ibu", 0)?;
+/// assert!(MmapReader::new("sorted.ibu")?.header().sorted());
+/// # Ok(())
+/// # }
+/// ```
+pub fn sort_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    num_threads: usize,
+) -> crate::Result<()> {
+    let num_threads = resolve_num_threads(num_threads);
+
+    let (header, mut records) = {
+        let reader = MmapReader::new(input)?;
+        (reader.header(), reader.records().to_vec())
+    };
+
+    // Sort one contiguous chunk per thread
+    let chunk_size = records.len().div_ceil(num_threads).max(1);
+    thread::scope(|scope| {
+        for chunk in records.chunks_mut(chunk_size) {
+            scope.spawn(move || chunk.sort_unstable());
+        }
+    });
+
+    let mut sorted_header = Header::new(header.bc_len, header.umi_len);
+    sorted_header.set_sorted();
+    let mut writer = Writer::from_path(output, sorted_header)?;
+    merge_sorted_chunks(&records, chunk_size, &mut writer)?;
+    writer.finish()
+}
+
+/// Sorts an IBU file of any size using bounded memory.
+///
+/// The input is streamed with a [`Reader`] in chunks of at most
+/// `max_memory_bytes`. Each chunk is sorted and written to a temporary run
+/// file through a headless [`Writer`], and the runs are then k-way merged
+/// into `output`. If the whole input fits in a single chunk, it is written
+/// straight to `output` without temporary files.
+///
+/// Run files are created next to `output` (hidden, with a `.run` suffix),
+/// since the system temporary directory is often memory-backed. They are
+/// removed once the merge completes, and also if sorting fails part way.
+///
+/// As with [`sort_file`], the output header keeps the input's barcode and
+/// UMI lengths and is marked as sorted, and `input` and `output` may be the
+/// same path.
+///
+/// # Arguments
+///
+/// * `input` - Path of the file to sort
+/// * `output` - Path of the sorted file (created or truncated)
+/// * `max_memory_bytes` - Memory budget for the record buffer. Each run holds
+///   `max_memory_bytes / 24` records (at least one). I/O buffers are not
+///   counted against it: the input reader and each run or output writer
+///   allocate about 1.1 MiB (one writer at a time), and the merge keeps an
+///   8 KiB read buffer per run.
+///
+/// # Errors
+///
+/// Returns an error if the input cannot be read or is not a valid IBU file, or
+/// if a run file or the output cannot be created or written. A run file that
+/// ends part way through a record yields [`IbuError::TruncatedRecord`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::external_sort;
+///
+/// # fn main() -> ibu::Result<()> {
+/// // Sort with a 4 GiB record buffer
+/// external_sort("huge.ibu", "huge.sorted.ibu", 4 << 30)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn external_sort<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    max_memory_bytes: usize,
+) -> crate::Result<()> {
+    let output = output.as_ref();
+    let chunk_size = (max_memory_bytes / RECORD_SIZE).max(1);
+
+    let reader = Reader::from_path(input)?;
+    let mut sorted_header = Header::new(reader.header().bc_len, reader.header().umi_len);
+    sorted_header.set_sorted();
+    let mut records = reader.peekable();
+
+    let mut runs = RunFiles::new(output);
+    let mut chunk = Vec::new();
+    loop {
+        chunk.clear();
+        for record in records.by_ref().take(chunk_size) {
+            chunk.push(record?);
+        }
+        let exhausted = records.peek().is_none();
+        chunk.sort_unstable();
+        if exhausted && runs.paths.is_empty() {
+            // Everything fit in memory: skip the temporary files
+            drop(records);
+            let mut writer = Writer::from_path(output, sorted_header)?;
+            writer.write_batch(&chunk)?;
+            return writer.finish();
+        }
+        if !chunk.is_empty() {
+            let mut writer = Writer::new_headless(runs.create()?);
+            writer.write_batch(&chunk)?;
+            writer.finish()?;
+        }
+        if exhausted {
+            break;
+        }
+    }
+    drop(records);
+    drop(chunk);
+
+    let streams = runs
+        .paths
+        .iter()
+        .map(|path| Ok(RunReader::new(BufReader::new(File::open(path)?))))
+        .collect::<crate::Result<Vec<_>>>()?;
+    let mut writer = Writer::from_path(output, sorted_header)?;
+    merge_streams(streams, |record| writer.write_record(&record))?;
+    writer.finish()
+}
+
+/// Merges sorted IBU files into a single sorted file.
+///
+/// Each input is streamed with its own [`Reader`] and the records are k-way
+/// merged into `output`, so memory use is independent of file size. Records
+/// that compare equal keep the order of `inputs`.
+///
+/// The output header takes the inputs' shared barcode and UMI lengths and
+/// the flags set on every input (such as [`collapsed`](Header::collapsed)),
+/// is marked as sorted, and stores the total number of records merged (see
+/// [`Header::record_count`]). A checksum is not carried over.
+///
+/// `output` must not be one of the inputs, since inputs are still being read
+/// while the output is written.
+///
+/// # Arguments
+///
+/// * `inputs` - Paths of the sorted files to merge
+/// * `output` - Path of the merged file (created or truncated)
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - No inputs are given
+/// - An input cannot be opened or read
+/// - An input's barcode or UMI length differs from the first input's
+///   ([`IbuError::HeaderMismatch`])
+/// - An input is not marked as sorted ([`IbuError::NotSorted`])
+/// - The output cannot be created or written
+///
+/// Inputs are validated before the output is created.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::merge_sorted;
+/// use std::path::{Path, PathBuf};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let lanes: Vec<PathBuf> = (1..=4).map(|i| PathBuf::from(format!("L00{i}.sorted.ibu"))).collect();
+/// merge_sorted(&lanes, Path::new("merged.ibu"))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn merge_sorted(inputs: &[PathBuf], output: &Path) -> crate::Result<()> {
+    let (header, readers) = open_sorted_inputs(inputs)?;
+    let mut writer = Writer::new(File::create(output)?, header)?;
+    merge_streams(readers, |record| writer.write_record(&record))?;
+    writer.finish_with_count()
+}
+
+/// Merges sorted IBU files while collapsing each `(barcode, umi)` to one record.
+///
+/// The inputs are k-way merged as in [`merge_sorted`], and every run of
+/// merged records sharing a barcode and UMI is written as a single record
+/// whose index is the summed count of the run, in the same pass. This is the
+/// reduce step after collapsing shards separately (see
+/// [`Ibu::collapse_umis`](crate::Ibu::collapse_umis)): a molecule seen in
+/// several shards ends up with its total read count.
+///
+/// A record of an input marked as [`collapsed`](Header::collapsed) counts
+/// for its index (its read count); a record of any other input counts for
+/// one, so raw and collapsed inputs may be mixed.
+///
+/// The output header takes the inputs' shared barcode and UMI lengths, is
+/// marked as sorted and collapsed, and stores the number of records written.
+/// `output` must not be one of the inputs.
+///
+/// # Arguments
+///
+/// * `inputs` - Paths of the sorted files to merge
+/// * `output` - Path of the collapsed file (created or truncated)
+///
+/// # Errors
+///
+/// Returns the errors of [`merge_sorted`], and [`IbuError::CountOverflow`]
+/// if a summed count exceeds `u64::MAX`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::merge_and_collapse;
+/// use std::path::{Path, PathBuf};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let shards: Vec<PathBuf> = (0..8).map(|i| PathBuf::from(format!("shard{i}.collapsed.ibu"))).collect();
+/// merge_and_collapse(&shards, Path::new("collapsed.ibu"))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn merge_and_collapse(inputs: &[PathBuf], output: &Path) -> crate::Result<()> {
+    let (mut header, readers) = open_sorted_inputs(inputs)?;
+    header.set_collapsed();
+    let streams = readers
+        .into_iter()
+        .map(|reader| {
+            let collapsed = reader.header().collapsed();
+            reader.map(move |record| {
+                record.map(|r| Record::new(r.barcode, r.umi, if collapsed { r.index } else { 1 }))
+            })
+        })
+        .collect();
+
+    let mut writer = Writer::new(File::create(output)?, header)?;
+    let mut molecule: Option<Record> = None;
+    merge_streams(streams, |record| {
+        match &mut molecule {
+            Some(current) if current.same_molecule(&record) => {
+                current.index =
+                    current
+                        .index
+                        .checked_add(record.index)
+                        .ok_or(IbuError::CountOverflow {
+                            barcode: current.barcode,
+                            umi: current.umi,
+                        })?;
+            }
+            _ => {
+                if let Some(done) = molecule.replace(record) {
+                    writer.write_record(&done)?;
+                }
+            }
+        }
+        Ok(())
+    })?;
+    if let Some(done) = molecule {
+        writer.write_record(&done)?;
+    }
+    writer.finish_with_count()
+}
+
+/// Opens sorted inputs sharing barcode and UMI lengths.
+///
+/// Returns a sorted header with the shared lengths and the record flags set
+/// on every input, and a reader per input.
+fn open_sorted_inputs(inputs: &[PathBuf]) -> crate::Result<(Header, Vec<Reader<BoxedReader>>)> {
+    let readers = inputs
+        .iter()
+        .map(Reader::from_path)
+        .collect::<crate::Result<Vec<_>>>()?;
+    let Some(first) = readers.first().map(Reader::header) else {
+        return Err(IbuError::Io(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "no input paths provided",
+        )));
+    };
+    for header in readers.iter().map(Reader::header) {
+        if header.bc_len != first.bc_len || header.umi_len != first.umi_len {
+            return Err(IbuError::HeaderMismatch {
+                expected: (first.bc_len, first.umi_len),
+                actual: (header.bc_len, header.umi_len),
+            });
+        }
+        if !header.sorted() {
+            return Err(IbuError::NotSorted);
+        }
+    }
+
+    let mut header = Header::new(first.bc_len, first.umi_len);
+    header.flags = readers.iter().fold(u64::MAX, |flags, reader| {
+        flags & reader.header().record_flags()
+    });
+    header.set_sorted();
+    Ok((header, readers))
+}
+
+/// Merges consecutive sorted chunks of `records` into `writer` in order.
+fn merge_sorted_chunks<W: Write>(
+    records: &[Record],
+    chunk_size: usize,
+    writer: &mut Writer<W>,
+) -> crate::Result<()> {
+    if records.len() <= chunk_size {
+        return writer.write_batch(records);
+    }
+    let streams = records
+        .chunks(chunk_size)
+        .map(|chunk| chunk.iter().copied().map(Ok))
+        .collect();
+    merge_streams(streams, |record| writer.write_record(&record))
+}
+
+/// Merges sorted record streams, passing each record to `emit` in order.
+///
+/// Ties are broken by stream position, so the merge is stable.
+fn merge_streams<I, F>(mut streams: Vec<I>, mut emit: F) -> crate::Result<()>
+where
+    I: Iterator<Item = crate::Result<Record>>,
+    F: FnMut(Record) -> crate::Result<()>,
+{
+    // Min-heap of the head record of each non-empty stream
+    let mut heads = BinaryHeap::with_capacity(streams.len());
+    for (i, stream) in streams.iter_mut().enumerate() {
+        if let Some(record) = stream.next() {
+            heads.push(Reverse((record?, i)));
+        }
+    }
+    while let Some(Reverse((record, i))) = heads.pop() {
+        emit(record)?;
+        if let Some(next) = streams[i].next() {
+            heads.push(Reverse((next?, i)));
+        }
+    }
+    Ok(())
+}
+
+/// Temporary run files of an external sort, removed on drop.
+struct RunFiles {
+    /// Output path the run names are derived from
+    output: PathBuf,
+
+    /// Runs created so far, in order
+    paths: Vec<PathBuf>,
+}
+
+impl RunFiles {
+    fn new(output: &Path) -> Self {
+        Self {
+            output: output.to_path_buf(),
+            paths: Vec::new(),
+        }
+    }
+
+    /// Creates the next uniquely named run file next to the output.
+    fn create(&mut self) -> crate::Result<File> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = self
+            .output
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let path = self.output.with_file_name(format!(
+            ".{}.{}.{}.run",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        self.paths.push(path);
+        Ok(file)
+    }
+}
+
+impl Drop for RunFiles {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Streams the records of a headless run file.
+///
+/// The run must end on a record boundary; a partial trailing record is
+/// reported as [`IbuError::TruncatedRecord`] rather than ending the run, so
+/// damaged runs cannot silently drop records from the merge.
+struct RunReader<R> {
+    /// Run file contents
+    inner: R,
+
+    /// Byte offset of the next record in the run
+    pos: usize,
+}
+
+impl<R> RunReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, pos: 0 }
+    }
+}
+
+impl<R: Read> Iterator for RunReader<R> {
+    type Item = crate::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = [0u8; RECORD_SIZE];
+        let mut filled = 0;
+        while filled < RECORD_SIZE {
+            match self.inner.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        match filled {
+            0 => None,
+            RECORD_SIZE => {
+                self.pos += RECORD_SIZE;
+                Some(Ok(bytemuck::pod_read_unaligned(&bytes)))
+            }
+            _ => Some(Err(IbuError::TruncatedRecord { pos: self.pos })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ibu;
+    use std::fs;
+
+    #[test]
+    fn test_sort_file() {
+        let input = "test_sort_file_input.ibu";
+        let output = "test_sort_file_output.ibu";
+        let records: Vec<Record> = (0..10_007u64)
+            .map(|i| Record::new((i * 7919) % 101, (i * 31) % 17, i % 5))
+            .collect();
+        let mut writer = Writer::from_path(input, Header::new(10, 8)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let mut expected = records.clone();
+        expected.sort_unstable();
+
+        // Merging across chunks matches a single-threaded sort
+        let mut merged = Writer::new_headless(Vec::new());
+        let mut chunked = records.clone();
+        for chunk in chunked.chunks_mut(1000) {
+            chunk.sort_unstable();
+        }
+        merge_sorted_chunks(&chunked, 1000, &mut merged).unwrap();
+        merged.finish().unwrap();
+        assert_eq!(
+            merged.get_ref().as_slice(),
+            bytemuck::cast_slice::<Record, u8>(&expected)
+        );
+
+        for num_threads in [1, 4] {
+            sort_file(input, output, num_threads).unwrap();
+            let reader = MmapReader::new(output).unwrap();
+            assert!(reader.header().sorted());
+            assert_eq!((reader.header().bc_len, reader.header().umi_len), (10, 8));
+            assert_eq!(reader.slice(0, reader.len()).unwrap(), expected.as_slice());
+        }
+
+        // Sorting in place
+        sort_file(input, input, 2).unwrap();
+        assert_eq!(fs::read(input).unwrap(), fs::read(output).unwrap());
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_sort_file_empty() {
+        let input = "test_sort_file_empty.ibu";
+        Writer::from_path(input, Header::new(16, 12))
+            .unwrap()
+            .finish()
+            .unwrap();
+        sort_file(input, input, 2).unwrap();
+        let reader = MmapReader::new(input).unwrap();
+        assert!(reader.header().sorted());
+        assert_eq!(reader.len(), 0);
+        fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn test_external_sort() {
+        let input = "test_external_sort_input.ibu";
+        let output = "test_external_sort_output.ibu";
+        let records: Vec<Record> = (0..5000u64)
+            .map(|i| Record::new((i * 7919) % 61, (i * 31) % 13, i % 3))
+            .collect();
+        let mut writer = Writer::from_path(input, Header::new(10, 8)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let mut expected = records.clone();
+        expected.sort_unstable();
+
+        // Many runs, an exact multiple of the input, and a single chunk
+        for max_memory in [0, 1000 * RECORD_SIZE, 7 * 1000 * RECORD_SIZE] {
+            external_sort(input, output, max_memory).unwrap();
+            let reader = MmapReader::new(output).unwrap();
+            assert!(reader.header().sorted());
+            assert_eq!((reader.header().bc_len, reader.header().umi_len), (10, 8));
+            assert_eq!(reader.slice(0, reader.len()).unwrap(), expected.as_slice());
+        }
+
+        // No run files are left behind
+        let leftovers = fs::read_dir(".")
+            .unwrap()
+            .filter(|e| {
+                let name = e.as_ref().unwrap().file_name();
+                let name = name.to_string_lossy();
+                name.starts_with(".test_external_sort_output.ibu") && name.ends_with(".run")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_external_sort_cleans_up_on_error() {
+        let input = "test_external_sort_truncated.ibu";
+        let mut writer = Writer::from_path(input, Header::new(16, 12)).unwrap();
+        writer.write_repeated(&Record::new(1, 2, 3), 100).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        let bytes = fs::read(input).unwrap();
+        fs::write(input, &bytes[..bytes.len() - 1]).unwrap();
+
+        let output = "test_external_sort_cleanup/sorted.ibu";
+        fs::create_dir_all("test_external_sort_cleanup").unwrap();
+        assert!(external_sort(input, output, 10 * RECORD_SIZE).is_err());
+        assert_eq!(
+            fs::read_dir("test_external_sort_cleanup").unwrap().count(),
+            0
+        );
+
+        fs::remove_dir("test_external_sort_cleanup").unwrap();
+        fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn test_run_reader_truncated() {
+        let records = [Record::new(1, 2, 3), Record::new(4, 5, 6)];
+        let mut bytes = bytemuck::cast_slice::<_, u8>(&records).to_vec();
+        bytes.extend_from_slice(&[0; RECORD_SIZE / 2]);
+
+        let mut run = RunReader::new(std::io::Cursor::new(bytes));
+        assert_eq!(run.next().unwrap().unwrap(), records[0]);
+        assert_eq!(run.next().unwrap().unwrap(), records[1]);
+        assert!(matches!(
+            run.next(),
+            Some(Err(IbuError::TruncatedRecord { pos })) if pos == 2 * RECORD_SIZE
+        ));
+
+        let run = RunReader::new(std::io::Cursor::new(Vec::new()));
+        assert_eq!(run.count(), 0);
+    }
+
+    #[test]
+    fn test_merge_sorted() {
+        let paths: Vec<PathBuf> = (0..3)
+            .map(|i| PathBuf::from(format!("test_merge_sorted_{i}.ibu")))
+            .collect();
+        let output = Path::new("test_merge_sorted_output.ibu");
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+
+        let mut expected = Vec::new();
+        for (i, path) in paths.iter().enumerate() {
+            let mut records: Vec<Record> = (0..500u64)
+                .map(|j| Record::new((j * 13 + i as u64) % 97, j % 7, i as u64))
+                .collect();
+            records.sort_unstable();
+            let mut writer = Writer::from_path(path, header).unwrap();
+            writer.write_batch(&records).unwrap();
+            writer.finish().unwrap();
+            expected.extend(records);
+        }
+        expected.sort();
+
+        merge_sorted(&paths, output).unwrap();
+        let reader = MmapReader::new(output).unwrap();
+        assert!(reader.header().sorted());
+        assert_eq!(reader.header().record_count(), Some(1500));
+        assert_eq!(reader.slice(0, reader.len()).unwrap(), expected.as_slice());
+        drop(reader);
+
+        // Flags shared by every input carry over, checksums do not
+        let mut collapsed = header;
+        collapsed.set_collapsed();
+        for path in &paths {
+            let mut writer =
+                Writer::new_with_checksum(File::create(path).unwrap(), collapsed).unwrap();
+            writer.write_record(&Record::new(1, 1, 1)).unwrap();
+            writer.finish_with_checksum().unwrap();
+        }
+        merge_sorted(&paths, output).unwrap();
+        let merged = MmapReader::new(output).unwrap().header();
+        assert!(merged.sorted());
+        assert!(merged.collapsed());
+        assert_eq!(merged.checksum(), None);
+
+        // A flag missing from one input is dropped
+        Writer::from_path(&paths[0], header)
+            .unwrap()
+            .finish()
+            .unwrap();
+        merge_sorted(&paths, output).unwrap();
+        assert!(!MmapReader::new(output).unwrap().header().collapsed());
+
+        // Unsorted inputs are rejected before the output is written
+        fs::remove_file(output).unwrap();
+        let mut writer = Writer::from_path(&paths[1], Header::new(16, 12)).unwrap();
+        writer.write_record(&Record::new(1, 1, 1)).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        assert!(matches!(
+            merge_sorted(&paths, output),
+            Err(IbuError::NotSorted)
+        ));
+        assert!(!output.exists());
+
+        // Mismatched lengths are rejected
+        let mut mismatched = Header::new(16, 10);
+        mismatched.set_sorted();
+        Writer::from_path(&paths[1], mismatched)
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert!(matches!(
+            merge_sorted(&paths, output),
+            Err(IbuError::HeaderMismatch { .. })
+        ));
+
+        assert!(merge_sorted(&[], output).is_err());
+
+        for path in &paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_merge_and_collapse() {
+        let paths: Vec<PathBuf> = (0..3)
+            .map(|i| PathBuf::from(format!("test_merge_and_collapse_{i}.ibu")))
+            .collect();
+        let output = Path::new("test_merge_and_collapse_output.ibu");
+        let raw: Vec<Vec<Record>> = (0..3u64)
+            .map(|i| {
+                let mut records: Vec<Record> = (0..400u64)
+                    .map(|j| Record::new((j * 7 + i) % 31, j % 3, j))
+                    .collect();
+                records.sort_unstable();
+                records
+            })
+            .collect();
+
+        // Two shards collapsed ahead of time, one left raw
+        for (i, (path, records)) in paths.iter().zip(&raw).enumerate() {
+            let mut ibu = Ibu::new(Header::new(16, 12), records.clone());
+            ibu.header.set_sorted();
+            if i < 2 {
+                ibu = ibu.collapse_umis().unwrap();
+            }
+            let mut writer = Writer::from_path(path, ibu.header).unwrap();
+            writer.write_batch(&ibu.records).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut all: Vec<Record> = raw.concat();
+        all.sort_unstable();
+        let mut expected = Ibu::new(Header::new(16, 12), all);
+        expected.header.set_sorted();
+        let expected = expected.collapse_umis().unwrap();
+
+        merge_and_collapse(&paths, output).unwrap();
+        let reader = MmapReader::new(output).unwrap();
+        assert!(reader.header().sorted());
+        assert!(reader.header().collapsed());
+        assert_eq!(
+            reader.header().record_count(),
+            Some(expected.records.len() as u64)
+        );
+        assert_eq!(
+            reader.slice(0, reader.len()).unwrap(),
+            expected.records.as_slice()
+        );
+        assert_eq!(reader.iter().map(|r| r.index).sum::<u64>(), 1200);
+        drop(reader);
+
+        // Summed counts must fit in the index
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        header.set_collapsed();
+        for path in &paths[..2] {
+            let mut writer = Writer::from_path(path, header).unwrap();
+            writer.write_record(&Record::new(1, 1, u64::MAX)).unwrap();
+            writer.finish().unwrap();
+        }
+        assert!(matches!(
+            merge_and_collapse(&paths[..2], output),
+            Err(IbuError::CountOverflow { barcode: 1, umi: 1 })
+        ));
+
+        assert!(merge_and_collapse(&[], output).is_err());
+
+        fs::remove_file(output).unwrap();
+        for path in &paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -3,9 +3,17 @@
 //! This module provides high-performance writing capabilities for IBU files,
 //! with support for buffering, batch operations, and compression.
 
-use std::{fs::File, io::Write, path::Path};
-
-use crate::{Header, Record, RECORD_SIZE};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{checksum::Checksum, Header, IbuError, Reader, Record, HEADER_SIZE, RECORD_SIZE};
 
 const DEFAULT_BUFFER_SIZE: usize = 48 * 1024 * RECORD_SIZE;
 pub type BoxedWriter = Box<dyn Write + Send>;
@@ -79,7 +87,6 @@ pub type BoxedWriter = Box<dyn Write + Send>;
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Clone)]
 pub struct Writer<W: Write> {
     /// Inner writer providing the data sink
     inner: W,
@@ -92,6 +99,145 @@ pub struct Writer<W: Write> {
 
     /// Number of records written so far
     records_written: u64,
+
+    /// Header written at construction (None for headless writers)
+    header: Option<Header>,
+
+    /// Byte offset of the header in the inner writer, once located
+    header_pos: Option<u64>,
+
+    /// Running checksum over record bytes (None unless checksumming is enabled)
+    checksum: Option<Checksum>,
+
+    /// Minimum and maximum barcode written so far
+    barcode_range: Option<(u64, u64)>,
+
+    /// Minimum and maximum index written so far
+    index_range: Option<(u64, u64)>,
+
+    /// Track barcode and index ranges (off unless requested, as it costs a
+    /// pass over every batch)
+    track_ranges: bool,
+
+    /// Last record written (None if no records have been written)
+    last_written: Option<Record>,
+
+    /// Pending rename for atomic file writes (None unless writing atomically)
+    atomic: Option<AtomicTarget>,
+
+    /// Stream to finalize once finished (None unless the inner writer needs it)
+    finalize: Option<Arc<Mutex<dyn FinishStream>>>,
+
+    /// Reject records with bits beyond the header's barcode and UMI lengths
+    validate: bool,
+}
+
+/// Output stream that must be explicitly finalized once all data is written.
+///
+/// Encoders write a trailer when their stream ends. Finishing explicitly
+/// rather than on drop lets [`Writer::finish`] report errors writing it.
+pub(crate) trait FinishStream: Write + Send {
+    /// Completes the stream and flushes it to its destination.
+    ///
+    /// Calling this again after it succeeded has no effect, and writes
+    /// after it succeeded fail.
+    fn finish_stream(&mut self) -> io::Result<()>;
+}
+
+/// Writes to a stream that the writer also holds for finalization.
+#[cfg(any(feature = "niffler", feature = "zstd"))]
+struct SharedStream<T>(Arc<Mutex<T>>);
+
+#[cfg(any(feature = "niffler", feature = "zstd"))]
+impl<T: Write> Write for SharedStream<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+/// Temporary file to be renamed over its target once writing finishes.
+///
+/// Not `Clone`: exactly one writer owns the temporary file and either
+/// commits or removes it.
+#[derive(Debug)]
+struct AtomicTarget {
+    /// Temporary file receiving the data
+    temp: PathBuf,
+
+    /// Final path of the file
+    path: PathBuf,
+}
+
+impl AtomicTarget {
+    /// Creates a uniquely named temporary file next to `path`.
+    fn create(path: &Path) -> crate::Result<(Self, File)> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = path.with_file_name(format!(
+            ".{}.{}.{}.tmp",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        let target = Self {
+            temp,
+            path: path.to_path_buf(),
+        };
+        Ok((target, file))
+    }
+
+    /// Syncs the temporary file to disk and renames it over the target.
+    fn commit(&self) -> crate::Result<()> {
+        File::open(&self.temp)?.sync_all()?;
+        fs::rename(&self.temp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Clones a writer over a cloneable sink, including its buffered records.
+///
+/// Atomic and compressed writers ([`Writer::from_path_atomic`],
+/// [`Writer::to_compressed_path`]) write to a [`BoxedWriter`], which is not
+/// `Clone`, so they cannot be cloned: two writers would otherwise share one
+/// temporary file or encoder.
+///
+/// ```rust,compile_fail
+/// use ibu::{Header, Writer};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let writer = Writer::from_path_atomic("output.ibu", Header::new(16, 12))?;
+/// let copy = writer.clone();
+/// # Ok(())
+/// # }
+/// ```
+impl<W: Write + Clone> Clone for Writer<W> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            buffer: self.buffer.clone(),
+            pos: self.pos,
+            records_written: self.records_written,
+            header: self.header,
+            header_pos: self.header_pos,
+            checksum: self.checksum,
+            barcode_range: self.barcode_range,
+            index_range: self.index_range,
+            track_ranges: self.track_ranges,
+            last_written: self.last_written,
+            // Only set on `Writer<BoxedWriter>`, which is never cloned
+            atomic: None,
+            finalize: None,
+            validate: self.validate,
+        }
+    }
 }
 
 impl<W: Write> Writer<W> {
@@ -100,6 +246,11 @@ impl<W: Write> Writer<W> {
     /// The header is written immediately to the underlying writer and validated.
     /// The writer is then ready to accept record data.
     ///
+    /// Any checksum or record count stored in `header` (for example in a
+    /// header copied from an input file) is cleared, since it describes other
+    /// records. Only [`finish_with_checksum`](Self::finish_with_checksum) and
+    /// [`finish_with_count`](Self::finish_with_count) store them.
+    ///
     /// # Arguments
     ///
     /// * `inner` - The data sink to write to
@@ -126,7 +277,9 @@ impl<W: Write> Writer<W> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn new(mut inner: W, header: Header) -> crate::Result<Self> {
+    pub fn new(mut inner: W, mut header: Header) -> crate::Result<Self> {
+        header.clear_reserved();
+
         // Write header immediately
         let header_bytes: &[u8] = bytemuck::bytes_of(&header);
         inner.write_all(header_bytes)?;
@@ -139,9 +292,127 @@ impl<W: Write> Writer<W> {
             buffer,
             pos: 0,
             records_written: 0,
+            header: Some(header),
+            header_pos: None,
+            checksum: None,
+            barcode_range: None,
+            index_range: None,
+            last_written: None,
+            atomic: None,
+            finalize: None,
+            validate: false,
+            track_ranges: false,
         })
     }
 
+    /// Creates a new writer that rejects records not fitting the header.
+    ///
+    /// Behaves like [`Writer::new`], but every record written is checked to
+    /// have no bits set above the `2 * bc_len` low bits of its barcode and the
+    /// `2 * umi_len` low bits of its UMI. Such bits are otherwise written
+    /// silently and make the record decode to the wrong sequence (and sort in
+    /// the wrong place) later. The check costs a few instructions per record,
+    /// so default writers skip it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header cannot be written. Writing a record then
+    /// fails with [`IbuError::BarcodeOverflow`] if its barcode does not fit,
+    /// or [`IbuError::ValueOutOfRange`] if its UMI does not. Batches
+    /// ([`write_batch`](Self::write_batch), [`write_soa`](Self::write_soa),
+    /// [`ingest`](Self::ingest), ...) are checked in full first, so nothing of
+    /// a failing batch is written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, IbuError, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::with_validation(Vec::new(), Header::new(4, 2))?;
+    /// writer.write_record(&Record::new(0xFF, 0xF, 0))?;
+    ///
+    /// // A 5-base barcode does not fit in 4 bases
+    /// assert!(matches!(
+    ///     writer.write_record(&Record::new(0x100, 0, 0)),
+    ///     Err(IbuError::BarcodeOverflow { value: 0x100, bc_len: 4 })
+    /// ));
+    /// assert_eq!(writer.records_written(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_validation(inner: W, header: Header) -> crate::Result<Self> {
+        let mut writer = Self::new(inner, header)?;
+        writer.validate = true;
+        Ok(writer)
+    }
+
+    /// Enables record validation on an existing writer.
+    ///
+    /// See [`with_validation`](Self::with_validation). Records written before
+    /// are not checked. Headless writers have no declared lengths, so this
+    /// has no effect on them.
+    pub fn enable_validation(&mut self) {
+        self.validate = true;
+    }
+
+    /// Checks that a record fits the header's lengths if validating.
+    ///
+    /// `pos` is the position of the record in the output.
+    fn check_record(&self, record: &Record, pos: u64) -> crate::Result<()> {
+        let Some(header) = self.header.filter(|_| self.validate) else {
+            return Ok(());
+        };
+        let masked = record.masked(&header);
+        if record.barcode != masked.barcode {
+            return Err(IbuError::BarcodeOverflow {
+                value: record.barcode,
+                bc_len: header.bc_len,
+            });
+        }
+        if record.umi != masked.umi {
+            return Err(IbuError::ValueOutOfRange {
+                pos: pos as usize,
+                field: "UMI",
+                value: record.umi,
+                len: header.umi_len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Creates a new writer with an internal buffer of `buffer_records` records.
+    ///
+    /// Behaves like [`Writer::new`], which buffers 48K records (~1.1MB). A
+    /// smaller buffer saves memory when writing many small files, while a
+    /// larger one reduces write calls on fast storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidInput`](std::io::ErrorKind::InvalidInput) I/O
+    /// error if `buffer_records` is zero (or the buffer size overflows
+    /// `usize`), or an error if writing the header to the sink fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::with_capacity(Vec::new(), Header::new(16, 12), 16)?;
+    /// writer.write_iter((0..100).map(|i| Record::new(i, 0, 0)))?;
+    /// writer.finish()?;
+    /// assert_eq!(writer.get_ref().len(), 32 + 100 * 24);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_capacity(inner: W, header: Header, buffer_records: usize) -> crate::Result<Self> {
+        let size = super::reader::buffer_size(buffer_records)?;
+        let mut writer = Self::new(inner, header)?;
+        writer.buffer = vec![0u8; size];
+        Ok(writer)
+    }
+
     /// Creates a new writer without writing a header.
     ///
     /// This creates a writer that only writes record data, without the IBU header.
@@ -175,6 +446,16 @@ impl<W: Write> Writer<W> {
             buffer,
             pos: 0,
             records_written: 0,
+            header: None,
+            header_pos: None,
+            checksum: None,
+            barcode_range: None,
+            index_range: None,
+            last_written: None,
+            atomic: None,
+            finalize: None,
+            validate: false,
+            track_ranges: false,
         }
     }
 
@@ -208,6 +489,74 @@ impl<W: Write> Writer<W> {
         self.records_written
     }
 
+    /// Enables tracking of the barcode and index ranges of written records.
+    ///
+    /// Once enabled, [`barcode_range`](Self::barcode_range) and
+    /// [`index_range`](Self::index_range) report the bounds of the records
+    /// written from then on, so producers can report them without a second
+    /// pass over the data. Tracking inspects every record of every batch, so
+    /// it is off by default to keep bulk writes free of per-record work.
+    pub fn enable_range_tracking(&mut self) {
+        self.track_ranges = true;
+    }
+
+    /// Returns the minimum and maximum barcode written so far.
+    ///
+    /// Returns `None` unless range tracking was enabled with
+    /// [`enable_range_tracking`](Self::enable_range_tracking), or if no
+    /// records have been written since.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.enable_range_tracking();
+    /// assert_eq!(writer.barcode_range(), None);
+    ///
+    /// writer.write_batch(&[Record::new(7, 0, 1), Record::new(3, 0, 9)])?;
+    /// writer.write_record(&Record::new(5, 0, 4))?;
+    /// writer.finish()?;
+    ///
+    /// assert_eq!(writer.barcode_range(), Some((3, 7)));
+    /// assert_eq!(writer.index_range(), Some((1, 9)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn barcode_range(&self) -> Option<(u64, u64)> {
+        self.barcode_range
+    }
+
+    /// Returns the minimum and maximum index written so far.
+    ///
+    /// Returns `None` unless range tracking is enabled, or if no records have
+    /// been written since. See [`barcode_range`](Self::barcode_range).
+    pub fn index_range(&self) -> Option<(u64, u64)> {
+        self.index_range
+    }
+
+    /// Returns the last record written so far.
+    ///
+    /// Returns `None` if no records have been written.
+    pub fn last_written(&self) -> Option<Record> {
+        self.last_written
+    }
+
+    /// Records a written record as the last one, extending the barcode and
+    /// index ranges if they are tracked.
+    ///
+    /// Records must be tracked in write order, so the last one tracked is the
+    /// last one written.
+    fn track_range(&mut self, record: &Record) {
+        if self.track_ranges {
+            extend_range(&mut self.barcode_range, record.barcode);
+            extend_range(&mut self.index_range, record.index);
+        }
+        self.last_written = Some(*record);
+    }
+
     /// Flushes the internal buffer to the underlying writer.
     ///
     /// This writes any buffered data to the inner writer but does not flush
@@ -258,6 +607,8 @@ impl<W: Write> Writer<W> {
     /// # }
     /// ```
     pub fn write_record(&mut self, record: &Record) -> crate::Result<()> {
+        self.check_record(record, self.records_written)?;
+
         // If buffer doesn't have space, flush it
         if self.pos + RECORD_SIZE > self.buffer.len() {
             self.flush_buffer()?;
@@ -265,9 +616,13 @@ impl<W: Write> Writer<W> {
 
         // Write record to buffer
         let record_bytes: &[u8] = bytemuck::bytes_of(record);
+        if let Some(checksum) = self.checksum.as_mut() {
+            checksum.update(record_bytes);
+        }
         self.buffer[self.pos..self.pos + RECORD_SIZE].copy_from_slice(record_bytes);
         self.pos += RECORD_SIZE;
         self.records_written += 1;
+        self.track_range(record);
 
         Ok(())
     }
@@ -318,8 +673,114 @@ impl<W: Write> Writer<W> {
         self.write_slice(records_bytes)
     }
 
+    /// Writes records from structure-of-arrays columns.
+    ///
+    /// Record `i` is `Record::new(barcodes[i], umis[i], indices[i])`. Records
+    /// are assembled directly in the internal buffer, so callers holding
+    /// barcodes, UMIs and indices in separate vectors do not need to build an
+    /// intermediate `Vec<Record>` for [`write_batch`](Self::write_batch).
+    ///
+    /// # Arguments
+    ///
+    /// * `barcodes` - Barcode of every record
+    /// * `umis` - UMI of every record
+    /// * `indices` - Index of every record
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::BufferSizeMismatch`](crate::IbuError::BufferSizeMismatch)
+    /// with the length of `barcodes` as expected if `umis` or `indices` has a
+    /// different length. Nothing is written in that case. Otherwise returns an
+    /// error if writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_soa(&[1, 2, 3], &[4, 5, 6], &[7, 8, 9])?;
+    /// assert_eq!(writer.records_written(), 3);
+    ///
+    /// assert!(writer.write_soa(&[1, 2], &[4], &[7, 8]).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_soa(
+        &mut self,
+        barcodes: &[u64],
+        umis: &[u64],
+        indices: &[u64],
+    ) -> crate::Result<()> {
+        for len in [umis.len(), indices.len()] {
+            if len != barcodes.len() {
+                return Err(IbuError::BufferSizeMismatch {
+                    expected: barcodes.len(),
+                    actual: len,
+                });
+            }
+        }
+        let mut records = barcodes
+            .iter()
+            .zip(umis)
+            .zip(indices)
+            .map(|((&barcode, &umi), &index)| Record::new(barcode, umi, index));
+        if self.validate {
+            for (i, record) in records.clone().enumerate() {
+                self.check_record(&record, self.records_written + i as u64)?;
+            }
+        }
+
+        // Pack as many records as fit into the buffer at a time
+        let mut remaining = barcodes.len();
+        while remaining > 0 {
+            if self.pos + RECORD_SIZE > self.buffer.len() {
+                self.flush_buffer()?;
+            }
+            let num_records = ((self.buffer.len() - self.pos) / RECORD_SIZE).min(remaining);
+            let start = self.pos;
+            let end = start + num_records * RECORD_SIZE;
+            for (chunk, record) in self.buffer[start..end]
+                .chunks_exact_mut(RECORD_SIZE)
+                .zip(records.by_ref())
+            {
+                chunk.copy_from_slice(bytemuck::bytes_of(&record));
+                if self.track_ranges {
+                    extend_range(&mut self.barcode_range, record.barcode);
+                    extend_range(&mut self.index_range, record.index);
+                }
+                self.last_written = Some(record);
+            }
+            if let Some(checksum) = self.checksum.as_mut() {
+                checksum.update(&self.buffer[start..end]);
+            }
+            self.pos = end;
+            self.records_written += num_records as u64;
+            remaining -= num_records;
+        }
+        Ok(())
+    }
+
     fn write_slice(&mut self, buffer: &[u8]) -> crate::Result<()> {
         let num_records = buffer.len() / RECORD_SIZE;
+        if self.validate {
+            for (i, chunk) in buffer.chunks_exact(RECORD_SIZE).enumerate() {
+                let record = bytemuck::pod_read_unaligned(chunk);
+                self.check_record(&record, self.records_written + i as u64)?;
+            }
+        }
+        if let Some(checksum) = self.checksum.as_mut() {
+            checksum.update(buffer);
+        }
+        // Ingested buffers are not necessarily aligned for `Record`
+        if self.track_ranges {
+            for chunk in buffer.chunks_exact(RECORD_SIZE) {
+                self.track_range(&bytemuck::pod_read_unaligned(chunk));
+            }
+        } else if let Some(last) = buffer.rchunks_exact(RECORD_SIZE).next() {
+            self.last_written = Some(bytemuck::pod_read_unaligned(last));
+        }
 
         // If the batch is larger than our buffer, write directly
         if buffer.len() > self.buffer.len() {
@@ -350,6 +811,119 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Writes a batch of records, checking that sorted order is maintained.
+    ///
+    /// Each record must compare greater than or equal to the one before it,
+    /// including the last record written by any earlier call (see
+    /// [`last_written`](Self::last_written)). This lets pipelines that mark
+    /// their output as sorted catch ordering bugs at write time, rather than
+    /// when a later binary search silently misses records.
+    ///
+    /// The check happens before anything is written, so an out-of-order batch
+    /// leaves the writer unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IbuError::OutOfOrder`](crate::IbuError::OutOfOrder) with the
+    /// first offending pair, or an error if writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, IbuError, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut header = Header::new(16, 12);
+    /// header.set_sorted();
+    /// let mut writer = Writer::new(Vec::new(), header)?;
+    ///
+    /// writer.write_batch_checked(&[Record::new(1, 0, 0), Record::new(2, 0, 0)])?;
+    /// assert!(matches!(
+    ///     writer.write_batch_checked(&[Record::new(1, 5, 0)]),
+    ///     Err(IbuError::OutOfOrder { .. })
+    /// ));
+    /// assert_eq!(writer.records_written(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_batch_checked(&mut self, records: &[Record]) -> crate::Result<()> {
+        let mut prev = self.last_written;
+        for &curr in records {
+            if let Some(prev) = prev.filter(|&prev| curr < prev) {
+                return Err(crate::IbuError::OutOfOrder { prev, curr });
+            }
+            prev = Some(curr);
+        }
+        self.write_batch(records)
+    }
+
+    /// Writes the same record `n` times.
+    ///
+    /// The record is copied into the internal buffer by repeatedly doubling
+    /// the filled region, rather than writing it one record at a time. This
+    /// makes it cheap to generate large synthetic datasets or to expand
+    /// run-length-encoded data.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The record to write
+    /// * `n` - Number of copies to write
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if flushing the internal buffer fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_repeated(&Record::new(1, 2, 3), 1000)?;
+    /// writer.finish()?;
+    ///
+    /// assert_eq!(writer.records_written(), 1000);
+    /// assert_eq!(writer.get_ref().len(), 32 + 1000 * 24);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_repeated(&mut self, record: &Record, n: usize) -> crate::Result<()> {
+        if n > 0 {
+            self.check_record(record, self.records_written)?;
+        }
+        let record_bytes: &[u8] = bytemuck::bytes_of(record);
+        let mut remaining = n;
+        while remaining > 0 {
+            if self.pos + RECORD_SIZE > self.buffer.len() {
+                self.flush_buffer()?;
+            }
+            let count = remaining.min((self.buffer.len() - self.pos) / RECORD_SIZE);
+            let start = self.pos;
+            let end = start + count * RECORD_SIZE;
+
+            // Seed one copy, then double the filled region until it is full
+            self.buffer[start..start + RECORD_SIZE].copy_from_slice(record_bytes);
+            let mut filled = start + RECORD_SIZE;
+            while filled < end {
+                let len = (filled - start).min(end - filled);
+                self.buffer.copy_within(start..start + len, filled);
+                filled += len;
+            }
+
+            if let Some(checksum) = self.checksum.as_mut() {
+                checksum.update(&self.buffer[start..end]);
+            }
+            self.pos = end;
+            remaining -= count;
+        }
+        if n > 0 {
+            self.track_range(record);
+        }
+        self.records_written += n as u64;
+        Ok(())
+    }
+
     /// Writes records from an iterator.
     ///
     /// This method consumes an iterator of records and writes them efficiently.
@@ -395,6 +969,101 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Streams the records of a reader that match a predicate into this writer.
+    ///
+    /// Records are read and written one at a time, so arbitrarily large inputs
+    /// are filtered in constant memory. The reader's header is not written;
+    /// this writer keeps its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Record stream to filter
+    /// * `pred` - Returns `true` for records that should be written
+    ///
+    /// # Returns
+    ///
+    /// The number of records written by this call.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from reading (e.g. a truncated stream) or
+    /// writing. Records matched before the error have already been written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// writer.write_iter((0..10).map(|i| Record::new(i, 0, 0)))?;
+    /// writer.finish()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+    /// let mut filtered = Writer::new(Vec::new(), reader.header())?;
+    /// let written = filtered.write_filtered(reader, |r| r.barcode % 2 == 0)?;
+    /// assert_eq!(written, 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_filtered<R, F>(&mut self, reader: Reader<R>, pred: F) -> crate::Result<u64>
+    where
+        R: Read,
+        F: FnMut(&Record) -> bool,
+    {
+        let mut written = 0;
+        for record in reader.filter_records(pred) {
+            self.write_record(&record?)?;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Streams the first `n` records of a reader into this writer.
+    ///
+    /// Reads through [`Reader::take_records`], so no more of the source than
+    /// needed is read or decompressed. With [`finish`](Self::finish) this
+    /// makes a `head -n` style preview a one-liner. The reader's header is not
+    /// written; this writer keeps its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Record stream to copy from
+    /// * `n` - Maximum number of records to copy
+    ///
+    /// # Returns
+    ///
+    /// The number of records written by this call, less than `n` only if the
+    /// stream ended first.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from reading (e.g. a truncated stream) or
+    /// writing. Records before the error have already been written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{Reader, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let reader = Reader::from_path("data.ibu.gz")?;
+    /// let mut head = Writer::from_path("head.ibu", reader.header())?;
+    /// head.write_head(reader, 1000)?;
+    /// head.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_head<R: Read>(&mut self, reader: Reader<R>, n: usize) -> crate::Result<u64> {
+        let mut written = 0;
+        for record in reader.take_records(n) {
+            self.write_record(&record?)?;
+            written += 1;
+        }
+        Ok(written)
+    }
+
     /// Finishes writing and flushes all buffers.
     ///
     /// This method must be called to ensure all data is written to the underlying
@@ -429,6 +1098,15 @@ impl<W: Write> Writer<W> {
     pub fn finish(&mut self) -> crate::Result<()> {
         self.flush_buffer()?;
         self.inner.flush()?;
+        if let Some(stream) = self.finalize.take() {
+            stream.lock().unwrap().finish_stream()?;
+        }
+        if let Some(target) = self.atomic.take() {
+            if let Err(e) = target.commit() {
+                fs::remove_file(&target.temp).ok();
+                return Err(e);
+            }
+        }
         Ok(())
     }
 
@@ -439,7 +1117,9 @@ impl<W: Write> Writer<W> {
     /// source writer is cleared after ingestion.
     ///
     /// This is useful for parallel writing patterns where multiple threads
-    /// write to separate buffers that are later merged.
+    /// write to separate buffers that are later merged; see
+    /// [`ParallelWriter`](crate::ParallelWriter) for a coordinator that does
+    /// this in a deterministic order.
     ///
     /// # Arguments
     ///
@@ -481,11 +1161,40 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
-    /// Consumes the writer and returns the underlying writer.
+    /// Returns a reference to the underlying writer.
+    ///
+    /// Data still held in the internal buffer has not yet reached the
+    /// underlying writer; call [`finish`](Self::finish) first to inspect the
+    /// complete output.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let writer = Writer::new(Vec::new(), Header::new(16, 12))?;
+    /// assert_eq!(writer.get_ref().len(), 32); // Header only
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Consumes the writer and returns the underlying writer.
     ///
     /// This method allows access to the underlying writer after the IBU writer
     /// is no longer needed. The writer should be finished before calling this.
     ///
+    /// An atomic writer ([`Writer::from_path_atomic`]) that was not finished
+    /// is discarded as if dropped: its temporary file is removed and the
+    /// target path is left untouched.
+    ///
+    /// A compressed writer ([`Writer::to_compressed_path`]) that was not
+    /// finished is finished first, so its encoder writes its trailer. Errors
+    /// doing so are ignored; call [`finish`](Writer::finish) to observe them.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -506,19 +1215,261 @@ impl<W: Write> Writer<W> {
     /// ```
     pub fn into_inner(self) -> W {
         use std::mem::ManuallyDrop;
-        let manual = ManuallyDrop::new(self);
+        let mut manual = ManuallyDrop::new(self);
+        if let Some(target) = manual.atomic.take() {
+            fs::remove_file(&target.temp).ok();
+        }
+        if manual.finalize.is_some() {
+            manual.finish().ok();
+            drop(manual.finalize.take());
+        }
+        drop(std::mem::take(&mut manual.buffer));
+        // SAFETY: `manual` is never dropped or used again, so `inner` is moved
+        // out exactly once. Its other fields own no resources: the buffer was
+        // taken above and the atomic target and stream were cleared.
         unsafe { std::ptr::read(&manual.inner) }
     }
 }
 
+impl<W: Write + Seek> Writer<W> {
+    /// Creates a new writer that records a whole-file checksum.
+    ///
+    /// Behaves like [`Writer::new`], but additionally hashes every record
+    /// written. The checksum is only stored once
+    /// [`finish_with_checksum`](Self::finish_with_checksum) rewrites the header,
+    /// so a writer that is dropped or finished normally still produces a valid
+    /// (unchecksummed) file.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The seekable data sink to write to
+    /// * `header` - The IBU file header
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the current stream position cannot be determined
+    /// or the header cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let header = Header::new(16, 12);
+    /// let mut writer = Writer::new_with_checksum(Cursor::new(Vec::new()), header)?;
+    /// writer.write_record(&Record::new(1, 2, 3))?;
+    /// writer.finish_with_checksum()?;
+    ///
+    /// let cursor = Cursor::new(writer.into_inner().into_inner());
+    /// let reader = Reader::new(cursor)?;
+    /// assert!(reader.header().checksum().is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_checksum(mut inner: W, header: Header) -> crate::Result<Self> {
+        let header_pos = inner.stream_position()?;
+        let mut writer = Self::new(inner, header)?;
+        writer.header_pos = Some(header_pos);
+        writer.checksum = Some(Checksum::default());
+        Ok(writer)
+    }
+
+    /// Enables the whole-file checksum on an existing writer.
+    ///
+    /// Equivalent to creating the writer with
+    /// [`new_with_checksum`](Self::new_with_checksum): every record written
+    /// from now on is hashed, and
+    /// [`finish_with_checksum`](Self::finish_with_checksum) stores the result
+    /// in the header. Enabling it again has no effect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidInput`](std::io::ErrorKind::InvalidInput) I/O error
+    /// if records were already written, since they would be missing from the
+    /// checksum, or an error if the stream position cannot be determined.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Cursor::new(Vec::new()), Header::new(16, 12))?;
+    /// writer.enable_checksum()?;
+    /// writer.write_record(&Record::new(1, 2, 3))?;
+    /// writer.finish_with_checksum()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner().into_inner()))?;
+    /// assert!(reader.verify_checksum()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enable_checksum(&mut self) -> crate::Result<()> {
+        if self.checksum.is_some() {
+            return Ok(());
+        }
+        if self.records_written > 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "checksum must be enabled before writing records",
+            )
+            .into());
+        }
+        if self.header.is_some() {
+            self.header_pos()?;
+        }
+        self.checksum = Some(Checksum::default());
+        Ok(())
+    }
+
+    /// Finishes writing and stores the record checksum in the header.
+    ///
+    /// Flushes all buffered records, then seeks back to the header and rewrites
+    /// it with the checksum flag set and the checksum stored in the reserved
+    /// bytes. The stream is left positioned at the end of the written data.
+    /// To store the record count as well, use
+    /// [`finish_with_count`](Self::finish_with_count) instead.
+    ///
+    /// If the writer was not created with
+    /// [`new_with_checksum`](Self::new_with_checksum), this is equivalent to
+    /// [`finish`](Self::finish).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if flushing, seeking, or rewriting the header fails.
+    pub fn finish_with_checksum(&mut self) -> crate::Result<()> {
+        self.finish()?;
+        if let (Some(mut header), Some(checksum)) = (self.header, self.checksum) {
+            header.set_checksum(checksum.value());
+            let header_pos = self.header_pos()?;
+            self.rewrite_header(header, header_pos)?;
+        }
+        Ok(())
+    }
+
+    /// Finishes writing and stores the record count in the header.
+    ///
+    /// Flushes all buffered records, then seeks back to the header and rewrites
+    /// it with [`records_written`](Self::records_written) stored in the
+    /// reserved bytes, so readers can learn the total via
+    /// [`Header::record_count`] without scanning. The stream is left
+    /// positioned at the end of the written data.
+    ///
+    /// If checksumming was enabled with
+    /// [`new_with_checksum`](Self::new_with_checksum) or
+    /// [`enable_checksum`](Self::enable_checksum), the checksum is stored
+    /// alongside the count, as by
+    /// [`finish_with_checksum`](Self::finish_with_checksum). Counts beyond
+    /// `u32::MAX` are recorded as unknown (see [`Header::set_record_count`]).
+    /// Headless writers have no header to update, and this is equivalent to
+    /// [`finish`](Self::finish). Writers over non-seekable sinks cannot
+    /// backfill the header and leave the count unknown.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if flushing, seeking, or rewriting the header fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{Header, Reader, Record, Writer};
+    /// use std::io::Cursor;
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::new(Cursor::new(Vec::new()), Header::new(16, 12))?;
+    /// writer.write_batch(&[Record::new(1, 2, 3), Record::new(4, 5, 6)])?;
+    /// writer.finish_with_count()?;
+    ///
+    /// let reader = Reader::new(Cursor::new(writer.into_inner().into_inner()))?;
+    /// assert_eq!(reader.header().record_count(), Some(2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn finish_with_count(&mut self) -> crate::Result<()> {
+        self.finish()?;
+        if let Some(mut header) = self.header {
+            header.set_record_count(self.records_written);
+            if let Some(checksum) = self.checksum {
+                header.set_checksum(checksum.value());
+            }
+            let header_pos = self.header_pos()?;
+            self.rewrite_header(header, header_pos)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the byte offset of the header in the inner writer.
+    ///
+    /// [`new_with_checksum`](Self::new_with_checksum) records it at
+    /// construction. Otherwise it is located the first time it is needed, as
+    /// the header directly precedes every byte written since, and the same
+    /// offset is reused by every later header rewrite.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream position cannot be determined, or is
+    /// before the end of the bytes written (the stream was seeked externally).
+    fn header_pos(&mut self) -> crate::Result<u64> {
+        if let Some(header_pos) = self.header_pos {
+            return Ok(header_pos);
+        }
+        let written =
+            HEADER_SIZE as u64 + self.records_written * RECORD_SIZE as u64 - self.pos as u64;
+        let header_pos = self
+            .inner
+            .stream_position()?
+            .checked_sub(written)
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "stream position precedes the written data",
+                )
+            })?;
+        self.header_pos = Some(header_pos);
+        Ok(header_pos)
+    }
+
+    /// Overwrites the header at `header_pos` and returns to the end of the data.
+    fn rewrite_header(&mut self, header: Header, header_pos: u64) -> crate::Result<()> {
+        let end = self.inner.stream_position()?;
+        self.inner.seek(SeekFrom::Start(header_pos))?;
+        self.inner.write_all(header.as_bytes())?;
+        self.inner.seek(SeekFrom::Start(end))?;
+        self.inner.flush()?;
+        self.header = Some(header);
+        Ok(())
+    }
+}
+
+/// Widens `range` to include `value`.
+fn extend_range(range: &mut Option<(u64, u64)>, value: u64) {
+    *range = match *range {
+        Some((min, max)) => Some((min.min(value), max.max(value))),
+        None => Some((value, value)),
+    };
+}
+
 /// Automatically finishes the writer when dropped.
 ///
 /// This ensures that any buffered data is written even if `finish()` is not
 /// called explicitly. However, errors during the automatic flush are ignored,
 /// so explicit calls to `finish()` are recommended for proper error handling.
+///
+/// Atomic writers are the exception: dropping one before `finish()` discards
+/// its temporary file and leaves the target path untouched.
 impl<W: Write> Drop for Writer<W> {
     fn drop(&mut self) {
-        self.finish().ok();
+        match self.atomic.take() {
+            Some(target) => {
+                fs::remove_file(&target.temp).ok();
+            }
+            None => {
+                self.finish().ok();
+            }
+        }
     }
 }
 
@@ -557,6 +1508,344 @@ impl Writer<BoxedWriter> {
         let file = File::create(path)?;
         Self::new(Box::new(file), header)
     }
+
+    /// Opens an existing file to append records to it.
+    ///
+    /// Reads and validates the file's header and checks that its barcode and
+    /// UMI lengths match `expected`. Since appended records may break the sort
+    /// order and change the record total, the on-disk header is rewritten in
+    /// place with the sorted flag and any stored checksum or record count
+    /// cleared. The returned writer is headless and positioned at the end of
+    /// the file.
+    ///
+    /// [`records_written`](Self::records_written) counts only the appended
+    /// records.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of an existing uncompressed IBU file
+    /// * `expected` - Header the appended records conform to
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file cannot be opened, read, or written
+    /// - The existing header is invalid
+    /// - The lengths differ from `expected` ([`IbuError::HeaderMismatch`])
+    /// - The file ends with a partial record ([`IbuError::InvalidMapSize`])
+    ///
+    /// [`IbuError::HeaderMismatch`]: crate::IbuError::HeaderMismatch
+    /// [`IbuError::InvalidMapSize`]: crate::IbuError::InvalidMapSize
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{Header, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let header = Header::new(16, 12);
+    /// let mut writer = Writer::append_to_path("output.ibu", &header)?;
+    /// writer.write_record(&Record::new(1, 2, 3))?;
+    /// writer.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn append_to_path<P: AsRef<Path>>(path: P, expected: &Header) -> crate::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut header_bytes = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header_bytes)?;
+        let header: Header = bytemuck::pod_read_unaligned(&header_bytes);
+        header.validate()?;
+        if (header.bc_len, header.umi_len) != (expected.bc_len, expected.umi_len) {
+            return Err(crate::IbuError::HeaderMismatch {
+                expected: (expected.bc_len, expected.umi_len),
+                actual: (header.bc_len, header.umi_len),
+            });
+        }
+        let end = file.seek(SeekFrom::End(0))?;
+        if !(end - HEADER_SIZE as u64).is_multiple_of(RECORD_SIZE as u64) {
+            return Err(crate::IbuError::InvalidMapSize);
+        }
+
+        let mut updated = header;
+        updated.clear_sorted();
+        updated.clear_reserved();
+        if updated != header {
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(updated.as_bytes())?;
+            file.seek(SeekFrom::End(0))?;
+        }
+        Ok(Self::new_headless(Box::new(file)))
+    }
+
+    /// Creates a writer that compresses its output to a file.
+    ///
+    /// The file is wrapped in a niffler encoder for `format` before the header
+    /// is written, producing files that [`Reader::from_path`](crate::Reader::from_path)
+    /// decompresses transparently. [`finish`](Writer::finish) flushes the
+    /// records and then finishes the encoder, so the compressed stream
+    /// (including any trailer) is complete once `finish` returns, and errors
+    /// writing the trailer are returned from it. Records written after a
+    /// successful `finish` fail when they are flushed, since the stream has
+    /// already ended.
+    ///
+    /// Compressed files cannot be memory-mapped; read them with
+    /// [`Reader`](crate::Reader).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path where the file should be created
+    /// * `header` - The IBU file header
+    /// * `format` - Compression format (`Format::No` writes raw bytes)
+    /// * `level` - Compression level
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file cannot be created
+    /// - The encoder cannot be created
+    /// - The header is invalid or cannot be written
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{Header, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let header = Header::new(16, 12);
+    /// let mut writer = Writer::to_compressed_path(
+    ///     "output.ibu.zst",
+    ///     header,
+    ///     niffler::Format::Zstd,
+    ///     niffler::Level::Three,
+    /// )?;
+    ///
+    /// writer.write_record(&Record::new(1, 2, 3))?;
+    /// writer.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "niffler")]
+    pub fn to_compressed_path<P: AsRef<Path>>(
+        path: P,
+        header: Header,
+        format: niffler::Format,
+        level: niffler::Level,
+    ) -> crate::Result<Self> {
+        let file = io::BufWriter::new(File::create(path)?);
+        let encoder = super::compress::CompressedWriter::new(file, format, level)?;
+        Self::with_finish_stream(encoder, header)
+    }
+
+    /// Creates a writer whose compression is chosen by the file extension.
+    ///
+    /// Paths ending in `.gz` are gzip-compressed and paths ending in `.zst`
+    /// are zstd-compressed, both at the default level; any other path is
+    /// written uncompressed as with [`from_path`](Writer::from_path). See
+    /// [`to_compressed_path`](Writer::to_compressed_path) for how compressed
+    /// output is finalized.
+    ///
+    /// # Errors
+    ///
+    /// See [`to_compressed_path`](Writer::to_compressed_path).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{Header, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// // Gzip-compressed because of the extension
+    /// let mut writer = Writer::from_path_auto("output.ibu.gz", Header::new(16, 12))?;
+    /// writer.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "niffler")]
+    pub fn from_path_auto<P: AsRef<Path>>(path: P, header: Header) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let (format, level) = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => (niffler::Format::Gzip, niffler::Level::Six),
+            Some("zst") => (niffler::Format::Zstd, niffler::Level::Three),
+            _ => return Self::from_path(path, header),
+        };
+        Self::to_compressed_path(path, header, format, level)
+    }
+
+    /// Creates a writer producing a seekable zstd-compressed file.
+    ///
+    /// The header and then every `frame_records` records are compressed as
+    /// independent zstd frames, and the offsets of the frames are recorded in
+    /// a seek table at the end of the file, so that a
+    /// [`SeekableZstdReader`](crate::SeekableZstdReader) can decompress only
+    /// the frame holding a requested record. Smaller frames make random
+    /// access cheaper at the cost of compression ratio. The file remains a
+    /// valid zstd stream, readable by [`Reader::from_path`].
+    ///
+    /// The last frame and the seek table are written by
+    /// [`finish`](Self::finish), which returns any error writing them; the
+    /// file is not usable for random access until `finish` has succeeded.
+    /// Records written after that fail when they are flushed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path where the file should be created
+    /// * `header` - The IBU file header
+    /// * `frame_records` - Number of records per compressed frame
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `frame_records` is zero (an [`InvalidInput`](std::io::ErrorKind::InvalidInput)
+    ///   I/O error, raised before the file is created)
+    /// - The file cannot be created
+    /// - The header is invalid or cannot be written
+    ///
+    /// [`finish`](Self::finish) additionally fails if the last frame or the
+    /// seek table cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{Header, Record, SeekableZstdReader, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let mut writer = Writer::to_seekable_zstd("archive.ibu.zst", Header::new(16, 12), 65_536)?;
+    /// writer.write_iter((0..1_000_000).map(|i| Record::new(i, 0, i)))?;
+    /// writer.finish()?;
+    ///
+    /// let mut reader = SeekableZstdReader::from_path("archive.ibu.zst")?;
+    /// assert_eq!(reader.get(123_456)?, Record::new(123_456, 0, 123_456));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "zstd")]
+    pub fn to_seekable_zstd<P: AsRef<Path>>(
+        path: P,
+        header: Header,
+        frame_records: usize,
+    ) -> crate::Result<Self> {
+        // Reject an empty frame size before creating the file
+        if frame_records == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "frames must hold at least one record",
+            )
+            .into());
+        }
+        let file = std::io::BufWriter::new(File::create(path)?);
+        let sink = super::seekable_zstd::SeekableZstdSink::new(file, frame_records)?;
+        Self::with_finish_stream(sink, header)
+    }
+
+    /// Creates a writer over `stream` that finishes it in [`finish`](Writer::finish).
+    #[cfg(any(feature = "niffler", feature = "zstd"))]
+    pub(crate) fn with_finish_stream<T: FinishStream + 'static>(
+        stream: T,
+        header: Header,
+    ) -> crate::Result<Self> {
+        let stream = Arc::new(Mutex::new(stream));
+        let mut writer = Self::new(Box::new(SharedStream(Arc::clone(&stream))), header)?;
+        writer.finalize = Some(stream);
+        Ok(writer)
+    }
+
+    /// Creates a writer that atomically replaces the file at `path`.
+    ///
+    /// Data is written to a temporary file in the same directory as `path`.
+    /// [`finish`](Writer::finish) syncs it to disk and renames it over `path`,
+    /// so other processes observe either no file (or the previous one) or the
+    /// complete new file, never a partial one. The rename is atomic as long as
+    /// the directory is on a single filesystem, which holds for a temporary
+    /// file next to its target.
+    ///
+    /// If the writer is dropped without calling `finish`, or finishing fails,
+    /// the temporary file is removed and `path` is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path where the file should appear
+    /// * `header` - The IBU file header
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temporary file cannot be created or the header
+    /// cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{Header, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let header = Header::new(16, 12);
+    /// let mut writer = Writer::from_path_atomic("output.ibu", header)?;
+    ///
+    /// writer.write_record(&Record::new(1, 2, 3))?;
+    /// // "output.ibu" only appears once finished
+    /// writer.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_path_atomic<P: AsRef<Path>>(path: P, header: Header) -> crate::Result<Self> {
+        let (target, file) = AtomicTarget::create(path.as_ref())?;
+        match Self::new(Box::new(file), header) {
+            Ok(mut writer) => {
+                writer.atomic = Some(target);
+                Ok(writer)
+            }
+            Err(e) => {
+                fs::remove_file(&target.temp).ok();
+                Err(e)
+            }
+        }
+    }
+    /// Creates a writer that writes to a file with direct I/O.
+    ///
+    /// On Linux the file is opened with `O_DIRECT`, so writes bypass the page
+    /// cache. `O_DIRECT` requires aligned buffers, offsets, and lengths; this is
+    /// handled internally by staging writes through a 4 KiB-aligned buffer and
+    /// zero-padding the final partial block, which is truncated away when the
+    /// writer is flushed. There are no alignment constraints on the caller.
+    ///
+    /// If the filesystem does not support `O_DIRECT`, or on platforms other
+    /// than Linux, this falls back to regular buffered I/O.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path where the file should be created
+    /// * `header` - The IBU file header
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or the header cannot be
+    /// written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ibu::{Header, Record, Writer};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let header = Header::new(16, 12);
+    /// let mut writer = Writer::from_path_direct("output.ibu", header)?;
+    ///
+    /// writer.write_record(&Record::new(1, 2, 3))?;
+    /// writer.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_path_direct<P: AsRef<Path>>(path: P, header: Header) -> crate::Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let file = super::direct::DirectWriter::create(path)?;
+            Self::new(Box::new(file), header)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Self::from_path(path, header)
+        }
+    }
     /// Creates a writer that writes to standard output.
     ///
     /// Useful for pipeline processing where IBU data should be written to stdout
@@ -864,6 +2153,59 @@ mod tests {
         assert_eq!(read_records[5], Record::new(12, 24, 36));
     }
 
+    #[test]
+    fn test_writer_checksum() {
+        let header = Header::new(16, 12);
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, i * 2, i * 3)).collect();
+
+        let mut writer = Writer::new_with_checksum(Cursor::new(Vec::new()), header).unwrap();
+        writer.write_record(&records[0]).unwrap();
+        writer.write_batch(&records[1..]).unwrap();
+        writer.finish_with_checksum().unwrap();
+
+        let buffer = writer.into_inner().into_inner();
+        assert_eq!(buffer.len(), 32 + 1000 * 24);
+
+        let written = Header::from_bytes(&buffer[..32]);
+        let expected = Checksum::of(bytemuck::cast_slice(&records));
+        assert_eq!(written.checksum(), Some(expected));
+    }
+
+    #[test]
+    fn test_writer_checksum_unfinished_is_plain() {
+        let header = Header::new(16, 12);
+        let mut writer = Writer::new_with_checksum(Cursor::new(Vec::new()), header).unwrap();
+        writer.write_record(&Record::new(1, 2, 3)).unwrap();
+        writer.finish().unwrap();
+
+        let buffer = writer.into_inner().into_inner();
+        let written = Header::from_bytes(&buffer[..32]);
+        assert_eq!(written.checksum(), None);
+    }
+
+    #[test]
+    fn test_writer_from_path_direct() {
+        use std::fs;
+
+        let temp_path = "test_writer_direct.ibu";
+        let records: Vec<Record> = (0..100_001).map(|i| Record::new(i, i * 2, i * 3)).collect();
+
+        let mut writer = Writer::from_path_direct(temp_path, Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        assert_eq!(
+            fs::metadata(temp_path).unwrap().len(),
+            (32 + records.len() * 24) as u64
+        );
+        let reader = Reader::from_path(temp_path).unwrap();
+        let read_records: Vec<Record> = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(records, read_records);
+
+        fs::remove_file(temp_path).unwrap();
+    }
+
     #[test]
     fn test_writer_clone() {
         let header = Header::new(16, 12);
@@ -874,4 +2216,548 @@ mod tests {
         let writer_clone = writer.clone();
         assert_eq!(writer.records_written(), writer_clone.records_written());
     }
+
+    #[test]
+    fn test_writer_ranges() {
+        // Ranges are only tracked on request
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&[Record::new(1, 0, 2)]).unwrap();
+        assert_eq!(writer.barcode_range(), None);
+        assert_eq!(writer.last_written(), Some(Record::new(1, 0, 2)));
+
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.enable_range_tracking();
+        assert_eq!(writer.barcode_range(), None);
+        assert_eq!(writer.index_range(), None);
+
+        writer.write_record(&Record::new(50, 0, 5)).unwrap();
+        assert_eq!(writer.barcode_range(), Some((50, 50)));
+
+        // Large batches bypass the buffer but are still tracked
+        let batch: Vec<Record> = (0..100_000).map(|i| Record::new(i + 10, 0, i)).collect();
+        writer.write_batch(&batch).unwrap();
+
+        let mut aux = Writer::new_headless(Vec::new());
+        aux.write_record(&Record::new(u64::MAX, 0, 3)).unwrap();
+        writer.ingest(&mut aux).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(writer.barcode_range(), Some((10, u64::MAX)));
+        assert_eq!(writer.index_range(), Some((0, 99_999)));
+    }
+
+    #[test]
+    fn test_writer_enable_checksum() {
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, i, i)).collect();
+
+        // Header at a non-zero stream position
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_all(&[0xFF; 5]).unwrap();
+        let mut writer = Writer::new(cursor, Header::new(16, 12)).unwrap();
+        writer.enable_checksum().unwrap();
+        writer.enable_checksum().unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_checksum().unwrap();
+
+        let mut cursor = writer.into_inner();
+        cursor.set_position(5);
+        let reader = Reader::new(cursor).unwrap();
+        assert!(reader.header().checksum().is_some());
+        assert!(reader.verify_checksum().unwrap());
+
+        // Both finishers rewrite the header at the same offset
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_all(&[0xFF; 5]).unwrap();
+        let mut writer = Writer::new(cursor, Header::new(16, 12)).unwrap();
+        writer.enable_checksum().unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_checksum().unwrap();
+        writer.finish_with_count().unwrap();
+
+        let mut cursor = writer.into_inner();
+        assert_eq!(&cursor.get_ref()[..5], &[0xFF; 5]);
+        cursor.set_position(5);
+        let reader = Reader::new(cursor).unwrap();
+        assert_eq!(reader.header().record_count(), Some(1000));
+        assert!(reader.verify_checksum().unwrap());
+
+        let mut writer = Writer::new(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        writer.write_record(&records[0]).unwrap();
+        assert!(writer.enable_checksum().is_err());
+    }
+
+    #[test]
+    fn test_writer_append_to_path() {
+        let temp_path = "test_writer_append.ibu";
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        let mut writer = Writer::new(File::create(temp_path).unwrap(), header).unwrap();
+        writer
+            .write_batch(&[Record::new(1, 0, 0), Record::new(2, 0, 0)])
+            .unwrap();
+        writer.finish_with_count().unwrap();
+        drop(writer);
+
+        let mut writer = Writer::append_to_path(temp_path, &Header::new(16, 12)).unwrap();
+        writer.write_record(&Record::new(0, 0, 0)).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(writer.records_written(), 1);
+        drop(writer);
+
+        let reader = Reader::from_path(temp_path).unwrap();
+        let appended = reader.header();
+        assert!(!appended.sorted());
+        assert_eq!(appended.record_count(), None);
+        let records: Vec<Record> = reader.collect::<crate::Result<_>>().unwrap();
+        assert_eq!(
+            records,
+            vec![
+                Record::new(1, 0, 0),
+                Record::new(2, 0, 0),
+                Record::new(0, 0, 0)
+            ]
+        );
+
+        assert!(matches!(
+            Writer::append_to_path(temp_path, &Header::new(16, 10)),
+            Err(crate::IbuError::HeaderMismatch { .. })
+        ));
+        std::fs::remove_file(temp_path).unwrap();
+        assert!(Writer::append_to_path(temp_path, &Header::new(16, 12)).is_err());
+    }
+
+    #[test]
+    fn test_writer_with_capacity() {
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, i, i)).collect();
+        let mut writer = Writer::with_capacity(Vec::new(), Header::new(16, 12), 1).unwrap();
+        assert_eq!(writer.buffer.len(), RECORD_SIZE);
+        for record in &records {
+            writer.write_record(record).unwrap();
+        }
+        writer.write_batch(&records[..3]).unwrap();
+        writer.write_repeated(&records[0], 5).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(writer.records_written(), 108);
+
+        let reader = Reader::new(Cursor::new(writer.into_inner())).unwrap();
+        let read: Vec<Record> = reader.collect::<crate::Result<_>>().unwrap();
+        assert_eq!(read[..100], records[..]);
+        assert_eq!(read.len(), 108);
+    }
+
+    #[test]
+    fn test_writer_with_zero_capacity() {
+        for records in [0, usize::MAX] {
+            let result = Writer::with_capacity(Vec::new(), Header::new(16, 12), records);
+            assert!(matches!(
+                result,
+                Err(IbuError::Io(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+            ));
+        }
+    }
+
+    #[test]
+    fn test_writer_write_repeated() {
+        let record = Record::new(0xABC, 0xDEF, 42);
+
+        // Spans several buffer flushes and a partially filled buffer
+        let n = DEFAULT_BUFFER_SIZE / RECORD_SIZE * 2 + 123;
+        let mut writer =
+            Writer::new_with_checksum(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        writer.enable_range_tracking();
+        writer.write_record(&Record::new(1, 1, 1)).unwrap();
+        writer.write_repeated(&record, n).unwrap();
+        writer.write_repeated(&record, 0).unwrap();
+        writer.finish_with_checksum().unwrap();
+        assert_eq!(writer.records_written(), n as u64 + 1);
+        assert_eq!(writer.barcode_range(), Some((1, 0xABC)));
+
+        let buffer = writer.into_inner().into_inner();
+        let reader = Reader::new(Cursor::new(buffer))
+            .unwrap()
+            .with_checksum_verification();
+        let read: Vec<Record> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(read.len(), n + 1);
+        assert!(read[1..].iter().all(|r| *r == record));
+    }
+
+    #[test]
+    fn test_writer_from_path_atomic() {
+        let temp_path = "test_writer_atomic.ibu";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, i, i)).collect();
+        let leftovers = || {
+            fs::read_dir(".")
+                .unwrap()
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    e.file_name()
+                        .to_string_lossy()
+                        .starts_with(".test_writer_atomic")
+                })
+                .count()
+        };
+
+        // Nothing appears at the target until finished
+        let mut writer = Writer::from_path_atomic(temp_path, Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        assert!(!Path::new(temp_path).exists());
+        writer.finish().unwrap();
+        assert!(Path::new(temp_path).exists());
+        assert_eq!(leftovers(), 0);
+        drop(writer);
+
+        let reader = Reader::from_path(temp_path).unwrap();
+        let read: Vec<Record> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, records);
+
+        // Dropping an unfinished writer keeps the previous file
+        let mut writer = Writer::from_path_atomic(temp_path, Header::new(16, 12)).unwrap();
+        writer.write_record(&Record::new(9, 9, 9)).unwrap();
+        drop(writer);
+        assert_eq!(leftovers(), 0);
+        let reader = Reader::from_path(temp_path).unwrap();
+        assert_eq!(reader.count(), 1000);
+
+        // Unwrapping an unfinished writer also discards its temporary file
+        let mut writer = Writer::from_path_atomic(temp_path, Header::new(16, 12)).unwrap();
+        writer.write_record(&Record::new(9, 9, 9)).unwrap();
+        drop(writer.into_inner());
+        assert_eq!(leftovers(), 0);
+        let reader = Reader::from_path(temp_path).unwrap();
+        assert_eq!(reader.count(), 1000);
+
+        fs::remove_file(temp_path).unwrap();
+    }
+
+    #[test]
+    fn test_writer_finish_with_count() {
+        let records: Vec<Record> = (0..100_000).map(|i| Record::new(i, i, i)).collect();
+
+        // The header is found at its original offset in the stream
+        let mut sink = Cursor::new(Vec::new());
+        sink.write_all(b"prefix").unwrap();
+        let mut writer = Writer::new(sink, Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_count().unwrap();
+
+        let buffer = writer.into_inner().into_inner();
+        let reader = Reader::new(Cursor::new(buffer[6..].to_vec())).unwrap();
+        assert_eq!(reader.header().record_count(), Some(100_000));
+        assert_eq!(reader.count(), 100_000);
+
+        // The count does not displace the checksum
+        let mut writer =
+            Writer::new_with_checksum(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_count().unwrap();
+        let reader = Reader::new(Cursor::new(writer.into_inner().into_inner())).unwrap();
+        assert_eq!(reader.header().record_count(), Some(100_000));
+        assert!(reader.header().checksum().is_some());
+        assert!(reader.verify_checksum().unwrap());
+
+        // Headless writers are unaffected
+        let mut writer = Writer::new_headless(Cursor::new(Vec::new()));
+        writer.write_record(&Record::new(1, 2, 3)).unwrap();
+        writer.finish_with_count().unwrap();
+        assert_eq!(writer.into_inner().into_inner().len(), RECORD_SIZE);
+    }
+
+    #[test]
+    fn test_writer_clears_copied_reserved() {
+        let input = "test_writer_copied_reserved_input.ibu";
+        let output = "test_writer_copied_reserved_output.ibu";
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, i, i)).collect();
+        let mut writer =
+            Writer::new_with_checksum(File::create(input).unwrap(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish_with_count().unwrap();
+        drop(writer);
+
+        // A subset written under the input's header stores neither its count
+        // nor its checksum
+        let header = crate::MmapReader::new(input).unwrap().header();
+        assert_eq!(header.record_count(), Some(100));
+        assert!(header.checksum().is_some());
+        let mut writer = Writer::from_path(output, header).unwrap();
+        writer.write_batch(&records[..30]).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let reader = crate::MmapReader::new(output).unwrap();
+        assert_eq!(reader.len(), 30);
+        assert_eq!(reader.header().record_count(), None);
+        assert_eq!(reader.header().checksum(), None);
+        assert!(crate::MmapReader::new_verified(output).is_ok());
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "niffler")]
+    fn test_writer_compressed_path() {
+        let records: Vec<Record> = (0..10_000).map(|i| Record::new(i % 7, i, 0)).collect();
+        for (path, magic) in [
+            ("test_writer_compressed.ibu.gz", &[0x1f, 0x8b][..]),
+            (
+                "test_writer_compressed.ibu.zst",
+                &[0x28, 0xb5, 0x2f, 0xfd][..],
+            ),
+            (
+                "test_writer_compressed_raw.ibu",
+                &crate::MAGIC.to_le_bytes()[..],
+            ),
+        ] {
+            let mut writer = Writer::from_path_auto(path, Header::new(16, 12)).unwrap();
+            writer.write_batch(&records).unwrap();
+            writer.finish().unwrap();
+
+            // The stream is complete before the writer is dropped
+            let bytes = std::fs::read(path).unwrap();
+            assert!(bytes.starts_with(magic));
+            let reader = Reader::from_path(path).unwrap();
+            let read: Vec<Record> = reader.collect::<crate::Result<_>>().unwrap();
+            assert_eq!(read, records);
+
+            drop(writer);
+            assert_eq!(std::fs::read(path).unwrap(), bytes);
+
+            // Unwrapping an unfinished compressed writer still completes the stream
+            if !path.ends_with("_raw.ibu") {
+                let mut writer = Writer::from_path_auto(path, Header::new(16, 12)).unwrap();
+                writer.write_batch(&records).unwrap();
+                drop(writer.into_inner());
+                assert_eq!(std::fs::read(path).unwrap(), bytes);
+            }
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    /// Sink accepting a fixed number of bytes and failing afterwards.
+    #[cfg(feature = "niffler")]
+    struct LimitedSink(usize);
+
+    #[cfg(feature = "niffler")]
+    impl Write for LimitedSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.len() > self.0 {
+                return Err(io::Error::other("sink is full"));
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "niffler")]
+    fn test_writer_compressed_finish_errors() {
+        use super::super::compress::CompressedWriter;
+
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(i, i, 0)).collect();
+        for format in [
+            niffler::Format::Gzip,
+            niffler::Format::Bzip,
+            niffler::Format::Lzma,
+            niffler::Format::Zstd,
+        ] {
+            // The trailer does not fit, which finish must report
+            let encoder =
+                CompressedWriter::new(LimitedSink(16), format, niffler::Level::One).unwrap();
+            let mut writer = Writer::with_finish_stream(encoder, Header::new(16, 12)).unwrap();
+            writer.write_batch(&records).unwrap();
+            assert!(writer.finish().is_err(), "{format:?}");
+        }
+
+        // Writes after the stream was finished fail rather than vanish
+        let encoder =
+            CompressedWriter::new(Vec::new(), niffler::Format::Gzip, niffler::Level::One).unwrap();
+        let mut writer = Writer::with_finish_stream(encoder, Header::new(16, 12)).unwrap();
+        writer.finish().unwrap();
+        writer.finish().unwrap();
+        writer.write_record(&Record::new(1, 2, 3)).unwrap();
+        assert!(writer.finish().is_err());
+    }
+
+    #[test]
+    fn test_write_head() {
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, 0, i)).collect();
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        let bytes = writer.into_inner();
+
+        let reader = Reader::new(Cursor::new(bytes.clone())).unwrap();
+        let mut head = Writer::new(Vec::new(), reader.header()).unwrap();
+        assert_eq!(head.write_head(reader, 10).unwrap(), 10);
+        head.finish().unwrap();
+        let read: Vec<Record> = Reader::new(Cursor::new(head.into_inner()))
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert_eq!(read, &records[..10]);
+
+        let reader = Reader::new(Cursor::new(bytes.clone())).unwrap();
+        let mut head = Writer::new_headless(Vec::new());
+        assert_eq!(head.write_head(reader, 1000).unwrap(), 100);
+
+        let reader = Reader::new(Cursor::new(&bytes[..bytes.len() - 1])).unwrap();
+        assert!(matches!(
+            Writer::new_headless(Vec::new()).write_head(reader, 1000),
+            Err(IbuError::TruncatedRecord { .. })
+        ));
+    }
+
+    #[test]
+    fn test_with_validation() {
+        let header = Header::new(4, 2);
+        let mut writer = Writer::with_validation(Vec::new(), header).unwrap();
+        writer.write_record(&Record::new(0xFF, 0xF, 0)).unwrap();
+        assert!(matches!(
+            writer.write_record(&Record::new(0, 0x10, 0)),
+            Err(IbuError::ValueOutOfRange {
+                pos: 1,
+                field: "UMI",
+                value: 0x10,
+                len: 2,
+            })
+        ));
+
+        // Batches fail as a whole
+        let batch = [Record::new(1, 0, 0), Record::new(0x100, 0, 0)];
+        assert!(matches!(
+            writer.write_batch(&batch),
+            Err(IbuError::BarcodeOverflow {
+                value: 0x100,
+                bc_len: 4,
+            })
+        ));
+        assert!(writer.write_soa(&[1, 0x100], &[0, 0], &[0, 0]).is_err());
+        assert!(writer.write_repeated(&batch[1], 10).is_err());
+        let mut other = Writer::new_headless(Vec::new());
+        other.write_batch(&batch).unwrap();
+        assert!(writer.ingest(&mut other).is_err());
+        assert_eq!(writer.records_written(), 1);
+
+        // Full-width lengths accept every value
+        let mut writer = Writer::with_validation(Vec::new(), Header::new(32, 32)).unwrap();
+        writer
+            .write_record(&Record::new(u64::MAX, u64::MAX, 0))
+            .unwrap();
+
+        // Default writers stay permissive
+        let mut writer = Writer::new(Vec::new(), header).unwrap();
+        writer.write_batch(&batch).unwrap();
+        writer.enable_validation();
+        assert!(writer.write_batch(&batch).is_err());
+        assert_eq!(writer.records_written(), 2);
+    }
+
+    #[test]
+    fn test_write_soa() {
+        let barcodes: Vec<u64> = (0..100_000).map(|i| i % 97).collect();
+        let umis: Vec<u64> = (0..100_000).map(|i| i * 3).collect();
+        let indices: Vec<u64> = (0..100_000).rev().collect();
+        let mut writer = Writer::with_capacity(Vec::new(), Header::new(16, 12), 1000).unwrap();
+        writer.enable_range_tracking();
+        writer.write_soa(&barcodes, &umis, &indices).unwrap();
+        writer.write_soa(&[], &[], &[]).unwrap();
+        assert_eq!(writer.records_written(), 100_000);
+        assert_eq!(writer.barcode_range(), Some((0, 96)));
+        assert_eq!(
+            writer.last_written(),
+            Some(Record::new(99_999 % 97, 299_997, 0))
+        );
+
+        // Mismatched columns write nothing
+        assert!(matches!(
+            writer.write_soa(&[1, 2], &[1, 2], &[1]),
+            Err(IbuError::BufferSizeMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        assert!(writer.write_soa(&[1], &[1, 2], &[1]).is_err());
+        assert_eq!(writer.records_written(), 100_000);
+
+        writer.finish().unwrap();
+        let records: Vec<Record> = Reader::new(Cursor::new(writer.into_inner()))
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        let expected: Vec<Record> = (0..100_000)
+            .map(|i| Record::new(barcodes[i], umis[i], indices[i]))
+            .collect();
+        assert_eq!(records, expected);
+
+        // Packing across buffer flushes checksums the same bytes as a batch
+        let mut soa =
+            Writer::new_with_checksum(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        soa.write_soa(&barcodes, &umis, &indices).unwrap();
+        soa.finish().unwrap();
+        let mut batch =
+            Writer::new_with_checksum(Cursor::new(Vec::new()), Header::new(16, 12)).unwrap();
+        batch.write_batch(&expected).unwrap();
+        batch.finish().unwrap();
+        assert_eq!(
+            soa.into_inner().into_inner(),
+            batch.into_inner().into_inner()
+        );
+    }
+
+    #[test]
+    fn test_write_batch_checked() {
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        assert_eq!(writer.last_written(), None);
+
+        writer
+            .write_batch_checked(&[Record::new(1, 0, 0), Record::new(1, 0, 0)])
+            .unwrap();
+        writer.write_record(&Record::new(2, 0, 0)).unwrap();
+        writer.write_batch_checked(&[]).unwrap();
+        assert_eq!(writer.last_written(), Some(Record::new(2, 0, 0)));
+
+        // Violations within a batch and across batches
+        assert!(matches!(
+            writer.write_batch_checked(&[Record::new(3, 0, 0), Record::new(2, 9, 9)]),
+            Err(crate::IbuError::OutOfOrder { prev, curr })
+                if prev == Record::new(3, 0, 0) && curr == Record::new(2, 9, 9)
+        ));
+        assert!(matches!(
+            writer.write_batch_checked(&[Record::new(1, 9, 9)]),
+            Err(crate::IbuError::OutOfOrder { prev, .. }) if prev == Record::new(2, 0, 0)
+        ));
+        assert_eq!(writer.records_written(), 3);
+
+        writer.write_repeated(&Record::new(4, 0, 0), 3).unwrap();
+        assert_eq!(writer.last_written(), Some(Record::new(4, 0, 0)));
+    }
+
+    #[test]
+    fn test_write_filtered() {
+        let mut writer = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        writer
+            .write_iter((0..100).map(|i| Record::new(i % 10, i, 0)))
+            .unwrap();
+        writer.finish().unwrap();
+        let bytes = writer.into_inner();
+
+        let reader = Reader::new(Cursor::new(bytes.clone())).unwrap();
+        let mut filtered = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        assert_eq!(
+            filtered.write_filtered(reader, |r| r.barcode < 3).unwrap(),
+            30
+        );
+        assert_eq!(filtered.records_written(), 30);
+
+        // Read errors propagate
+        let mut truncated = bytes;
+        truncated.pop();
+        let reader = Reader::new(Cursor::new(truncated)).unwrap();
+        let mut filtered = Writer::new(Vec::new(), Header::new(16, 12)).unwrap();
+        assert!(filtered.write_filtered(reader, |_| true).is_err());
+    }
 }
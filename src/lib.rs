@@ -16,9 +16,9 @@
 //! - Version: Format version (currently 2)
 //! - Barcode length: Length in bases (max 32)
 //! - UMI length: Length in bases (max 32)
-//! - Flags: Bit flags (bit 0 = sorted)
-//! - Record count: Total records (0 if unknown)
-//! - Reserved: 8 bytes for future use
+//! - Flags: Bit flags (bit 0 = sorted, bit 1 = checksum, bit 2 = collapsed)
+//! - Reserved: 8 bytes holding the CRC-32 record checksum (bytes 0-3, if bit 1
+//!   is set) and the total record count (bytes 4-7, 0 if unknown)
 //!
 //! ### Record (24 bytes)
 //! - Barcode: `u64` with 2-bit encoding
@@ -170,12 +170,101 @@
 //! # }
 //! ```
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod bus;
+#[cfg(feature = "std")]
+mod checksum;
 mod constructs;
+#[cfg(feature = "std")]
+mod correct;
+#[cfg(feature = "std")]
+mod distinct;
+#[cfg(feature = "std")]
+mod entropy;
 mod error;
+#[cfg(feature = "fastq")]
+mod fastq;
+#[cfg(feature = "std")]
+mod histogram;
+#[cfg(feature = "std")]
+mod index_counts;
+#[cfg(feature = "std")]
 mod io;
+#[cfg(feature = "serde")]
+mod manifest;
+#[cfg(feature = "std")]
+mod matrix;
+#[cfg(feature = "hdf5")]
+mod molecule_info;
+mod packed;
+#[cfg(feature = "std")]
 mod parallel;
+#[cfg(feature = "std")]
+mod slice;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
+mod summary;
+#[cfg(feature = "std")]
+mod text;
+#[cfg(feature = "std")]
+mod transform;
 
-pub use constructs::{Header, Record, HEADER_SIZE, MAGIC, RECORD_SIZE, VERSION};
+#[cfg(feature = "std")]
+pub use bus::{from_bus, to_bus};
+#[cfg(feature = "std")]
+pub use constructs::Ibu;
+pub use constructs::{
+    Alphabet, Header, HeaderBuilder, Record, RecordBuilder, RecordDisplay, HEADER_SIZE, MAGIC,
+    RECORD_SIZE, V1_HEADER_SIZE, VERSION,
+};
+#[cfg(feature = "std")]
+pub use correct::{BarcodeCorrector, CorrectionStats};
+#[cfg(feature = "std")]
+pub use distinct::{ApproxBarcodeCounter, DistinctBarcodeCounter};
+#[cfg(feature = "std")]
+pub use entropy::{BarcodeEntropy, EntropyProcessor};
 pub use error::{IbuError, IntoIbuError, Result};
-pub use io::{load_to_vec, MmapReader, Reader, Writer};
-pub use parallel::{ParallelProcessor, ParallelReader};
+#[cfg(feature = "fastq")]
+pub use fastq::{ingest_fastq, FastqIngest, FastqStats};
+#[cfg(feature = "std")]
+pub use histogram::IndexHistogram;
+#[cfg(feature = "std")]
+pub use index_counts::{count_by_index_sorted, IndexCounter, SortedIndexCounts};
+#[cfg(feature = "zstd")]
+pub use io::SeekableZstdReader;
+#[cfg(feature = "std")]
+pub use io::{
+    chain_readers, external_sort, load_bounds, load_to_soa, load_to_vec, merge_and_collapse,
+    merge_sorted, ring_buffer, sort_file, write_random_parallel, write_random_parallel_with,
+    BarcodeBoundaries, BarcodeDepth, ChainedReader, ChannelParallelReader, Decoded, DecodedRecord,
+    DecodedStrict, FilterRecords, FirstSeen, MmapReader, MmapWriter, NullSink, OverflowPolicy,
+    ParallelWriter, ReadOptions, Reader, RingSink, RingSource, SeekReader, TakeRecords, Writer,
+};
+#[cfg(feature = "serde")]
+pub use manifest::{read_manifest, Manifest, ManifestEntry, MANIFEST_FILE_NAME};
+#[cfg(feature = "std")]
+pub use matrix::{to_sparse_matrix, SparseMatrix};
+#[cfg(feature = "hdf5")]
+pub use molecule_info::{
+    from_molecule_info, from_molecule_info_with, MoleculeIndex, MOLECULE_INFO_UMI_LEN,
+};
+pub use packed::hamming_neighbors;
+#[cfg(feature = "std")]
+pub use parallel::{ParallelOptions, ParallelProcessor, ParallelReader, BATCH_SIZE};
+#[cfg(feature = "std")]
+pub use slice::{coalesce_index_runs, verify_index_sorted_within_barcode};
+#[cfg(feature = "niffler")]
+pub use stats::sample_compression_ratio;
+#[cfg(feature = "std")]
+pub use stats::{depth_quantiles, distinct_bc_umi_pairs, index_quantiles};
+#[cfg(feature = "std")]
+pub use summary::{summarize, summarize_with, IbuStats, SummaryProcessor};
+#[cfg(feature = "std")]
+pub use text::{from_tsv, to_tsv, to_tsv_raw};
+#[cfg(feature = "std")]
+pub use transform::{reencode_alphabet, remap_indices, split_at, MissingPolicy};
@@ -0,0 +1,144 @@
+//! JSON manifests describing multi-file outputs.
+//!
+//! Operations that produce several output files (shards, rollover files,
+//! spills) can record what they wrote in a [`Manifest`]. Saved next to the
+//! outputs, the manifest lets downstream steps discover every file, its
+//! record count, and its header without scanning a directory.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::Header;
+
+/// Default file name for a manifest saved alongside its outputs.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// A single output file listed in a [`Manifest`].
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestEntry {
+    /// Path of the output file, as given when it was recorded
+    pub path: PathBuf,
+
+    /// Number of records written to the file
+    pub records: u64,
+
+    /// Header written to the file
+    pub header: Header,
+}
+
+/// List of output files produced by a multi-file operation.
+///
+/// Entries are kept in the order they were recorded.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{read_manifest, Header, Manifest};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let header = Header::new(16, 12);
+///
+/// let mut manifest = Manifest::new();
+/// manifest.push("shard_0.ibu", 1000, header);
+/// manifest.push("shard_1.ibu", 750, header);
+/// manifest.write("manifest.json")?;
+///
+/// let manifest = read_manifest("manifest.json")?;
+/// assert_eq!(manifest.total_records(), 1750);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    /// Output files in the order they were written
+    pub outputs: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Creates an empty manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an output file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the output file
+    /// * `records` - Number of records written to it
+    /// * `header` - Header written to it
+    pub fn push<P: AsRef<Path>>(&mut self, path: P, records: u64, header: Header) {
+        self.outputs.push(ManifestEntry {
+            path: path.as_ref().to_path_buf(),
+            records,
+            header,
+        });
+    }
+
+    /// Returns the total number of records across all outputs.
+    pub fn total_records(&self) -> u64 {
+        self.outputs.iter().map(|entry| entry.records).sum()
+    }
+
+    /// Writes the manifest as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> crate::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a manifest written by [`Manifest::write`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened, or
+/// [`IbuError::Json`](crate::IbuError::Json) if it is not a valid manifest.
+pub fn read_manifest<P: AsRef<Path>>(path: P) -> crate::Result<Manifest> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IbuError;
+    use std::fs;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let temp_file = "test_manifest_roundtrip.json";
+        let mut sorted = Header::new(16, 12);
+        sorted.set_sorted();
+
+        let mut manifest = Manifest::new();
+        manifest.push("a.ibu", 10, sorted);
+        manifest.push("dir/b.ibu", 0, Header::new(20, 10));
+        manifest.write(temp_file).unwrap();
+
+        let read = read_manifest(temp_file).unwrap();
+        assert_eq!(read, manifest);
+        assert_eq!(read.total_records(), 10);
+        assert_eq!(read.outputs[1].path, PathBuf::from("dir/b.ibu"));
+        assert!(read.outputs[0].header.sorted());
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_read_manifest_invalid() {
+        let temp_file = "test_manifest_invalid.json";
+        fs::write(temp_file, "{\"outputs\": 3}").unwrap();
+        assert!(matches!(read_manifest(temp_file), Err(IbuError::Json(_))));
+        fs::remove_file(temp_file).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+//! Sparse barcode × feature count matrices.
+//!
+//! This module pivots records into the count matrix at the end of most
+//! single-cell pipelines: one row per distinct barcode, one column per
+//! distinct index value (feature), and one entry per `(barcode, index)` pair
+//! holding its number of records. Matrices can be exported in Matrix Market
+//! format for use with scanpy, Seurat, and similar tools.
+
+use std::io::Write;
+
+use crate::Record;
+
+/// Sparse count matrix in coordinate (triplet) form.
+///
+/// Rows correspond to [`barcodes`](Self::barcodes) and columns to
+/// [`features`](Self::features), both in ascending order. Entries are stored
+/// row-major as `(row, col, count)` with zero-based positions and no
+/// duplicates; absent entries are zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{to_sparse_matrix, Record};
+///
+/// let records = vec![
+///     Record::new(7, 0, 2),
+///     Record::new(7, 1, 2),
+///     Record::new(3, 0, 5),
+/// ];
+/// let matrix = to_sparse_matrix(&records);
+/// assert_eq!(matrix.barcodes, vec![3, 7]);
+/// assert_eq!(matrix.features, vec![2, 5]);
+/// assert_eq!(matrix.entries, vec![(0, 1, 1), (1, 0, 2)]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SparseMatrix {
+    /// Barcode of each row, ascending
+    pub barcodes: Vec<u64>,
+
+    /// Index value (feature) of each column, ascending
+    pub features: Vec<u64>,
+
+    /// Non-zero entries as `(row, col, count)`, sorted by row then column
+    pub entries: Vec<(usize, usize, u64)>,
+}
+
+impl SparseMatrix {
+    /// Returns the number of rows (distinct barcodes).
+    pub fn num_rows(&self) -> usize {
+        self.barcodes.len()
+    }
+
+    /// Returns the number of columns (distinct features).
+    pub fn num_cols(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Returns the number of non-zero entries.
+    pub fn nnz(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Writes the matrix in Matrix Market coordinate format.
+    ///
+    /// The output is an `integer general` coordinate matrix with one-based
+    /// positions, with barcodes as rows and features as columns. This is the
+    /// cells-as-rows orientation loaded by `scanpy.read_mtx`; 10x-style
+    /// `matrix.mtx` files are the transpose (features × barcodes).
+    ///
+    /// Row and column labels are not part of the format; write
+    /// [`barcodes`](Self::barcodes) and [`features`](Self::features)
+    /// separately if they are needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ibu::{to_sparse_matrix, Record};
+    ///
+    /// # fn main() -> ibu::Result<()> {
+    /// let matrix = to_sparse_matrix(&[Record::new(1, 0, 4), Record::new(1, 1, 4)]);
+    /// let mut output = Vec::new();
+    /// matrix.write_matrix_market(&mut output)?;
+    ///
+    /// let text = String::from_utf8(output).unwrap();
+    /// assert!(text.ends_with("1 1 1\n1 1 2\n"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_matrix_market<W: Write>(&self, mut writer: W) -> crate::Result<()> {
+        writeln!(writer, "%%MatrixMarket matrix coordinate integer general")?;
+        writeln!(
+            writer,
+            "{} {} {}",
+            self.num_rows(),
+            self.num_cols(),
+            self.nnz()
+        )?;
+        for &(row, col, count) in &self.entries {
+            writeln!(writer, "{} {} {}", row + 1, col + 1, count)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Pivots records into a sparse barcode × index count matrix.
+///
+/// Each entry counts the records sharing a `(barcode, index)` pair; UMIs are
+/// ignored, so deduplicate molecules first if counts should be per molecule.
+/// The records need not be sorted.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{to_sparse_matrix, Record};
+///
+/// let records: Vec<Record> = (0..6).map(|i| Record::new(i % 2, i, i % 3)).collect();
+/// let matrix = to_sparse_matrix(&records);
+/// assert_eq!(matrix.num_rows(), 2);
+/// assert_eq!(matrix.num_cols(), 3);
+/// assert_eq!(matrix.entries.iter().map(|e| e.2).sum::<u64>(), 6);
+/// ```
+pub fn to_sparse_matrix(records: &[Record]) -> SparseMatrix {
+    let mut pairs: Vec<(u64, u64)> = records.iter().map(|r| (r.barcode, r.index)).collect();
+    pairs.sort_unstable();
+
+    let mut features: Vec<u64> = pairs.iter().map(|&(_, index)| index).collect();
+    features.sort_unstable();
+    features.dedup();
+
+    let mut matrix = SparseMatrix {
+        features,
+        ..Default::default()
+    };
+    for run in pairs.chunk_by(|a, b| a == b) {
+        let (barcode, index) = run[0];
+        if matrix.barcodes.last() != Some(&barcode) {
+            matrix.barcodes.push(barcode);
+        }
+        let row = matrix.barcodes.len() - 1;
+        let col = matrix.features.binary_search(&index).unwrap();
+        matrix.entries.push((row, col, run.len() as u64));
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_sparse_matrix() {
+        let records = vec![
+            Record::new(9, 0, 100),
+            Record::new(2, 0, 100),
+            Record::new(9, 1, 100),
+            Record::new(9, 2, 5),
+            Record::new(2, 1, 7),
+            Record::new(9, 3, 100),
+        ];
+        let matrix = to_sparse_matrix(&records);
+        assert_eq!(matrix.barcodes, vec![2, 9]);
+        assert_eq!(matrix.features, vec![5, 7, 100]);
+        assert_eq!(
+            matrix.entries,
+            vec![(0, 1, 1), (0, 2, 1), (1, 0, 1), (1, 2, 3)]
+        );
+
+        let empty = to_sparse_matrix(&[]);
+        assert_eq!((empty.num_rows(), empty.num_cols(), empty.nnz()), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_write_matrix_market() {
+        let records = vec![
+            Record::new(2, 0, 100),
+            Record::new(9, 1, 100),
+            Record::new(9, 2, 5),
+        ];
+        let mut output = Vec::new();
+        to_sparse_matrix(&records)
+            .write_matrix_market(&mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "%%MatrixMarket matrix coordinate integer general\n\
+             2 2 3\n\
+             1 2 1\n\
+             2 1 1\n\
+             2 2 1\n"
+        );
+    }
+}
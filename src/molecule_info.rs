@@ -0,0 +1,178 @@
+//! Import of 10x Genomics `molecule_info.h5` files.
+//!
+//! CellRanger writes one entry per molecule (a unique barcode, UMI and
+//! feature triple) to `molecule_info.h5`. This module maps the per-molecule
+//! datasets of that file onto IBU records:
+//!
+//! | HDF5 dataset   | Type            | IBU field                              |
+//! |----------------|-----------------|----------------------------------------|
+//! | `barcodes`     | fixed ASCII     | barcode alphabet, addressed by index   |
+//! | `barcode_idx`  | unsigned int    | `barcode` (via `barcodes`, 2-bit)      |
+//! | `umi`          | unsigned int    | `umi` (already 2-bit encoded)          |
+//! | `feature_idx`  | unsigned int    | `index` with [`MoleculeIndex::Feature`] |
+//! | `count`        | unsigned int    | `index` with [`MoleculeIndex::Count`]   |
+//!
+//! Barcode strings are packed with the canonical `ACGT` alphabet, first base
+//! in the most significant occupied bits, which is the convention CellRanger
+//! uses for the `umi` dataset. Other datasets (`gem_group`, `library_idx`,
+//! `features/*`, `barcode_info/*` and the metrics) are not imported; feature
+//! names can be recovered from `features/id` using the imported index.
+//!
+//! Requires the `hdf5` feature and a system HDF5 library.
+
+use std::path::Path;
+
+use hdf5::types::FixedAscii;
+
+use crate::{packed::pack, Alphabet, Header, IbuError, Record};
+
+/// Default UMI length of 10x Genomics 3' v3 chemistry.
+pub const MOLECULE_INFO_UMI_LEN: u32 = 12;
+
+/// Dataset of `molecule_info.h5` stored in each record's index.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MoleculeIndex {
+    /// Store the feature (gene) index from `feature_idx`
+    #[default]
+    Feature,
+
+    /// Store the read count of the molecule from `count`
+    Count,
+}
+
+/// Reads the molecules of a 10x Genomics `molecule_info.h5` file.
+///
+/// This is [`from_molecule_info_with`] using [`MoleculeIndex::Feature`] and
+/// a UMI length of [`MOLECULE_INFO_UMI_LEN`] bases.
+///
+/// # Arguments
+///
+/// * `path` - Path of the `molecule_info.h5` file
+///
+/// # Errors
+///
+/// See [`from_molecule_info_with`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::from_molecule_info;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let (header, records) = from_molecule_info("molecule_info.h5")?;
+/// println!("{} molecules with {}bp barcodes", records.len(), header.bc_len);
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_molecule_info<P: AsRef<Path>>(path: P) -> crate::Result<(Header, Vec<Record>)> {
+    from_molecule_info_with(path, MoleculeIndex::Feature, MOLECULE_INFO_UMI_LEN)
+}
+
+/// Reads the molecules of a 10x Genomics `molecule_info.h5` file.
+///
+/// Returns one record per molecule, in file order, together with a header
+/// whose barcode length is the width of the `barcodes` strings. The records
+/// are not marked as sorted. See the [module documentation](self) for the
+/// dataset mapping.
+///
+/// # Arguments
+///
+/// * `path` - Path of the `molecule_info.h5` file
+/// * `index` - Dataset to store in each record's index
+/// * `umi_len` - UMI length in bases (10 for v2 chemistry, 12 for v3)
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The file cannot be opened or a dataset is missing ([`IbuError::Hdf5`])
+/// - `umi_len` or the barcode width is not between 1 and 32
+/// - The per-molecule datasets differ in length ([`IbuError::BufferSizeMismatch`])
+/// - A `barcode_idx` entry is out of range ([`IbuError::InvalidIndex`])
+/// - A barcode contains a base other than `A`, `C`, `G` or `T`
+///   ([`IbuError::InvalidBase`])
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{from_molecule_info_with, MoleculeIndex};
+///
+/// # fn main() -> ibu::Result<()> {
+/// // v2 chemistry, keeping read counts per molecule
+/// let (_header, records) = from_molecule_info_with("molecule_info.h5", MoleculeIndex::Count, 10)?;
+/// let reads: u64 = records.iter().map(|r| r.index).sum();
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_molecule_info_with<P: AsRef<Path>>(
+    path: P,
+    index: MoleculeIndex,
+    umi_len: u32,
+) -> crate::Result<(Header, Vec<Record>)> {
+    if umi_len == 0 || umi_len > 32 {
+        return Err(IbuError::InvalidUmiLength(umi_len));
+    }
+
+    let file = hdf5::File::open(path)?;
+    let barcodes = file.dataset("barcodes")?;
+    let bc_len = barcodes.dtype()?.size() as u32;
+    if bc_len == 0 || bc_len > 32 {
+        return Err(IbuError::InvalidBarcodeLength(bc_len));
+    }
+    let barcodes = barcodes
+        .read_raw::<FixedAscii<32>>()?
+        .iter()
+        .map(|barcode| encode_barcode(barcode.as_bytes()))
+        .collect::<crate::Result<Vec<u64>>>()?;
+
+    let barcode_idx = file.dataset("barcode_idx")?.read_raw::<u64>()?;
+    let umis = file.dataset("umi")?.read_raw::<u64>()?;
+    let indices = match index {
+        MoleculeIndex::Feature => file.dataset("feature_idx")?,
+        MoleculeIndex::Count => file.dataset("count")?,
+    }
+    .read_raw::<u64>()?;
+    for len in [umis.len(), indices.len()] {
+        if len != barcode_idx.len() {
+            return Err(IbuError::BufferSizeMismatch {
+                expected: barcode_idx.len(),
+                actual: len,
+            });
+        }
+    }
+
+    let records = barcode_idx
+        .iter()
+        .zip(umis)
+        .zip(indices)
+        .map(|((&idx, umi), index)| {
+            let barcode = barcodes.get(idx as usize).ok_or(IbuError::InvalidIndex {
+                idx: idx as usize,
+                max: barcodes.len(),
+            })?;
+            Ok(Record::new(*barcode, umi, index))
+        })
+        .collect::<crate::Result<Vec<Record>>>()?;
+
+    Ok((Header::new(bc_len, umi_len), records))
+}
+
+/// Packs a barcode string with the first base in the highest bits.
+fn encode_barcode(seq: &[u8]) -> crate::Result<u64> {
+    pack(seq, &Alphabet::canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_barcode() {
+        assert_eq!(encode_barcode(b"A").unwrap(), 0);
+        assert_eq!(encode_barcode(b"ACGT").unwrap(), 0b00_01_10_11);
+        assert_eq!(encode_barcode(b"TA").unwrap(), 0b11_00);
+        assert!(matches!(
+            encode_barcode(b"ACNT"),
+            Err(IbuError::InvalidBase { base: 'N' })
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+//! Helpers for 2-bit packed sequences.
+//!
+//! Barcodes and UMIs are stored as `u64` values holding up to 32 bases at 2
+//! bits per base, with the first base in the most significant occupied bits
+//! (as in the BUS format): base `i` of a `len`-base sequence occupies bits
+//! `2 * (len - 1 - i)..2 * (len - i)`. Unless noted otherwise, the functions
+//! in this module operate directly on that packed representation, without
+//! decoding to nucleotides.
+
+use alloc::vec::Vec;
+
+use crate::{Alphabet, IbuError};
+
+/// Returns a mask covering the 2-bit encoding of `len` bases.
+pub(crate) fn base_mask(len: u32) -> u64 {
+    if len >= 32 {
+        u64::MAX
+    } else {
+        (1u64 << (2 * len)) - 1
+    }
+}
+
+/// Iterates over all sequences at Hamming distance 1 from a packed sequence.
+///
+/// Yields every value that differs from `value` in exactly one of its `len`
+/// bases, for `3 * len` neighbors in total. Neighbors are ordered from the
+/// last base (lowest bits) to the first, then by substituted code. Bits above the `len` bases are carried
+/// over unchanged. For `len == 32` all 64 bits hold bases.
+///
+/// This is the core of barcode error correction: each neighbor of an observed
+/// barcode can be looked up in a whitelist.
+///
+/// # Panics
+///
+/// Panics if `len` is greater than 32.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::hamming_neighbors;
+///
+/// // "CA" (C = 01 in the high bits, A = 00 in the low bits)
+/// let neighbors: Vec<u64> = hamming_neighbors(0b01_00, 2).collect();
+/// assert_eq!(neighbors.len(), 6);
+/// assert_eq!(&neighbors[..3], &[0b01_01, 0b01_10, 0b01_11]);
+/// ```
+pub fn hamming_neighbors(value: u64, len: u32) -> impl Iterator<Item = u64> {
+    assert!(len <= 32, "sequence length {len} exceeds 32 bases");
+    (0..len).flat_map(move |pos| {
+        let shift = 2 * pos;
+        let current = (value >> shift) & 0b11;
+        let cleared = value & !(0b11 << shift);
+        (0..4u64)
+            .filter(move |&code| code != current)
+            .map(move |code| cleared | (code << shift))
+    })
+}
+
+/// Packs a nucleotide sequence of at most 32 bases.
+///
+/// # Errors
+///
+/// Returns [`IbuError::InvalidBase`] for the first base not in `alphabet`.
+pub(crate) fn pack(seq: &[u8], alphabet: &Alphabet) -> crate::Result<u64> {
+    debug_assert!(seq.len() <= 32);
+    seq.iter().try_fold(0u64, |packed, &base| {
+        let code = alphabet
+            .encode(base)
+            .ok_or(IbuError::InvalidBase { base: base as char })?;
+        Ok((packed << 2) | code as u64)
+    })
+}
+
+/// Decodes a packed `len`-base sequence to nucleotides.
+///
+/// Bits above the `len` bases are ignored.
+pub(crate) fn unpack(value: u64, len: u32, alphabet: &Alphabet) -> Vec<u8> {
+    (0..len.min(32))
+        .rev()
+        .map(|slot| alphabet.decode((value >> (2 * slot)) as u8))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_base_mask() {
+        assert_eq!(base_mask(1), 0b11);
+        assert_eq!(base_mask(16), u32::MAX as u64);
+        assert_eq!(base_mask(32), u64::MAX);
+    }
+
+    #[test]
+    fn test_pack() {
+        let alphabet = Alphabet::canonical();
+        assert_eq!(pack(b"", &alphabet).unwrap(), 0);
+        assert_eq!(pack(b"ACGT", &alphabet).unwrap(), 0b00_01_10_11);
+        assert_eq!(pack(&[b'T'; 32], &alphabet).unwrap(), u64::MAX);
+        assert!(matches!(
+            pack(b"ACNT", &alphabet),
+            Err(IbuError::InvalidBase { base: 'N' })
+        ));
+    }
+
+    #[test]
+    fn test_unpack() {
+        let alphabet = Alphabet::canonical();
+        assert_eq!(unpack(0b00_01_10_11, 4, &alphabet), b"ACGT");
+        assert_eq!(unpack(0b11_00, 2, &alphabet), b"TA");
+        assert_eq!(unpack(0xFF_00, 2, &alphabet), b"AA");
+        assert_eq!(unpack(u64::MAX, 32, &alphabet), vec![b'T'; 32]);
+        assert!(unpack(7, 0, &alphabet).is_empty());
+    }
+
+    #[test]
+    fn test_hamming_neighbors() {
+        let value = 0x1234_5678;
+        let neighbors: Vec<u64> = hamming_neighbors(value, 16).collect();
+        assert_eq!(neighbors.len(), 48);
+
+        let unique: HashSet<u64> = neighbors.iter().copied().collect();
+        assert_eq!(unique.len(), 48);
+        assert!(!unique.contains(&value));
+        for neighbor in neighbors {
+            // Exactly one base differs and nothing leaks past the sequence
+            let diff = neighbor ^ value;
+            assert_eq!(diff & !base_mask(16), 0);
+            let differing_bases = (0..16).filter(|i| (diff >> (2 * i)) & 0b11 != 0).count();
+            assert_eq!(differing_bases, 1);
+        }
+    }
+
+    #[test]
+    fn test_hamming_neighbors_boundaries() {
+        // All 64 bits are bases, including the top base
+        let neighbors: Vec<u64> = hamming_neighbors(u64::MAX, 32).collect();
+        assert_eq!(neighbors.len(), 96);
+        assert!(neighbors.contains(&(u64::MAX >> 2)));
+        assert!(neighbors.contains(&!(0b11 << 62)));
+
+        assert_eq!(hamming_neighbors(0, 0).count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds 32 bases")]
+    fn test_hamming_neighbors_too_long() {
+        let _ = hamming_neighbors(0, 33);
+    }
+}
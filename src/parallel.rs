@@ -19,7 +19,294 @@
 //! - The `on_batch_complete` callback allows efficient aggregation of results
 //! - Memory-mapped files enable zero-copy access to records across threads
 
-use crate::{Record, Result};
+use std::{
+    fmt,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+};
+
+use crate::{IbuError, Record, Result};
+
+/// Default number of records per batch for parallel processing.
+///
+/// This constant defines how many records are processed in each batch during
+/// parallel processing. The value of 1M records (~24MB) provides a good balance
+/// between:
+/// - Minimizing synchronization overhead (larger batches)
+/// - Maintaining responsive progress updates (smaller batches)
+/// - Fitting comfortably in CPU caches
+///
+/// Each thread processes records in chunks of this size, calling
+/// `on_batch_complete()` after each chunk.
+pub const BATCH_SIZE: usize = 1024 * 1024;
+
+/// Callback receiving the cumulative number of records processed.
+type ProgressCallback = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// Options for parallel record processing.
+///
+/// A builder over every knob of the parallel pipeline, consumed by
+/// [`MmapReader::process`](crate::MmapReader::process). Options not set keep
+/// their defaults, which match
+/// [`process_parallel`](ParallelReader::process_parallel): all available
+/// cores, batches of [`BATCH_SIZE`] records, unordered batch completion,
+/// unnamed threads, and no progress reporting.
+///
+/// # Ordered Processing
+///
+/// By default each thread processes a contiguous range of the file, and
+/// threads call `on_batch_complete()` independently. With
+/// [`ordered`](Self::ordered) enabled, batches are instead dealt to threads
+/// round-robin, and `on_batch_complete()` calls are serialized in file order.
+/// Records are still processed in parallel, but a processor that flushes
+/// results in `on_batch_complete()` sees its batches aggregated in the order
+/// they appear in the file.
+///
+/// # Work Stealing
+///
+/// Contiguous ranges balance poorly when the cost per record varies across
+/// the file. With [`work_stealing`](Self::work_stealing) enabled, threads
+/// instead claim the next unprocessed batch from a shared counter whenever
+/// they finish one, so fast threads take over work from slow regions. The
+/// batch size is then the unit of work distribution: smaller batches balance
+/// better at the cost of more `on_batch_complete()` calls. Combined with
+/// [`ordered`](Self::ordered), batches are still completed in file order.
+///
+/// # Overlapping Context
+///
+/// With [`overlap`](Self::overlap) set to `n`, every contiguous range of
+/// records a thread processes is preceded by up to `n` records before its
+/// start, passed to
+/// [`process_context_record`](ParallelProcessor::process_context_record)
+/// instead of `process_record()`. Sliding-window algorithms can use these
+/// records as look-behind context without double counting them. Context
+/// never extends before the first record of the file.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{MmapReader, ParallelOptions, ParallelProcessor, Record};
+///
+/// #[derive(Clone, Default)]
+/// struct NoOpProcessor;
+///
+/// impl ParallelProcessor for NoOpProcessor {
+///     fn process_record(&mut self, _record: Record) -> ibu::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = MmapReader::new("data.ibu")?;
+/// let total = reader.len() as u64;
+/// let options = ParallelOptions::new()
+///     .threads(8)
+///     .batch_size(64 * 1024)
+///     .thread_name_prefix("ibu-worker")
+///     .progress_callback(move |done| eprintln!("{}/{} records", done, total));
+///
+/// reader.process(NoOpProcessor, &options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ParallelOptions {
+    /// Number of threads (0 = all available cores)
+    pub(crate) threads: usize,
+
+    /// Number of records per batch
+    pub(crate) batch_size: usize,
+
+    /// Serialize batch completion in file order
+    pub(crate) ordered: bool,
+
+    /// Prefix for worker thread names (None = unnamed)
+    pub(crate) thread_name_prefix: Option<String>,
+
+    /// Called after every batch with the cumulative record count
+    pub(crate) progress_callback: Option<ProgressCallback>,
+
+    /// Number of context records preceding each contiguous range
+    pub(crate) overlap: usize,
+
+    /// Claim batches dynamically instead of splitting the file into ranges
+    pub(crate) work_stealing: bool,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            batch_size: BATCH_SIZE,
+            ordered: false,
+            thread_name_prefix: None,
+            progress_callback: None,
+            overlap: 0,
+            work_stealing: false,
+        }
+    }
+}
+
+impl fmt::Debug for ParallelOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParallelOptions")
+            .field("threads", &self.threads)
+            .field("batch_size", &self.batch_size)
+            .field("ordered", &self.ordered)
+            .field("thread_name_prefix", &self.thread_name_prefix)
+            .field("progress_callback", &self.progress_callback.is_some())
+            .field("overlap", &self.overlap)
+            .field("work_stealing", &self.work_stealing)
+            .finish()
+    }
+}
+
+impl ParallelOptions {
+    /// Creates options with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of threads (0 = use all available cores).
+    ///
+    /// The count is capped at the available cores and at the number of
+    /// records, so small inputs never spawn idle threads.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets the number of records per batch.
+    ///
+    /// `on_batch_complete()` and the progress callback run once per batch.
+    /// A batch size of 0 is treated as 1.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets whether batch completions are serialized in file order.
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// Names worker threads `{prefix}-{tid}`, e.g. for profilers and debuggers.
+    pub fn thread_name_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.thread_name_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets a callback receiving the cumulative number of records processed.
+    ///
+    /// The callback runs on worker threads after each completed batch, so it
+    /// should be cheap. Counts from concurrent threads may be reported out of
+    /// order unless [`ordered`](Self::ordered) is enabled.
+    pub fn progress_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        self.progress_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets the number of context records preceding each contiguous range.
+    ///
+    /// See [Overlapping Context](Self#overlapping-context).
+    pub fn overlap(mut self, overlap: usize) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Sets whether threads claim batches dynamically from a shared counter.
+    ///
+    /// See [Work Stealing](Self#work-stealing).
+    pub fn work_stealing(mut self, work_stealing: bool) -> Self {
+        self.work_stealing = work_stealing;
+        self
+    }
+}
+
+/// Hands out turns to batches in increasing order across threads.
+///
+/// Used to serialize batch completion in file order. A failed thread aborts
+/// the sequence so that threads waiting on its batches are released.
+#[derive(Default)]
+pub(crate) struct BatchTurns {
+    /// Next batch allowed to complete, and whether processing was aborted
+    state: Mutex<(usize, bool)>,
+    changed: Condvar,
+}
+
+impl BatchTurns {
+    /// Blocks until it is `batch`'s turn; returns `false` if aborted.
+    pub(crate) fn wait_for(&self, batch: usize) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while state.0 != batch && !state.1 {
+            state = self.changed.wait(state).unwrap();
+        }
+        !state.1
+    }
+
+    /// Passes the turn to the next batch.
+    pub(crate) fn advance(&self) {
+        self.state.lock().unwrap().0 += 1;
+        self.changed.notify_all();
+    }
+
+    /// Releases all waiting threads without granting further turns.
+    pub(crate) fn abort(&self) {
+        self.state.lock().unwrap().1 = true;
+        self.changed.notify_all();
+    }
+}
+
+/// Resolves a requested thread count against the available cores.
+///
+/// A request of 0 means "use all available cores"; larger requests are capped
+/// at the number of available cores.
+pub(crate) fn resolve_num_threads(num_threads: usize) -> usize {
+    if num_threads == 0 {
+        num_cpus::get()
+    } else {
+        num_threads.min(num_cpus::get())
+    }
+}
+
+/// Runs a worker, converting a panic into [`IbuError::Process`].
+///
+/// The panic message is kept when the payload is a string, so a panicking
+/// processor surfaces as an error from the parallel entry point rather than
+/// as an opaque panic when its thread is joined.
+pub(crate) fn catch_panic<T>(work: impl FnOnce() -> Result<T>) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(work)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        Err(IbuError::Process(
+            format!("worker thread panicked: {message}").into(),
+        ))
+    })
+}
+
+/// Splits `len` items into `num_threads` contiguous ranges.
+///
+/// Ranges are of equal size, except the last which also takes the remainder.
+pub(crate) fn thread_ranges(len: usize, num_threads: usize) -> impl Iterator<Item = Range<usize>> {
+    let per_thread = len / num_threads;
+    let remainder = len % num_threads;
+    (0..num_threads).map(move |i| {
+        let start = i * per_thread;
+        if i == num_threads - 1 {
+            start..start + per_thread + remainder
+        } else {
+            start..start + per_thread
+        }
+    })
+}
 
 /// Trait for types that can process records in parallel.
 ///
@@ -32,6 +319,7 @@ use crate::{Record, Result};
 /// 2. Records are processed individually via `process_record`
 /// 3. After processing a batch, `on_batch_complete` is called for aggregation
 /// 4. This cycle repeats until all records are processed
+/// 5. `on_thread_complete` is called once as each thread finishes
 ///
 /// # Thread Safety
 ///
@@ -163,6 +451,46 @@ pub trait ParallelProcessor: Send + Clone {
         Ok(())
     }
 
+    /// Receives a context-only record preceding a thread's range.
+    ///
+    /// Called with the records before each contiguous range a thread
+    /// processes when an [`overlap`](ParallelOptions::overlap) is set, in file
+    /// order and before the range's first `process_record()` call. Context
+    /// records are also processed normally by another thread, so they should
+    /// inform state (e.g. a sliding window) without being counted.
+    ///
+    /// The default implementation ignores the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The context record
+    ///
+    /// # Errors
+    ///
+    /// Should return an error if processing fails. This will stop the entire
+    /// parallel processing operation.
+    #[allow(unused_variables)]
+    fn process_context_record(&mut self, record: Record) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once when a thread has processed all of its records.
+    ///
+    /// Runs after the thread's last [`on_batch_complete`](Self::on_batch_complete),
+    /// so it is the place to flush state that is not tied to batch boundaries,
+    /// such as a value carried across batches or a final summary per thread.
+    /// It is not called on threads that stop early because processing failed.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Should return an error if finalization fails. This will be reported as
+    /// the result of the parallel processing operation.
+    fn on_thread_complete(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     /// Sets the thread ID for this processor instance.
     ///
     /// Called once per thread before processing begins. Can be useful for:
@@ -198,7 +526,8 @@ pub trait ParallelProcessor: Send + Clone {
 /// # Threading Model
 ///
 /// The parallel processing uses a divide-and-conquer approach:
-/// 1. The total number of records is divided evenly across threads
+/// 1. The total number of records is divided evenly across threads (never more
+///    threads than records)
 /// 2. Each thread processes its assigned range independently
 /// 3. Within each thread, records are processed in batches for efficiency
 /// 4. Results are aggregated through the processor's `on_batch_complete` method
@@ -264,6 +593,12 @@ pub trait ParallelReader {
     /// - Any thread encounters a processing error
     /// - Thread creation or coordination fails
     /// - The processor returns an error from `process_record` or `on_batch_complete`
+    /// - The processor panics, reported as [`IbuError::Process`] with the panic
+    ///   message
+    ///
+    /// Implementations stop the remaining threads at their next batch boundary
+    /// once any thread fails, so an early error is not delayed by processing
+    /// the rest of the input.
     ///
     /// # Examples
     ///
@@ -351,6 +686,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_thread_ranges() {
+        let ranges: Vec<_> = thread_ranges(10, 3).collect();
+        assert_eq!(ranges, vec![0..3, 3..6, 6..10]);
+
+        let ranges: Vec<_> = thread_ranges(2, 4).collect();
+        assert_eq!(ranges, vec![0..0, 0..0, 0..0, 0..2]);
+
+        let total: usize = thread_ranges(1_000_003, 7).map(|r| r.len()).sum();
+        assert_eq!(total, 1_000_003);
+    }
+
+    #[test]
+    #[allow(clippy::assertions_on_constants)]
+    fn test_batch_size_constant() {
+        assert_eq!(BATCH_SIZE, 1024 * 1024);
+        assert!(BATCH_SIZE > 0);
+        // Should be reasonable size for memory usage
+        assert!(BATCH_SIZE * crate::RECORD_SIZE < 100 * 1024 * 1024); // < 100MB
+    }
+
+    #[test]
+    fn test_parallel_options() {
+        let options = ParallelOptions::default();
+        assert_eq!(options.threads, 0);
+        assert_eq!(options.batch_size, BATCH_SIZE);
+        assert!(!options.ordered);
+        assert!(options.progress_callback.is_none());
+
+        let options = ParallelOptions::new()
+            .threads(3)
+            .batch_size(0)
+            .ordered(true)
+            .thread_name_prefix("worker")
+            .progress_callback(|_| {});
+        assert_eq!(options.threads, 3);
+        assert_eq!(options.batch_size, 1);
+        assert!(options.ordered);
+        assert_eq!(options.thread_name_prefix.as_deref(), Some("worker"));
+        assert!(format!("{:?}", options).contains("progress_callback: true"));
+    }
+
+    #[test]
+    fn test_resolve_num_threads() {
+        assert_eq!(resolve_num_threads(0), num_cpus::get());
+        assert_eq!(resolve_num_threads(1), 1);
+        assert!(resolve_num_threads(usize::MAX) <= num_cpus::get());
+    }
+
     #[test]
     fn test_processor_basic_functionality() {
         let processor = TestProcessor::default();
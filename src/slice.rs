@@ -0,0 +1,228 @@
+//! Shared algorithms over slices of records.
+//!
+//! These helpers operate on plain `&[Record]` so they can back both owned
+//! collections ([`Ibu`](crate::Ibu)) and zero-copy views such as those
+//! returned by [`MmapReader::slice`](crate::MmapReader::slice).
+
+use std::ops::Range;
+
+use crate::{IbuError, Record};
+
+/// Returns the contiguous run of records with the given barcode.
+///
+/// The slice must be sorted by barcode. Returns an empty slice if the barcode
+/// is not present. Runs in O(log n).
+pub(crate) fn search_barcode(records: &[Record], barcode: u64) -> &[Record] {
+    &records[barcode_range(records, barcode)]
+}
+
+/// Returns the index range of the records with the given barcode.
+///
+/// The slice must be sorted by barcode. Returns an empty range positioned
+/// where the barcode would be inserted if it is not present. Runs in O(log n).
+pub(crate) fn barcode_range(records: &[Record], barcode: u64) -> Range<usize> {
+    let start = records.partition_point(|r| r.barcode < barcode);
+    let end = start + records[start..].partition_point(|r| r.barcode == barcode);
+    start..end
+}
+
+/// Iterates over runs of consecutive records sharing a barcode.
+///
+/// Yields `(barcode, run)` pairs in order. On sorted input each barcode is
+/// yielded exactly once.
+pub(crate) fn group_by_barcode(records: &[Record]) -> impl Iterator<Item = (u64, &[Record])> {
+    records
+        .chunk_by(|a, b| a.barcode == b.barcode)
+        .map(|run| (run[0].barcode, run))
+}
+
+/// Returns whether the records are in non-decreasing order.
+pub(crate) fn is_sorted(records: &[Record]) -> bool {
+    records.is_sorted()
+}
+
+/// Verifies that indices are non-decreasing within each barcode run.
+///
+/// This is a weaker invariant than global sortedness: UMIs may be in any order,
+/// but within every run of consecutive records sharing a barcode, each index
+/// must be greater than or equal to the previous one. This is the expected
+/// layout of barcode-major files whose records are index-sorted per cell.
+///
+/// # Errors
+///
+/// Returns [`IbuError::IndexRegression`] with the position of the first record
+/// whose index is smaller than its predecessor's within the same barcode.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{verify_index_sorted_within_barcode, IbuError, Record};
+///
+/// let records = vec![
+///     Record::new(1, 9, 0),
+///     Record::new(1, 3, 5),
+///     Record::new(2, 0, 1), // new barcode, index may restart
+///     Record::new(2, 0, 0), // regression
+/// ];
+/// assert!(matches!(
+///     verify_index_sorted_within_barcode(&records),
+///     Err(IbuError::IndexRegression { pos: 3 })
+/// ));
+/// assert!(verify_index_sorted_within_barcode(&records[..3]).is_ok());
+/// ```
+pub fn verify_index_sorted_within_barcode(records: &[Record]) -> crate::Result<()> {
+    match records
+        .windows(2)
+        .position(|w| w[0].barcode == w[1].barcode && w[1].index < w[0].index)
+    {
+        Some(i) => Err(IbuError::IndexRegression { pos: i + 1 }),
+        None => Ok(()),
+    }
+}
+
+/// Coalesces records with consecutive indices into ranges.
+///
+/// Scans the records in order and merges each record into the current run
+/// when it shares the run's barcode and UMI and its index is exactly one more
+/// than the previous record's. Every run is reported as
+/// `(barcode, umi, start, end)` with an inclusive `end`, so a single record
+/// yields `start == end`. This is a compact representation of coverage-style
+/// data where the index is a coordinate.
+///
+/// The input should be sorted, so that all records of a `(barcode, umi)` pair
+/// are adjacent and their indices ascending; otherwise runs are simply split
+/// wherever the order breaks. Repeated indices are not merged and start a new
+/// run.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{coalesce_index_runs, Record};
+///
+/// let records = vec![
+///     Record::new(1, 7, 10),
+///     Record::new(1, 7, 11),
+///     Record::new(1, 7, 12),
+///     Record::new(1, 7, 20),
+///     Record::new(1, 8, 21), // new UMI
+/// ];
+/// assert_eq!(
+///     coalesce_index_runs(&records),
+///     vec![(1, 7, 10, 12), (1, 7, 20, 20), (1, 8, 21, 21)]
+/// );
+/// ```
+pub fn coalesce_index_runs(records: &[Record]) -> Vec<(u64, u64, u64, u64)> {
+    records
+        .chunk_by(|a, b| {
+            a.barcode == b.barcode && a.umi == b.umi && a.index.checked_add(1) == Some(b.index)
+        })
+        .map(|run| {
+            let first = run[0];
+            (
+                first.barcode,
+                first.umi,
+                first.index,
+                run[run.len() - 1].index,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_records() -> Vec<Record> {
+        vec![
+            Record::new(1, 0, 0),
+            Record::new(1, 1, 0),
+            Record::new(3, 0, 0),
+            Record::new(5, 0, 0),
+            Record::new(5, 0, 1),
+            Record::new(5, 2, 0),
+        ]
+    }
+
+    #[test]
+    fn test_search_barcode() {
+        let records = sorted_records();
+        assert_eq!(search_barcode(&records, 1), &records[0..2]);
+        assert_eq!(search_barcode(&records, 3), &records[2..3]);
+        assert_eq!(search_barcode(&records, 5), &records[3..6]);
+        assert!(search_barcode(&records, 0).is_empty());
+        assert!(search_barcode(&records, 4).is_empty());
+        assert!(search_barcode(&records, 6).is_empty());
+        assert!(search_barcode(&[], 1).is_empty());
+    }
+
+    #[test]
+    fn test_group_by_barcode() {
+        let records = sorted_records();
+        let groups: Vec<(u64, usize)> = group_by_barcode(&records)
+            .map(|(bc, run)| (bc, run.len()))
+            .collect();
+        assert_eq!(groups, vec![(1, 2), (3, 1), (5, 3)]);
+        assert_eq!(group_by_barcode(&[]).count(), 0);
+    }
+
+    #[test]
+    fn test_verify_index_sorted_within_barcode() {
+        // Globally unsorted by UMI, but index-sorted per barcode
+        let records = vec![
+            Record::new(1, 5, 0),
+            Record::new(1, 2, 0),
+            Record::new(1, 9, 3),
+            Record::new(2, 1, 1),
+            Record::new(2, 0, 8),
+        ];
+        assert!(verify_index_sorted_within_barcode(&records).is_ok());
+        assert!(verify_index_sorted_within_barcode(&[]).is_ok());
+        assert!(verify_index_sorted_within_barcode(&records[..1]).is_ok());
+
+        let mut regressed = records.clone();
+        regressed[2].index = 0;
+        regressed[4].index = 0;
+        assert!(matches!(
+            verify_index_sorted_within_barcode(&regressed),
+            Err(IbuError::IndexRegression { pos: 4 })
+        ));
+
+        regressed[1].index = u64::MAX;
+        assert!(matches!(
+            verify_index_sorted_within_barcode(&regressed),
+            Err(IbuError::IndexRegression { pos: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_is_sorted() {
+        let mut records = sorted_records();
+        assert!(is_sorted(&records));
+        records.swap(0, 5);
+        assert!(!is_sorted(&records));
+        assert!(is_sorted(&[]));
+    }
+
+    #[test]
+    fn test_coalesce_index_runs() {
+        let records = vec![
+            Record::new(1, 0, 5),
+            Record::new(1, 0, 6),
+            Record::new(1, 0, 6), // repeated index
+            Record::new(1, 0, 7),
+            Record::new(2, 0, 8), // new barcode continues the index
+            Record::new(2, 0, u64::MAX - 1),
+            Record::new(2, 0, u64::MAX),
+        ];
+        assert_eq!(
+            coalesce_index_runs(&records),
+            vec![
+                (1, 0, 5, 6),
+                (1, 0, 6, 7),
+                (2, 0, 8, 8),
+                (2, 0, u64::MAX - 1, u64::MAX),
+            ]
+        );
+        assert!(coalesce_index_runs(&[]).is_empty());
+    }
+}
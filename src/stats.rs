@@ -0,0 +1,374 @@
+//! Summary statistics over IBU records.
+//!
+//! This module provides common single-cell metrics. Functions taking an
+//! [`MmapReader`] split the records into contiguous per-thread ranges and
+//! merge the per-thread results; functions taking a slice of records work on
+//! any in-memory source.
+
+use std::{collections::HashMap, thread};
+
+use crate::{
+    parallel::{catch_panic, resolve_num_threads, thread_ranges},
+    IbuError, MmapReader, Record,
+};
+#[cfg(feature = "niffler")]
+use {
+    crate::{NullSink, Reader, Writer},
+    std::path::Path,
+};
+
+/// Counts distinct `(barcode, umi)` pairs in a sorted file.
+///
+/// Since equal pairs are adjacent in sorted input, each thread counts the
+/// positions in its range where the pair differs from the preceding record.
+/// The first record of every range is compared against the last record of the
+/// previous range, so a run of equal pairs split across two threads is
+/// counted exactly once. No hashing is needed and the result is exact.
+///
+/// # Arguments
+///
+/// * `reader` - Memory-mapped reader over a sorted file
+/// * `num_threads` - Number of threads to use (0 = use all available cores)
+///
+/// # Errors
+///
+/// Returns [`IbuError::NotSorted`] if the header is not marked as sorted, or
+/// [`IbuError::Process`] with the panic message if a worker thread panics.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{distinct_bc_umi_pairs, MmapReader};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = MmapReader::new("sorted.ibu")?;
+/// let molecules = distinct_bc_umi_pairs(&reader, 0)?;
+/// println!("{} distinct molecules", molecules);
+/// # Ok(())
+/// # }
+/// ```
+pub fn distinct_bc_umi_pairs(reader: &MmapReader, num_threads: usize) -> crate::Result<u64> {
+    if !reader.header().sorted() {
+        return Err(IbuError::NotSorted);
+    }
+    let records = reader.records();
+    let num_threads = resolve_num_threads(num_threads);
+
+    let total = thread::scope(|scope| {
+        let handles: Vec<_> = thread_ranges(records.len(), num_threads)
+            .map(|range| {
+                scope.spawn(move || {
+                    catch_panic(|| {
+                        let mut count = 0u64;
+                        for i in range {
+                            let record = &records[i];
+                            let is_new = i == 0 || {
+                                let prev = &records[i - 1];
+                                prev.barcode != record.barcode || prev.umi != record.umi
+                            };
+                            count += is_new as u64;
+                        }
+                        Ok(count)
+                    })
+                })
+            })
+            .collect();
+        // Workers catch their own panics, so joining cannot fail
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    });
+
+    total
+}
+
+/// Computes exact quantiles of the record index values.
+///
+/// Quantiles use the nearest-rank method on the sorted values: quantile `q`
+/// is the value at position `round(q * (n - 1))`, so `0.0` is the minimum,
+/// `0.5` the median and `1.0` the maximum. Values are selected in expected
+/// linear time per quantile on a copy of the indices, so the records need not
+/// be sorted.
+///
+/// # Returns
+///
+/// One value per entry of `qs`, in the same order, or an empty vector if
+/// `records` is empty.
+///
+/// # Panics
+///
+/// Panics if any quantile is outside `[0.0, 1.0]`.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{index_quantiles, Record};
+///
+/// let records: Vec<Record> = (1..=5).map(|i| Record::new(0, 0, i * 10)).collect();
+/// assert_eq!(index_quantiles(&records, &[0.0, 0.5, 1.0]), vec![10, 30, 50]);
+/// ```
+pub fn index_quantiles(records: &[Record], qs: &[f64]) -> Vec<u64> {
+    let values = records.iter().map(|r| r.index).collect();
+    quantiles(values, qs)
+}
+
+/// Computes exact quantiles of per-barcode read depth.
+///
+/// The depth of a barcode is its number of records. Depths are counted over
+/// all records (which need not be sorted), then quantiles are taken over the
+/// per-barcode depths with the same nearest-rank method as
+/// [`index_quantiles`].
+///
+/// # Returns
+///
+/// One depth per entry of `qs`, in the same order, or an empty vector if
+/// `records` is empty.
+///
+/// # Panics
+///
+/// Panics if any quantile is outside `[0.0, 1.0]`.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{depth_quantiles, Record};
+///
+/// // Barcode 1 has 1 record, barcode 2 has 2, barcode 3 has 3
+/// let records: Vec<Record> = [1, 2, 2, 3, 3, 3]
+///     .into_iter()
+///     .map(|bc| Record::new(bc, 0, 0))
+///     .collect();
+/// assert_eq!(depth_quantiles(&records, &[0.0, 0.5, 1.0]), vec![1, 2, 3]);
+/// ```
+pub fn depth_quantiles(records: &[Record], qs: &[f64]) -> Vec<u64> {
+    let mut depths: HashMap<u64, u64> = HashMap::new();
+    for record in records {
+        *depths.entry(record.barcode).or_default() += 1;
+    }
+    quantiles(depths.into_values().collect(), qs)
+}
+
+/// Estimates the compression ratio of a file's records without writing output.
+///
+/// The first `sample_records` records are serialized and compressed with
+/// `format` at level 6 into a byte-counting sink, and the ratio of
+/// uncompressed to compressed bytes is returned. The header is excluded from
+/// both sizes. Values above 1 mean the records compress; sorted files
+/// typically compress far better than unsorted ones.
+///
+/// Since the sample is a prefix of the file, the estimate assumes the start of
+/// the file is representative of the rest.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file to sample (may itself be compressed)
+/// * `sample_records` - Maximum number of records to compress
+/// * `format` - Compression format to evaluate
+///
+/// # Returns
+///
+/// The compression ratio, or `1.0` if the file has no records.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or the compressor fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::sample_compression_ratio;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let ratio = sample_compression_ratio("data.ibu", 1_000_000, niffler::Format::Zstd)?;
+/// if ratio > 2.0 {
+///     println!("Worth compressing ({:.1}x)", ratio);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "niffler")]
+pub fn sample_compression_ratio<P: AsRef<Path>>(
+    path: P,
+    sample_records: usize,
+    format: niffler::Format,
+) -> crate::Result<f64> {
+    let reader = Reader::from_path(path)?;
+
+    let mut sink = NullSink::new();
+    let uncompressed = {
+        let compressor = niffler::get_writer(Box::new(&mut sink), format, niffler::Level::Six)?;
+        let mut writer = Writer::new_headless(compressor);
+        for record in reader.take(sample_records) {
+            writer.write_record(&record?)?;
+        }
+        writer.finish()?;
+        writer.records_written() * crate::RECORD_SIZE as u64
+        // The compressor is dropped here, flushing its trailer into the sink
+    };
+
+    let compressed = sink.bytes_written();
+    if uncompressed == 0 || compressed == 0 {
+        return Ok(1.0);
+    }
+    Ok(uncompressed as f64 / compressed as f64)
+}
+
+/// Selects nearest-rank quantiles from an unsorted set of values.
+fn quantiles(mut values: Vec<u64>, qs: &[f64]) -> Vec<u64> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    let last = values.len() - 1;
+    qs.iter()
+        .map(|&q| {
+            assert!((0.0..=1.0).contains(&q), "quantile {q} is outside [0, 1]");
+            let rank = (q * last as f64).round() as usize;
+            *values.select_nth_unstable(rank).1
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, Record, Writer};
+    use std::collections::HashSet;
+    use std::fs;
+
+    fn create_test_file(path: &str, sorted: bool, records: &[Record]) {
+        let mut header = Header::new(16, 12);
+        if sorted {
+            header.set_sorted();
+        }
+        let file = fs::File::create(path).unwrap();
+        let mut writer = Writer::new(file, header).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_distinct_bc_umi_pairs() {
+        let temp_file = "test_distinct_bc_umi_pairs.ibu";
+        let mut records: Vec<Record> = (0..10_000)
+            .map(|i| Record::new(i % 37, (i * 7) % 11, i))
+            .collect();
+        records.sort();
+        create_test_file(temp_file, true, &records);
+
+        let expected = records
+            .iter()
+            .map(|r| (r.barcode, r.umi))
+            .collect::<HashSet<_>>()
+            .len() as u64;
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        for num_threads in [1, 2, 3, 8] {
+            assert_eq!(
+                distinct_bc_umi_pairs(&reader, num_threads).unwrap(),
+                expected
+            );
+        }
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_index_quantiles() {
+        // Indices 0..=100 in shuffled order
+        let records: Vec<Record> = (0..=100u64)
+            .map(|i| Record::new(0, 0, (i * 37) % 101))
+            .collect();
+        assert_eq!(
+            index_quantiles(&records, &[0.0, 0.25, 0.5, 0.9, 1.0]),
+            vec![0, 25, 50, 90, 100]
+        );
+        assert!(index_quantiles(&[], &[0.5]).is_empty());
+        assert!(index_quantiles(&records, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_depth_quantiles() {
+        // Barcode `b` has `b` records, interleaved
+        let mut records = Vec::new();
+        for round in 1..=10 {
+            for barcode in round..=10 {
+                records.push(Record::new(barcode, 0, 0));
+            }
+        }
+        assert_eq!(depth_quantiles(&records, &[0.0, 0.5, 1.0]), vec![1, 6, 10]);
+        assert!(depth_quantiles(&[], &[0.5]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "outside [0, 1]")]
+    fn test_quantiles_out_of_range() {
+        index_quantiles(&[Record::new(0, 0, 0)], &[1.5]);
+    }
+
+    #[cfg(feature = "niffler")]
+    #[test]
+    fn test_sample_compression_ratio() {
+        let (sorted_file, random_file) = ("test_ratio_sorted.ibu", "test_ratio_random.ibu");
+        let sorted: Vec<Record> = (0..50_000)
+            .map(|i| Record::new(i / 100, i % 100, 0))
+            .collect();
+        create_test_file(sorted_file, true, &sorted);
+        let random: Vec<Record> = (0..50_000u64)
+            .map(|i| {
+                let x = i.wrapping_mul(0x9e3779b97f4a7c15).rotate_left(17);
+                Record::new(x, x.wrapping_mul(31), x ^ 0x5555)
+            })
+            .collect();
+        create_test_file(random_file, false, &random);
+
+        let sorted_ratio =
+            sample_compression_ratio(sorted_file, 10_000, niffler::Format::Gzip).unwrap();
+        let random_ratio =
+            sample_compression_ratio(random_file, 10_000, niffler::Format::Gzip).unwrap();
+        assert!(sorted_ratio > 5.0, "sorted ratio {sorted_ratio}");
+        assert!(random_ratio < sorted_ratio);
+
+        // Uncompressed output is the same size as the input
+        let ratio = sample_compression_ratio(sorted_file, 10_000, niffler::Format::No).unwrap();
+        assert_eq!(ratio, 1.0);
+
+        fs::remove_file(sorted_file).unwrap();
+        fs::remove_file(random_file).unwrap();
+    }
+
+    #[test]
+    fn test_distinct_bc_umi_pairs_split_run() {
+        // A single pair spanning every thread boundary must be counted once
+        let temp_file = "test_distinct_bc_umi_pairs_split.ibu";
+        let records: Vec<Record> = (0..1000).map(|i| Record::new(1, 1, i)).collect();
+        create_test_file(temp_file, true, &records);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert_eq!(distinct_bc_umi_pairs(&reader, 4).unwrap(), 1);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_distinct_bc_umi_pairs_empty() {
+        let temp_file = "test_distinct_bc_umi_pairs_empty.ibu";
+        create_test_file(temp_file, true, &[]);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert_eq!(distinct_bc_umi_pairs(&reader, 4).unwrap(), 0);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_distinct_bc_umi_pairs_requires_sorted() {
+        let temp_file = "test_distinct_bc_umi_pairs_unsorted.ibu";
+        create_test_file(temp_file, false, &[Record::new(1, 2, 3)]);
+
+        let reader = MmapReader::new(temp_file).unwrap();
+        assert!(matches!(
+            distinct_bc_umi_pairs(&reader, 1),
+            Err(IbuError::NotSorted)
+        ));
+
+        fs::remove_file(temp_file).unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+//! One-call summary of an IBU file.
+//!
+//! [`summarize`] maps a file and gathers the record count, index range and
+//! mean, and number of distinct barcodes in a single parallel pass with
+//! [`SummaryProcessor`], alongside the lengths and sort flag of the header.
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    ApproxBarcodeCounter, DistinctBarcodeCounter, Header, MmapReader, ParallelProcessor,
+    ParallelReader, Record,
+};
+
+/// HyperLogLog precision used for approximate distinct barcode counts.
+const APPROX_PRECISION: u32 = 14;
+
+/// Summary statistics of an IBU file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IbuStats {
+    /// Number of records
+    pub records: u64,
+
+    /// Barcode length in bases, from the header
+    pub bc_len: u32,
+
+    /// UMI length in bases, from the header
+    pub umi_len: u32,
+
+    /// Whether the header marks the records as sorted
+    pub sorted: bool,
+
+    /// Number of distinct barcodes
+    pub distinct_barcodes: u64,
+
+    /// Whether `distinct_barcodes` is exact rather than a HyperLogLog estimate
+    pub distinct_exact: bool,
+
+    /// Smallest index (None without records)
+    pub min_index: Option<u64>,
+
+    /// Largest index (None without records)
+    pub max_index: Option<u64>,
+
+    /// Mean index (None without records)
+    pub mean_index: Option<f64>,
+}
+
+/// Index statistics accumulated over records.
+#[derive(Clone, Copy, Debug, Default)]
+struct IndexSummary {
+    /// Number of records
+    count: u64,
+
+    /// Sum of indices (wide enough not to overflow)
+    sum: u128,
+
+    /// Smallest and largest index
+    bounds: Option<(u64, u64)>,
+}
+
+impl IndexSummary {
+    /// Adds a record's index.
+    fn insert(&mut self, index: u64) {
+        self.count += 1;
+        self.sum += u128::from(index);
+        self.bounds = Some(match self.bounds {
+            Some((min, max)) => (min.min(index), max.max(index)),
+            None => (index, index),
+        });
+    }
+
+    /// Adds the statistics of `other`.
+    fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.sum += other.sum;
+        if let Some((min, max)) = other.bounds {
+            self.bounds = Some(match self.bounds {
+                Some((a, b)) => (a.min(min), b.max(max)),
+                None => (min, max),
+            });
+        }
+    }
+}
+
+/// Distinct barcode counter, exact or approximate.
+#[derive(Clone, Debug)]
+enum BarcodeCounter {
+    Exact(DistinctBarcodeCounter),
+    Approx(ApproxBarcodeCounter),
+}
+
+/// Parallel processor gathering the statistics of [`IbuStats`].
+///
+/// Distinct barcodes are counted exactly with a [`DistinctBarcodeCounter`],
+/// whose memory grows with the number of barcodes, or estimated in fixed
+/// memory with an [`ApproxBarcodeCounter`] (about 1% relative error).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{ChannelParallelReader, ParallelReader, Reader, SummaryProcessor};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let reader = Reader::from_path("data.ibu.gz")?;
+/// let header = reader.header();
+/// let summary = SummaryProcessor::new(false);
+/// ChannelParallelReader::new(reader).process_parallel(summary.clone(), 0)?;
+/// println!("{:?}", summary.stats(header));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SummaryProcessor {
+    /// Index statistics since the last completed batch
+    local: IndexSummary,
+
+    /// Index statistics merged from all completed batches
+    shared: Arc<Mutex<IndexSummary>>,
+
+    /// Distinct barcode counter (shares its own merged state)
+    barcodes: BarcodeCounter,
+}
+
+impl SummaryProcessor {
+    /// Creates a processor with no observations.
+    ///
+    /// # Arguments
+    ///
+    /// * `exact_barcodes` - Count distinct barcodes exactly instead of
+    ///   estimating them
+    pub fn new(exact_barcodes: bool) -> Self {
+        let barcodes = if exact_barcodes {
+            BarcodeCounter::Exact(DistinctBarcodeCounter::new())
+        } else {
+            BarcodeCounter::Approx(ApproxBarcodeCounter::new(APPROX_PRECISION))
+        };
+        Self {
+            local: IndexSummary::default(),
+            shared: Arc::default(),
+            barcodes,
+        }
+    }
+
+    /// Returns the statistics over all completed batches.
+    ///
+    /// The lengths and sort flag are taken from `header`, which should be
+    /// the header of the processed records.
+    pub fn stats(&self, header: Header) -> IbuStats {
+        let index = *self.shared.lock().unwrap();
+        let (distinct_barcodes, distinct_exact) = match &self.barcodes {
+            BarcodeCounter::Exact(counter) => (counter.count() as u64, true),
+            BarcodeCounter::Approx(counter) => (counter.count(), false),
+        };
+        IbuStats {
+            records: index.count,
+            bc_len: header.bc_len,
+            umi_len: header.umi_len,
+            sorted: header.sorted(),
+            distinct_barcodes,
+            distinct_exact,
+            min_index: index.bounds.map(|(min, _)| min),
+            max_index: index.bounds.map(|(_, max)| max),
+            mean_index: (index.count > 0).then(|| index.sum as f64 / index.count as f64),
+        }
+    }
+}
+
+impl ParallelProcessor for SummaryProcessor {
+    fn process_record(&mut self, record: Record) -> crate::Result<()> {
+        self.local.insert(record.index);
+        match &mut self.barcodes {
+            BarcodeCounter::Exact(counter) => counter.process_record(record),
+            BarcodeCounter::Approx(counter) => counter.process_record(record),
+        }
+    }
+
+    fn on_batch_complete(&mut self) -> crate::Result<()> {
+        self.shared
+            .lock()
+            .unwrap()
+            .merge(&std::mem::take(&mut self.local));
+        match &mut self.barcodes {
+            BarcodeCounter::Exact(counter) => counter.on_batch_complete(),
+            BarcodeCounter::Approx(counter) => counter.on_batch_complete(),
+        }
+    }
+}
+
+/// Summarizes an IBU file with an exact distinct barcode count.
+///
+/// This is [`summarize_with`] with `exact_barcodes` set.
+///
+/// # Arguments
+///
+/// * `path` - Path to the IBU file
+///
+/// # Errors
+///
+/// See [`summarize_with`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::summarize;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let stats = summarize("data.ibu")?;
+/// println!(
+///     "{} records, {} barcodes, mean index {:?}",
+///     stats.records, stats.distinct_barcodes, stats.mean_index
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn summarize<P: AsRef<Path>>(path: P) -> crate::Result<IbuStats> {
+    summarize_with(path, true)
+}
+
+/// Summarizes an IBU file in one parallel pass over a memory map.
+///
+/// Distinct barcodes are counted exactly if `exact_barcodes` is set, which
+/// needs memory for every distinct barcode; otherwise they are estimated with
+/// a fixed-size HyperLogLog sketch. All available cores are used.
+///
+/// # Arguments
+///
+/// * `path` - Path to the IBU file
+/// * `exact_barcodes` - Count distinct barcodes exactly instead of
+///   estimating them
+///
+/// # Errors
+///
+/// Returns the errors of [`MmapReader::new`].
+pub fn summarize_with<P: AsRef<Path>>(path: P, exact_barcodes: bool) -> crate::Result<IbuStats> {
+    let reader = MmapReader::new(path)?;
+    let processor = SummaryProcessor::new(exact_barcodes);
+    reader.process_parallel(processor.clone(), 0)?;
+    Ok(processor.stats(reader.header()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Writer;
+    use std::fs;
+
+    #[test]
+    fn test_summarize() {
+        let temp_file = "test_summarize.ibu";
+        let records: Vec<Record> = (0..10_000)
+            .map(|i| Record::new(i % 500, i, 10 + i % 7))
+            .collect();
+        let mut header = Header::new(12, 10);
+        header.set_sorted();
+        let mut writer = Writer::new(fs::File::create(temp_file).unwrap(), header).unwrap();
+        writer.write_batch(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let stats = summarize(temp_file).unwrap();
+        let mean = records.iter().map(|r| r.index).sum::<u64>() as f64 / 10_000.0;
+        assert_eq!(
+            stats,
+            IbuStats {
+                records: 10_000,
+                bc_len: 12,
+                umi_len: 10,
+                sorted: true,
+                distinct_barcodes: 500,
+                distinct_exact: true,
+                min_index: Some(10),
+                max_index: Some(16),
+                mean_index: Some(mean),
+            }
+        );
+
+        let approx = summarize_with(temp_file, false).unwrap();
+        assert!(!approx.distinct_exact);
+        assert!(approx.distinct_barcodes.abs_diff(500) <= 25);
+        assert_eq!(approx.records, 10_000);
+
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[test]
+    fn test_summarize_empty() {
+        let temp_file = "test_summarize_empty.ibu";
+        let mut writer =
+            Writer::new(fs::File::create(temp_file).unwrap(), Header::new(16, 12)).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let stats = summarize(temp_file).unwrap();
+        assert_eq!(stats.records, 0);
+        assert_eq!(stats.distinct_barcodes, 0);
+        assert!(!stats.sorted);
+        assert_eq!(
+            (stats.min_index, stats.max_index, stats.mean_index),
+            (None, None, None)
+        );
+
+        assert!(summarize("missing_summarize.ibu").is_err());
+        fs::remove_file(temp_file).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_ibu_stats_serde() {
+        let stats = IbuStats {
+            records: 3,
+            bc_len: 16,
+            umi_len: 12,
+            sorted: false,
+            distinct_barcodes: 2,
+            distinct_exact: true,
+            min_index: Some(1),
+            max_index: Some(5),
+            mean_index: Some(3.0),
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        assert!(json.contains("\"distinct_barcodes\":2"));
+        assert_eq!(serde_json::from_str::<IbuStats>(&json).unwrap(), stats);
+    }
+}
@@ -0,0 +1,328 @@
+//! Tab-separated text export and import of IBU records.
+//!
+//! This module dumps records as human-readable TSV for inspection and
+//! debugging, and builds IBU files from TSV tables. Rows are
+//! `barcode\tumi\tindex`, with the barcode and UMI either decoded to
+//! nucleotides or printed as raw hexadecimal values.
+
+use std::io::{BufRead, BufWriter, Read, Write};
+
+use crate::{
+    packed::{pack, unpack},
+    Alphabet, Header, IbuError, Reader, Record, Writer,
+};
+
+/// Column names written as the first line of every export.
+const TSV_HEADER: &str = "barcode\tumi\tindex";
+
+/// Writes the records of a reader as TSV with decoded sequences.
+///
+/// The first line is the column header `barcode\tumi\tindex`, followed by one
+/// row per record. Barcodes and UMIs are decoded with the canonical `ACGT`
+/// alphabet to the lengths declared in the reader's header; bits above those
+/// lengths are ignored. Use [`to_tsv_raw`] for values that are not real
+/// sequences.
+///
+/// Output is buffered internally and flushed before returning.
+///
+/// # Returns
+///
+/// The number of records written.
+///
+/// # Errors
+///
+/// Returns the first error from reading the records or writing the output.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{to_tsv, Header, Reader, Record, Writer};
+/// use std::io::Cursor;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let mut writer = Writer::new(Vec::new(), Header::new(4, 2))?;
+/// writer.write_record(&Record::new(0b00_01_10_11, 0b11_00, 7))?;
+/// writer.finish()?;
+///
+/// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+/// let mut output = Vec::new();
+/// assert_eq!(to_tsv(reader, &mut output)?, 1);
+/// assert_eq!(output, b"barcode\tumi\tindex\nACGT\tTA\t7\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_tsv<R: Read, W: Write>(reader: Reader<R>, writer: W) -> crate::Result<u64> {
+    let header = reader.header();
+    let alphabet = Alphabet::canonical();
+    write_rows(reader, writer, |out, record| {
+        out.write_all(&unpack(record.barcode, header.bc_len, &alphabet))?;
+        out.write_all(b"\t")?;
+        out.write_all(&unpack(record.umi, header.umi_len, &alphabet))?;
+        writeln!(out, "\t{}", record.index)
+    })
+}
+
+/// Writes the records of a reader as TSV with raw hexadecimal values.
+///
+/// Same layout as [`to_tsv`], but the barcode and UMI are printed as their
+/// packed `u64` values in hexadecimal (e.g. `0x1b`) instead of being decoded.
+/// This is useful when the fields do not hold real sequences or carry bits
+/// above their declared lengths.
+///
+/// # Returns
+///
+/// The number of records written.
+///
+/// # Errors
+///
+/// Returns the first error from reading the records or writing the output.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{to_tsv_raw, Header, Reader, Record, Writer};
+/// use std::io::Cursor;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let mut writer = Writer::new(Vec::new(), Header::new(4, 2))?;
+/// writer.write_record(&Record::new(0x1b, 0xc, 7))?;
+/// writer.finish()?;
+///
+/// let reader = Reader::new(Cursor::new(writer.into_inner()))?;
+/// let mut output = Vec::new();
+/// to_tsv_raw(reader, &mut output)?;
+/// assert_eq!(output, b"barcode\tumi\tindex\n0x1b\t0xc\t7\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_tsv_raw<R: Read, W: Write>(reader: Reader<R>, writer: W) -> crate::Result<u64> {
+    write_rows(reader, writer, |out, record| {
+        writeln!(
+            out,
+            "{:#x}\t{:#x}\t{}",
+            record.barcode, record.umi, record.index
+        )
+    })
+}
+
+/// Writes the column header, then one row per record with `write_row`.
+fn write_rows<R, W, F>(reader: Reader<R>, writer: W, mut write_row: F) -> crate::Result<u64>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(&mut BufWriter<W>, &Record) -> std::io::Result<()>,
+{
+    let mut out = BufWriter::new(writer);
+    writeln!(out, "{TSV_HEADER}")?;
+    let mut written = 0;
+    for record in reader {
+        write_row(&mut out, &record?)?;
+        written += 1;
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+/// Reads records from a TSV table of sequences and writes them to `output`.
+///
+/// Each line holds `barcode\tumi\tindex`, with the barcode and UMI as
+/// nucleotide sequences of exactly `bc_len` and `umi_len` bases, encoded with
+/// the canonical `ACGT` alphabet. A leading `barcode\tumi\tindex` header line
+/// (as written by [`to_tsv`]) is skipped, as are blank lines. Trailing carriage
+/// returns are ignored, so files with Windows line endings are accepted.
+///
+/// Records are streamed to `output` as they are parsed. `output` is not
+/// finished, so more records can follow; call [`Writer::finish`] when done.
+///
+/// # Arguments
+///
+/// * `reader` - Source of the TSV text
+/// * `bc_len` - Barcode length in bases
+/// * `umi_len` - UMI length in bases
+/// * `output` - Writer receiving the records
+///
+/// # Returns
+///
+/// The number of records written.
+///
+/// # Errors
+///
+/// Returns [`IbuError::InvalidLine`] with the 1-based line number for a line
+/// with the wrong number of columns, a sequence of the wrong length or with a
+/// base outside `ACGT`, or an index that is not an unsigned integer. Also
+/// returns the errors of [`Header::validate`] for invalid lengths, and any
+/// error from reading the text or writing the records.
+///
+/// # Examples
+///
+/// ```rust
+/// use ibu::{from_tsv, Header, Record, Writer};
+///
+/// # fn main() -> ibu::Result<()> {
+/// let text = "barcode\tumi\tindex\nACGT\tTA\t7\n\nAAAA\tCC\t8\n";
+/// let mut writer = Writer::new(Vec::new(), Header::new(4, 2))?;
+/// assert_eq!(from_tsv(text.as_bytes(), 4, 2, &mut writer)?, 2);
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_tsv<R: BufRead, W: Write>(
+    reader: R,
+    bc_len: u32,
+    umi_len: u32,
+    output: &mut Writer<W>,
+) -> crate::Result<u64> {
+    Header::new(bc_len, umi_len).validate()?;
+    let alphabet = Alphabet::canonical();
+    let mut written = 0;
+    let mut seen_content = false;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let first = !seen_content;
+        seen_content = true;
+        if first && line == TSV_HEADER {
+            continue;
+        }
+        let record = parse_line(line, bc_len, umi_len, &alphabet).map_err(|message| {
+            IbuError::InvalidLine {
+                line: i + 1,
+                message,
+            }
+        })?;
+        output.write_record(&record)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Parses one `barcode\tumi\tindex` row, describing the problem on failure.
+fn parse_line(
+    line: &str,
+    bc_len: u32,
+    umi_len: u32,
+    alphabet: &Alphabet,
+) -> Result<Record, String> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    let [barcode, umi, index] = columns[..] else {
+        return Err(format!("expected 3 columns, found {}", columns.len()));
+    };
+    let encode = |field: &str, seq: &str, len: u32| {
+        if seq.len() != len as usize {
+            return Err(format!(
+                "{field} {seq:?} has {} bases, expected {len}",
+                seq.len()
+            ));
+        }
+        pack(seq.as_bytes(), alphabet).map_err(|e| format!("{field} {seq:?}: {e}"))
+    };
+    let index = index
+        .parse()
+        .map_err(|_| format!("index {index:?} is not an unsigned integer"))?;
+    Ok(Record::new(
+        encode("barcode", barcode, bc_len)?,
+        encode("UMI", umi, umi_len)?,
+        index,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn reader(header: Header, records: &[Record]) -> Reader<Cursor<Vec<u8>>> {
+        let mut writer = Writer::new(Vec::new(), header).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+        Reader::new(Cursor::new(writer.into_inner())).unwrap()
+    }
+
+    #[test]
+    fn test_to_tsv() {
+        let records = [Record::new(0, u64::MAX, 0), Record::new(0b11_10, 0b01, 42)];
+        let mut output = Vec::new();
+        let written = to_tsv(reader(Header::new(2, 3), &records), &mut output).unwrap();
+        assert_eq!(written, 2);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "barcode\tumi\tindex\nAA\tTTT\t0\nTG\tAAC\t42\n"
+        );
+
+        let mut output = Vec::new();
+        to_tsv_raw(reader(Header::new(2, 3), &records), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "barcode\tumi\tindex\n0x0\t0xffffffffffffffff\t0\n0xe\t0x1\t42\n"
+        );
+    }
+
+    #[test]
+    fn test_tsv_roundtrip() {
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, i * 7 % 64, i)).collect();
+        let mut text = Vec::new();
+        to_tsv(reader(Header::new(4, 3), &records), &mut text).unwrap();
+
+        let mut writer = Writer::new(Vec::new(), Header::new(4, 3)).unwrap();
+        assert_eq!(from_tsv(text.as_slice(), 4, 3, &mut writer).unwrap(), 100);
+        writer.finish().unwrap();
+        let read: Vec<Record> = Reader::new(Cursor::new(writer.into_inner()))
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn test_from_tsv_errors() {
+        let parse = |text: &str| {
+            let mut writer = Writer::new_headless(Vec::new());
+            from_tsv(text.as_bytes(), 2, 2, &mut writer)
+        };
+        let line_of = |text: &str| match parse(text) {
+            Err(IbuError::InvalidLine { line, .. }) => line,
+            other => panic!("unexpected result {other:?}"),
+        };
+
+        // No header, blank lines, and CRLF endings
+        assert_eq!(parse("\r\nAC\tGT\t1\r\n\n  \nTT\tAA\t2").unwrap(), 2);
+        assert_eq!(parse("").unwrap(), 0);
+
+        assert_eq!(line_of("barcode\tumi\tindex\nAC\tGT\n"), 2);
+        assert_eq!(line_of("AC\tGT\t1\n\nACG\tGT\t2\n"), 3);
+        assert_eq!(line_of("AN\tGT\t1\n"), 1);
+        assert_eq!(line_of("AC\tGT\t-1\n"), 1);
+        // The header is only skipped as the first line
+        assert_eq!(line_of("AC\tGT\t1\nbarcode\tumi\tindex\n"), 2);
+
+        assert!(matches!(
+            from_tsv("".as_bytes(), 0, 2, &mut Writer::new_headless(Vec::new())),
+            Err(IbuError::InvalidBarcodeLength(0))
+        ));
+    }
+
+    #[test]
+    fn test_to_tsv_errors() {
+        // Header line only for an empty stream
+        let mut output = Vec::new();
+        assert_eq!(
+            to_tsv(reader(Header::new(2, 2), &[]), &mut output).unwrap(),
+            0
+        );
+        assert_eq!(output, b"barcode\tumi\tindex\n");
+
+        let mut writer = Writer::new(Vec::new(), Header::new(2, 2)).unwrap();
+        writer.write_record(&Record::new(1, 2, 3)).unwrap();
+        writer.finish().unwrap();
+        let mut bytes = writer.into_inner();
+        bytes.pop();
+        let reader = Reader::new(Cursor::new(bytes)).unwrap();
+        assert!(matches!(
+            to_tsv(reader, Vec::new()),
+            Err(IbuError::TruncatedRecord { .. })
+        ));
+    }
+}
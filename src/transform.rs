@@ -0,0 +1,410 @@
+//! File-to-file transformations of IBU records.
+//!
+//! This module provides routine streaming rewrites of IBU files, such as
+//! relabeling record fields, without holding the whole file in memory.
+
+use std::{collections::HashMap, path::Path};
+
+use crate::{constructs::translate_sequence, Alphabet, IbuError, Reader, Writer};
+
+/// Policy for records whose index is missing from a remapping table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum MissingPolicy {
+    /// Fail with [`IbuError::MissingIndex`] on the first unmapped index.
+    #[default]
+    Error,
+    /// Write the record with its original index.
+    Keep,
+    /// Skip the record.
+    Drop,
+}
+
+/// Rewrites every record's index through a remapping table.
+///
+/// Records are streamed from `input` to `output`, with each index replaced by
+/// its entry in `map`. Indices missing from `map` are handled according to
+/// `on_missing`. The output header matches the input header, except that the
+/// sorted flag is cleared, since new indices can break the sort order within a
+/// `(barcode, umi)` group, and the record count and checksum are dropped, since
+/// records may be dropped and their indices change.
+///
+/// # Arguments
+///
+/// * `input` - Path of the file to read (may be compressed)
+/// * `output` - Path of the file to write
+/// * `map` - Table from old index to new index
+/// * `on_missing` - How to handle indices missing from `map`
+///
+/// # Returns
+///
+/// The number of records written to `output`.
+///
+/// # Errors
+///
+/// Returns an error if reading or writing fails, or
+/// [`IbuError::MissingIndex`] if an index is unmapped under
+/// [`MissingPolicy::Error`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{remap_indices, MissingPolicy};
+/// use std::collections::HashMap;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let map: HashMap<u64, u64> = [(0, 10), (1, 11)].into_iter().collect();
+/// let written = remap_indices("input.ibu", "output.ibu", &map, MissingPolicy::Drop)?;
+/// println!("Wrote {} remapped records", written);
+/// # Ok(())
+/// # }
+/// ```
+pub fn remap_indices<P: AsRef<Path>>(
+    input: P,
+    output: P,
+    map: &HashMap<u64, u64>,
+    on_missing: MissingPolicy,
+) -> crate::Result<u64> {
+    let reader = Reader::from_path(input)?;
+    let mut header = reader.header();
+    header.clear_sorted();
+    header.clear_reserved();
+
+    let mut writer = Writer::from_path(output, header)?;
+    for record in reader {
+        let mut record = record?;
+        match (map.get(&record.index), on_missing) {
+            (Some(&index), _) => record.index = index,
+            (None, MissingPolicy::Keep) => {}
+            (None, MissingPolicy::Drop) => continue,
+            (None, MissingPolicy::Error) => return Err(IbuError::MissingIndex(record.index)),
+        }
+        writer.write_record(&record)?;
+    }
+    writer.finish()?;
+    Ok(writer.records_written())
+}
+
+/// Splits a file into two files at a record count.
+///
+/// The first `n` records are written to `first_out` and the remaining records
+/// to `second_out`. Both outputs receive a copy of the input header, including
+/// the sorted flag, since a prefix or suffix of sorted data is still sorted.
+/// The input's record count and checksum describe the whole input, so they are
+/// not copied. If the input holds fewer than `n` records, `second_out` is
+/// empty.
+///
+/// # Arguments
+///
+/// * `input` - Path of the file to read (may be compressed)
+/// * `first_out` - Path for the first `n` records
+/// * `second_out` - Path for the remaining records
+/// * `n` - Number of records in the first output
+///
+/// # Returns
+///
+/// The number of records written to each output.
+///
+/// # Errors
+///
+/// Returns an error if reading or writing fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::split_at;
+///
+/// # fn main() -> ibu::Result<()> {
+/// let (train, test) = split_at("all.ibu", "train.ibu", "test.ibu", 1_000_000)?;
+/// println!("train: {}, test: {}", train, test);
+/// # Ok(())
+/// # }
+/// ```
+pub fn split_at<P: AsRef<Path>>(
+    input: P,
+    first_out: P,
+    second_out: P,
+    n: usize,
+) -> crate::Result<(u64, u64)> {
+    let mut reader = Reader::from_path(input)?;
+    let mut header = reader.header();
+    header.clear_reserved();
+
+    let mut first = Writer::from_path(first_out, header)?;
+    for record in reader.by_ref().take(n) {
+        first.write_record(&record?)?;
+    }
+    first.finish()?;
+
+    let mut second = Writer::from_path(second_out, header)?;
+    for record in reader {
+        second.write_record(&record?)?;
+    }
+    second.finish()?;
+
+    Ok((first.records_written(), second.records_written()))
+}
+
+/// Re-encodes every barcode and UMI from one alphabet to another.
+///
+/// Each of the `bc_len` barcode bases and `umi_len` UMI bases given by the
+/// header is decoded under `from` and re-encoded under `to`; bits beyond the
+/// header lengths and the index are copied unchanged. The output header
+/// matches the input header, except that the sorted flag is cleared when the
+/// alphabets differ, since changing the code of a base changes record order,
+/// and the record count and checksum are dropped, since the checksum no longer
+/// matches the rewritten records.
+///
+/// # Arguments
+///
+/// * `input` - Path of the file to read (may be compressed)
+/// * `output` - Path of the file to write
+/// * `from` - Alphabet the input was encoded with
+/// * `to` - Alphabet to encode the output with
+///
+/// # Returns
+///
+/// The number of records written to `output`.
+///
+/// # Errors
+///
+/// Returns an error if reading or writing fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ibu::{reencode_alphabet, Alphabet};
+///
+/// # fn main() -> ibu::Result<()> {
+/// // Migrate a file encoded with T=00, G=01, C=10, A=11 to the canonical mapping
+/// let legacy = Alphabet::new(*b"TGCA")?;
+/// reencode_alphabet("legacy.ibu", "canonical.ibu", legacy, Alphabet::canonical())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn reencode_alphabet<P: AsRef<Path>>(
+    input: P,
+    output: P,
+    from: Alphabet,
+    to: Alphabet,
+) -> crate::Result<u64> {
+    let reader = Reader::from_path(input)?;
+    let mut header = reader.header();
+    if from != to {
+        header.clear_sorted();
+    }
+    header.clear_reserved();
+    let table = from.translation(&to);
+
+    let mut writer = Writer::from_path(output, header)?;
+    for record in reader {
+        let mut record = record?;
+        record.barcode = translate_sequence(record.barcode, header.bc_len, &table);
+        record.umi = translate_sequence(record.umi, header.umi_len, &table);
+        writer.write_record(&record)?;
+    }
+    writer.finish()?;
+    Ok(writer.records_written())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, MmapReader, Record};
+    use std::fs;
+
+    fn create_test_file(path: &str, records: &[Record]) {
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        let mut writer = Writer::from_path(path, header).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish().unwrap();
+    }
+
+    /// Writes `records` with a stored record count and checksum.
+    fn create_counted_file(path: &str, records: &[Record]) {
+        let mut header = Header::new(16, 12);
+        header.set_sorted();
+        let mut writer =
+            Writer::new_with_checksum(fs::File::create(path).unwrap(), header).unwrap();
+        writer.write_batch(records).unwrap();
+        writer.finish_with_count().unwrap();
+    }
+
+    fn read_all(path: &str) -> (Header, Vec<Record>) {
+        let reader = Reader::from_path(path).unwrap();
+        let header = reader.header();
+        (header, reader.collect::<crate::Result<Vec<_>>>().unwrap())
+    }
+
+    fn test_map() -> HashMap<u64, u64> {
+        [(0, 100), (1, 101)].into_iter().collect()
+    }
+
+    #[test]
+    fn test_split_at() {
+        let paths = ["test_split_in.ibu", "test_split_a.ibu", "test_split_b.ibu"];
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, 0, 0)).collect();
+        create_test_file(paths[0], &records);
+
+        let counts = split_at(paths[0], paths[1], paths[2], 30).unwrap();
+        assert_eq!(counts, (30, 70));
+
+        let (header_a, records_a) = read_all(paths[1]);
+        let (header_b, records_b) = read_all(paths[2]);
+        assert!(header_a.sorted());
+        assert!(header_b.sorted());
+        assert_eq!(records_a, &records[..30]);
+        assert_eq!(records_b, &records[30..]);
+
+        // Splitting past the end leaves the second file empty
+        let counts = split_at(paths[0], paths[1], paths[2], 1000).unwrap();
+        assert_eq!(counts, (100, 0));
+        assert!(read_all(paths[2]).1.is_empty());
+
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_split_at_counted_input() {
+        let paths = [
+            "test_split_counted_in.ibu",
+            "test_split_counted_a.ibu",
+            "test_split_counted_b.ibu",
+        ];
+        let records: Vec<Record> = (0..100).map(|i| Record::new(i, 0, 0)).collect();
+        create_counted_file(paths[0], &records);
+
+        // Neither half inherits the input's count or checksum
+        split_at(paths[0], paths[1], paths[2], 30).unwrap();
+        for (path, len) in [(paths[1], 30), (paths[2], 70)] {
+            let reader = MmapReader::new_verified(path).unwrap();
+            assert_eq!(reader.len(), len);
+            assert_eq!(reader.header().record_count(), None);
+            assert_eq!(reader.header().checksum(), None);
+            assert!(reader.header().sorted());
+        }
+
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_reencode_alphabet() {
+        let paths = [
+            "test_reencode_in.ibu",
+            "test_reencode_out.ibu",
+            "test_reencode_back.ibu",
+        ];
+        let records: Vec<Record> = (0..100)
+            .map(|i| Record::new(i * 12345, i * 67, i))
+            .collect();
+        create_test_file(paths[0], &records);
+
+        let legacy = Alphabet::new(*b"TGCA").unwrap();
+        let canonical = Alphabet::canonical();
+        assert_eq!(
+            reencode_alphabet(paths[0], paths[1], canonical, legacy).unwrap(),
+            100
+        );
+
+        let (header, converted) = read_all(paths[1]);
+        assert!(!header.sorted());
+        // Under TGCA every code is complemented (c -> 3 - c)
+        let bc_mask = (1u64 << 32) - 1;
+        let umi_mask = (1u64 << 24) - 1;
+        for (original, converted) in records.iter().zip(&converted) {
+            assert_eq!(converted.barcode, original.barcode ^ bc_mask);
+            assert_eq!(converted.umi, original.umi ^ umi_mask);
+            assert_eq!(converted.index, original.index);
+        }
+
+        reencode_alphabet(paths[1], paths[2], legacy, canonical).unwrap();
+        assert_eq!(read_all(paths[2]).1, records);
+
+        // Identical alphabets preserve the sorted flag
+        reencode_alphabet(paths[0], paths[1], canonical, canonical).unwrap();
+        assert!(read_all(paths[1]).0.sorted());
+
+        // A stored checksum over the original encoding is not carried over
+        create_counted_file(paths[0], &records);
+        reencode_alphabet(paths[0], paths[1], canonical, legacy).unwrap();
+        let reader = MmapReader::new_verified(paths[1]).unwrap();
+        assert_eq!(reader.len(), 100);
+        assert_eq!(reader.header().checksum(), None);
+
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_remap_indices_keep() {
+        let (input, output) = ("test_remap_keep_in.ibu", "test_remap_keep_out.ibu");
+        create_test_file(input, &[Record::new(1, 1, 0), Record::new(1, 2, 2)]);
+
+        let written = remap_indices(input, output, &test_map(), MissingPolicy::Keep).unwrap();
+        assert_eq!(written, 2);
+
+        let (header, records) = read_all(output);
+        assert!(!header.sorted());
+        assert_eq!(records, vec![Record::new(1, 1, 100), Record::new(1, 2, 2)]);
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_remap_indices_drop() {
+        let (input, output) = ("test_remap_drop_in.ibu", "test_remap_drop_out.ibu");
+        create_test_file(
+            input,
+            &[
+                Record::new(1, 1, 0),
+                Record::new(1, 2, 2),
+                Record::new(2, 1, 1),
+            ],
+        );
+
+        let written = remap_indices(input, output, &test_map(), MissingPolicy::Drop).unwrap();
+        assert_eq!(written, 2);
+
+        let (_, records) = read_all(output);
+        assert_eq!(
+            records,
+            vec![Record::new(1, 1, 100), Record::new(2, 1, 101)]
+        );
+
+        // A stored count and checksum are not carried over
+        create_counted_file(
+            input,
+            &[
+                Record::new(1, 1, 0),
+                Record::new(1, 2, 2),
+                Record::new(2, 1, 1),
+            ],
+        );
+        remap_indices(input, output, &test_map(), MissingPolicy::Drop).unwrap();
+        let reader = MmapReader::new_verified(output).unwrap();
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.header().record_count(), None);
+        assert_eq!(reader.header().checksum(), None);
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_remap_indices_error() {
+        let (input, output) = ("test_remap_error_in.ibu", "test_remap_error_out.ibu");
+        create_test_file(input, &[Record::new(1, 1, 0), Record::new(1, 2, 7)]);
+
+        let result = remap_indices(input, output, &test_map(), MissingPolicy::Error);
+        assert!(matches!(result, Err(IbuError::MissingIndex(7))));
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+}